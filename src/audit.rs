@@ -0,0 +1,101 @@
+use std::process::Command;
+use std::time::SystemTime;
+
+/// A signal sent to kill a process, as written to the system log when
+/// `audit_kills` is on
+pub struct KillRecord<'a> {
+    pub pid: u32,
+    pub name: &'a str,
+    /// Ports the process was listening on, if known
+    pub ports: &'a [u16],
+    /// Signal name without the `SIG` prefix, e.g. "KILL"
+    pub signal: &'a str,
+    /// Why the signal couldn't be sent, if it wasn't
+    pub error: Option<&'a str>,
+}
+
+impl KillRecord<'_> {
+    /// One `key=value` line, quoting the values that may contain spaces
+    pub fn message(&self, user: &str, at: SystemTime) -> String {
+        let ports = if self.ports.is_empty() {
+            "-".to_string()
+        } else {
+            self.ports.iter().map(u16::to_string).collect::<Vec<_>>().join(",")
+        };
+        let result = match self.error {
+            Some(error) => format!("failed: {}", error),
+            None => "sent".to_string(),
+        };
+        format!(
+            "kill user={} pid={} name={:?} ports={} signal=SIG{} time={} result={:?}",
+            user,
+            self.pid,
+            self.name,
+            ports,
+            self.signal.to_uppercase(),
+            humantime::format_rfc3339_seconds(at),
+            result,
+        )
+    }
+}
+
+/// Write a kill to the system log with `logger`, which reaches syslog or the
+/// journal. Auditing never gets in the way of a kill, so failures, e.g. on a
+/// system without `logger`, are only logged at debug level.
+pub fn audit_kill(record: &KillRecord) {
+    let message = record.message(&current_user(), SystemTime::now());
+    match Command::new("logger").args(["-t", "port-manager", "-p", "auth.notice", "--", &message]).output() {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => tracing::debug!("logger failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+        Err(e) => tracing::debug!("Failed to run logger: {}", e),
+    }
+}
+
+/// Name of the user running port-manager, for the audit trail. The
+/// environment can be missing or changed, so `id` is asked first.
+fn current_user() -> String {
+    Command::new("id")
+        .arg("-un")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|user| !user.is_empty())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+    
+    /// 2024-06-01 14:32:05 UTC
+    fn at() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_717_252_325)
+    }
+    
+    #[test]
+    fn sent_kills_record_every_field() {
+        let record = KillRecord { pid: 4242, name: "node server", ports: &[3000, 3001], signal: "term", error: None };
+        assert_eq!(
+            record.message("alice", at()),
+            r#"kill user=alice pid=4242 name="node server" ports=3000,3001 signal=SIGTERM time=2024-06-01T14:32:05Z result="sent""#,
+        );
+    }
+    
+    #[test]
+    fn failed_kills_record_why() {
+        let record = KillRecord { pid: 1, name: "init", ports: &[], signal: "KILL", error: Some("Operation not permitted") };
+        assert_eq!(
+            record.message("bob", at()),
+            r#"kill user=bob pid=1 name="init" ports=- signal=SIGKILL time=2024-06-01T14:32:05Z result="failed: Operation not permitted""#,
+        );
+    }
+    
+    #[test]
+    fn quotes_in_names_are_escaped() {
+        let record = KillRecord { pid: 7, name: "say \"hi\"", ports: &[80], signal: "TERM", error: None };
+        assert!(record.message("alice", at()).contains(r#"name="say \"hi\"""#));
+    }
+}
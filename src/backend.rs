@@ -0,0 +1,1144 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+
+use crate::error::{IoContext, PortManagerError, Result};
+use clap::ValueEnum;
+use sysinfo::System;
+
+use crate::process::{self, kill_result, AddressFamily, DiscoveryMode, PortProcess, Protocol, TcpState};
+
+/// Environment variable that turns on demo mode, like `--demo`
+pub const DEMO_ENV_VAR: &str = "PORT_MANAGER_DEMO";
+
+/// Environment variable naming a file of captured lsof output, like `--lsof-input`
+pub const LSOF_INPUT_ENV_VAR: &str = "PORT_MANAGER_LSOF_INPUT";
+
+/// Which discovery backend to use
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum BackendKind {
+    /// Use the first available backend
+    Auto,
+    /// Parse `lsof` output
+    Lsof,
+    /// Read /proc/net/tcp and resolve PIDs with `fuser` (Linux only)
+    Fuser,
+    /// Show a fixed set of made-up processes, for demos and screenshots
+    #[value(hide = true)]
+    Demo,
+}
+
+/// A way of finding which processes have sockets open. Backends are `Send` so
+/// the server mode can share the app between request threads.
+pub trait Backend: Send {
+    /// Short name shown to the user
+    fn name(&self) -> &'static str;
+    
+    /// Check whether the tools this backend needs are installed
+    fn is_available(&self) -> bool;
+    
+    /// Find processes with sockets in the given mode
+    fn discover(&self, mode: DiscoveryMode, system: &System) -> Result<Vec<PortProcess>>;
+    
+    /// Count established connections by local port
+    fn connection_counts(&self) -> Result<HashMap<u16, u64>>;
+    
+    /// What the backend read to discover processes, unparsed, for
+    /// diagnostic bundles
+    fn raw_output(&self, mode: DiscoveryMode) -> Result<String>;
+    
+    /// Check whether a process still has a socket on a port, for re-checking
+    /// one row without a full refresh. Backends that can't ask about a single
+    /// process fall back to full discovery.
+    fn holds_port(&self, pid: u32, port: u16, mode: DiscoveryMode, system: &System) -> Result<bool> {
+        Ok(self.discover(mode, system)?.iter().any(|p| p.pid == pid && p.port == port))
+    }
+    
+    /// Check whether a process still exists, for backends whose processes
+    /// aren't in the local process table. `None` when it can't be told,
+    /// e.g. for made-up processes.
+    fn is_running(&self, _pid: u32) -> Result<Option<bool>> {
+        Ok(None)
+    }
+    
+    /// Whether the processes are made up, so must not be signalled or
+    /// checked against the real process table
+    fn is_demo(&self) -> bool {
+        false
+    }
+    
+    /// Whether the processes run on this machine, so can be looked up in the
+    /// local process table and /proc
+    fn is_local(&self) -> bool {
+        !self.is_demo()
+    }
+    
+    /// The other machine the processes run on, if they aren't on this one
+    fn host(&self) -> Option<&str> {
+        None
+    }
+    
+    /// Send a signal to a discovered process, refusing protected processes
+    fn send_signal(&self, pid: u32, signal: &str) -> Result<()> {
+        process::send_signal(pid, signal)
+    }
+}
+
+/// Pick a backend, falling back to the next one when tools are missing
+pub fn select_backend(kind: BackendKind) -> Result<Box<dyn Backend>> {
+    let candidates: Vec<Box<dyn Backend>> = match kind {
+        BackendKind::Auto => vec![
+            Box::new(LsofBackend),
+            #[cfg(target_os = "linux")]
+            Box::new(FuserBackend),
+        ],
+        BackendKind::Lsof => vec![Box::new(LsofBackend)],
+        #[cfg(target_os = "linux")]
+        BackendKind::Fuser => vec![Box::new(FuserBackend)],
+        #[cfg(not(target_os = "linux"))]
+        BackendKind::Fuser => {
+            return Err(PortManagerError::BackendUnavailable("The fuser backend is only supported on Linux".to_string()));
+        }
+        BackendKind::Demo => vec![Box::new(DemoBackend)],
+    };
+    
+    let mut unavailable = Vec::new();
+    for backend in candidates {
+        if backend.is_available() {
+            return Ok(backend);
+        }
+        unavailable.push(backend.name());
+    }
+    
+    Err(PortManagerError::BackendUnavailable(format!(
+        "No discovery backend available (tried: {})",
+        unavailable.join(", ")
+    )))
+}
+
+/// Check whether a command can be spawned at all
+fn command_exists(program: &str, version_flag: &str) -> bool {
+    Command::new(program).arg(version_flag).output().is_ok()
+}
+
+/// Discovery using `lsof`, the default on every platform
+pub struct LsofBackend;
+
+impl Backend for LsofBackend {
+    fn name(&self) -> &'static str {
+        "lsof"
+    }
+    
+    fn is_available(&self) -> bool {
+        command_exists("lsof", "-v")
+    }
+    
+    fn discover(&self, mode: DiscoveryMode, system: &System) -> Result<Vec<PortProcess>> {
+        Ok(parse_lsof_output(&self.raw_output(mode)?, system))
+    }
+    
+    fn raw_output(&self, mode: DiscoveryMode) -> Result<String> {
+        // Use `lsof` to find processes with TCP sockets in the requested state
+        let output = Command::new("lsof")
+            .args(["-iTCP", "-P", "-n", lsof_state(mode)])
+            .output()
+            .map_err(|e| PortManagerError::spawn("lsof", e))?;
+        
+        if !output.status.success() {
+            return Err(PortManagerError::CommandFailed {
+                command: "lsof".to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        
+        String::from_utf8(output.stdout)
+            .map_err(|e| PortManagerError::parse("lsof output", e))
+    }
+    
+    fn holds_port(&self, pid: u32, port: u16, mode: DiscoveryMode, _system: &System) -> Result<bool> {
+        // `-a` ANDs the PID and socket selections; lsof exits non-zero when
+        // nothing matches
+        let output = Command::new("lsof")
+            .args(["-a", "-p", &pid.to_string(), &format!("-iTCP:{}", port), "-P", "-n", lsof_state(mode)])
+            .output()
+            .map_err(|e| PortManagerError::spawn("lsof", e))?;
+        Ok(output.status.success() && !output.stdout.is_empty())
+    }
+    
+    fn connection_counts(&self) -> Result<HashMap<u16, u64>> {
+        let output = Command::new("lsof")
+            .args(["-iTCP", "-P", "-n", "-sTCP:ESTABLISHED"])
+            .output()
+            .map_err(|e| PortManagerError::spawn("lsof", e))?;
+        
+        // lsof exits non-zero when there are no matching sockets
+        Ok(count_established(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Count established connections by local port in `lsof -iTCP -P -n` output
+fn count_established(output: &str) -> HashMap<u16, u64> {
+    let mut counts = HashMap::new();
+    for line in output.lines().skip(1) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 9 || parts.get(9).is_some_and(|state| *state != "(ESTABLISHED)") {
+            continue;
+        }
+        
+        let local_addr = parts[8].split_once("->").map_or(parts[8], |(local, _)| local);
+        if let Some(Ok(port)) = local_addr.split(':').next_back().map(str::parse::<u16>) {
+            *counts.entry(port).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// lsof flag selecting sockets in the state a discovery mode looks for
+fn lsof_state(mode: DiscoveryMode) -> &'static str {
+    match mode {
+        DiscoveryMode::Listeners => "-sTCP:LISTEN",
+        DiscoveryMode::Connections => "-sTCP:^LISTEN",
+    }
+}
+
+/// Parse `lsof -iTCP -P -n` output into port processes
+fn parse_lsof_output(output_str: &str, system: &System) -> Vec<PortProcess> {
+    parse_lsof_rows(output_str, |pid, name, port, host, family, remote| {
+        PortProcess::new(pid, name, port, host, family, remote, system)
+    })
+}
+
+/// Parse `lsof` output, creating each row with `new_process` from its PID,
+/// name, port, local host, family and remote endpoint
+fn parse_lsof_rows<F>(output_str: &str, new_process: F) -> Vec<PortProcess>
+where
+    F: Fn(u32, String, u16, String, AddressFamily, Option<String>) -> PortProcess,
+{
+    let mut port_processes = Vec::new();
+    
+    // Skip the header line
+    for line in output_str.lines().skip(1) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 9 {
+            let process_name = parts[0].to_string();
+            let pid_str = parts[1];
+            let family = if parts[4] == "IPv6" {
+                AddressFamily::Ipv6
+            } else {
+                AddressFamily::Ipv4
+            };
+            
+            // Address is typically like `*:8080`, or `127.0.0.1:52000->127.0.0.1:8080`
+            // for an established connection
+            let (local_addr, remote) = match parts[8].split_once("->") {
+                Some((local, remote)) => (local, Some(remote.to_string())),
+                None => (parts[8], None),
+            };
+            
+            // Split the local address into host and port. Without -P, lsof
+            // prints service names like `http` instead of numbers.
+            if let Some((host, port_str)) = local_addr.rsplit_once(':') {
+                if let Ok(pid) = pid_str.parse::<u32>() {
+                    let port = port_str.parse::<u16>().ok().or_else(|| service_port(port_str));
+                    if port.is_none() {
+                        tracing::debug!("Could not resolve port {:?} of PID {}", port_str, pid);
+                    }
+                    let mut process = new_process(pid, process_name, port.unwrap_or(0), host.to_string(), family, remote);
+                    if port.is_none() {
+                        process.unresolved_port = Some(port_str.to_string());
+                    }
+                    process.fd = Some(parts[3].to_string());
+                    if parts[7] == "UDP" {
+                        process.protocol = Protocol::Udp;
+                    }
+                    // Captured and remote output can name PIDs that can't be looked up here
+                    if process.user.is_empty() {
+                        process.user = parts[2].to_string();
+                    }
+                    // The state follows the address, e.g. `(CLOSE_WAIT)`
+                    if let Some(Ok(state)) = parts.get(9).map(|state| state.trim_matches(|c| c == '(' || c == ')').parse()) {
+                        process.state = state;
+                    }
+                    // DEVICE is the socket inode on Linux, and a kernel address elsewhere
+                    if cfg!(target_os = "linux") {
+                        process.socket_inode = parts[5].parse().ok();
+                    }
+                    port_processes.push(process);
+                }
+            }
+        }
+    }
+    
+    port_processes
+}
+
+/// Port of a TCP service name like `http`, from /etc/services
+fn service_port(name: &str) -> Option<u16> {
+    static SERVICES: OnceLock<HashMap<String, u16>> = OnceLock::new();
+    SERVICES
+        .get_or_init(|| fs::read_to_string("/etc/services").map(|contents| parse_services(&contents)).unwrap_or_default())
+        .get(name)
+        .copied()
+}
+
+/// Map the TCP service names and aliases in /etc/services to their ports,
+/// e.g. `http  80/tcp  www  # WorldWideWeb HTTP`
+fn parse_services(contents: &str) -> HashMap<String, u16> {
+    let mut services = HashMap::new();
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_proto)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some(Ok(port)) = port_proto.strip_suffix("/tcp").map(str::parse::<u16>) else {
+            continue;
+        };
+        for name in std::iter::once(name).chain(fields) {
+            services.entry(name.to_string()).or_insert(port);
+        }
+    }
+    services
+}
+
+/// Discovery from lsof output captured earlier instead of running lsof, for
+/// sandboxes where spawning it isn't allowed and for replaying the output
+/// from a bug report. A file is read again on every refresh, so it can be
+/// edited while the TUI runs; standard input is read once. The PIDs may not
+/// exist on this machine, so nothing is signalled, as in demo mode.
+pub struct CapturedLsofBackend {
+    /// File holding the output, or `-` for standard input
+    path: PathBuf,
+    /// Standard input, read when the backend was created
+    stdin: Option<String>,
+}
+
+impl CapturedLsofBackend {
+    /// Read captured output from a file, or from standard input for `-`
+    pub fn new(path: PathBuf) -> Result<Self> {
+        let stdin = if path == Path::new("-") {
+            let mut output = String::new();
+            io::stdin().read_to_string(&mut output)
+                .io_context("Failed to read lsof output from standard input")?;
+            Some(output)
+        } else {
+            None
+        };
+        Ok(Self { path, stdin })
+    }
+    
+    /// The captured output's header and TCP sockets. Captures made with
+    /// plain `lsof -i` also list UDP sockets, which have no state.
+    fn tcp_output(&self) -> Result<String> {
+        let output = match &self.stdin {
+            Some(output) => output.clone(),
+            None => fs::read_to_string(&self.path)
+                .io_context(format!("Failed to read lsof output from {}", self.path.display()))?,
+        };
+        Ok(output.lines()
+            .enumerate()
+            .filter(|(index, line)| *index == 0 || line.split_whitespace().nth(7) == Some("TCP"))
+            .map(|(_, line)| line)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+impl Backend for CapturedLsofBackend {
+    fn name(&self) -> &'static str {
+        "lsof (captured)"
+    }
+    
+    fn is_available(&self) -> bool {
+        true
+    }
+    
+    fn discover(&self, mode: DiscoveryMode, _system: &System) -> Result<Vec<PortProcess>> {
+        // The PIDs may belong to another machine or an earlier boot, so only
+        // what the capture itself says is shown. It may hold every state, so
+        // pick out the ones lsof would have been asked for.
+        let mut processes = parse_lsof_rows(&self.tcp_output()?, PortProcess::without_lookups);
+        processes.retain(|p| (p.state == TcpState::Listen) == (mode == DiscoveryMode::Listeners));
+        Ok(processes)
+    }
+    
+    fn connection_counts(&self) -> Result<HashMap<u16, u64>> {
+        Ok(count_established(&self.tcp_output()?))
+    }
+    
+    fn raw_output(&self, _mode: DiscoveryMode) -> Result<String> {
+        self.tcp_output()
+    }
+    
+    fn is_demo(&self) -> bool {
+        true
+    }
+}
+
+/// Discovery on another machine with `--host`: lsof, ps and kill run there
+/// over SSH, and their output is parsed here
+pub struct SshBackend {
+    /// SSH destination, e.g. `deploy@staging`
+    host: String,
+    /// The ssh client, replaced by a fake one in tests
+    ssh: PathBuf,
+}
+
+impl SshBackend {
+    pub fn new(host: String) -> Self {
+        Self { host, ssh: PathBuf::from("ssh") }
+    }
+    
+    /// Run a command on the host. BatchMode makes ssh fail instead of asking
+    /// for a password or to trust a host key, which the TUI couldn't show.
+    fn run(&self, args: &[&str]) -> Result<Output> {
+        // Quoted for the remote shell, which would take `^` in `-sTCP:^LISTEN`
+        // as a pipe in some shells
+        let command: Vec<String> = args.iter().map(|arg| format!("'{}'", arg.replace('\'', "'\\''"))).collect();
+        let output = Command::new(&self.ssh)
+            .args(["-o", "BatchMode=yes", "-o", "ConnectTimeout=5", "--", &self.host, &command.join(" ")])
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| PortManagerError::spawn("ssh", e))?;
+        
+        // ssh exits with 255 for its own errors, and passes on the command's otherwise
+        if output.status.code() == Some(255) {
+            let error = String::from_utf8_lossy(&output.stderr);
+            let mut message = error.lines().last().unwrap_or("connection failed").trim().to_string();
+            if message.contains("Permission denied") {
+                message.push_str(" (only key-based logins work, e.g. with ssh-agent)");
+            }
+            return Err(PortManagerError::CommandFailed { command: format!("ssh {}", self.host), message });
+        }
+        Ok(output)
+    }
+    
+    /// Look up the user, resources and command line of each process with
+    /// one `ps` call
+    fn lookup_processes(&self, processes: &mut [PortProcess]) -> Result<()> {
+        let mut pids: Vec<String> = processes.iter().map(|p| p.pid.to_string()).collect();
+        pids.sort();
+        pids.dedup();
+        if pids.is_empty() {
+            return Ok(());
+        }
+        
+        let pids = pids.join(",");
+        let output = self.run(&["ps", "-o", "pid=,user=,rss=,pcpu=,args=", "-p", &pids])?;
+        let found = parse_ps_output(&String::from_utf8_lossy(&output.stdout));
+        for process in processes {
+            if let Some(found) = found.get(&process.pid) {
+                process.user = found.user.clone();
+                process.memory = found.memory;
+                process.cpu_usage = found.cpu_usage;
+                process.command = found.command.clone();
+            }
+        }
+        Ok(())
+    }
+    
+    /// lsof's output on the host, with `args` selecting the sockets. Several
+    /// `-i` selectors are ORed, so each caller passes its own. lsof exits
+    /// non-zero without printing anything when no socket matches, which
+    /// isn't an error.
+    fn lsof(&self, args: &[&str]) -> Result<String> {
+        let output = self.run(&[&["lsof", "-P", "-n"], args].concat())?;
+        if !output.status.success() && !output.stderr.is_empty() {
+            return Err(PortManagerError::CommandFailed {
+                command: format!("lsof on {}", self.host),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl Backend for SshBackend {
+    fn name(&self) -> &'static str {
+        "lsof over ssh"
+    }
+    
+    fn is_available(&self) -> bool {
+        command_exists("ssh", "-V")
+    }
+    
+    fn discover(&self, mode: DiscoveryMode, _system: &System) -> Result<Vec<PortProcess>> {
+        let mut processes = parse_lsof_rows(&self.raw_output(mode)?, PortProcess::without_lookups);
+        self.lookup_processes(&mut processes)?;
+        Ok(processes)
+    }
+    
+    fn raw_output(&self, mode: DiscoveryMode) -> Result<String> {
+        self.lsof(&["-iTCP", lsof_state(mode)])
+    }
+    
+    fn holds_port(&self, pid: u32, port: u16, mode: DiscoveryMode, _system: &System) -> Result<bool> {
+        // `-a` ANDs the PID and socket selections
+        let output = self.lsof(&["-a", "-p", &pid.to_string(), &format!("-iTCP:{}", port), lsof_state(mode)])?;
+        Ok(!output.is_empty())
+    }
+    
+    fn is_running(&self, pid: u32) -> Result<Option<bool>> {
+        // ps exits non-zero and prints nothing for a PID that's gone
+        let output = self.run(&["ps", "-o", "pid=", "-p", &pid.to_string()])?;
+        Ok(Some(output.status.success() && !output.stdout.is_empty()))
+    }
+    
+    fn connection_counts(&self) -> Result<HashMap<u16, u64>> {
+        Ok(count_established(&self.lsof(&["-iTCP", "-sTCP:ESTABLISHED"])?))
+    }
+    
+    fn is_local(&self) -> bool {
+        false
+    }
+    
+    fn host(&self) -> Option<&str> {
+        Some(&self.host)
+    }
+    
+    /// Signal the process with `kill` on the host. The local protections
+    /// don't apply there, except that PID 0 is never a process.
+    fn send_signal(&self, pid: u32, signal: &str) -> Result<()> {
+        if pid == 0 {
+            return Err(PortManagerError::OrphanedSocket);
+        }
+        let output = self.run(&["kill", &format!("-{}", signal), &pid.to_string()])?;
+        kill_result(&output, pid, signal)
+    }
+}
+
+/// What `ps` on another machine says about one of its processes
+struct RemoteProcess {
+    user: String,
+    /// Resident memory in bytes
+    memory: u64,
+    cpu_usage: f32,
+    command: String,
+}
+
+/// Map each PID in `ps -o pid=,user=,rss=,pcpu=,args=` output to what ps
+/// found, e.g. `  812 www-data  5120  0.3 nginx: worker process`
+fn parse_ps_output(output: &str) -> HashMap<u32, RemoteProcess> {
+    output.lines()
+        .filter_map(|line| {
+            // The command line is everything after the fourth field, spaces and all
+            let mut rest = line;
+            let mut field = || {
+                let (field, after) = rest.trim_start().split_once(char::is_whitespace)?;
+                rest = after;
+                Some(field)
+            };
+            let pid = field()?.parse().ok()?;
+            let user = field()?.to_string();
+            // RSS is in KiB
+            let memory = field()?.parse::<u64>().ok()? * 1024;
+            let cpu_usage = field()?.parse().ok()?;
+            let command = rest.trim().to_string();
+            Some((pid, RemoteProcess { user, memory, cpu_usage, command }))
+        })
+        .collect()
+}
+
+/// Discovery for Linux systems without a usable lsof. Sockets are read from
+/// /proc/net/tcp, which is world-readable, and `fuser` resolves each port to
+/// PIDs because scanning /proc/<pid>/fd needs permissions we may not have.
+#[cfg(target_os = "linux")]
+pub struct FuserBackend;
+
+#[cfg(target_os = "linux")]
+impl Backend for FuserBackend {
+    fn name(&self) -> &'static str {
+        "fuser"
+    }
+    
+    fn is_available(&self) -> bool {
+        command_exists("fuser", "-V")
+    }
+    
+    fn discover(&self, mode: DiscoveryMode, system: &System) -> Result<Vec<PortProcess>> {
+        let mut sockets = Vec::new();
+        for (path, family) in [("/proc/net/tcp", AddressFamily::Ipv4), ("/proc/net/tcp6", AddressFamily::Ipv6)] {
+            if let Ok(contents) = std::fs::read_to_string(path) {
+                sockets.extend(
+                    parse_proc_net_tcp(&contents, mode)
+                        .into_iter()
+                        .map(|(local, port, remote, state, inode, queues, uid)| (port, local, family, remote, state, inode, queues, uid)),
+                );
+            }
+        }
+        
+        // Sockets on the same port, address and family resolve to the same PIDs.
+        // fuser can't tell addresses on one port apart, so a port bound to
+        // several addresses by different processes is attributed to all of them.
+        // Of several sockets sharing a port (e.g. SO_REUSEPORT), the first
+        // inode is kept.
+        sockets.sort();
+        sockets.dedup_by(|a, b| (a.0, &a.1, a.2, &a.3) == (b.0, &b.1, b.2, &b.3));
+        
+        // fuser can only see the sockets of other users' processes as root
+        use std::os::unix::fs::MetadataExt;
+        let own_uid = fs::metadata("/proc/self").map(|meta| meta.uid()).ok();
+        
+        let mut port_processes = Vec::new();
+        for (port, local, family, remote, state, inode, (send_queue, recv_queue), uid) in sockets {
+            // fuser takes `local_port[,remote_host[,remote_port]]`
+            let query = match &remote {
+                Some((host, remote_port)) => format!("{},{},{}", port, host, remote_port),
+                None => port.to_string(),
+            };
+            
+            let family_flag = match family {
+                AddressFamily::Ipv4 => "-4",
+                AddressFamily::Ipv6 => "-6",
+            };
+            
+            let output = Command::new("fuser")
+                .args([family_flag, "-n", "tcp", &query])
+                .output()
+                .map_err(|e| PortManagerError::spawn("fuser", e))?;
+            
+            // fuser prints the PIDs on stdout and the `port/tcp:` labels on stderr
+            let pids = parse_fuser_output(&String::from_utf8_lossy(&output.stdout));
+            // A listener no process holds, though fuser could have seen it.
+            // Connections without one, like TIME_WAIT, are normal.
+            if pids.is_empty() && state == TcpState::Listen && own_uid.is_some_and(|own| own == 0 || own == uid) {
+                let mut process = PortProcess::orphaned(port, local.clone(), family);
+                process.socket_inode = Some(inode);
+                process.send_queue = Some(send_queue);
+                process.recv_queue = Some(recv_queue);
+                port_processes.push(process);
+            }
+            for pid in pids {
+                let name = system.process(sysinfo::Pid::from_u32(pid))
+                    .map(|info| info.name().to_string())
+                    .unwrap_or_default();
+                let remote = remote.as_ref().map(|(host, remote_port)| format_endpoint(host, *remote_port));
+                let mut process = PortProcess::new(pid, name, port, local.clone(), family, remote, system);
+                process.state = state;
+                process.socket_inode = Some(inode);
+                process.send_queue = Some(send_queue);
+                process.recv_queue = Some(recv_queue);
+                port_processes.push(process);
+            }
+        }
+        
+        Ok(port_processes)
+    }
+    
+    fn connection_counts(&self) -> Result<HashMap<u16, u64>> {
+        let mut counts = HashMap::new();
+        for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+            if let Ok(contents) = std::fs::read_to_string(path) {
+                for (_, port, _, state, _, _, _) in parse_proc_net_tcp(&contents, DiscoveryMode::Connections) {
+                    if state == TcpState::Established {
+                        *counts.entry(port).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        Ok(counts)
+    }
+    
+    /// The socket tables fuser is asked about. Its own output is one PID
+    /// list per socket, so it isn't repeated here.
+    fn raw_output(&self, _mode: DiscoveryMode) -> Result<String> {
+        let mut output = String::new();
+        for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+            let contents = fs::read_to_string(path).unwrap_or_else(|e| format!("(unreadable: {})\n", e));
+            output.push_str(&format!("{}:\n{}", path, contents));
+        }
+        Ok(output)
+    }
+}
+
+/// Local address, local port, (for connections) remote endpoint, state, inode,
+/// (send, receive) queue sizes and owning user ID of a socket
+#[cfg(target_os = "linux")]
+type ProcSocket = (String, u16, Option<(String, u16)>, TcpState, u64, (u64, u64), u32);
+
+/// Parse /proc/net/tcp(6) into local addresses and ports (and remote endpoints
+/// for connections), states, inodes, queue sizes and owners of sockets
+/// matching the discovery mode.
+/// Unspecified addresses are shown as `*`, like lsof does.
+#[cfg(target_os = "linux")]
+fn parse_proc_net_tcp(contents: &str, mode: DiscoveryMode) -> Vec<ProcSocket> {
+    let mut sockets = Vec::new();
+    
+    // Skip the header line
+    for line in contents.lines().skip(1) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 10 {
+            continue;
+        }
+        let Some(state) = TcpState::from_kernel_code(parts[3]) else {
+            continue;
+        };
+        if (state == TcpState::Listen) != (mode == DiscoveryMode::Listeners) {
+            continue;
+        }
+        
+        let Some((local_ip, local_port)) = parse_hex_endpoint(parts[1]) else {
+            continue;
+        };
+        let local = match local_ip.as_str() {
+            "0.0.0.0" | "::" => "*".to_string(),
+            ip if ip.contains(':') => format!("[{}]", ip),
+            ip => ip.to_string(),
+        };
+        
+        // A listener's remote side is all zeroes, so only keep it for connections
+        let remote = match mode {
+            DiscoveryMode::Listeners => None,
+            DiscoveryMode::Connections => parse_hex_endpoint(parts[2]),
+        };
+        
+        let uid = parts[7].parse().unwrap_or_default();
+        let inode = parts[9].parse().unwrap_or_default();
+        
+        // tx_queue:rx_queue in hex, the Send-Q and Recv-Q columns of ss
+        let queues = parts[4].split_once(':')
+            .and_then(|(tx, rx)| Some((u64::from_str_radix(tx, 16).ok()?, u64::from_str_radix(rx, 16).ok()?)))
+            .unwrap_or_default();
+        
+        sockets.push((local, local_port, remote, state, inode, queues, uid));
+    }
+    
+    sockets
+}
+
+/// Parse a `0100007F:1F90` style address into an IP string and port
+#[cfg(target_os = "linux")]
+fn parse_hex_endpoint(endpoint: &str) -> Option<(String, u16)> {
+    let (addr_hex, port_hex) = endpoint.split_once(':')?;
+    let port = u16::from_str_radix(port_hex, 16).ok()?;
+    
+    // The kernel prints each 32-bit word of the address in host (little-endian) order
+    if addr_hex.len() % 8 != 0 {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    for word in addr_hex.as_bytes().chunks(8) {
+        let word = u32::from_str_radix(std::str::from_utf8(word).ok()?, 16).ok()?;
+        bytes.extend(word.to_le_bytes());
+    }
+    
+    let ip = match bytes.len() {
+        4 => std::net::Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string(),
+        16 => {
+            let octets: [u8; 16] = bytes.try_into().ok()?;
+            std::net::Ipv6Addr::from(octets).to_string()
+        }
+        _ => return None,
+    };
+    
+    Some((ip, port))
+}
+
+/// Format an IP and port the way lsof does
+#[cfg(target_os = "linux")]
+fn format_endpoint(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Parse the PIDs printed by `fuser -n tcp`. PIDs may carry a trailing
+/// access letter (e.g. `1234e`), which is stripped.
+#[cfg(target_os = "linux")]
+fn parse_fuser_output(output: &str) -> Vec<u32> {
+    output
+        .split_whitespace()
+        .filter_map(|token| token.trim_end_matches(|c: char| c.is_ascii_alphabetic()).parse().ok())
+        .collect()
+}
+
+/// Made-up processes for trying the UI without real listeners, e.g. for
+/// screenshots or on platforms without lsof. Nothing is run or signalled.
+pub struct DemoBackend;
+
+/// Listeners shown in demo mode: PID, name, port, local address, user, command
+/// and minutes since start
+const DEMO_LISTENERS: &[(u32, &str, u16, &str, &str, &str, u64)] = &[
+    (4242, "nginx", 80, "*", "root", "nginx: master process /usr/sbin/nginx", 7 * 24 * 60),
+    (4243, "nginx", 443, "*", "root", "nginx: master process /usr/sbin/nginx", 7 * 24 * 60),
+    (5120, "node", 3000, "127.0.0.1", "dev", "node /home/dev/app/node_modules/.bin/next dev", 2),
+    (5377, "python3", 8000, "127.0.0.1", "dev", "python3 -m http.server 8000", 45),
+    (6021, "java", 8080, "*", "dev", "java -Xmx2g -jar build/libs/api-0.3.1-SNAPSHOT.jar --server.port=8080", 180),
+    (812, "postgres", 5432, "127.0.0.1", "postgres", "/usr/lib/postgresql/16/bin/postgres -D /var/lib/postgresql/16/main", 3 * 24 * 60),
+    (907, "redis-server", 6379, "127.0.0.1", "redis", "/usr/bin/redis-server 127.0.0.1:6379", 3 * 24 * 60),
+];
+
+/// Connections shown in demo mode: PID, name, local port, remote endpoint and state
+const DEMO_CONNECTIONS: &[(u32, &str, u16, &str, TcpState)] = &[
+    (5120, "node", 3000, "127.0.0.1:52114", TcpState::Established),
+    (6021, "java", 8080, "10.0.0.12:49822", TcpState::Established),
+    (6021, "java", 8080, "10.0.0.15:50310", TcpState::CloseWait),
+    (812, "postgres", 5432, "127.0.0.1:41876", TcpState::Established),
+];
+
+impl DemoBackend {
+    /// Build a process without looking anything up in the process table
+    fn process(pid: u32, name: &str, port: u16, local_addr: &str, remote: Option<String>) -> PortProcess {
+        let listener = DEMO_LISTENERS.iter().find(|listener| listener.0 == pid);
+        let (user, command, minutes) = listener
+            .map(|&(_, _, _, _, user, command, minutes)| (user, command, minutes))
+            .unwrap_or_default();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        
+        PortProcess {
+            pid,
+            name: name.to_string(),
+            port,
+            local_addr: local_addr.to_string(),
+            family: AddressFamily::Ipv4,
+            protocol: Protocol::Tcp,
+            dual_stack: false,
+            command: command.to_string(),
+            user: user.to_string(),
+            state: if remote.is_some() { TcpState::Established } else { TcpState::Listen },
+            remote,
+            // Vary the resources by PID so sorting and top mode have something to show
+            memory: u64::from(pid % 97 + 3) * 1024 * 1024 * 7,
+            cpu_usage: (pid % 23) as f32 / 2.0,
+            threads: Some((pid % 31 + 1) as usize),
+            exited: false,
+            orphaned: false,
+            stopped: false,
+            start_time: Some(now.saturating_sub(minutes * 60)),
+            sysinfo_name: name.to_string(),
+            sysinfo_exe: String::new(),
+            sysinfo_command: command.to_string(),
+            net_namespace: None,
+            systemd_unit: None,
+            // Show the container badge on one of them
+            container_id: (name == "redis-server").then(|| "3f2a9c1d7b4e".to_string()),
+            fd: None,
+            socket_inode: None,
+            recv_queue: None,
+            send_queue: None,
+            unresolved_port: None,
+        }
+    }
+}
+
+impl Backend for DemoBackend {
+    fn name(&self) -> &'static str {
+        "demo"
+    }
+    
+    fn is_available(&self) -> bool {
+        true
+    }
+    
+    fn discover(&self, mode: DiscoveryMode, _system: &System) -> Result<Vec<PortProcess>> {
+        Ok(match mode {
+            DiscoveryMode::Listeners => DEMO_LISTENERS
+                .iter()
+                .map(|&(pid, name, port, local, ..)| Self::process(pid, name, port, local, None))
+                .collect(),
+            DiscoveryMode::Connections => DEMO_CONNECTIONS
+                .iter()
+                .map(|&(pid, name, port, remote, state)| PortProcess {
+                    state,
+                    ..Self::process(pid, name, port, "127.0.0.1", Some(remote.to_string()))
+                })
+                .collect(),
+        })
+    }
+    
+    fn connection_counts(&self) -> Result<HashMap<u16, u64>> {
+        let mut counts = HashMap::new();
+        for &(_, _, port, _, state) in DEMO_CONNECTIONS {
+            if state == TcpState::Established {
+                *counts.entry(port).or_insert(0) += 1;
+            }
+        }
+        Ok(counts)
+    }
+    
+    fn raw_output(&self, _mode: DiscoveryMode) -> Result<String> {
+        Ok("(demo data, nothing was discovered)".to_string())
+    }
+    
+    fn is_demo(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    
+    const PROC_NET_TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0BB8 00000000:0000 0A 00000000:00000003 00:00000000 00000000  1000        0 4242 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 4343 1 0000000000000000 100 0 0 10 0
+   2: 0100007F:1F90 0100007F:D431 01 0000000A:00000000 00:00000000 00000000  1000        0 5151 1 0000000000000000 20 4 30 10 -1
+   3: 0100007F:1F91 0100007F:D432 06 00000000:00000000 03:00001770 00000000     0        0 0 3 0000000000000000
+";
+    
+    #[test]
+    fn proc_net_tcp_listeners() {
+        let sockets = parse_proc_net_tcp(PROC_NET_TCP, DiscoveryMode::Listeners);
+        assert_eq!(sockets, [
+            ("*".to_string(), 3000, None, TcpState::Listen, 4242, (0, 3), 1000),
+            ("127.0.0.1".to_string(), 8080, None, TcpState::Listen, 4343, (0, 0), 0),
+        ]);
+    }
+    
+    #[test]
+    fn proc_net_tcp_connections() {
+        let sockets = parse_proc_net_tcp(PROC_NET_TCP, DiscoveryMode::Connections);
+        assert_eq!(sockets, [
+            ("127.0.0.1".to_string(), 8080, Some(("127.0.0.1".to_string(), 54321)), TcpState::Established, 5151, (10, 0), 1000),
+            ("127.0.0.1".to_string(), 8081, Some(("127.0.0.1".to_string(), 54322)), TcpState::TimeWait, 0, (0, 0), 0),
+        ]);
+    }
+    
+    #[test]
+    fn proc_net_tcp6_addresses() {
+        let contents = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:2249 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000 65534        0 159279 1 0000000000000000 100 0 0 10 0
+   1: 00000000000000000000000001000000:0BB8 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 159280 1 0000000000000000 100 0 0 10 0
+   2: 0000000000000000FFFF00000100007F:0BB9 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 159281 1 0000000000000000 100 0 0 10 0
+";
+        let local: Vec<(String, u16)> = parse_proc_net_tcp(contents, DiscoveryMode::Listeners)
+            .into_iter()
+            .map(|(local, port, ..)| (local, port))
+            .collect();
+        assert_eq!(local, [
+            ("*".to_string(), 8777),
+            ("[::1]".to_string(), 3000),
+            ("[::ffff:127.0.0.1]".to_string(), 3001),
+        ]);
+    }
+    
+    #[test]
+    fn proc_net_tcp_skips_malformed_lines() {
+        let contents = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0BB8 00000000:0000 0A
+   1: ZZZZZZZZ:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1 1 0000000000000000 100 0 0 10 0
+   2: 00000000:GGGG 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 2 1 0000000000000000 100 0 0 10 0
+   3: 00000000:0BB8 00000000:0000 FF 00000000:00000000 00:00000000 00000000  1000        0 3 1 0000000000000000 100 0 0 10 0
+   4: 000000:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 4 1 0000000000000000 100 0 0 10 0
+
+   5: 00000000:0BBA 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 5 1 0000000000000000 100 0 0 10 0
+";
+        let sockets = parse_proc_net_tcp(contents, DiscoveryMode::Listeners);
+        assert_eq!(sockets.len(), 1);
+        assert_eq!((sockets[0].1, sockets[0].4), (3002, 5));
+    }
+    
+    #[test]
+    fn fuser_output_pids() {
+        assert_eq!(parse_fuser_output(" 4242 4343"), [4242, 4343]);
+        assert_eq!(parse_fuser_output(" 1234e 5678f\n"), [1234, 5678]);
+        // fuser writes the port to stderr, which may end up in the same output
+        assert_eq!(parse_fuser_output("3000/tcp:  4242e"), [4242]);
+        assert!(parse_fuser_output("").is_empty());
+        assert!(parse_fuser_output("3000/tcp:\n").is_empty());
+    }
+    
+    #[test]
+    fn hex_endpoints() {
+        assert_eq!(parse_hex_endpoint("0100007F:1F90"), Some(("127.0.0.1".to_string(), 8080)));
+        assert_eq!(parse_hex_endpoint("0101A8C0:0016"), Some(("192.168.1.1".to_string(), 22)));
+        assert_eq!(parse_hex_endpoint("0100007F"), None);
+        assert_eq!(parse_hex_endpoint("00007F:1F90"), None);
+        assert_eq!(parse_hex_endpoint("0100007F:10000"), None);
+    }
+    
+    /// Rows as `lsof -iTCP -P -n` prints them, with the FD and DEVICE columns
+    const LSOF_OUTPUT: &str = "COMMAND   PID  USER   FD   TYPE DEVICE SIZE/OFF NODE NAME
+node     4242 alice   23u  IPv4 118723      0t0  TCP *:3000 (LISTEN)
+java     4343 bob    112u  IPv6 219901      0t0  TCP [::1]:8080->[::1]:52114 (ESTABLISHED)
+";
+    
+    fn parse_lsof(output: &str) -> Vec<PortProcess> {
+        parse_lsof_rows(output, |pid, name, port, host, family, remote| {
+            PortProcess::without_lookups(pid, name, port, host, family, remote)
+        })
+    }
+    
+    #[test]
+    fn lsof_rows_keep_fd_and_device() {
+        let processes = parse_lsof(LSOF_OUTPUT);
+        assert_eq!(processes.len(), 2);
+        
+        let node = &processes[0];
+        assert_eq!((node.pid, node.name.as_str(), node.port, node.local_addr.as_str()), (4242, "node", 3000, "*"));
+        assert_eq!(node.fd.as_deref(), Some("23u"));
+        assert_eq!(node.socket_inode, Some(118723));
+        assert_eq!((node.user.as_str(), node.state), ("alice", TcpState::Listen));
+        
+        let java = &processes[1];
+        assert_eq!((java.family, java.remote.as_deref()), (AddressFamily::Ipv6, Some("[::1]:52114")));
+        assert_eq!(java.fd.as_deref(), Some("112u"));
+        assert_eq!(java.socket_inode, Some(219901));
+        assert_eq!(java.state, TcpState::Established);
+    }
+    
+    #[test]
+    fn lsof_rows_record_their_protocol() {
+        let output = "COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME
+node 4242 alice 23u IPv4 118723 0t0 TCP *:3000 (LISTEN)
+node 4242 alice 24u IPv4 118724 0t0 UDP *:5353
+";
+        let protocols: Vec<Protocol> = parse_lsof(output).iter().map(|p| p.protocol).collect();
+        assert_eq!(protocols, [Protocol::Tcp, Protocol::Udp]);
+    }
+    
+    #[test]
+    fn lsof_rows_without_a_numeric_device_have_no_inode() {
+        let output = "COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME
+node 4242 alice 23u IPv4 0xa1b2c3d4e5f60718 0t0 TCP *:3000 (LISTEN)
+";
+        let processes = parse_lsof(output);
+        assert_eq!(processes[0].fd.as_deref(), Some("23u"));
+        assert_eq!(processes[0].socket_inode, None);
+    }
+    
+    #[test]
+    fn proc_net_tcp_queue_columns() {
+        let contents = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0BB8 00000000:0000 0A 0000001A:00000FFF 00:00000000 00000000  1000        0 1 1 0000000000000000 100 0 0 10 0
+   1: 00000000:0BB9 00000000:0000 0A ZZZZZZZZ:00000001 00:00000000 00000000  1000        0 2 1 0000000000000000 100 0 0 10 0
+   2: 00000000:0BBA 00000000:0000 0A 00000000 00:00000000 00000000  1000        0 3 1 0000000000000000 100 0 0 10 0
+";
+        let queues: Vec<(u16, (u64, u64))> = parse_proc_net_tcp(contents, DiscoveryMode::Listeners)
+            .into_iter()
+            .map(|(_, port, _, _, _, queues, _)| (port, queues))
+            .collect();
+        // Unreadable queues count as empty rather than dropping the socket
+        assert_eq!(queues, [(3000, (26, 4095)), (3001, (0, 0)), (3002, (0, 0))]);
+    }
+    
+    #[test]
+    fn services_map_tcp_names_and_aliases() {
+        let services = parse_services("# Network services
+http            80/tcp          www             # WorldWideWeb HTTP
+http            80/udp
+https           443/tcp
+domain          53/udp
+postgresql      5432/tcp        postgres
+broken          99x/tcp
+lonely
+");
+        assert_eq!(services.get("http"), Some(&80));
+        assert_eq!(services.get("www"), Some(&80));
+        assert_eq!(services.get("https"), Some(&443));
+        assert_eq!(services.get("postgres"), Some(&5432));
+        assert_eq!(services.get("domain"), None);
+        assert_eq!(services.get("broken"), None);
+        assert_eq!(services.len(), 5);
+    }
+    
+    #[test]
+    fn lsof_rows_with_port_names_are_resolved_or_kept() {
+        let processes = parse_lsof("COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME
+node 4242 alice 23u IPv4 118723 0t0 TCP *:no-such-service (LISTEN)
+node 4242 alice 24u IPv4 118724 0t0 TCP *:99999 (LISTEN)
+java 4343 bob 25u IPv4 118725 0t0 TCP *:8080 (LISTEN)
+nginx 4444 www 26u IPv4 118726 0t0 TCP *:http (LISTEN)
+");
+        let ports: Vec<(u16, Option<&str>)> = processes.iter().map(|p| (p.port, p.unresolved_port.as_deref())).collect();
+        // Service names are looked up in /etc/services
+        assert_eq!(ports, [(0, Some("no-such-service")), (0, Some("99999")), (8080, None), (80, None)]);
+        assert_eq!(processes[0].port_text(), "?");
+        assert_eq!(processes[2].port_text(), "8080");
+    }
+    
+    /// A directory holding a fake `ssh` that answers like a host would from
+    /// fixtures, logging each remote command it's given
+    struct FakeSsh(PathBuf);
+    
+    impl FakeSsh {
+        fn new(name: &str) -> Self {
+            use std::os::unix::fs::PermissionsExt;
+            
+            let dir = std::env::temp_dir().join(format!("port-manager-ssh-test-{}-{}", std::process::id(), name));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("lsof.txt"), LSOF_OUTPUT).unwrap();
+            let script = format!(r#"#!/bin/sh
+dir='{}'
+host="$6"
+echo "$7" >> "$dir/commands"
+if [ "$host" = locked ]; then
+    echo 'Warning: Permanently added the host key' >&2
+    echo 'alice@locked: Permission denied (publickey).' >&2
+    exit 255
+fi
+case "$7" in
+    "'lsof'"*) cat "$dir/lsof.txt" ;;
+    "'ps' '-o' 'pid=,user=,rss=,pcpu=,args='"*) echo ' 4242 alice  5120  1.5 node server.js --port 3000' ;;
+    "'kill'"*) ;;
+esac
+"#, dir.display());
+            let ssh = dir.join("ssh");
+            fs::write(&ssh, script).unwrap();
+            fs::set_permissions(&ssh, fs::Permissions::from_mode(0o755)).unwrap();
+            Self(dir)
+        }
+        
+        fn backend(&self, host: &str) -> SshBackend {
+            SshBackend { host: host.to_string(), ssh: self.0.join("ssh") }
+        }
+        
+        fn commands(&self) -> Vec<String> {
+            fs::read_to_string(self.0.join("commands")).unwrap_or_default().lines().map(str::to_string).collect()
+        }
+    }
+    
+    impl Drop for FakeSsh {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+    
+    #[test]
+    fn remote_output_is_parsed_here() {
+        let ssh = FakeSsh::new("discover");
+        let backend = ssh.backend("deploy@staging");
+        let processes = backend.discover(DiscoveryMode::Listeners, &System::new()).unwrap();
+        assert_eq!(processes.len(), 2);
+        let node = &processes[0];
+        assert_eq!((node.pid, node.port, node.user.as_str()), (4242, 3000, "alice"));
+        assert_eq!((node.memory, node.cpu_usage, node.command.as_str()), (5120 * 1024, 1.5, "node server.js --port 3000"));
+        // ps didn't list it, so lsof's user is kept
+        assert_eq!((processes[1].user.as_str(), processes[1].command.as_str()), ("bob", ""));
+        assert_eq!(ssh.commands(), [
+            "'lsof' '-P' '-n' '-iTCP' '-sTCP:LISTEN'",
+            "'ps' '-o' 'pid=,user=,rss=,pcpu=,args=' '-p' '4242,4343'",
+        ]);
+        assert_eq!(backend.host(), Some("deploy@staging"));
+        assert!(!backend.is_local());
+    }
+    
+    #[test]
+    fn remote_kills_run_over_ssh() {
+        let ssh = FakeSsh::new("kill");
+        let backend = ssh.backend("deploy@staging");
+        backend.send_signal(4242, "TERM").unwrap();
+        assert!(matches!(backend.send_signal(0, "TERM"), Err(PortManagerError::OrphanedSocket)));
+        assert_eq!(ssh.commands(), ["'kill' '-TERM' '4242'"]);
+    }
+    
+    #[test]
+    fn ssh_failures_explain_key_only_logins() {
+        let ssh = FakeSsh::new("locked");
+        let Err(e) = ssh.backend("locked").discover(DiscoveryMode::Listeners, &System::new()) else {
+            panic!("a host refusing the login should fail discovery");
+        };
+        assert_eq!(
+            e.to_string(),
+            "ssh locked failed: alice@locked: Permission denied (publickey). (only key-based logins work, e.g. with ssh-agent)",
+        );
+    }
+}
@@ -1,88 +1,2385 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::PathBuf;
-use anyhow::{Result, Context};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::error::{IoContext, PortManagerError, Result};
+use crossterm::event::{KeyCode, KeyModifiers};
 use directories::ProjectDirs;
+use glob::Pattern;
+use regex::Regex;
+
+/// How a filter pattern is compared against process names
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Name contains the pattern
+    #[default]
+    Contains,
+    /// Name is exactly the pattern
+    Exact,
+    /// Name starts with the pattern
+    Prefix,
+    /// Name ends with the pattern
+    Suffix,
+    /// Name matches the pattern as a shell glob, e.g. `com.apple.*`
+    Glob,
+    /// Name matches the pattern as a regular expression
+    Regex,
+}
+
+impl MatchMode {
+    /// Compile a pattern for this mode, failing if it isn't a valid glob or
+    /// regular expression
+    pub fn compile(&self, pattern: &str) -> Result<CompiledPattern> {
+        Ok(match self {
+            MatchMode::Contains => CompiledPattern::Contains(pattern.to_string()),
+            MatchMode::Exact => CompiledPattern::Exact(pattern.to_string()),
+            MatchMode::Prefix => CompiledPattern::Prefix(pattern.to_string()),
+            MatchMode::Suffix => CompiledPattern::Suffix(pattern.to_string()),
+            MatchMode::Glob => CompiledPattern::Glob(
+                Pattern::new(pattern).map_err(|e| PortManagerError::parse("glob pattern", e))?,
+            ),
+            MatchMode::Regex => CompiledPattern::Regex(
+                Regex::new(pattern).map_err(|e| PortManagerError::parse("regular expression", e))?,
+            ),
+        })
+    }
+    
+    /// Compile several patterns into one matcher. Invalid patterns are left
+    /// out, so they never match rather than hiding everything.
+    pub fn compile_all<'a>(&self, patterns: impl IntoIterator<Item = &'a String>) -> NamePatterns {
+        NamePatterns(patterns.into_iter().filter_map(|pattern| self.compile(pattern).ok()).collect())
+    }
+    
+    /// Check that a pattern is valid for this mode
+    pub fn validate(&self, pattern: &str) -> Result<()> {
+        self.compile(pattern).map(|_| ())
+    }
+    
+    /// Short name for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchMode::Contains => "contains",
+            MatchMode::Exact => "exact",
+            MatchMode::Prefix => "prefix",
+            MatchMode::Suffix => "suffix",
+            MatchMode::Glob => "glob",
+            MatchMode::Regex => "regex",
+        }
+    }
+    
+    /// The next mode, used to cycle through modes in the UI
+    pub fn next(&self) -> Self {
+        match self {
+            MatchMode::Contains => MatchMode::Exact,
+            MatchMode::Exact => MatchMode::Prefix,
+            MatchMode::Prefix => MatchMode::Suffix,
+            MatchMode::Suffix => MatchMode::Glob,
+            MatchMode::Glob => MatchMode::Regex,
+            MatchMode::Regex => MatchMode::Contains,
+        }
+    }
+}
+
+/// A pattern compiled for a match mode, so globs and regexes are parsed once
+/// rather than for every process they're checked against
+#[derive(Debug, Clone)]
+pub enum CompiledPattern {
+    Contains(String),
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+    Glob(Pattern),
+    Regex(Regex),
+}
+
+impl CompiledPattern {
+    /// Check whether a process name matches
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            CompiledPattern::Contains(pattern) => name.contains(pattern.as_str()),
+            CompiledPattern::Exact(pattern) => name == pattern,
+            CompiledPattern::Prefix(pattern) => name.starts_with(pattern.as_str()),
+            CompiledPattern::Suffix(pattern) => name.ends_with(pattern.as_str()),
+            CompiledPattern::Glob(glob) => glob.matches(name),
+            CompiledPattern::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+// Regex has no equality, so compiled patterns compare by their source text
+impl PartialEq for CompiledPattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (CompiledPattern::Contains(a), CompiledPattern::Contains(b))
+            | (CompiledPattern::Exact(a), CompiledPattern::Exact(b))
+            | (CompiledPattern::Prefix(a), CompiledPattern::Prefix(b))
+            | (CompiledPattern::Suffix(a), CompiledPattern::Suffix(b)) => a == b,
+            (CompiledPattern::Glob(a), CompiledPattern::Glob(b)) => a == b,
+            (CompiledPattern::Regex(a), CompiledPattern::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+/// Name patterns compiled together, e.g. the names of a preset. A name
+/// matches when any of them does.
+#[derive(Debug, Clone, Default)]
+pub struct NamePatterns(Vec<CompiledPattern>);
+
+impl NamePatterns {
+    /// Check whether a process name matches any pattern
+    pub fn matches(&self, name: &str) -> bool {
+        self.0.iter().any(|pattern| pattern.matches(name))
+    }
+}
+
+/// Process names filtered out of the box, which the quick toggle always covers
+pub const DEFAULT_FILTERS: [&str; 2] = ["Browser", "ControlCE"];
+
+/// A filtered process name with an optional note explaining it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "FilterEntryRepr", into = "FilterEntryRepr")]
+pub struct FilterEntry {
+    /// Pattern matched against process names using the match mode
+    pub pattern: String,
+    /// Why the filter was added, shown in the filter management view
+    pub note: Option<String>,
+    /// Switched on and off by the quick toggle along with the default filters
+    pub toggleable: bool,
+    /// Whether the filter hides anything. Turning it off keeps it in the list.
+    pub enabled: bool,
+    /// The pattern compiled for the config's match mode by
+    /// `Config::compile_patterns`, or `None` while it's invalid in that mode
+    pub matcher: Option<CompiledPattern>,
+}
+
+impl FilterEntry {
+    /// An enabled filter without a note. It doesn't match anything until the
+    /// config it's added to compiles it.
+    pub fn new(pattern: String) -> Self {
+        Self { pattern, note: None, toggleable: false, enabled: true, matcher: None }
+    }
+    
+    /// Whether the quick toggle switches this filter
+    pub fn is_quick_toggle(&self) -> bool {
+        self.toggleable || DEFAULT_FILTERS.contains(&self.pattern.as_str())
+    }
+}
+
+/// How a filter is written in the config file. Enabled filters without a note
+/// stay plain strings, so files from before notes existed keep working.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum FilterEntryRepr {
+    Pattern(String),
+    Annotated {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        toggleable: bool,
+        #[serde(default = "enabled_by_default", skip_serializing_if = "is_true")]
+        enabled: bool,
+    },
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+impl From<FilterEntryRepr> for FilterEntry {
+    fn from(repr: FilterEntryRepr) -> Self {
+        match repr {
+            FilterEntryRepr::Pattern(pattern) => Self::new(pattern),
+            FilterEntryRepr::Annotated { pattern, note, toggleable, enabled } => Self { pattern, note, toggleable, enabled, matcher: None },
+        }
+    }
+}
+
+impl From<FilterEntry> for FilterEntryRepr {
+    fn from(entry: FilterEntry) -> Self {
+        if entry.note.is_none() && !entry.toggleable && entry.enabled {
+            FilterEntryRepr::Pattern(entry.pattern)
+        } else {
+            FilterEntryRepr::Annotated {
+                pattern: entry.pattern,
+                note: entry.note,
+                toggleable: entry.toggleable,
+                enabled: entry.enabled,
+            }
+        }
+    }
+}
+
+/// Column the process table is sorted by
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortColumn {
+    Pid,
+    #[default]
+    Port,
+    Name,
+    Cpu,
+    Memory,
+    Threads,
+    /// How many of the shown sockets the process holds
+    Sockets,
+}
+
+impl SortColumn {
+    /// Short name for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Pid => "PID",
+            SortColumn::Port => "port",
+            SortColumn::Name => "name",
+            SortColumn::Cpu => "CPU",
+            SortColumn::Memory => "memory",
+            SortColumn::Threads => "threads",
+            SortColumn::Sockets => "sockets",
+        }
+    }
+    
+    /// The next column, used to cycle through columns in the UI
+    pub fn next(&self) -> Self {
+        match self {
+            SortColumn::Pid => SortColumn::Port,
+            SortColumn::Port => SortColumn::Name,
+            SortColumn::Name => SortColumn::Cpu,
+            SortColumn::Cpu => SortColumn::Memory,
+            SortColumn::Memory => SortColumn::Threads,
+            SortColumn::Threads => SortColumn::Sockets,
+            SortColumn::Sockets => SortColumn::Pid,
+        }
+    }
+}
+
+/// A column of the process table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableColumn {
+    /// Markers like 🔒 for a privileged port
+    Indicators,
+    Pid,
+    Port,
+    /// Remote endpoint, only shown for connections
+    Remote,
+    /// TCP state, only shown for connections
+    State,
+    Name,
+    Cpu,
+    Memory,
+    Threads,
+    Command,
+}
+
+impl TableColumn {
+    /// Every column in the default order
+    pub const ALL: [TableColumn; 10] = [
+        TableColumn::Indicators,
+        TableColumn::Pid,
+        TableColumn::Port,
+        TableColumn::Remote,
+        TableColumn::State,
+        TableColumn::Name,
+        TableColumn::Cpu,
+        TableColumn::Memory,
+        TableColumn::Threads,
+        TableColumn::Command,
+    ];
+    
+    /// Name used in the `columns` config list
+    pub fn key(&self) -> &'static str {
+        match self {
+            TableColumn::Indicators => "indicators",
+            TableColumn::Pid => "pid",
+            TableColumn::Port => "port",
+            TableColumn::Remote => "remote",
+            TableColumn::State => "state",
+            TableColumn::Name => "name",
+            TableColumn::Cpu => "cpu",
+            TableColumn::Memory => "memory",
+            TableColumn::Threads => "threads",
+            TableColumn::Command => "command",
+        }
+    }
+    
+    /// Column with the given config name, ignoring case
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|column| column.key().eq_ignore_ascii_case(key))
+    }
+    
+    /// Header shown above the column
+    pub fn title(&self) -> &'static str {
+        match self {
+            TableColumn::Indicators => "",
+            TableColumn::Pid => "PID",
+            TableColumn::Port => "Port",
+            TableColumn::Remote => "Remote",
+            TableColumn::State => "State",
+            TableColumn::Name => "Process Name",
+            TableColumn::Cpu => "CPU %",
+            TableColumn::Memory => "Memory",
+            TableColumn::Threads => "Threads",
+            TableColumn::Command => "Command",
+        }
+    }
+    
+    /// Sort column the column's values are sorted by, if any
+    pub fn sort_column(&self) -> Option<SortColumn> {
+        match self {
+            TableColumn::Pid => Some(SortColumn::Pid),
+            TableColumn::Port => Some(SortColumn::Port),
+            TableColumn::Name => Some(SortColumn::Name),
+            TableColumn::Cpu => Some(SortColumn::Cpu),
+            TableColumn::Memory => Some(SortColumn::Memory),
+            TableColumn::Threads => Some(SortColumn::Threads),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with a process that exits while it is being discovered
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitedProcesses {
+    /// Leave it out of the list
+    #[default]
+    Drop,
+    /// Show it, marked as exited
+    Mark,
+}
+
+/// How a kill is confirmed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmStyle {
+    /// A popup over the list with the full command line
+    #[default]
+    Popup,
+    /// A one-line prompt in the footer, keeping the list in view. Kills
+    /// that need a typed confirmation still use the popup.
+    Inline,
+}
+
+/// How the selected row is highlighted
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightStyle {
+    /// Blue background, or inverse video when `NO_COLOR` is set
+    #[default]
+    Auto,
+    /// White on blue
+    Blue,
+    /// Swap the foreground and background colors
+    Inverse,
+    /// Underline the row
+    Underline,
+    /// Only the `>>` marker in front of the row
+    Arrow,
+}
+
+/// How boxes around the views and popups are drawn
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderStyle {
+    /// Lines with rounded corners
+    #[default]
+    Rounded,
+    /// Lines with square corners
+    Plain,
+    /// Double lines
+    Double,
+    /// No borders, only titles
+    None,
+}
+
+/// How rows off the focused port are de-emphasized
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DimStyle {
+    /// Dark gray text
+    #[default]
+    Gray,
+    /// The terminal's faint text, keeping each row's own color
+    Faint,
+}
+
+/// Process list actions whose keys can be changed with `keybindings`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    Up,
+    Down,
+    Search,
+    Mark,
+    Kill,
+    KillNow,
+    Details,
+    Filter,
+    ToggleView,
+    Refresh,
+    RecordCleanup,
+    Quit,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 12] = [
+        KeyAction::Up,
+        KeyAction::Down,
+        KeyAction::Search,
+        KeyAction::Mark,
+        KeyAction::Kill,
+        KeyAction::KillNow,
+        KeyAction::Details,
+        KeyAction::Filter,
+        KeyAction::ToggleView,
+        KeyAction::Refresh,
+        KeyAction::RecordCleanup,
+        KeyAction::Quit,
+    ];
+    
+    /// Keys bound to the action unless the config says otherwise
+    pub fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            KeyAction::Up => &["up"],
+            KeyAction::Down => &["down"],
+            KeyAction::Search => &["/"],
+            KeyAction::Mark => &["space"],
+            KeyAction::Kill => &["k"],
+            KeyAction::KillNow => &["K"],
+            KeyAction::Details => &["i"],
+            KeyAction::Filter => &["f"],
+            KeyAction::ToggleView => &["F"],
+            KeyAction::Refresh => &["r"],
+            KeyAction::RecordCleanup => &["J"],
+            KeyAction::Quit => &["q"],
+        }
+    }
+    
+    /// Description shown in the help line
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeyAction::Up => "Up",
+            KeyAction::Down => "Down",
+            KeyAction::Search => "Search",
+            KeyAction::Mark => "Mark",
+            KeyAction::Kill => "Kill process",
+            KeyAction::KillNow => "Kill without confirming",
+            KeyAction::Details => "Details",
+            KeyAction::Filter => "Filter process",
+            KeyAction::ToggleView => "Manage filters",
+            KeyAction::Refresh => "Refresh",
+            KeyAction::RecordCleanup => "Record cleanup script",
+            KeyAction::Quit => "Quit",
+        }
+    }
+}
+
+/// Keys the process list handles itself, with what they do. Keybindings
+/// can't take them over, or the built-in command would become unreachable.
+pub const RESERVED_KEYS: &[(&str, &str)] = &[
+    ("ctrl-c", "quit"),
+    ("ctrl-z", "undo filter change"),
+    ("ctrl-y", "redo filter change"),
+    ("u", "undo filter change"),
+    ("U", "redo filter change"),
+    ("R", "re-check the selected process"),
+    ("p", "pause refresh"),
+    ("I", "ignore the selected PID"),
+    ("t", "top mode"),
+    ("T", "top key"),
+    ("h", "hide commands"),
+    ("e", "executables only"),
+    ("l", "hide loopback-only"),
+    ("L", "port links"),
+    ("o", "hide recent"),
+    ("v", "cycle presets"),
+    ("A", "age colors"),
+    ("g", "group by address"),
+    ("G", "group by unit"),
+    ("Y", "arm auto-kill"),
+    ("E", "set baseline"),
+    ("Q", "unexpected only"),
+    ("W", "containers/host only"),
+    ("C", "cycle TCP states"),
+    ("N", "kill stats"),
+    ("X", "clean slate"),
+    ("O", "port order"),
+    ("V", "quick toggle filters"),
+    ("n", "next with same name"),
+    ("H", "highlight"),
+    ("D", "focus port"),
+    ("esc", "clear the port focus"),
+    ("left", "scroll commands"),
+    ("right", "scroll commands"),
+    ("P", "profiles"),
+    ("M", "system monitor"),
+    ("x", "export kill script"),
+    ("c", "copy row"),
+    ("w", "share view"),
+    ("b", "diagnostics"),
+    ("z", "suspend"),
+    ("Z", "resume"),
+    ("s", "sort column"),
+    ("S", "sort direction"),
+    ("a", "actions"),
+    ("B", "kill and filter"),
+    ("?", "list every key"),
+];
+
+/// What Enter does in the process list, unless `keybindings` binds it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EnterAction {
+    /// Ask to kill the selected process, like `k`
+    Kill,
+    /// Open the detail popup, like `i`
+    #[default]
+    Details,
+    /// Do nothing
+    Nothing,
+}
+
+impl EnterAction {
+    /// The key action Enter stands in for
+    pub fn action(&self) -> Option<KeyAction> {
+        match self {
+            EnterAction::Kill => Some(KeyAction::Kill),
+            EnterAction::Details => Some(KeyAction::Details),
+            EnterAction::Nothing => None,
+        }
+    }
+}
+
+/// Parse a key like `k`, `K`, `ctrl-r`, `enter`, `space` or `f5`
+pub fn parse_key(key: &str) -> Result<(KeyCode, KeyModifiers)> {
+    let invalid = || PortManagerError::parse("key", format!("{:?}", key));
+    let (modifiers, name) = match key.split_once(['-', '+']) {
+        Some((modifier, name)) if modifier.eq_ignore_ascii_case("ctrl") && !name.is_empty() => (KeyModifiers::CONTROL, name),
+        Some((modifier, name)) if modifier.eq_ignore_ascii_case("alt") && !name.is_empty() => (KeyModifiers::ALT, name),
+        _ => (KeyModifiers::NONE, key),
+    };
+    
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match name.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            lower => lower.strip_prefix('f')
+                .and_then(|n| n.parse().ok())
+                .filter(|n| (1..=12).contains(n))
+                .map(KeyCode::F)
+                .ok_or_else(invalid)?,
+        },
+    };
+    Ok((code, modifiers))
+}
+
+/// How the process list is laid out
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessLayout {
+    /// Cards on narrow terminals, otherwise the table
+    #[default]
+    Auto,
+    /// Always the table
+    Table,
+    /// A multi-line card per process, for narrow terminals like phone SSH clients
+    Cards,
+}
+
+/// Colors used for states like open and firewalled ports or killed and
+/// failed processes, and for age coloring
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Palette {
+    /// Green for good, red for bad
+    #[default]
+    Standard,
+    /// Blue for good, orange for bad, with age tiers also marked by symbol,
+    /// for red-green color blindness
+    Accessible,
+}
+
+impl Palette {
+    /// Name shown in the status footer
+    pub fn label(&self) -> &'static str {
+        match self {
+            Palette::Standard => "standard",
+            Palette::Accessible => "accessible",
+        }
+    }
+}
+
+/// Color for rows whose process has been running at least `min_age_secs`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AgeTier {
+    pub min_age_secs: u64,
+    /// Color name (e.g. `yellow`), palette index or `#rrggbb`
+    pub color: String,
+}
+
+impl AgeTier {
+    fn new(min_age_secs: u64, color: &str) -> Self {
+        Self { min_age_secs, color: color.to_string() }
+    }
+}
+
+/// Process field that the live search matches against
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    Name,
+    Command,
+    Port,
+    User,
+}
+
+impl SearchField {
+    /// Every field, for searching everything
+    pub const ALL: [SearchField; 4] = [SearchField::Name, SearchField::Command, SearchField::Port, SearchField::User];
+    
+    /// Short name for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchField::Name => "name",
+            SearchField::Command => "command",
+            SearchField::Port => "port",
+            SearchField::User => "user",
+        }
+    }
+}
+
+/// Listeners to kill when running with `--auto-kill`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AutoKillRules {
+    /// Kill whatever is listening on these ports
+    pub ports: Vec<u16>,
+    /// Kill listeners whose process name matches (using the filter match mode)
+    pub names: Vec<String>,
+}
+
+impl AutoKillRules {
+    /// A check of whether a listener, given its port and process name,
+    /// matches any rule, with the names compiled once
+    pub fn matcher(&self, match_mode: MatchMode) -> impl Fn(u16, &str) -> bool + '_ {
+        let names = match_mode.compile_all(&self.names);
+        move |port, process_name| self.ports.contains(&port) || names.matches(process_name)
+    }
+    
+    /// Check whether there are no rules at all
+    pub fn is_empty(&self) -> bool {
+        self.ports.is_empty() && self.names.is_empty()
+    }
+}
+
+/// One kill recorded into a cleanup script. A listener matches when it
+/// matches everything the step gives; recorded kills give both.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(default)]
+pub struct CleanupStep {
+    /// Process name, matched with the filter match mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl CleanupStep {
+    /// Check whether a listener matches the step, given the step's name
+    /// compiled, or `None` if that's invalid. A step without a name or port
+    /// matches nothing, rather than everything.
+    fn matches(&self, port: u16, process_name: &str, name: Option<&CompiledPattern>) -> bool {
+        (self.name.is_some() || self.port.is_some())
+            && self.port.is_none_or(|step_port| step_port == port)
+            && (self.name.is_none() || name.is_some_and(|name| name.matches(process_name)))
+    }
+}
+
+/// Kills recorded in the TUI and saved under a name, replayed with
+/// `--run-cleanup` to reset a dev environment in one go
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CleanupScript {
+    pub name: String,
+    pub steps: Vec<CleanupStep>,
+}
+
+impl CleanupScript {
+    /// A check of whether a listener, given its port and process name,
+    /// matches any step, with the step names compiled once
+    pub fn matcher(&self, match_mode: MatchMode) -> impl Fn(u16, &str) -> bool + '_ {
+        let names: Vec<Option<CompiledPattern>> = self.steps.iter()
+            .map(|step| step.name.as_ref().and_then(|name| match_mode.compile(name).ok()))
+            .collect();
+        move |port, process_name| {
+            self.steps.iter().zip(&names).any(|(step, name)| step.matches(port, process_name, name.as_ref()))
+        }
+    }
+}
+
+/// How killing a process on a port is handled, from most to least lenient
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum KillPolicy {
+    /// Kill without asking, even with `confirm_kill` on
+    NeverConfirm,
+    /// Always ask first, even with `confirm_kill` off or with `K`
+    AlwaysConfirm,
+    /// Refuse to kill at all
+    Protected,
+}
+
+/// A kill policy for an inclusive range of ports, e.g.
+/// `{ "ports": [3000, 3999], "policy": "never_confirm" }`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PortPolicy {
+    pub ports: [u16; 2],
+    pub policy: KillPolicy,
+}
+
+/// A listener recorded as expected when the baseline was set
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BaselineEntry {
+    pub name: String,
+    pub port: u16,
+}
+
+/// A PID, or an inclusive range of PIDs, to hide. PIDs are reused once a
+/// process exits, so these suit short-lived setups like test runs.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum PidFilter {
+    Pid(u32),
+    Range([u32; 2]),
+}
+
+impl PidFilter {
+    /// Check whether a PID is covered by this filter
+    pub fn contains(&self, pid: u32) -> bool {
+        match *self {
+            PidFilter::Pid(filtered) => pid == filtered,
+            PidFilter::Range([start, end]) => (start..=end).contains(&pid),
+        }
+    }
+}
+
+/// A condition for hiding a process that can combine name, command, port and
+/// user checks, e.g. `{ "all": [{ "name": "node" }, { "ports": [9000, 65535] }] }`
+/// hides node only on ports from 9000 up. The flat `filtered_process_names`
+/// list is the same as one `name` rule per entry.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterRule {
+    /// Process name matches the pattern using the match mode
+    Name(String),
+    /// Full command line matches the regular expression, whatever the match
+    /// mode, e.g. `--port 300\d`
+    Command(String),
+    /// Port is in the inclusive range
+    Ports([u16; 2]),
+    /// Process runs as this user
+    User(String),
+    /// Every inner rule matches
+    All(Vec<FilterRule>),
+    /// At least one inner rule matches
+    Any(Vec<FilterRule>),
+    /// The inner rule doesn't match
+    Not(Box<FilterRule>),
+}
+
+impl FilterRule {
+    /// Command patterns in this rule that aren't valid regular expressions,
+    /// which never match
+    pub fn invalid_patterns(&self) -> Vec<&str> {
+        match self {
+            FilterRule::Command(pattern) if MatchMode::Regex.validate(pattern).is_err() => vec![pattern.as_str()],
+            FilterRule::All(rules) | FilterRule::Any(rules) => rules.iter().flat_map(FilterRule::invalid_patterns).collect(),
+            FilterRule::Not(rule) => rule.invalid_patterns(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A filter rule with its patterns compiled for the match mode. Invalid
+/// patterns are `None` and never match.
+#[derive(Debug, Clone)]
+enum CompiledRule {
+    Name(Option<CompiledPattern>),
+    Command(Option<CompiledPattern>),
+    Ports([u16; 2]),
+    User(String),
+    All(Vec<CompiledRule>),
+    Any(Vec<CompiledRule>),
+    Not(Box<CompiledRule>),
+}
+
+impl CompiledRule {
+    fn new(rule: &FilterRule, match_mode: MatchMode) -> Self {
+        let compile = |rules: &[FilterRule]| rules.iter().map(|rule| CompiledRule::new(rule, match_mode)).collect();
+        match rule {
+            FilterRule::Name(pattern) => CompiledRule::Name(match_mode.compile(pattern).ok()),
+            FilterRule::Command(pattern) => CompiledRule::Command(MatchMode::Regex.compile(pattern).ok()),
+            FilterRule::Ports(range) => CompiledRule::Ports(*range),
+            FilterRule::User(name) => CompiledRule::User(name.clone()),
+            FilterRule::All(rules) => CompiledRule::All(compile(rules)),
+            FilterRule::Any(rules) => CompiledRule::Any(compile(rules)),
+            FilterRule::Not(rule) => CompiledRule::Not(Box::new(CompiledRule::new(rule, match_mode))),
+        }
+    }
+    
+    /// Check whether a process matches this rule
+    fn matches(&self, process_name: &str, command: &str, port: u16, user: &str) -> bool {
+        let matches = |rule: &CompiledRule| rule.matches(process_name, command, port, user);
+        match self {
+            CompiledRule::Name(pattern) => pattern.as_ref().is_some_and(|pattern| pattern.matches(process_name)),
+            CompiledRule::Command(pattern) => pattern.as_ref().is_some_and(|pattern| pattern.matches(command)),
+            CompiledRule::Ports([start, end]) => (*start..=*end).contains(&port),
+            CompiledRule::User(name) => name == user,
+            CompiledRule::All(rules) => rules.iter().all(matches),
+            CompiledRule::Any(rules) => rules.iter().any(matches),
+            CompiledRule::Not(rule) => !matches(rule),
+        }
+    }
+}
+
+/// The settings that decide which processes are shown, saved before each
+/// change so it can be undone
+#[derive(Debug, Clone)]
+pub struct FilterSettings {
+    pub filtered_process_names: Vec<FilterEntry>,
+    pub match_mode: MatchMode,
+    pub min_port: u16,
+    pub max_port: u16,
+}
+
+/// Process name filters written to a standalone file so a team can share
+/// them. PID filters are left out, since PIDs only mean something on the
+/// machine they came from.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterFile {
+    /// How the patterns were matched where they were exported
+    pub match_mode: MatchMode,
+    pub filtered_process_names: Vec<FilterEntry>,
+}
+
+/// What merging a filter file into the config changed
+#[derive(Debug)]
+pub struct FilterImport {
+    /// Filters that weren't in the config yet
+    pub added: usize,
+    /// Filters the config already had
+    pub duplicates: usize,
+    /// Match mode the file was exported with
+    pub match_mode: MatchMode,
+}
+
+/// A named view of the processes a task cares about, shown in place of the
+/// configured port range and filters while it is active
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FilterPreset {
+    /// Shown in the status footer while the preset is active
+    pub name: String,
+    /// Show whatever is on these ports
+    pub ports: Vec<u16>,
+    /// Show processes whose name matches (using the filter match mode)
+    pub names: Vec<String>,
+}
+
+impl FilterPreset {
+    /// A check of whether a process, given its port and name, is part of the
+    /// preset, with the names compiled once
+    pub fn matcher(&self, match_mode: MatchMode) -> impl Fn(u16, &str) -> bool + '_ {
+        let names = match_mode.compile_all(&self.names);
+        move |port, process_name| self.ports.contains(&port) || names.matches(process_name)
+    }
+}
+
+/// A named command that can be run against the selected process
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Action {
+    /// Shown in the actions menu
+    pub name: String,
+    /// Shell command with `{pid}`, `{port}` and `{name}` placeholders
+    pub command: String,
+}
+
+impl Action {
+    /// Fill in the placeholders for a process. The name is shell-quoted since
+    /// process names are not under the user's control.
+    pub fn render(&self, pid: u32, port: u16, process_name: &str) -> String {
+        let quoted_name = format!("'{}'", process_name.replace('\'', r"'\''"));
+        self.command
+            .replace("{pid}", &pid.to_string())
+            .replace("{port}", &port.to_string())
+            .replace("{name}", &quoted_name)
+    }
+}
+
+/// External command whose output is shown as an extra field in the detail
+/// popup, e.g. a script that maps a port to the container serving it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Enrichment {
+    /// Label the output is shown under
+    pub label: String,
+    /// Shell command given `<pid> <port>` on stdin, printing one line
+    pub command: String,
+    /// How long to wait for the command before giving up, in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for Enrichment {
+    fn default() -> Self {
+        Self {
+            label: "Info".to_string(),
+            command: String::new(),
+            timeout_ms: 2000,
+        }
+    }
+}
+
+/// Name of this machine, from /etc/hostname or else the `hostname` command
+pub fn current_hostname() -> Option<String> {
+    let name = fs::read_to_string("/etc/hostname").ok().or_else(|| {
+        let output = Command::new("hostname").output().ok().filter(|output| output.status.success())?;
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    })?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Profile stored in the main `config.json`
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Where the active configuration came from
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConfigSource {
+    /// No file existed, so defaults were written
+    #[default]
+    Defaulted,
+    /// Read from an existing file
+    Loaded,
+    /// Read from the path given with `--config`
+    Override,
+    /// The file didn't parse, so it was backed up and replaced with defaults
+    Recovered,
+}
+
+impl ConfigSource {
+    /// Short description for display
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConfigSource::Defaulted => "created with defaults",
+            ConfigSource::Loaded => "loaded",
+            ConfigSource::Override => "set with --config",
+            ConfigSource::Recovered => "reset to defaults after a parse error",
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Config {
+    /// Name of the profile this config was loaded from
+    #[serde(skip)]
+    profile: String,
+    /// File this config is read from and saved to
+    #[serde(skip)]
+    path: PathBuf,
+    /// How this config was obtained
+    #[serde(skip)]
+    source: ConfigSource,
     /// Minimum port to display (inclusive)
     pub min_port: u16,
     /// Maximum port to display (inclusive)
     pub max_port: u16,
     /// List of process names to filter out
-    pub filtered_process_names: Vec<String>,
+    pub filtered_process_names: Vec<FilterEntry>,
+    /// PIDs and PID ranges to filter out, e.g. `[1234, [5000, 5100]]`
+    pub filtered_pids: Vec<PidFilter>,
+    /// Rules combining name, port and user checks; a process matching any
+    /// of them is hidden along with those the name filters hide
+    pub filter_rules: Vec<FilterRule>,
+    /// `filter_rules` compiled for the match mode by `compile_patterns`
+    #[serde(skip)]
+    compiled_rules: Vec<CompiledRule>,
+    /// How filter entries are matched against process names
+    pub match_mode: MatchMode,
+    /// How long a filter snoozed in the filter view stays off (minutes), or
+    /// 0 to keep it off until restart
+    pub snooze_minutes: u64,
+    /// How long to wait for keyboard input on each loop iteration (milliseconds)
+    pub poll_timeout_ms: u64,
+    /// How often to redraw the screen when nothing has changed (milliseconds)
+    pub redraw_interval_ms: u64,
+    /// How often to refresh the process list, or 0 to only refresh on demand (milliseconds)
+    pub refresh_interval_ms: u64,
+    /// Longest auto-refresh interval reached by backing off while nothing changes (milliseconds)
+    pub refresh_backoff_max_ms: u64,
+    /// Ask for confirmation before killing a process
+    pub confirm_kill: bool,
+    /// Whether kill confirmations are a popup or a prompt in the footer
+    pub confirm_style: ConfirmStyle,
+    /// Kill policies for port ranges, checked in order; the first range
+    /// holding the port wins, and `confirm_kill` decides for the rest
+    pub port_policies: Vec<PortPolicy>,
+    /// Killing more processes than this at once requires typing "yes"
+    pub bulk_kill_threshold: usize,
+    /// Signal sent to kill a process, e.g. "TERM" or "KILL"
+    pub kill_signal: String,
+    /// Write every kill to the system log (syslog or the journal) for auditing
+    pub audit_kills: bool,
+    /// How long non-interactive kills wait for a port to be freed (milliseconds)
+    pub kill_timeout_ms: u64,
+    /// Raise a desktop notification, or ring the terminal bell, when a port
+    /// waited on with `--wait-for` or `--wait-until-free` changes
+    pub notify_on_change: bool,
+    /// Rules applied by `--auto-kill`
+    pub auto_kill: AutoKillRules,
+    /// Names the clean slate kill (`X`) leaves running, matched with the
+    /// filter match mode
+    pub keep_processes: Vec<String>,
+    /// Listeners expected on this machine, set with `E`. Once set, any other
+    /// listener is flagged as unexpected.
+    pub baseline: Vec<BaselineEntry>,
+    /// Kills recorded with `j`, replayed with `--run-cleanup <name>`
+    pub cleanup_scripts: Vec<CleanupScript>,
+    /// Mask command lines, which can contain secrets, e.g. when screen sharing
+    pub hide_commands: bool,
+    /// Show only the executable in the Command column, not the whole command line
+    pub short_commands: bool,
+    /// Show memory in binary units (MiB) instead of decimal units (MB)
+    pub binary_units: bool,
+    /// Show a process listening on a port over both IPv4 and IPv6 as one row
+    pub merge_dual_stack: bool,
+    /// Use Unicode glyphs for row indicators instead of ASCII letters
+    pub use_glyphs: bool,
+    /// How the selected row is highlighted
+    pub highlight_style: HighlightStyle,
+    /// How rows off the focused port are dimmed
+    pub focus_dim_style: DimStyle,
+    /// How boxes around the views and popups are drawn
+    pub border_style: BorderStyle,
+    /// Drop the borders and spacing of the main views to fit more rows
+    pub dense: bool,
+    /// Colors for good and bad states and age tiers
+    pub palette: Palette,
+    /// Make listening ports clickable links to `http://localhost:<port>` in
+    /// terminals that support OSC 8 hyperlinks
+    pub port_links: bool,
+    /// Keys for process list actions, replacing the default keys of each
+    /// action listed, e.g. `{ "kill": ["d"], "up": ["up", "k"] }`. Keys the
+    /// process list handles itself, in `RESERVED_KEYS`, can't be used.
+    pub keybindings: BTreeMap<KeyAction, Vec<String>>,
+    /// What Enter does in the process list when no keybinding uses it
+    pub enter_action: EnterAction,
+    /// Whether processes are shown as a table or as cards
+    pub process_layout: ProcessLayout,
+    /// Commands offered in the actions menu
+    pub actions: Vec<Action>,
+    /// Quick filters cycled through with `v`
+    pub presets: Vec<FilterPreset>,
+    /// Hide processes that only listen on loopback addresses
+    pub hide_loopback_only: bool,
+    /// Show a column with each process's thread count
+    pub show_threads: bool,
+    /// Columns of the process table in order, by name, e.g.
+    /// `["port", "name", "pid", "command"]`. The default layout when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<String>>,
+    /// Most rows of the process list rendered at once, paging with the
+    /// selection, for hosts with hundreds of listeners. Unlimited when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rows: Option<usize>,
+    /// Column the process table is sorted by
+    pub sort_column: SortColumn,
+    /// Sort from highest to lowest instead of lowest to highest
+    pub sort_descending: bool,
+    /// Keep system processes below the user's own, whatever the sort
+    pub system_processes_last: bool,
+    /// Show in the detail popup whether the firewall lets connections reach
+    /// the port. Reading the rules usually needs root.
+    pub check_firewall: bool,
+    /// Look up the hostname of a listener's bind address for the details
+    /// popup. Off by default, since lookups may ask a DNS server.
+    pub reverse_dns: bool,
+    /// Show in the detail popup what a local Kubernetes cluster has to do
+    /// with the port, using the kubeconfig and `kubectl`
+    pub kubernetes: bool,
+    /// Look up the systemd unit of each process, shown in the detail popup
+    /// and used to group the table with `G` (Linux only)
+    pub systemd_units: bool,
+    /// Command run for the process in the detail popup to show extra details
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enrichment: Option<Enrichment>,
+    /// File of `port = name` lines naming the services on a team's ports,
+    /// layered over /etc/services and the built-in names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub services_file: Option<PathBuf>,
+    /// Save the view, selection, search and toggles on exit and restore them
+    /// on the next launch
+    pub restore_session: bool,
+    /// Count kills per process name and port in a local file for the stats
+    /// view. The file is never sent anywhere.
+    pub kill_stats: bool,
+    /// Show start times as timestamps instead of how long ago they were
+    pub absolute_times: bool,
+    /// Color rows by how long their process has been running
+    pub age_coloring: bool,
+    /// Flash a row for one refresh when its CPU usage rises by at least this
+    /// many percentage points since the previous refresh, or 0 to never flash
+    pub cpu_spike_threshold: f32,
+    /// Colors used by age coloring, picking the oldest tier a process has reached
+    pub age_tiers: Vec<AgeTier>,
+    /// Fields the live search matches against
+    pub search_fields: Vec<SearchField>,
+    /// What to do with processes that exit while the list is being built
+    pub exited_processes: ExitedProcesses,
+    /// Show rows whose port lsof gave as a name that couldn't be resolved,
+    /// with `?` as the port, instead of leaving them out
+    pub show_unresolved_ports: bool,
+    /// Machine the config was created on, recorded the first time it's saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Warn at startup when the config was created on another machine, e.g.
+    /// after syncing dotfiles
+    pub warn_on_host_mismatch: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Self {
+        let mut config = Self {
+            profile: DEFAULT_PROFILE.to_string(),
+            path: PathBuf::new(),
+            source: ConfigSource::Defaulted,
             // Default to non-privileged ports (above 1023)
             min_port: 1024,
             // Common maximum for ephemeral ports
             max_port: 49151,
             // Default filtered process names
-            filtered_process_names: vec![
-                "Browser".to_string(),
-                "ControlCE".to_string(),
+            filtered_process_names: DEFAULT_FILTERS.iter()
+                .map(|pattern| FilterEntry::new(pattern.to_string()))
+                .collect(),
+            filtered_pids: Vec::new(),
+            filter_rules: Vec::new(),
+            compiled_rules: Vec::new(),
+            // Substring matching, as before match modes existed
+            match_mode: MatchMode::Contains,
+            snooze_minutes: 15,
+            // Short poll keeps keystrokes responsive
+            poll_timeout_ms: 50,
+            // Idle redraws only need to keep animations ticking
+            redraw_interval_ms: 1000,
+            // Off unless asked for, since each refresh runs discovery again
+            refresh_interval_ms: 0,
+            refresh_backoff_max_ms: 30000,
+            confirm_kill: true,
+            confirm_style: ConfirmStyle::Popup,
+            port_policies: Vec::new(),
+            bulk_kill_threshold: 5,
+            // Same as the original `kill -9`
+            kill_signal: "KILL".to_string(),
+            audit_kills: false,
+            kill_timeout_ms: 3000,
+            notify_on_change: false,
+            // Never kill anything unless the user adds rules
+            auto_kill: AutoKillRules::default(),
+            keep_processes: Vec::new(),
+            baseline: Vec::new(),
+            cleanup_scripts: Vec::new(),
+            hide_commands: false,
+            short_commands: false,
+            binary_units: false,
+            merge_dual_stack: true,
+            // Not every terminal font has the glyphs
+            use_glyphs: false,
+            highlight_style: HighlightStyle::Auto,
+            border_style: BorderStyle::Rounded,
+            dense: false,
+            focus_dim_style: DimStyle::Gray,
+            palette: Palette::Standard,
+            port_links: false,
+            keybindings: BTreeMap::new(),
+            // Opening details is harmless if Enter is pressed out of habit
+            enter_action: EnterAction::Details,
+            process_layout: ProcessLayout::Auto,
+            actions: Vec::new(),
+            presets: Vec::new(),
+            hide_loopback_only: false,
+            // Ascending by port, as before sorting was configurable
+            show_threads: false,
+            columns: None,
+            max_rows: None,
+            sort_column: SortColumn::Port,
+            sort_descending: false,
+            system_processes_last: false,
+            check_firewall: false,
+            reverse_dns: false,
+            kubernetes: false,
+            systemd_units: false,
+            enrichment: None,
+            services_file: None,
+            restore_session: false,
+            kill_stats: false,
+            absolute_times: false,
+            age_coloring: false,
+            // High enough that ordinary request bursts don't flash
+            cpu_spike_threshold: 50.0,
+            // Fresh in green, hours old in yellow, days old in red
+            age_tiers: vec![
+                AgeTier::new(0, "green"),
+                AgeTier::new(60 * 60, "yellow"),
+                AgeTier::new(24 * 60 * 60, "red"),
             ],
-        }
+            search_fields: vec![SearchField::Name, SearchField::Command, SearchField::Port],
+            exited_processes: ExitedProcesses::Drop,
+            show_unresolved_ports: false,
+            hostname: None,
+            warn_on_host_mismatch: false,
+        };
+        config.compile_patterns();
+        config
     }
 }
 
 impl Config {
-    /// Get the config file path
-    fn config_path() -> Result<PathBuf> {
+    /// Get the config directory, which holds the default config, profiles
+    /// and backups
+    pub fn config_dir() -> Result<PathBuf> {
         let proj_dirs = ProjectDirs::from("com", "portmanager", "portmanager")
-            .context("Could not determine config directory")?;
+            .ok_or(PortManagerError::NoConfigDir)?;
         
         let config_dir = proj_dirs.config_dir();
-        fs::create_dir_all(config_dir).context("Failed to create config directory")?;
+        fs::create_dir_all(config_dir).io_context("Failed to create config directory")?;
         
-        Ok(config_dir.join("config.json"))
+        Ok(config_dir.to_path_buf())
     }
     
-    /// Load configuration from disk, or create default if it doesn't exist
-    pub fn load() -> Result<Self> {
-        let config_path = Self::config_path()?;
+    /// Get the directory holding named profiles
+    fn profiles_dir(config_dir: &Path) -> Result<PathBuf> {
+        let profiles_dir = config_dir.join("profiles");
+        fs::create_dir_all(&profiles_dir).io_context("Failed to create profiles directory")?;
+        Ok(profiles_dir)
+    }
+    
+    /// Get the config file path for a profile
+    fn profile_path(config_dir: &Path, profile: &str) -> Result<PathBuf> {
+        if profile == DEFAULT_PROFILE {
+            return Ok(config_dir.join("config.json"));
+        }
         
-        if config_path.exists() {
-            let config_str = fs::read_to_string(&config_path)
-                .context("Failed to read config file")?;
+        // Profile names become file names, so keep them to a single path component
+        if profile.is_empty() || profile.contains(['/', '\\']) || profile.starts_with('.') {
+            return Err(PortManagerError::parse("profile name", format!("{:?}", profile)));
+        }
+        
+        Ok(Self::profiles_dir(config_dir)?.join(format!("{}.json", profile)))
+    }
+    
+    /// List the profiles kept in `config_dir`, starting with the default profile
+    pub fn list_profiles_in(config_dir: &Path) -> Result<Vec<String>> {
+        let mut profiles = Vec::new();
+        
+        for entry in fs::read_dir(Self::profiles_dir(config_dir)?).io_context("Failed to read profiles directory")? {
+            let path = entry.io_context("Failed to read profiles directory")?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    profiles.push(name.to_string());
+                }
+            }
+        }
+        
+        profiles.sort();
+        profiles.insert(0, DEFAULT_PROFILE.to_string());
+        Ok(profiles)
+    }
+    
+    /// Load a profile's configuration from disk, or create default if it doesn't exist.
+    /// With `recover`, a file that doesn't parse is backed up and replaced with defaults.
+    pub fn load(profile: &str, recover: bool) -> Result<Self> {
+        Self::load_in(&Self::config_dir()?, profile, recover)
+    }
+    
+    /// Load a profile kept in `config_dir`, as with [`Config::load`]
+    pub fn load_in(config_dir: &Path, profile: &str, recover: bool) -> Result<Self> {
+        let mut config = Self::load_path(&Self::profile_path(config_dir, profile)?, recover)?;
+        config.profile = profile.to_string();
+        Ok(config)
+    }
+    
+    /// Load configuration from an explicit path given on the command line
+    pub fn load_override(path: &Path, recover: bool) -> Result<Self> {
+        let mut config = Self::load_path(path, recover)?;
+        if config.source != ConfigSource::Recovered {
+            config.source = ConfigSource::Override;
+        }
+        Ok(config)
+    }
+    
+    /// Load configuration from a file, or create default if it doesn't exist
+    fn load_path(config_path: &Path, recover: bool) -> Result<Self> {
+        let mut config = if config_path.exists() {
+            let config_str = fs::read_to_string(config_path)
+                .io_context("Failed to read config file")?;
             
-            serde_json::from_str(&config_str)
-                .context("Failed to parse config file")
+            match serde_json::from_str::<Self>(&config_str) {
+                Ok(mut config) => {
+                    config.validate_keybindings()?;
+                    config.source = ConfigSource::Loaded;
+                    config
+                }
+                Err(e) if recover => {
+                    // Keep the broken file so hand edits aren't lost
+                    let backup = Self::backup_path(config_path);
+                    fs::copy(config_path, &backup)
+                        .io_context(format!("Failed to back up config file to {}", backup.display()))?;
+                    tracing::warn!("Failed to parse {}: {}; backed up to {} and using defaults", config_path.display(), e, backup.display());
+                    Self {
+                        source: ConfigSource::Recovered,
+                        ..Self::default()
+                    }
+                }
+                Err(error) => return Err(PortManagerError::ConfigError { path: config_path.to_path_buf(), error }),
+            }
         } else {
-            let config = Self::default();
+            Self::default()
+        };
+        
+        config.path = config_path.to_path_buf();
+        config.compile_patterns();
+        let invalid = config.invalid_filters();
+        if !invalid.is_empty() {
+            tracing::warn!("Invalid filter pattern(s) in {}, which never match: {}", config_path.display(), invalid.join(", "));
+        }
+        // Saved along with the next change, or right away for a new file
+        if config.hostname.is_none() {
+            config.hostname = current_hostname();
+        }
+        if matches!(config.source, ConfigSource::Defaulted | ConfigSource::Recovered) {
             config.save()?;
-            Ok(config)
         }
+        Ok(config)
+    }
+    
+    /// The machine the config was created on and this machine's name, when
+    /// `warn_on_host_mismatch` is on and they differ
+    pub fn host_mismatch(&self) -> Option<(&str, String)> {
+        let created_on = self.hostname.as_deref().filter(|_| self.warn_on_host_mismatch)?;
+        let current = current_hostname()?;
+        (created_on != current).then_some((created_on, current))
+    }
+    
+    /// Where a config file that failed to parse is backed up, e.g. `config.json.bak`
+    pub fn backup_path(config_path: &Path) -> PathBuf {
+        let mut backup = config_path.as_os_str().to_owned();
+        backup.push(".bak");
+        PathBuf::from(backup)
+    }
+    
+    /// Read this config's file again, e.g. after it was edited by hand
+    pub fn reload(&self) -> Result<Self> {
+        let config_str = fs::read_to_string(&self.path)
+            .io_context("Failed to read config file")?;
+        
+        let mut config: Self = serde_json::from_str(&config_str)
+            .map_err(|error| PortManagerError::ConfigError { path: self.path.clone(), error })?;
+        config.validate_keybindings()?;
+        config.compile_patterns();
+        config.profile = self.profile.clone();
+        config.path = self.path.clone();
+        config.source = match self.source {
+            ConfigSource::Override => ConfigSource::Override,
+            _ => ConfigSource::Loaded,
+        };
+        Ok(config)
+    }
+    
+    /// Name of the profile this config belongs to
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+    
+    /// Path of the file this config is read from and saved to
+    pub fn config_path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// How this config was obtained
+    pub fn source(&self) -> ConfigSource {
+        self.source
     }
     
     /// Save configuration to disk
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path()?;
+        // Serializing plain data can't fail
         let config_str = serde_json::to_string_pretty(self)
-            .context("Failed to serialize config")?;
+            .expect("config serializes to JSON");
         
-        fs::write(&config_path, config_str)
-            .context("Failed to write config file")?;
+        fs::write(&self.path, config_str)
+            .io_context("Failed to write config file")?;
         
         Ok(())
     }
     
+    /// The current filter settings
+    pub fn filter_settings(&self) -> FilterSettings {
+        FilterSettings {
+            filtered_process_names: self.filtered_process_names.clone(),
+            match_mode: self.match_mode,
+            min_port: self.min_port,
+            max_port: self.max_port,
+        }
+    }
+    
+    /// Replace the filter settings, e.g. to undo a change, and save
+    pub fn set_filter_settings(&mut self, settings: FilterSettings) -> Result<()> {
+        self.filtered_process_names = settings.filtered_process_names;
+        self.match_mode = settings.match_mode;
+        self.min_port = settings.min_port;
+        self.max_port = settings.max_port;
+        self.compile_patterns();
+        self.save()
+    }
+    
     /// Add a process name to the filter list
     pub fn add_filtered_process(&mut self, process_name: String) -> Result<()> {
-        if !self.filtered_process_names.contains(&process_name) {
-            self.filtered_process_names.push(process_name);
+        if !self.filtered_process_names.iter().any(|filter| filter.pattern == process_name) {
+            self.filtered_process_names.push(FilterEntry::new(process_name));
+            self.compile_patterns();
             self.save()?;
         }
         Ok(())
     }
     
+    /// Write the process name filters to a standalone file and return how many
+    pub fn export_filters(&self, path: &Path) -> Result<usize> {
+        let file = FilterFile {
+            match_mode: self.match_mode,
+            filtered_process_names: self.filtered_process_names.clone(),
+        };
+        let contents = serde_json::to_string_pretty(&file).expect("filter file serializes to JSON");
+        fs::write(path, contents).io_context(format!("Failed to write {}", path.display()))?;
+        Ok(file.filtered_process_names.len())
+    }
+    
+    /// Merge the filters from a file written by `export_filters`, skipping
+    /// patterns the config already has, and save. Nothing is merged if any
+    /// pattern is invalid under this config's match mode.
+    pub fn import_filters(&mut self, path: &Path) -> Result<FilterImport> {
+        let contents = fs::read_to_string(path).io_context(format!("Failed to read {}", path.display()))?;
+        let file: FilterFile = serde_json::from_str(&contents)
+            .map_err(|e| PortManagerError::parse("filter file", format!("{}: {}", path.display(), e)))?;
+        
+        for filter in &file.filtered_process_names {
+            self.match_mode.validate(&filter.pattern).map_err(|e| {
+                PortManagerError::parse("filter file", format!("{}: pattern {:?}: {}", path.display(), filter.pattern, e))
+            })?;
+        }
+        
+        let mut import = FilterImport { added: 0, duplicates: 0, match_mode: file.match_mode };
+        for filter in file.filtered_process_names {
+            if self.filtered_process_names.iter().any(|existing| existing.pattern == filter.pattern) {
+                import.duplicates += 1;
+            } else {
+                self.filtered_process_names.push(filter);
+                import.added += 1;
+            }
+        }
+        if import.added > 0 {
+            self.compile_patterns();
+            self.save()?;
+        }
+        Ok(import)
+    }
+    
+    /// Compile the filter patterns and rules for the match mode, after they
+    /// or the mode change, so refreshes don't compile them for every process
+    fn compile_patterns(&mut self) {
+        for filter in &mut self.filtered_process_names {
+            filter.matcher = self.match_mode.compile(&filter.pattern).ok();
+        }
+        self.compiled_rules = self.filter_rules.iter().map(|rule| CompiledRule::new(rule, self.match_mode)).collect();
+    }
+    
+    /// Filter patterns that aren't valid in the match mode, so never match
+    pub fn invalid_filters(&self) -> Vec<&str> {
+        self.filtered_process_names.iter()
+            .filter(|filter| filter.matcher.is_none())
+            .map(|filter| filter.pattern.as_str())
+            .chain(self.filter_rules.iter().flat_map(FilterRule::invalid_patterns))
+            .collect()
+    }
+    
+    /// Check whether a process name is hidden by the filter list, skipping
+    /// filter patterns that are snoozed
+    pub fn is_filtered(&self, process_name: &str, snoozed: impl Fn(&str) -> bool) -> bool {
+        self.filtered_process_names
+            .iter()
+            .filter(|filtered| filtered.enabled && !snoozed(&filtered.pattern))
+            .any(|filtered| filtered.matcher.as_ref().is_some_and(|matcher| matcher.matches(process_name)))
+    }
+    
+    /// Kill policy of the first range holding the port, if any
+    pub fn port_policy(&self, port: u16) -> Option<KillPolicy> {
+        self.port_policies.iter()
+            .find(|rule| (rule.ports[0]..=rule.ports[1]).contains(&port))
+            .map(|rule| rule.policy)
+    }
+    
+    /// Replace the baseline with these listeners, returning how many there are
+    pub fn set_baseline(&mut self, listeners: impl IntoIterator<Item = BaselineEntry>) -> Result<usize> {
+        let listeners: BTreeSet<BaselineEntry> = listeners.into_iter().collect();
+        self.baseline = listeners.into_iter().collect();
+        self.save()?;
+        Ok(self.baseline.len())
+    }
+    
+    /// Save a cleanup script, replacing any with the same name
+    pub fn save_cleanup_script(&mut self, script: CleanupScript) -> Result<()> {
+        match self.cleanup_scripts.iter_mut().find(|existing| existing.name == script.name) {
+            Some(existing) => *existing = script,
+            None => self.cleanup_scripts.push(script),
+        }
+        self.save()
+    }
+    
+    /// The cleanup script with this name, if one was saved
+    pub fn cleanup_script(&self, name: &str) -> Option<&CleanupScript> {
+        self.cleanup_scripts.iter().find(|script| script.name == name)
+    }
+    
+    /// Check whether a listener is missing from the baseline. Nothing is
+    /// unexpected until a baseline is set.
+    pub fn is_unexpected(&self, name: &str, port: u16) -> bool {
+        !self.baseline.is_empty() && !self.baseline.iter().any(|entry| entry.port == port && entry.name == name)
+    }
+    
+    /// The keep list for clean slate kills, compiled for the match mode
+    pub fn kept_processes(&self) -> NamePatterns {
+        self.match_mode.compile_all(&self.keep_processes)
+    }
+    
+    /// Check whether a process is hidden by one of the filter rules
+    pub fn is_rule_filtered(&self, process_name: &str, command: &str, port: u16, user: &str) -> bool {
+        self.compiled_rules.iter().any(|rule| rule.matches(process_name, command, port, user))
+    }
+    
+    /// Keys bound to an action, as written in the config
+    pub fn keys_for(&self, action: KeyAction) -> Vec<String> {
+        match self.keybindings.get(&action) {
+            Some(keys) => keys.clone(),
+            None => action.default_keys().iter().map(|key| key.to_string()).collect(),
+        }
+    }
+    
+    /// Action run by a key press, if any. Keys without Ctrl or Alt only
+    /// match presses without them, so `ctrl-k` doesn't trigger `k`. Enter
+    /// falls back to `enter_action` when no keybinding uses it.
+    pub fn key_action(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<KeyAction> {
+        let modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        self.bound_action(code, modifiers).or_else(|| {
+            (code == KeyCode::Enter && modifiers.is_empty())
+                .then(|| self.enter_action.action())
+                .flatten()
+        })
+    }
+    
+    /// Action a keybinding (or default key) binds to a key press
+    fn bound_action(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<KeyAction> {
+        KeyAction::ALL.into_iter().find(|action| {
+            self.keys_for(*action).iter()
+                .filter_map(|key| parse_key(key).ok())
+                .any(|key| key == (code, modifiers))
+        })
+    }
+    
+    /// Whether Enter runs an action through `enter_action` rather than a keybinding
+    pub fn enter_runs(&self, action: KeyAction) -> bool {
+        self.enter_action.action() == Some(action) && self.bound_action(KeyCode::Enter, KeyModifiers::NONE).is_none()
+    }
+    
+    /// Check that every bound key parses, no key is bound to two actions,
+    /// and none takes over a key the process list handles itself
+    fn validate_keybindings(&self) -> Result<()> {
+        let mut bound: Vec<((KeyCode, KeyModifiers), KeyAction)> = Vec::new();
+        for action in KeyAction::ALL {
+            for key in self.keys_for(action) {
+                let parsed = parse_key(&key)?;
+                let reserved = RESERVED_KEYS.iter().find(|(reserved, _)| parse_key(reserved).ok() == Some(parsed));
+                if let Some((_, command)) = reserved {
+                    return Err(PortManagerError::parse(
+                        "keybindings",
+                        format!("\"{}\" for {:?} is already the key for {}; pick another key", key, action, command),
+                    ));
+                }
+                if let Some((_, other)) = bound.iter().find(|(existing, _)| *existing == parsed) {
+                    return Err(PortManagerError::parse(
+                        "keybindings",
+                        format!("\"{}\" is bound to both {:?} and {:?}", key, other, action),
+                    ));
+                }
+                bound.push((parsed, action));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Check whether a PID is hidden by the PID filters
+    pub fn is_pid_filtered(&self, pid: u32) -> bool {
+        self.filtered_pids.iter().any(|filter| filter.contains(pid))
+    }
+    
+    /// Switch to the next match mode
+    pub fn cycle_match_mode(&mut self) -> Result<()> {
+        self.match_mode = self.match_mode.next();
+        self.compile_patterns();
+        self.save()
+    }
+    
+    /// Sort by the next column, starting in ascending order
+    pub fn cycle_sort_column(&mut self) -> Result<()> {
+        // Sorting by a hidden column would look random. The socket count has
+        // no column; group headers and the detail popup show it.
+        let mut shown: Vec<SortColumn> = self.table_columns().iter().filter_map(TableColumn::sort_column).collect();
+        shown.push(SortColumn::Sockets);
+        self.sort_column = self.sort_column.next();
+        while !shown.is_empty() && !shown.contains(&self.sort_column) {
+            self.sort_column = self.sort_column.next();
+        }
+        self.sort_descending = false;
+        self.save()
+    }
+    
+    /// Columns of the process table in order. Unknown names in `columns` are
+    /// skipped, and the default layout is used if none are left. The thread
+    /// count is only part of the default layout with `show_threads`.
+    pub fn table_columns(&self) -> Vec<TableColumn> {
+        let configured: Vec<TableColumn> = self.columns.iter().flatten()
+            .filter_map(|key| TableColumn::from_key(key))
+            .collect();
+        if !configured.is_empty() {
+            return configured;
+        }
+        TableColumn::ALL.into_iter()
+            .filter(|column| *column != TableColumn::Threads || self.show_threads)
+            .collect()
+    }
+    
+    /// Names in `columns` that aren't columns
+    pub fn unknown_columns(&self) -> Vec<&str> {
+        self.columns.iter().flatten()
+            .filter(|key| TableColumn::from_key(key).is_none())
+            .map(String::as_str)
+            .collect()
+    }
+    
+    /// Sort by a column in the given direction
+    pub fn set_sort(&mut self, column: SortColumn, descending: bool) -> Result<()> {
+        self.sort_column = column;
+        self.sort_descending = descending;
+        self.save()
+    }
+    
+    /// Flip the sort direction
+    pub fn toggle_sort_direction(&mut self) -> Result<()> {
+        self.sort_descending = !self.sort_descending;
+        self.save()
+    }
+    
+    /// Move a filter one place up or down the list, returning its new index
+    pub fn move_filtered_process(&mut self, index: usize, up: bool) -> Result<usize> {
+        let target = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|&target| target < self.filtered_process_names.len())
+        };
+        
+        match target {
+            Some(target) if index < self.filtered_process_names.len() => {
+                self.filtered_process_names.swap(index, target);
+                self.save()?;
+                Ok(target)
+            }
+            _ => Ok(index),
+        }
+    }
+    
+    /// Turn a filter off without removing it, or back on, returning whether
+    /// it's now enabled
+    pub fn toggle_filter_enabled(&mut self, index: usize) -> Result<Option<bool>> {
+        let Some(filter) = self.filtered_process_names.get_mut(index) else {
+            return Ok(None);
+        };
+        filter.enabled = !filter.enabled;
+        let enabled = filter.enabled;
+        self.save()?;
+        Ok(Some(enabled))
+    }
+    
+    /// Add a filter to the quick toggle, or take it out, returning whether
+    /// it's now included
+    pub fn toggle_filter_toggleable(&mut self, index: usize) -> Result<Option<bool>> {
+        let Some(filter) = self.filtered_process_names.get_mut(index) else {
+            return Ok(None);
+        };
+        filter.toggleable = !filter.toggleable;
+        let toggleable = filter.is_quick_toggle();
+        self.save()?;
+        Ok(Some(toggleable))
+    }
+    
+    /// Turn the quick toggle filters off if any is on, or else all back on.
+    /// Returns whether they're now enabled, or None if there are none.
+    pub fn toggle_quick_filters(&mut self) -> Result<Option<bool>> {
+        let quick = || self.filtered_process_names.iter().filter(|filter| filter.is_quick_toggle());
+        if quick().next().is_none() {
+            return Ok(None);
+        }
+        let enable = !quick().any(|filter| filter.enabled);
+        for filter in self.filtered_process_names.iter_mut().filter(|filter| filter.is_quick_toggle()) {
+            filter.enabled = enable;
+        }
+        self.save()?;
+        Ok(Some(enable))
+    }
+    
     /// Remove a process name from the filter list
     pub fn remove_filtered_process(&mut self, process_name: &str) -> Result<()> {
-        self.filtered_process_names.retain(|name| name != process_name);
+        self.filtered_process_names.retain(|filter| filter.pattern != process_name);
         self.save()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// A directory of test files, removed along with it
+    struct TestDir(PathBuf);
+    
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+    
+    /// A config file holding `contents` in a directory of its own
+    fn write_temp(contents: &str) -> (PathBuf, TestDir) {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = TestDir(std::env::temp_dir().join(format!(
+            "port-manager-config-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        )));
+        fs::create_dir_all(&dir.0).unwrap();
+        let path = dir.0.join("config.json");
+        fs::write(&path, contents).unwrap();
+        (path, dir)
+    }
+    
+    /// A config loaded from `contents` in a directory of its own
+    fn load_temp(contents: &str) -> (Config, TestDir) {
+        let (path, dir) = write_temp(contents);
+        (Config::load_override(&path, false).unwrap(), dir)
+    }
+    
+    /// The config as it would be read again from its file
+    fn reload(config: &Config) -> Config {
+        Config::load_override(config.config_path(), false).unwrap()
+    }
+    
+    #[test]
+    fn profiles_load_from_their_own_files() {
+        let (_, dir) = write_temp(r#"{ "filtered_process_names": ["node"] }"#);
+        fs::create_dir_all(dir.0.join("profiles")).unwrap();
+        fs::write(dir.0.join("profiles").join("web.json"), r#"{ "filtered_process_names": ["vite"] }"#).unwrap();
+        fs::write(dir.0.join("profiles").join("notes.txt"), "").unwrap();
+        assert_eq!(Config::list_profiles_in(&dir.0).unwrap(), [DEFAULT_PROFILE, "web"]);
+        
+        let web = Config::load_in(&dir.0, "web", false).unwrap();
+        assert_eq!(web.profile(), "web");
+        assert_eq!(web.filtered_process_names.iter().map(|f| f.pattern.as_str()).collect::<Vec<_>>(), ["vite"]);
+        let default = Config::load_in(&dir.0, DEFAULT_PROFILE, false).unwrap();
+        assert_eq!(default.filtered_process_names.iter().map(|f| f.pattern.as_str()).collect::<Vec<_>>(), ["node"]);
+        
+        for name in ["", "../web", ".hidden"] {
+            assert!(Config::load_in(&dir.0, name, false).is_err(), "{:?} was accepted", name);
+        }
+    }
+    
+    const NAMES: [&str; 4] = ["node", "nodemon", "com.apple.WebKit", "postgres"];
+    
+    /// The sample names a pattern matches in a mode
+    fn matching(mode: MatchMode, pattern: &str) -> Vec<&'static str> {
+        let matcher = mode.compile(pattern).unwrap();
+        NAMES.into_iter().filter(|name| matcher.matches(name)).collect()
+    }
+    
+    #[test]
+    fn contains_matches_substrings() {
+        assert_eq!(matching(MatchMode::Contains, "ode"), ["node", "nodemon"]);
+        assert_eq!(matching(MatchMode::Contains, "apple"), ["com.apple.WebKit"]);
+    }
+    
+    #[test]
+    fn exact_matches_whole_names_only() {
+        assert_eq!(matching(MatchMode::Exact, "node"), ["node"]);
+        assert!(matching(MatchMode::Exact, "nod").is_empty());
+    }
+    
+    #[test]
+    fn prefix_and_suffix_match_the_ends() {
+        assert_eq!(matching(MatchMode::Prefix, "node"), ["node", "nodemon"]);
+        assert_eq!(matching(MatchMode::Suffix, "mon"), ["nodemon"]);
+        assert_eq!(matching(MatchMode::Suffix, "res"), ["postgres"]);
+    }
+    
+    #[test]
+    fn regex_matches_anywhere_unless_anchored() {
+        assert_eq!(matching(MatchMode::Regex, "^node$"), ["node"]);
+        assert_eq!(matching(MatchMode::Regex, "o.e"), ["node", "nodemon"]);
+    }
+    
+    #[test]
+    fn glob_matches_whole_names() {
+        assert_eq!(matching(MatchMode::Glob, "com.apple.*"), ["com.apple.WebKit"]);
+        assert_eq!(matching(MatchMode::Glob, "node*"), ["node", "nodemon"]);
+        assert_eq!(matching(MatchMode::Glob, "no?e"), ["node"]);
+        assert!(matching(MatchMode::Glob, "apple").is_empty());
+    }
+    
+    #[test]
+    fn invalid_patterns_dont_compile() {
+        assert!(MatchMode::Regex.compile("(").is_err());
+        assert!(MatchMode::Regex.compile("[a-").is_err());
+        assert!(MatchMode::Glob.compile("[").is_err());
+        assert!(MatchMode::Glob.compile("***").is_err());
+        // Only glob and regex have a syntax to get wrong
+        assert!(MatchMode::Contains.compile("(").is_ok());
+        assert!(MatchMode::Exact.compile("[").is_ok());
+    }
+    
+    #[test]
+    fn matching_is_case_sensitive() {
+        assert!(matching(MatchMode::Contains, "NODE").is_empty());
+        assert!(matching(MatchMode::Exact, "Postgres").is_empty());
+    }
+    
+    #[test]
+    fn compile_all_skips_invalid_patterns() {
+        let names = MatchMode::Regex.compile_all(&["(".to_string(), "^post".to_string()]);
+        assert!(names.matches("postgres"));
+        assert!(!names.matches("node"));
+    }
+    
+    #[test]
+    fn filters_are_compiled_for_the_match_mode() {
+        let mut config = Config {
+            filtered_process_names: vec![FilterEntry::new("node".to_string())],
+            match_mode: MatchMode::Exact,
+            ..Config::default()
+        };
+        config.compile_patterns();
+        assert!(config.is_filtered("node", |_| false));
+        assert!(!config.is_filtered("nodemon", |_| false));
+        
+        // Switching modes recompiles the existing filters
+        config.match_mode = MatchMode::Prefix;
+        config.compile_patterns();
+        assert!(config.is_filtered("nodemon", |_| false));
+    }
+    
+    #[test]
+    fn snoozed_and_disabled_filters_dont_hide() {
+        let mut config = Config {
+            filtered_process_names: vec![FilterEntry::new("node".to_string())],
+            ..Config::default()
+        };
+        config.compile_patterns();
+        assert!(!config.is_filtered("node", |pattern| pattern == "node"));
+        config.filtered_process_names[0].enabled = false;
+        assert!(!config.is_filtered("node", |_| false));
+    }
+    
+    #[test]
+    fn invalid_filters_are_found_at_load_and_never_match() {
+        let (config, _dir) = load_temp(r#"{
+            "filtered_process_names": ["(", "^node$"],
+            "filter_rules": [{ "command": "--port [" }],
+            "match_mode": "regex"
+        }"#);
+        
+        assert_eq!(config.invalid_filters(), ["(", "--port ["]);
+        assert!(config.filtered_process_names[0].matcher.is_none());
+        assert!(!config.is_filtered("(", |_| false));
+        assert!(!config.is_rule_filtered("node", "node --port [", 3000, "alice"));
+        // The valid pattern next to it still works
+        assert!(config.is_filtered("node", |_| false));
+        assert!(!config.is_filtered("nodemon", |_| false));
+    }
+    
+    #[test]
+    fn filter_entries_read_plain_strings() {
+        let entries: Vec<FilterEntry> = serde_json::from_str(r#"["node", "Browser"]"#).unwrap();
+        assert_eq!(entries, [FilterEntry::new("node".to_string()), FilterEntry::new("Browser".to_string())]);
+    }
+    
+    #[test]
+    fn filter_entries_read_objects() {
+        let entries: Vec<FilterEntry> = serde_json::from_str(r#"[
+            { "pattern": "node", "note": "dev servers" },
+            { "pattern": "java", "toggleable": true, "enabled": false },
+            { "pattern": "ruby" }
+        ]"#).unwrap();
+        assert_eq!(entries[0].note.as_deref(), Some("dev servers"));
+        assert!(entries[0].enabled && !entries[0].toggleable);
+        assert!(entries[1].toggleable && !entries[1].enabled);
+        assert_eq!(entries[2], FilterEntry::new("ruby".to_string()));
+    }
+    
+    #[test]
+    fn filter_entries_round_trip() {
+        let entries = vec![
+            FilterEntry::new("node".to_string()),
+            FilterEntry { note: Some("CI agent".to_string()), ..FilterEntry::new("java".to_string()) },
+            FilterEntry { toggleable: true, enabled: false, ..FilterEntry::new("ruby".to_string()) },
+        ];
+        let json = serde_json::to_string(&entries).unwrap();
+        // Plain filters stay strings and defaults are left out
+        assert_eq!(json, r#"["node",{"pattern":"java","note":"CI agent"},{"pattern":"ruby","toggleable":true,"enabled":false}]"#);
+        assert_eq!(serde_json::from_str::<Vec<FilterEntry>>(&json).unwrap(), entries);
+    }
+    
+    #[test]
+    fn filter_entries_reject_other_shapes() {
+        assert!(serde_json::from_str::<FilterEntry>("42").is_err());
+        assert!(serde_json::from_str::<FilterEntry>(r#"{ "note": "no pattern" }"#).is_err());
+    }
+    
+    fn policy(ports: [u16; 2], policy: KillPolicy) -> PortPolicy {
+        PortPolicy { ports, policy }
+    }
+    
+    #[test]
+    fn port_policy_takes_the_first_range_holding_the_port() {
+        let config = Config {
+            port_policies: vec![
+                policy([5432, 5432], KillPolicy::Protected),
+                policy([3000, 3999], KillPolicy::NeverConfirm),
+                policy([3500, 3500], KillPolicy::Protected),
+                policy([1, 1024], KillPolicy::AlwaysConfirm),
+                policy([1, 65535], KillPolicy::AlwaysConfirm),
+            ],
+            ..Config::default()
+        };
+        assert_eq!(config.port_policy(5432), Some(KillPolicy::Protected));
+        assert_eq!(config.port_policy(3000), Some(KillPolicy::NeverConfirm));
+        assert_eq!(config.port_policy(3999), Some(KillPolicy::NeverConfirm));
+        // Shadowed by the wider range listed before it
+        assert_eq!(config.port_policy(3500), Some(KillPolicy::NeverConfirm));
+        assert_eq!(config.port_policy(80), Some(KillPolicy::AlwaysConfirm));
+        assert_eq!(config.port_policy(8080), Some(KillPolicy::AlwaysConfirm));
+    }
+    
+    #[test]
+    fn port_policy_is_none_outside_every_range() {
+        let config = Config {
+            port_policies: vec![policy([3000, 3999], KillPolicy::NeverConfirm)],
+            ..Config::default()
+        };
+        assert_eq!(config.port_policy(2999), None);
+        assert_eq!(config.port_policy(4000), None);
+        assert_eq!(Config::default().port_policy(3000), None);
+    }
+    
+    #[test]
+    fn kill_policies_order_from_lenient_to_strict() {
+        assert!(KillPolicy::NeverConfirm < KillPolicy::AlwaysConfirm);
+        assert!(KillPolicy::AlwaysConfirm < KillPolicy::Protected);
+    }
+    
+    fn patterns(config: &Config) -> Vec<&str> {
+        config.filtered_process_names.iter().map(|filter| filter.pattern.as_str()).collect()
+    }
+    
+    #[test]
+    fn reordered_filters_persist() {
+        let (mut config, _dir) = load_temp(r#"{ "filtered_process_names": ["a", { "pattern": "b", "note": "keep" }, "c"] }"#);
+        assert_eq!(config.move_filtered_process(2, true).unwrap(), 1);
+        assert_eq!(config.move_filtered_process(0, false).unwrap(), 1);
+        assert_eq!(patterns(&config), ["c", "a", "b"]);
+        
+        let reloaded = reload(&config);
+        assert_eq!(patterns(&reloaded), ["c", "a", "b"]);
+        assert_eq!(reloaded.filtered_process_names[2].note.as_deref(), Some("keep"));
+        assert_eq!(reloaded.filtered_process_names, config.filtered_process_names);
+    }
+    
+    #[test]
+    fn filters_dont_move_past_the_ends() {
+        let (mut config, _dir) = load_temp(r#"{ "filtered_process_names": ["a", "b"] }"#);
+        assert_eq!(config.move_filtered_process(0, true).unwrap(), 0);
+        assert_eq!(config.move_filtered_process(1, false).unwrap(), 1);
+        assert_eq!(config.move_filtered_process(5, true).unwrap(), 5);
+        assert_eq!(patterns(&config), ["a", "b"]);
+    }
+    
+    #[test]
+    fn sort_state_round_trips() {
+        let (mut config, _dir) = load_temp("{}");
+        assert_eq!((config.sort_column, config.sort_descending), (SortColumn::Port, false));
+        
+        config.set_sort(SortColumn::Memory, true).unwrap();
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!((&json["sort_column"], &json["sort_descending"]), (&serde_json::json!("memory"), &serde_json::json!(true)));
+        let reloaded = reload(&config);
+        assert_eq!((reloaded.sort_column, reloaded.sort_descending), (SortColumn::Memory, true));
+        
+        config.toggle_sort_direction().unwrap();
+        assert!(!reload(&config).sort_descending);
+    }
+    
+    #[test]
+    fn cycling_the_sort_column_persists() {
+        let (mut config, _dir) = load_temp(r#"{ "sort_column": "name", "sort_descending": true }"#);
+        config.cycle_sort_column().unwrap();
+        let reloaded = reload(&config);
+        assert_ne!(reloaded.sort_column, SortColumn::Name);
+        assert_eq!(reloaded.sort_column, config.sort_column);
+        // A new column starts ascending
+        assert!(!reloaded.sort_descending);
+    }
+    
+    const MALFORMED: &str = r#"{ "filtered_process_names": ["node", }"#;
+    
+    #[test]
+    fn malformed_config_is_backed_up_and_reset() {
+        let (path, _dir) = write_temp(MALFORMED);
+        let config = Config::load_override(&path, true).unwrap();
+        assert_eq!(config.source, ConfigSource::Recovered);
+        assert_eq!(config.filtered_process_names.len(), Config::default().filtered_process_names.len());
+        
+        // The hand edit survives in the backup, and the file parses again
+        assert_eq!(fs::read_to_string(Config::backup_path(&path)).unwrap(), MALFORMED);
+        assert_eq!(reload(&config).source, ConfigSource::Override);
+    }
+    
+    #[test]
+    fn malformed_config_fails_without_recovery() {
+        let (path, _dir) = write_temp(MALFORMED);
+        let error = Config::load_override(&path, false).unwrap_err();
+        assert!(matches!(error, PortManagerError::ConfigError { .. }));
+        assert!(!Config::backup_path(&path).exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), MALFORMED);
+    }
+    
+    #[test]
+    fn pid_filters_take_single_pids_and_ranges() {
+        let (config, _dir) = load_temp(r#"{ "filtered_pids": [4242, [5000, 5010]] }"#);
+        assert_eq!(config.filtered_pids, [PidFilter::Pid(4242), PidFilter::Range([5000, 5010])]);
+        assert!(config.is_pid_filtered(4242));
+        assert!(!config.is_pid_filtered(4243));
+        assert!(config.is_pid_filtered(5000));
+        assert!(config.is_pid_filtered(5010));
+        assert!(!config.is_pid_filtered(4999));
+        assert!(!config.is_pid_filtered(5011));
+        assert!(!Config::default().is_pid_filtered(4242));
+    }
+    
+    #[test]
+    fn keys_parse_with_modifiers_and_names() {
+        assert_eq!(parse_key("k").unwrap(), (KeyCode::Char('k'), KeyModifiers::NONE));
+        assert_eq!(parse_key("K").unwrap(), (KeyCode::Char('K'), KeyModifiers::NONE));
+        assert_eq!(parse_key("ctrl-r").unwrap(), (KeyCode::Char('r'), KeyModifiers::CONTROL));
+        assert_eq!(parse_key("Alt+x").unwrap(), (KeyCode::Char('x'), KeyModifiers::ALT));
+        assert_eq!(parse_key("-").unwrap(), (KeyCode::Char('-'), KeyModifiers::NONE));
+        assert_eq!(parse_key("space").unwrap(), (KeyCode::Char(' '), KeyModifiers::NONE));
+        assert_eq!(parse_key("Enter").unwrap(), (KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(parse_key("f5").unwrap(), (KeyCode::F(5), KeyModifiers::NONE));
+        assert_eq!(parse_key("F12").unwrap(), (KeyCode::F(12), KeyModifiers::NONE));
+        for invalid in ["", "f13", "f0", "ctrl-", "banana", "shift-k"] {
+            assert!(parse_key(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+    
+    #[test]
+    fn keybindings_replace_the_default_keys() {
+        let (config, _dir) = load_temp(r#"{ "keybindings": { "kill": ["d"], "quit": ["ctrl-q", "f10"] } }"#);
+        let none = KeyModifiers::NONE;
+        assert_eq!(config.key_action(KeyCode::Char('d'), none), Some(KeyAction::Kill));
+        assert_eq!(config.key_action(KeyCode::Char('k'), none), None);
+        assert_eq!(config.key_action(KeyCode::Char('q'), KeyModifiers::CONTROL), Some(KeyAction::Quit));
+        assert_eq!(config.key_action(KeyCode::F(10), none), Some(KeyAction::Quit));
+        assert_eq!(config.key_action(KeyCode::Char('q'), none), None);
+        // Unmapped actions keep their defaults, Shift is part of the letter,
+        // and Ctrl doesn't trigger a plain key
+        assert_eq!(config.key_action(KeyCode::Char('r'), none), Some(KeyAction::Refresh));
+        assert_eq!(config.key_action(KeyCode::Char('K'), KeyModifiers::SHIFT), Some(KeyAction::KillNow));
+        assert_eq!(config.key_action(KeyCode::Char('r'), KeyModifiers::CONTROL), None);
+    }
+    
+    #[test]
+    fn down_can_be_remapped_to_j() {
+        let (config, _dir) = load_temp(r#"{ "keybindings": { "down": ["down", "j"] } }"#);
+        let none = KeyModifiers::NONE;
+        assert_eq!(config.key_action(KeyCode::Char('j'), none), Some(KeyAction::Down));
+        assert_eq!(config.key_action(KeyCode::Down, none), Some(KeyAction::Down));
+        // Recording a cleanup script stays on its own key
+        assert_eq!(config.key_action(KeyCode::Char('J'), KeyModifiers::SHIFT), Some(KeyAction::RecordCleanup));
+    }
+    
+    #[test]
+    fn conflicting_keybindings_fail_to_load() {
+        let load = |contents: &str| {
+            let (path, _dir) = write_temp(contents);
+            Config::load_override(&path, true).map(|_| ()).unwrap_err().to_string()
+        };
+        assert_eq!(
+            load(r#"{ "keybindings": { "kill": ["r"] } }"#),
+            "Invalid keybindings: \"r\" is bound to both Kill and Refresh",
+        );
+        assert_eq!(
+            load(r#"{ "keybindings": { "details": ["?"] } }"#),
+            "Invalid keybindings: \"?\" for Details is already the key for list every key; pick another key",
+        );
+        assert_eq!(load(r#"{ "keybindings": { "search": ["ctrl-"] } }"#), "Invalid key: \"ctrl-\"");
+        
+        // Moving a default key away frees it for another action
+        let (config, _dir) = load_temp(r#"{ "keybindings": { "kill": ["r"], "refresh": ["ctrl-r"] } }"#);
+        assert_eq!(config.key_action(KeyCode::Char('r'), KeyModifiers::NONE), Some(KeyAction::Kill));
+    }
+    
+    #[test]
+    fn exported_filters_import_elsewhere() {
+        let (source, dir) = load_temp(r#"{
+            "match_mode": "exact",
+            "filtered_process_names": ["node", { "pattern": "java", "note": "CI agent" }, { "pattern": "ruby", "toggleable": true, "enabled": false }],
+            "filtered_pids": [4242]
+        }"#);
+        let path = dir.0.join("filters.json");
+        assert_eq!(source.export_filters(&path).unwrap(), 3);
+        
+        let (mut target, _dir) = load_temp(r#"{ "match_mode": "exact", "filtered_process_names": ["node", "vite"] }"#);
+        let import = target.import_filters(&path).unwrap();
+        assert_eq!((import.added, import.duplicates, import.match_mode), (2, 1, MatchMode::Exact));
+        assert!(target.filtered_pids.is_empty());
+        
+        // Merged, with the notes and switches kept, and saved
+        for config in [&target, &reload(&target)] {
+            assert_eq!(patterns(config), ["node", "vite", "java", "ruby"]);
+            let filters = &config.filtered_process_names;
+            assert_eq!(filters[2].note.as_deref(), Some("CI agent"));
+            assert!(filters[3].toggleable && !filters[3].enabled);
+        }
+        assert!(target.is_filtered("java", |_| false));
+        
+        // A second import only finds duplicates
+        let import = target.import_filters(&path).unwrap();
+        assert_eq!((import.added, import.duplicates), (0, 3));
+    }
+    
+    #[test]
+    fn bad_filter_files_are_rejected_whole() {
+        let (mut config, dir) = load_temp(r#"{ "match_mode": "regex", "filtered_process_names": ["node"] }"#);
+        let path = dir.0.join("filters.json");
+        let mut import = |contents: &str| {
+            fs::write(&path, contents).unwrap();
+            config.import_filters(&path).map(|_| ()).unwrap_err().to_string()
+        };
+        
+        assert!(import(r#"{ "match_mode": "regex", "filtered_process_names": 3 }"#).starts_with("Invalid filter file: "));
+        assert!(import(r#"{ "filtered_process_names": ["java"] }"#).contains("missing field `match_mode`"));
+        assert!(import(r#"{ "match_mode": "regex", "filtered_process_names": [], "filtered_pids": [1] }"#).contains("unknown field `filtered_pids`"));
+        assert!(import(r#"{ "match_mode": "regex", "filtered_process_names": ["java", "(unclosed"] }"#).contains("pattern \"(unclosed\""));
+        assert!(import("not json").starts_with("Invalid filter file: "));
+        
+        assert_eq!(patterns(&config), ["node"]);
+        assert_eq!(patterns(&reload(&config)), ["node"]);
+    }
+    
+    /// Whether each filter is on
+    fn enabled(config: &Config) -> Vec<bool> {
+        config.filtered_process_names.iter().map(|filter| filter.enabled).collect()
+    }
+    
+    #[test]
+    fn quick_toggle_switches_default_and_marked_filters() {
+        let (mut config, _dir) = load_temp(r#"{
+            "filtered_process_names": ["Browser", "ControlCE", "node", { "pattern": "java", "toggleable": true }]
+        }"#);
+        assert_eq!(config.toggle_quick_filters().unwrap(), Some(false));
+        assert_eq!(enabled(&config), [false, false, true, false]);
+        assert!(!config.is_filtered("java", |_| false));
+        assert!(config.is_filtered("node", |_| false));
+        // Switched off, not removed, and saved that way
+        assert_eq!(enabled(&reload(&config)), [false, false, true, false]);
+        
+        assert_eq!(config.toggle_quick_filters().unwrap(), Some(true));
+        assert_eq!(enabled(&config), [true, true, true, true]);
+        
+        // With any of them on, the toggle turns them all off first
+        config.toggle_filter_enabled(0).unwrap();
+        assert_eq!(config.toggle_quick_filters().unwrap(), Some(false));
+        assert_eq!(enabled(&config), [false, false, true, false]);
+    }
+    
+    #[test]
+    fn quick_toggle_needs_a_quick_filter() {
+        let (mut config, _dir) = load_temp(r#"{ "filtered_process_names": ["node"] }"#);
+        assert_eq!(config.toggle_quick_filters().unwrap(), None);
+        assert_eq!(enabled(&config), [true]);
+        
+        assert_eq!(config.toggle_filter_toggleable(0).unwrap(), Some(true));
+        assert_eq!(config.toggle_quick_filters().unwrap(), Some(false));
+        assert_eq!(config.toggle_filter_toggleable(0).unwrap(), Some(false));
+        assert_eq!(config.toggle_filter_toggleable(1).unwrap(), None);
+        assert_eq!(config.toggle_filter_enabled(1).unwrap(), None);
+    }
+    
+    #[test]
+    fn default_filters_stay_in_the_quick_toggle() {
+        let (mut config, _dir) = load_temp(r#"{ "filtered_process_names": ["Browser"] }"#);
+        assert!(config.filtered_process_names[0].is_quick_toggle());
+        assert_eq!(config.toggle_filter_toggleable(0).unwrap(), Some(true));
+        assert_eq!(config.toggle_filter_toggleable(0).unwrap(), Some(true));
+    }
+    
+    #[test]
+    fn rules_combine_with_all_any_and_not() {
+        let (config, _dir) = load_temp(r#"{
+            "filter_rules": [
+                { "all": [{ "name": "node" }, { "ports": [9000, 65535] }] },
+                { "any": [{ "user": "postgres" }, { "command": "--inspect(=|\\s|$)" }] },
+                { "all": [{ "name": "java" }, { "not": { "ports": [8080, 8080] } }] }
+            ]
+        }"#);
+        let hidden = |name: &str, command: &str, port: u16, user: &str| config.is_rule_filtered(name, command, port, user);
+        
+        // node only from port 9000 up
+        assert!(hidden("node", "node server.js", 9500, "alice"));
+        assert!(hidden("node", "node server.js", 65535, "alice"));
+        assert!(!hidden("node", "node server.js", 8999, "alice"));
+        assert!(!hidden("vite", "vite", 9500, "alice"));
+        
+        // Either the user or the command is enough
+        assert!(hidden("postgres", "postgres -D /data", 5432, "postgres"));
+        assert!(hidden("deno", "deno run --inspect main.ts", 3000, "alice"));
+        assert!(!hidden("deno", "deno run --inspector-off main.ts", 3000, "alice"));
+        
+        // java everywhere but 8080
+        assert!(hidden("java", "java -jar api.jar", 8081, "alice"));
+        assert!(!hidden("java", "java -jar api.jar", 8080, "alice"));
+    }
+    
+    #[test]
+    fn rules_follow_the_match_mode_for_names_only() {
+        let rules = r#""filter_rules": [{ "name": "node" }, { "command": "^python3? -m http\\.server" }]"#;
+        let (contains, _dir) = load_temp(&format!(r#"{{ "match_mode": "contains", {} }}"#, rules));
+        let (exact, _dir) = load_temp(&format!(r#"{{ "match_mode": "exact", {} }}"#, rules));
+        assert!(contains.is_rule_filtered("nodemon", "", 3000, "alice"));
+        assert!(!exact.is_rule_filtered("nodemon", "", 3000, "alice"));
+        assert!(exact.is_rule_filtered("node", "", 3000, "alice"));
+        // Commands are always regular expressions
+        for config in [&contains, &exact] {
+            assert!(config.is_rule_filtered("python3", "python3 -m http.server 8000", 8000, "alice"));
+            assert!(!config.is_rule_filtered("python3", "python3 -m http_server", 8000, "alice"));
+        }
+    }
+    
+    #[test]
+    fn host_mismatch_needs_another_host_and_the_warning_on() {
+        let current = current_hostname().expect("this machine has a hostname");
+        
+        let (config, _dir) = load_temp(r#"{ "hostname": "ci-runner-7", "warn_on_host_mismatch": true }"#);
+        assert_eq!(config.host_mismatch(), Some(("ci-runner-7", current.clone())));
+        
+        let (config, _dir) = load_temp(r#"{ "hostname": "ci-runner-7" }"#);
+        assert_eq!(config.host_mismatch(), None);
+        
+        let (config, _dir) = load_temp(&format!(r#"{{ "hostname": "{}", "warn_on_host_mismatch": true }}"#, current));
+        assert_eq!(config.host_mismatch(), None);
+        
+        // A config without a hostname is tagged with this machine's
+        let (config, _dir) = load_temp(r#"{ "warn_on_host_mismatch": true }"#);
+        assert_eq!(config.hostname.as_deref(), Some(current.as_str()));
+        assert_eq!(config.host_mismatch(), None);
+    }
+    
+    #[test]
+    fn invalid_command_regexes_never_match_and_are_reported() {
+        let (config, _dir) = load_temp(r#"{
+            "filtered_process_names": [],
+            "filter_rules": [
+                { "command": "--port (300" },
+                { "not": { "any": [{ "user": "root" }, { "command": "[z-a]" }] } },
+                { "command": "--port 300\\d" }
+            ]
+        }"#);
+        assert_eq!(config.invalid_filters(), ["--port (300", "[z-a]"]);
+        assert!(!config.is_rule_filtered("node", "node app.js --port (300", 3000, "root"));
+        assert!(config.is_rule_filtered("node", "node app.js --port 3001", 3001, "root"));
+        assert!(!config.is_rule_filtered("node", "node app.js --port 8080", 8080, "root"));
+    }
+    
+    #[test]
+    fn columns_follow_the_configured_order() {
+        let (config, _dir) = load_temp(r#"{ "columns": ["port", "name", "pid", "command"] }"#);
+        assert_eq!(config.table_columns(), [TableColumn::Port, TableColumn::Name, TableColumn::Pid, TableColumn::Command]);
+        assert!(config.unknown_columns().is_empty());
+        
+        let (config, _dir) = load_temp(r#"{ "columns": ["memory", "colour", "port"] }"#);
+        assert_eq!(config.table_columns(), [TableColumn::Memory, TableColumn::Port]);
+        assert_eq!(config.unknown_columns(), ["colour"]);
+    }
+    
+    #[test]
+    fn unknown_columns_fall_back_to_the_default_layout() {
+        let (config, _dir) = load_temp(r#"{ "columns": ["colour", "size"] }"#);
+        assert_eq!(config.table_columns(), TableColumn::ALL.into_iter().filter(|c| *c != TableColumn::Threads).collect::<Vec<_>>());
+        assert_eq!(config.unknown_columns(), ["colour", "size"]);
+        
+        let (config, _dir) = load_temp(r#"{ "show_threads": true }"#);
+        assert_eq!(config.table_columns(), TableColumn::ALL);
+    }
+    
+    #[test]
+    fn listeners_missing_from_the_baseline_are_unexpected() {
+        let (mut config, _dir) = load_temp("{}");
+        assert!(!config.is_unexpected("nc", 4444));
+        
+        let entry = |name: &str, port| BaselineEntry { name: name.to_string(), port };
+        assert_eq!(config.set_baseline([entry("sshd", 22), entry("node", 3000), entry("sshd", 22)]).unwrap(), 2);
+        assert_eq!(reload(&config).baseline, [entry("node", 3000), entry("sshd", 22)]);
+        assert!(!config.is_unexpected("node", 3000));
+        assert!(config.is_unexpected("nc", 4444));
+        // Both the name and the port have to match
+        assert!(config.is_unexpected("nc", 3000));
+        assert!(config.is_unexpected("node", 3001));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{IoContext, PortManagerError, Result};
+use crate::process::PortProcess;
+
+/// A process as read back from a snapshot written with `--format json`.
+/// Fields the diff doesn't use are ignored.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SnapshotEntry {
+    pub pid: u32,
+    pub name: String,
+    /// Null for ports that couldn't be resolved, which the diff skips
+    pub port: Option<u16>,
+}
+
+impl From<&PortProcess> for SnapshotEntry {
+    fn from(process: &PortProcess) -> Self {
+        Self {
+            pid: process.pid,
+            name: process.name.clone(),
+            port: process.unresolved_port.is_none().then_some(process.port),
+        }
+    }
+}
+
+/// A process holding a port, as compared between snapshots
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Owner {
+    pub name: String,
+    pub pid: u32,
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (PID {})", self.name, self.pid)
+    }
+}
+
+/// How a port differs between two snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub enum PortChange {
+    /// Only held in the second snapshot
+    Added { port: u16, after: Vec<Owner> },
+    /// Only held in the first snapshot
+    Removed { port: u16, before: Vec<Owner> },
+    /// Held in both, but by different processes. A restarted server shows up
+    /// here too, since its PID changed.
+    Changed { port: u16, before: Vec<Owner>, after: Vec<Owner> },
+}
+
+impl fmt::Display for PortChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |owners: &[Owner]| owners.iter().map(Owner::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            PortChange::Added { port, after } => write!(f, "+ {:>5}  {}", port, list(after)),
+            PortChange::Removed { port, before } => write!(f, "- {:>5}  {}", port, list(before)),
+            PortChange::Changed { port, before, after } => write!(f, "~ {:>5}  {} -> {}", port, list(before), list(after)),
+        }
+    }
+}
+
+/// Read a snapshot written with `--format json`
+pub fn load_snapshot(path: &Path) -> Result<Vec<SnapshotEntry>> {
+    let contents = fs::read_to_string(path).io_context(format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| PortManagerError::parse("snapshot", format!("{}: {} (write snapshots with --format json)", path.display(), e)))
+}
+
+/// Compare two snapshots port by port, in port order. A port held on several
+/// addresses, or by several processes, is compared as the set of its owners.
+pub fn diff_snapshots(before: &[SnapshotEntry], after: &[SnapshotEntry]) -> Vec<PortChange> {
+    let before = owners_by_port(before);
+    let after = owners_by_port(after);
+    
+    let ports: BTreeSet<u16> = before.keys().chain(after.keys()).copied().collect();
+    ports.into_iter()
+        .filter_map(|port| match (before.get(&port), after.get(&port)) {
+            (None, Some(after)) => Some(PortChange::Added { port, after: after.iter().cloned().collect() }),
+            (Some(before), None) => Some(PortChange::Removed { port, before: before.iter().cloned().collect() }),
+            (Some(old), Some(new)) if old != new => Some(PortChange::Changed {
+                port,
+                before: old.iter().cloned().collect(),
+                after: new.iter().cloned().collect(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Group a snapshot's processes by the port they hold
+fn owners_by_port(entries: &[SnapshotEntry]) -> BTreeMap<u16, BTreeSet<Owner>> {
+    let mut owners: BTreeMap<u16, BTreeSet<Owner>> = BTreeMap::new();
+    for entry in entries {
+        let Some(port) = entry.port else {
+            continue;
+        };
+        owners.entry(port).or_default().insert(Owner { name: entry.name.clone(), pid: entry.pid });
+    }
+    owners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn entry(pid: u32, name: &str, port: u16) -> SnapshotEntry {
+        SnapshotEntry { pid, name: name.to_string(), port: Some(port) }
+    }
+    
+    fn owner(pid: u32, name: &str) -> Owner {
+        Owner { name: name.to_string(), pid }
+    }
+    
+    #[test]
+    fn appeared_and_disappeared_ports() {
+        let before = [entry(10, "node", 3000), entry(20, "redis-server", 6379)];
+        let after = [entry(10, "node", 3000), entry(30, "vite", 5173)];
+        assert_eq!(diff_snapshots(&before, &after), [
+            PortChange::Added { port: 5173, after: vec![owner(30, "vite")] },
+            PortChange::Removed { port: 6379, before: vec![owner(20, "redis-server")] },
+        ]);
+    }
+    
+    #[test]
+    fn restarted_process_changes_its_port() {
+        let before = [entry(10, "node", 3000)];
+        let after = [entry(11, "node", 3000)];
+        assert_eq!(diff_snapshots(&before, &after), [
+            PortChange::Changed { port: 3000, before: vec![owner(10, "node")], after: vec![owner(11, "node")] },
+        ]);
+    }
+    
+    #[test]
+    fn ports_on_several_addresses_compare_as_one() {
+        // The same process on IPv4 and IPv6 is one owner
+        let before = [entry(10, "node", 3000), entry(10, "node", 3000)];
+        let after = [entry(10, "node", 3000)];
+        assert!(diff_snapshots(&before, &after).is_empty());
+        
+        // A second process joining a port changes it
+        let after = [entry(10, "node", 3000), entry(12, "node", 3000)];
+        assert_eq!(diff_snapshots(&before, &after), [
+            PortChange::Changed { port: 3000, before: vec![owner(10, "node")], after: vec![owner(10, "node"), owner(12, "node")] },
+        ]);
+    }
+    
+    #[test]
+    fn unresolved_ports_are_skipped() {
+        let before = [SnapshotEntry { pid: 10, name: "node".to_string(), port: None }];
+        assert!(diff_snapshots(&before, &[]).is_empty());
+    }
+    
+    #[test]
+    fn watched_processes_become_entries() {
+        use crate::process::AddressFamily;
+        
+        let listener = PortProcess::without_lookups(10, "node".to_string(), 3000, "*".to_string(), AddressFamily::Ipv4, None);
+        let unresolved = PortProcess { unresolved_port: Some("weird".to_string()), port: 0, ..listener.clone() };
+        let entries: Vec<SnapshotEntry> = [&listener, &unresolved].into_iter().map(SnapshotEntry::from).collect();
+        assert_eq!((entries[0].pid, entries[0].name.as_str(), entries[0].port), (10, "node", Some(3000)));
+        assert_eq!(entries[1].port, None);
+    }
+    
+    #[test]
+    fn changes_display_like_a_diff() {
+        let changes = diff_snapshots(&[entry(10, "node", 3000), entry(20, "ruby", 4000)], &[entry(11, "node", 3000), entry(30, "vite", 80)]);
+        let lines: Vec<String> = changes.iter().map(PortChange::to_string).collect();
+        assert_eq!(lines, ["+    80  vite (PID 30)", "~  3000  node (PID 10) -> node (PID 11)", "-  4000  ruby (PID 20)"]);
+    }
+    
+    #[test]
+    fn snapshots_read_json_output() {
+        let entries: Vec<SnapshotEntry> = serde_json::from_str(
+            r#"[{ "pid": 10, "name": "node", "port": 3000, "address": "*", "user": "alice" }, { "pid": 11, "name": "ruby", "port": null }]"#
+        ).unwrap();
+        assert_eq!((entries[0].pid, entries[0].port), (10, Some(3000)));
+        assert_eq!(entries[1].port, None);
+    }
+    
+    /// Snapshot files written to a directory of their own, removed along with it
+    struct TestDir(std::path::PathBuf);
+    
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+    
+    fn test_dir(name: &str) -> TestDir {
+        let dir = TestDir(std::env::temp_dir().join(format!("port-manager-diff-test-{}-{}", std::process::id(), name)));
+        fs::create_dir_all(&dir.0).unwrap();
+        dir
+    }
+    
+    #[test]
+    fn snapshots_written_as_json_diff_after_loading() {
+        use crate::format::{write_processes, OutputFormat, WriteOptions};
+        use crate::process::AddressFamily;
+        
+        let dir = test_dir("round-trip");
+        let process = |pid, name: &str, port| PortProcess::without_lookups(pid, name.to_string(), port, "*".to_string(), AddressFamily::Ipv4, None);
+        let write = |file: &str, processes: &[PortProcess]| {
+            let mut out = Vec::new();
+            write_processes(&mut out, OutputFormat::Json, processes, &WriteOptions { binary_units: false, hide_commands: false }).unwrap();
+            let path = dir.0.join(file);
+            fs::write(&path, out).unwrap();
+            path
+        };
+        let before = write("before.json", &[process(10, "node", 3000), process(20, "ruby", 4000)]);
+        let after = write("after.json", &[process(11, "node", 3000), process(30, "vite", 5173)]);
+        
+        let changes = diff_snapshots(&load_snapshot(&before).unwrap(), &load_snapshot(&after).unwrap());
+        assert_eq!(changes, [
+            PortChange::Changed { port: 3000, before: vec![owner(10, "node")], after: vec![owner(11, "node")] },
+            PortChange::Removed { port: 4000, before: vec![owner(20, "ruby")] },
+            PortChange::Added { port: 5173, after: vec![owner(30, "vite")] },
+        ]);
+        assert!(diff_snapshots(&load_snapshot(&before).unwrap(), &load_snapshot(&before).unwrap()).is_empty());
+    }
+    
+    #[test]
+    fn snapshots_that_are_not_json_output_are_rejected() {
+        let dir = test_dir("bad");
+        let list = dir.0.join("list.txt");
+        fs::write(&list, "node 3000 (PID 10)\n").unwrap();
+        let e = load_snapshot(&list).unwrap_err();
+        assert!(matches!(e, PortManagerError::ParseError { what: "snapshot", .. }));
+        assert!(e.to_string().contains("write snapshots with --format json"));
+        
+        let e = load_snapshot(&dir.0.join("missing.json")).unwrap_err();
+        assert!(e.to_string().starts_with("Failed to read "));
+    }
+}
@@ -0,0 +1,44 @@
+use std::net::IpAddr;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use crate::error::PortManagerError;
+use crate::error::Result;
+
+/// Look up the hostname of an address through the system resolver, which may
+/// ask a DNS server and so can take a while. Returns `None` when the address
+/// has no name.
+#[cfg(target_os = "linux")]
+pub fn reverse_lookup(ip: IpAddr) -> Result<Option<String>> {
+    // 192.168.1.20    devbox.lan
+    let output = Command::new("getent")
+        .args(["hosts", &ip.to_string()])
+        .output()
+        .map_err(|e| PortManagerError::spawn("getent", e))?;
+    
+    // getent exits non-zero when there is no name
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string))
+}
+
+#[cfg(target_os = "macos")]
+pub fn reverse_lookup(ip: IpAddr) -> Result<Option<String>> {
+    // name: devbox.lan
+    let output = Command::new("dscacheutil")
+        .args(["-q", "host", "-a", "ip_address", &ip.to_string()])
+        .output()
+        .map_err(|e| PortManagerError::spawn("dscacheutil", e))?;
+    
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("name: "))
+        .map(|name| name.trim().to_string()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn reverse_lookup(_ip: IpAddr) -> Result<Option<String>> {
+    Ok(None)
+}
@@ -0,0 +1,94 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{IoContext, PortManagerError, Result};
+
+/// How often to check whether the command has finished
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Run an enrichment command through the shell, writing `<pid> <port>` to
+/// its stdin, and return the first line it prints. The command is killed if
+/// it hasn't finished within `timeout`.
+pub fn run_enrichment(command: &str, pid: u32, port: u16, timeout: Duration) -> Result<String> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| PortManagerError::spawn("sh", e))?;
+    
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its input may have closed stdin already
+        let _ = writeln!(stdin, "{} {}", pid, port);
+    }
+    
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().io_context("Failed to wait for enrichment command")? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(PortManagerError::CommandFailed {
+                command: command.to_string(),
+                message: format!("timed out after {}", humantime::format_duration(timeout)),
+            });
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    if let Some(mut pipe) = child.stdout.take() {
+        let _ = pipe.read_to_string(&mut stdout);
+    }
+    if let Some(mut pipe) = child.stderr.take() {
+        let _ = pipe.read_to_string(&mut stderr);
+    }
+    
+    if !status.success() {
+        let message = stderr.lines().next().map(str::trim).filter(|line| !line.is_empty());
+        return Err(PortManagerError::CommandFailed {
+            command: command.to_string(),
+            message: message.map(str::to_string).unwrap_or_else(|| status.to_string()),
+        });
+    }
+    
+    Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Stands in for a script mapping ports to pods
+    const DUMMY_SCRIPT: &str = r#"read pid port; echo "pod-$port (pid $pid)"; echo ignored"#;
+    
+    #[test]
+    fn script_gets_pid_and_port_on_stdin() {
+        let output = run_enrichment(DUMMY_SCRIPT, 4242, 3000, Duration::from_secs(5)).unwrap();
+        assert_eq!(output, "pod-3000 (pid 4242)");
+        assert_eq!(run_enrichment("true", 4242, 3000, Duration::from_secs(5)).unwrap(), "");
+    }
+    
+    #[test]
+    fn failures_report_the_first_stderr_line() {
+        let e = run_enrichment("echo 'no such pod' >&2; exit 3", 4242, 3000, Duration::from_secs(5)).unwrap_err();
+        assert_eq!(e.to_string(), "echo 'no such pod' >&2; exit 3 failed: no such pod");
+        
+        let e = run_enrichment("exit 3", 4242, 3000, Duration::from_secs(5)).unwrap_err();
+        assert_eq!(e.to_string(), "exit 3 failed: exit status: 3");
+    }
+    
+    #[test]
+    fn slow_commands_are_killed_after_the_timeout() {
+        let started = Instant::now();
+        let e = run_enrichment("sleep 10", 4242, 3000, Duration::from_millis(200)).unwrap_err();
+        assert_eq!(e.to_string(), "sleep 10 failed: timed out after 200ms");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,122 @@
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Ways the core of port-manager can fail. Kept apart so callers can react to
+/// the kind of failure, e.g. suggesting sudo on `PermissionDenied`.
+#[derive(Debug, Error)]
+pub enum PortManagerError {
+    /// A tool we run, like `lsof` or `kill`, isn't installed
+    #[error("{command} is not installed or not on the PATH")]
+    CommandNotFound { command: String },
+    
+    /// A tool ran but reported a failure
+    #[error("{command} failed: {message}")]
+    CommandFailed { command: String, message: String },
+    
+    /// The process belongs to another user
+    #[error("Permission denied sending SIG{signal} to PID {pid}; it may belong to another user")]
+    PermissionDenied { pid: u32, signal: String },
+    
+    /// The process had already exited
+    #[error("No process with PID {0}")]
+    ProcessNotFound(u32),
+    
+    /// No process owns the socket, so there is nothing to signal
+    #[error("No process owns this socket, so killing can't free it; it may be held by the kernel (check `ss -tlnpe`, or reboot)")]
+    OrphanedSocket,
+    
+    /// The process must never be signalled
+    #[error("Refusing to send SIG{signal} to {reason} (PID {pid})")]
+    ProtectedProcess { pid: u32, signal: String, reason: &'static str },
+    
+    /// Output or input couldn't be understood
+    #[error("Invalid {what}: {message}")]
+    ParseError { what: &'static str, message: String },
+    
+    /// The config file doesn't parse
+    #[error("Failed to parse config file {}: {error}", path.display())]
+    ConfigError { path: PathBuf, error: serde_json::Error },
+    
+    /// There is no home directory to keep the config in
+    #[error("Could not determine config directory")]
+    NoConfigDir,
+    
+    /// No usable discovery backend
+    #[error("{0}")]
+    BackendUnavailable(String),
+    
+    /// Reading or writing a file failed
+    #[error("{context}: {error}")]
+    Io { context: String, error: io::Error },
+    
+    /// The address to serve on is taken or not ours to use
+    #[error("Failed to listen on {address}: {error}")]
+    Bind { address: SocketAddr, error: io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, PortManagerError>;
+
+impl PortManagerError {
+    /// Error for a command that couldn't be started
+    pub fn spawn(command: &str, error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::NotFound {
+            PortManagerError::CommandNotFound { command: command.to_string() }
+        } else {
+            PortManagerError::Io { context: format!("Failed to execute {}", command), error }
+        }
+    }
+    
+    /// Error for a malformed value
+    pub fn parse(what: &'static str, message: impl ToString) -> Self {
+        PortManagerError::ParseError { what, message: message.to_string() }
+    }
+}
+
+/// Adds a description to I/O errors, like anyhow's `context`
+pub trait IoContext<T> {
+    fn io_context(self, context: impl Into<String>) -> Result<T>;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    fn io_context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|error| PortManagerError::Io { context: context.into(), error })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn spawn_errors_tell_missing_commands_apart() {
+        let error = PortManagerError::spawn("lsof", io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(&error, PortManagerError::CommandNotFound { command } if command == "lsof"));
+        assert_eq!(error.to_string(), "lsof is not installed or not on the PATH");
+        
+        let error = PortManagerError::spawn("lsof", io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(&error, PortManagerError::Io { context, .. } if context == "Failed to execute lsof"));
+    }
+    
+    #[test]
+    fn parse_and_io_errors_keep_their_context() {
+        let error = PortManagerError::parse("port", "70000 is out of range");
+        assert!(matches!(error, PortManagerError::ParseError { what: "port", .. }));
+        assert_eq!(error.to_string(), "Invalid port: 70000 is out of range");
+        
+        let result: io::Result<()> = Err(io::Error::other("disk full"));
+        let error = result.io_context("Failed to write config file").unwrap_err();
+        assert_eq!(error.to_string(), "Failed to write config file: disk full");
+    }
+    
+    #[test]
+    fn signal_errors_name_the_pid() {
+        let error = PortManagerError::PermissionDenied { pid: 4242, signal: "TERM".to_string() };
+        assert_eq!(error.to_string(), "Permission denied sending SIGTERM to PID 4242; it may belong to another user");
+        assert_eq!(PortManagerError::ProcessNotFound(4242).to_string(), "No process with PID 4242");
+        let error = PortManagerError::ProtectedProcess { pid: 1, signal: "KILL".to_string(), reason: "port-manager itself" };
+        assert_eq!(error.to_string(), "Refusing to send SIGKILL to port-manager itself (PID 1)");
+    }
+}
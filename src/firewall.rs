@@ -0,0 +1,259 @@
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+use crate::error::{PortManagerError, Result};
+
+/// Whether the system firewall lets new connections reach a port. This is a
+/// best-effort reading of the inbound rules: rules limited to some sources,
+/// connection states or other chains are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FirewallStatus {
+    /// Inbound connections are allowed
+    Open,
+    /// Inbound connections are dropped or rejected
+    Firewalled,
+    /// The rules couldn't be read, e.g. without root
+    Unknown(String),
+}
+
+impl FirewallStatus {
+    /// Text shown in the detail popup
+    pub fn label(&self) -> String {
+        match self {
+            FirewallStatus::Open => "open".to_string(),
+            FirewallStatus::Firewalled => "firewalled".to_string(),
+            FirewallStatus::Unknown(reason) => format!("unknown ({})", reason),
+        }
+    }
+}
+
+/// Check the system firewall's rules for a TCP port
+pub fn firewall_status(port: u16) -> FirewallStatus {
+    match read_status(port) {
+        Ok(status) => status,
+        Err(e) => FirewallStatus::Unknown(e.to_string()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_status(port: u16) -> Result<FirewallStatus> {
+    let rules = run_rules_command("iptables", &["-L", "INPUT", "-n"])?;
+    Ok(iptables_status(&rules, port))
+}
+
+#[cfg(target_os = "macos")]
+fn read_status(port: u16) -> Result<FirewallStatus> {
+    let rules = run_rules_command("pfctl", &["-sr"])?;
+    Ok(pf_status(&rules, port))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_status(_port: u16) -> Result<FirewallStatus> {
+    Ok(FirewallStatus::Unknown("not supported on this platform".to_string()))
+}
+
+/// Run a command that lists firewall rules and return its output
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run_rules_command(command: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(command)
+        .args(args)
+        .output()
+        .map_err(|e| PortManagerError::spawn(command, e))?;
+    
+    if !output.status.success() {
+        // Both tools explain a missing privilege on the first line
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(PortManagerError::CommandFailed {
+            command: command.to_string(),
+            message: error.lines().next().unwrap_or("no output").trim().to_string(),
+        });
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Find the verdict for a port in `iptables -L INPUT -n` output. The first
+/// matching rule wins, falling back to the chain's policy.
+#[cfg(target_os = "linux")]
+fn iptables_status(rules: &str, port: u16) -> FirewallStatus {
+    let mut policy = FirewallStatus::Open;
+    
+    for line in rules.lines() {
+        // Chain INPUT (policy DROP)
+        if let Some(rest) = line.strip_prefix("Chain INPUT (policy ") {
+            if matches!(rest.trim_end_matches(')'), "DROP" | "REJECT") {
+                policy = FirewallStatus::Firewalled;
+            }
+            continue;
+        }
+        
+        // ACCEPT  tcp  --  0.0.0.0/0  0.0.0.0/0  tcp dpt:22
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 5 {
+            continue;
+        }
+        let verdict = match fields[0] {
+            "ACCEPT" => FirewallStatus::Open,
+            "DROP" | "REJECT" => FirewallStatus::Firewalled,
+            _ => continue,
+        };
+        if !matches!(fields[1], "tcp" | "all" | "0") || fields[3] != "0.0.0.0/0" {
+            continue;
+        }
+        
+        let matches = fields[5..].iter().find_map(|field| {
+            if let Some(dport) = field.strip_prefix("dpt:") {
+                Some(dport.parse() == Ok(port))
+            } else if let Some(range) = field.strip_prefix("dpts:") {
+                let (start, end) = range.split_once(':')?;
+                Some(matches!((start.parse(), end.parse()), (Ok(start), Ok(end)) if (start..=end).contains(&port)))
+            } else {
+                None
+            }
+        });
+        match matches {
+            Some(true) => return verdict,
+            Some(false) => continue,
+            // Without a port, only rules with no other conditions apply to
+            // every new connection; "state RELATED,ESTABLISHED" doesn't
+            None if fields.len() == 5 => return verdict,
+            None => continue,
+        }
+    }
+    
+    policy
+}
+
+/// Find the verdict for a port in `pfctl -sr` output. The last matching rule
+/// wins unless a matching rule is marked `quick`, and pf passes everything
+/// no rule matches.
+#[cfg(target_os = "macos")]
+fn pf_status(rules: &str, port: u16) -> FirewallStatus {
+    let mut status = FirewallStatus::Open;
+    
+    for line in rules.lines() {
+        // pass in quick proto tcp from any to any port = 22
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let verdict = match words.first() {
+            Some(&"pass") => FirewallStatus::Open,
+            Some(&"block") => FirewallStatus::Firewalled,
+            _ => continue,
+        };
+        if words.contains(&"out") {
+            continue;
+        }
+        if let Some(index) = words.iter().position(|word| *word == "proto") {
+            if words.get(index + 1).is_some_and(|proto| *proto != "tcp") {
+                continue;
+            }
+        }
+        
+        let applies = match words.iter().position(|word| *word == "port") {
+            Some(index) => pf_port_matches(&words[index + 1..], port),
+            None => true,
+        };
+        if applies {
+            status = verdict;
+            if words.contains(&"quick") {
+                break;
+            }
+        }
+    }
+    
+    status
+}
+
+/// Check a pf port expression: `= 22`, `22`, `8000:8100` or `{ 80 443 }`
+#[cfg(target_os = "macos")]
+fn pf_port_matches(words: &[&str], port: u16) -> bool {
+    let ports: &[&str] = match words {
+        ["=", rest @ ..] => &rest[..rest.len().min(1)],
+        ["{", rest @ ..] => {
+            let end = rest.iter().position(|word| *word == "}").unwrap_or(rest.len());
+            &rest[..end]
+        }
+        _ => &words[..words.len().min(1)],
+    };
+    
+    ports.iter().any(|spec| {
+        let spec = spec.trim_end_matches(',');
+        match spec.split_once(':') {
+            Some((start, end)) => matches!((start.parse(), end.parse()), (Ok(start), Ok(end)) if (start..=end).contains(&port)),
+            None => spec.parse() == Ok(port),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[cfg(target_os = "linux")]
+    const IPTABLES_RULES: &str = "Chain INPUT (policy DROP)
+target     prot opt source               destination
+ACCEPT     all  --  0.0.0.0/0            0.0.0.0/0            state RELATED,ESTABLISHED
+ACCEPT     tcp  --  0.0.0.0/0            0.0.0.0/0            tcp dpt:22
+ACCEPT     tcp  --  10.0.0.0/8           0.0.0.0/0            tcp dpt:5432
+REJECT     tcp  --  0.0.0.0/0            0.0.0.0/0            tcp dpt:8080 reject-with icmp-port-unreachable
+ACCEPT     tcp  --  0.0.0.0/0            0.0.0.0/0            tcp dpts:8000:8100
+";
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn iptables_first_matching_rule_wins() {
+        assert_eq!(iptables_status(IPTABLES_RULES, 22), FirewallStatus::Open);
+        assert_eq!(iptables_status(IPTABLES_RULES, 8050), FirewallStatus::Open);
+        assert_eq!(iptables_status(IPTABLES_RULES, 8080), FirewallStatus::Firewalled);
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn iptables_falls_back_to_the_policy() {
+        // Rules limited to some sources or connection states are skipped
+        assert_eq!(iptables_status(IPTABLES_RULES, 5432), FirewallStatus::Firewalled);
+        assert_eq!(iptables_status(IPTABLES_RULES, 3000), FirewallStatus::Firewalled);
+        
+        let open = "Chain INPUT (policy ACCEPT)\ntarget     prot opt source               destination\n";
+        assert_eq!(iptables_status(open, 3000), FirewallStatus::Open);
+        let drop_all = format!("{}DROP       all  --  0.0.0.0/0            0.0.0.0/0\n", open);
+        assert_eq!(iptables_status(&drop_all, 3000), FirewallStatus::Firewalled);
+    }
+    
+    #[cfg(target_os = "macos")]
+    const PF_RULES: &str = "block drop in all
+pass in quick proto tcp from any to any port = 22
+pass in proto tcp from any to any port { 80 443 }
+pass in proto tcp from any to any port 8000:8100
+block drop in proto tcp from any to any port = 8080
+pass in proto udp from any to any port = 3000
+pass out all
+";
+    
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn pf_last_matching_rule_wins() {
+        assert_eq!(pf_status(PF_RULES, 80), FirewallStatus::Open);
+        assert_eq!(pf_status(PF_RULES, 443), FirewallStatus::Open);
+        assert_eq!(pf_status(PF_RULES, 8050), FirewallStatus::Open);
+        assert_eq!(pf_status(PF_RULES, 8080), FirewallStatus::Firewalled);
+        // UDP and outbound rules don't apply
+        assert_eq!(pf_status(PF_RULES, 3000), FirewallStatus::Firewalled);
+    }
+    
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn pf_quick_rules_stop_matching() {
+        assert_eq!(pf_status(PF_RULES, 22), FirewallStatus::Open);
+        let rules = "block in quick proto tcp from any to any port = 22\npass in all\n";
+        assert_eq!(pf_status(rules, 22), FirewallStatus::Firewalled);
+        assert_eq!(pf_status(rules, 23), FirewallStatus::Open);
+        assert_eq!(pf_status("", 22), FirewallStatus::Open);
+    }
+    
+    #[test]
+    fn status_labels() {
+        assert_eq!(FirewallStatus::Open.label(), "open");
+        assert_eq!(FirewallStatus::Firewalled.label(), "firewalled");
+        assert_eq!(FirewallStatus::Unknown("iptables is not installed or not on the PATH".to_string()).label(), "unknown (iptables is not installed or not on the PATH)");
+    }
+}
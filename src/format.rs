@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::{IoContext, Result};
+use crate::process::{AddressFamily, PortProcess};
+
+/// Format a byte count with one decimal place in the largest fitting unit,
+/// using powers of 1024 (KiB, MiB, ...) when `binary` is set and powers of
+/// 1000 (KB, MB, ...) otherwise
+pub fn format_bytes(bytes: u64, binary: bool) -> String {
+    let (base, units) = if binary {
+        (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"])
+    } else {
+        (1000.0, ["B", "KB", "MB", "GB", "TB", "PB", "EB"])
+    };
+    
+    // Whole bytes don't need a decimal place
+    if (bytes as f64) < base {
+        return format!("{} B", bytes);
+    }
+    
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    
+    // Rounding can carry into the next unit, e.g. 999,999 bytes
+    if (value * 10.0).round() / 10.0 >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    
+    format!("{:.1} {}", value, units[unit])
+}
+
+/// Format for printing the process list without starting the TUI
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for reading in a terminal
+    List,
+    /// A JSON array of processes
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Prometheus text-format metrics, e.g. for a node-exporter textfile collector
+    Prometheus,
+}
+
+/// A process as written by the JSON formatter
+#[derive(Serialize)]
+struct ProcessRecord<'a> {
+    pid: u32,
+    name: &'a str,
+    /// Null when the port couldn't be resolved
+    port: Option<u16>,
+    address: &'a str,
+    family: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote: Option<&'a str>,
+    /// TCP state, e.g. `LISTEN` or `CLOSE_WAIT`
+    state: &'static str,
+    user: &'a str,
+    cpu_usage: f32,
+    /// Resident memory in bytes
+    memory: u64,
+    /// Null where threads can't be counted
+    threads: Option<usize>,
+    command: &'a str,
+}
+
+/// Options shared by the formatters
+pub struct WriteOptions {
+    /// Show memory in binary units in the list format
+    pub binary_units: bool,
+    /// Mask command lines
+    pub hide_commands: bool,
+}
+
+/// Write processes to `out` in the given format
+pub fn write_processes(out: &mut dyn Write, format: OutputFormat, processes: &[PortProcess], options: &WriteOptions) -> Result<()> {
+    match format {
+        OutputFormat::List => write_list(out, processes, options),
+        OutputFormat::Json => write_json(out, processes, options),
+        OutputFormat::Csv => write_csv(out, processes, options),
+        OutputFormat::Prometheus => write_prometheus(out, processes),
+    }
+    .io_context("Failed to write processes")
+}
+
+/// Label for a process's address family
+fn family_label(process: &PortProcess) -> &'static str {
+    match (process.dual_stack, process.family) {
+        (true, _) => "dual",
+        (false, AddressFamily::Ipv4) => "ipv4",
+        (false, AddressFamily::Ipv6) => "ipv6",
+    }
+}
+
+/// Write one line per process with aligned columns
+fn write_list(out: &mut dyn Write, processes: &[PortProcess], options: &WriteOptions) -> io::Result<()> {
+    let name_width = processes.iter()
+        .map(|p| p.name.chars().count())
+        .chain(std::iter::once("NAME".len()))
+        .max()
+        .unwrap_or(0);
+    
+    writeln!(out, "{:>7}  {:>5}  {:<name_width$}  {:>6}  {:>10}  COMMAND", "PID", "PORT", "NAME", "CPU%", "MEMORY")?;
+    for process in processes {
+        writeln!(
+            out,
+            "{:>7}  {:>5}  {:<name_width$}  {:>6.1}  {:>10}  {}",
+            process.pid,
+            process.port_text(),
+            process.name,
+            process.cpu_usage,
+            format_bytes(process.memory, options.binary_units),
+            process.display_command(options.hide_commands),
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a pretty-printed JSON array
+fn write_json(out: &mut dyn Write, processes: &[PortProcess], options: &WriteOptions) -> io::Result<()> {
+    let records: Vec<ProcessRecord> = processes.iter()
+        .map(|process| ProcessRecord {
+            pid: process.pid,
+            name: &process.name,
+            port: process.unresolved_port.is_none().then_some(process.port),
+            address: &process.local_addr,
+            family: family_label(process),
+            remote: process.remote.as_deref(),
+            state: process.state.label(),
+            user: &process.user,
+            cpu_usage: process.cpu_usage,
+            memory: process.memory,
+            threads: process.threads,
+            command: process.display_command(options.hide_commands),
+        })
+        .collect();
+    
+    serde_json::to_writer_pretty(&mut *out, &records)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Column names of the CSV and TSV formats
+const RECORD_HEADER: [&str; 10] = ["pid", "port", "name", "address", "family", "remote", "user", "cpu_usage", "memory", "command"];
+
+/// A process's values for the CSV and TSV formats, with memory in bytes
+fn record_fields(process: &PortProcess, options: &WriteOptions) -> [String; 10] {
+    [
+        process.pid.to_string(),
+        process.port_text(),
+        process.name.clone(),
+        process.local_addr.clone(),
+        family_label(process).to_string(),
+        process.remote.clone().unwrap_or_default(),
+        process.user.clone(),
+        format!("{:.1}", process.cpu_usage),
+        process.memory.to_string(),
+        process.display_command(options.hide_commands).to_string(),
+    ]
+}
+
+/// Write CSV with memory in bytes, so it can be summed in a spreadsheet
+fn write_csv(out: &mut dyn Write, processes: &[PortProcess], options: &WriteOptions) -> io::Result<()> {
+    writeln!(out, "{}", RECORD_HEADER.join(","))?;
+    for process in processes {
+        let fields = record_fields(process, options).map(|field| csv_field(&field));
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// A process as one tab-separated line with the same columns as the CSV
+/// format. Tabs and line breaks inside values become spaces.
+pub fn tsv_row(process: &PortProcess, options: &WriteOptions) -> String {
+    record_fields(process, options)
+        .map(|field| field.replace(['\t', '\n', '\r'], " "))
+        .join("\t")
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Reads a gauge's value from a process, `None` when it isn't known
+type GaugeValue = fn(&PortProcess) -> Option<String>;
+
+/// Write Prometheus text-format metrics: one `port_manager_listener` sample
+/// per socket, and resource gauges per process, since a process holding
+/// several ports would otherwise be counted once for each
+fn write_prometheus(out: &mut dyn Write, processes: &[PortProcess]) -> io::Result<()> {
+    writeln!(out, "# HELP port_manager_listener A socket held by a process, always 1")?;
+    writeln!(out, "# TYPE port_manager_listener gauge")?;
+    for process in processes {
+        let mut labels = vec![
+            ("port", process.port_text()),
+            ("process", process.name.clone()),
+            ("pid", process.pid.to_string()),
+            ("address", process.local_addr.clone()),
+            ("family", family_label(process).to_string()),
+            ("state", process.state.label().to_string()),
+            ("user", process.user.clone()),
+        ];
+        if let Some(remote) = &process.remote {
+            labels.push(("remote", remote.clone()));
+        }
+        writeln!(out, "port_manager_listener{{{}}} 1", prometheus_labels(&labels))?;
+    }
+    
+    let by_pid: BTreeMap<u32, &PortProcess> = processes.iter()
+        .filter(|process| !process.orphaned)
+        .map(|process| (process.pid, process))
+        .collect();
+    let gauges: [(&str, &str, GaugeValue); 3] = [
+        ("port_manager_memory_bytes", "Resident memory of a process in bytes", |p| Some(p.memory.to_string())),
+        ("port_manager_cpu_usage_percent", "CPU usage of a process in percent", |p| Some(format!("{:.1}", p.cpu_usage))),
+        ("port_manager_threads", "Number of threads of a process", |p| p.threads.map(|threads| threads.to_string())),
+    ];
+    for (metric, help, value) in gauges {
+        writeln!(out, "# HELP {} {}", metric, help)?;
+        writeln!(out, "# TYPE {} gauge", metric)?;
+        for (pid, process) in &by_pid {
+            if let Some(value) = value(process) {
+                let labels = prometheus_labels(&[("pid", pid.to_string()), ("process", process.name.clone())]);
+                writeln!(out, "{}{{{}}} {}", metric, labels, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Join labels as `name="value"` pairs, escaping backslashes, quotes and line
+/// breaks as the text format requires
+fn prometheus_labels(labels: &[(&str, String)]) -> String {
+    labels.iter()
+        .map(|(name, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PortManagerError;
+    use crate::process::HIDDEN_COMMAND;
+    
+    fn listener(pid: u32, name: &str, port: u16) -> PortProcess {
+        PortProcess {
+            user: "alice".to_string(),
+            memory: 1024,
+            ..PortProcess::without_lookups(pid, name.to_string(), port, "*".to_string(), AddressFamily::Ipv4, None)
+        }
+    }
+    
+    fn prometheus(processes: &[PortProcess]) -> String {
+        let mut out = Vec::new();
+        write_prometheus(&mut out, processes).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+    
+    /// A writer whose output has nowhere to go, like a closed pipe
+    struct BrokenPipe;
+    
+    impl Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+        
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    #[test]
+    fn write_failures_are_io_errors() {
+        let options = WriteOptions { binary_units: false, hide_commands: false };
+        for format in [OutputFormat::List, OutputFormat::Json, OutputFormat::Csv, OutputFormat::Prometheus] {
+            let error = write_processes(&mut BrokenPipe, format, &[listener(4242, "node", 3000)], &options).unwrap_err();
+            assert!(matches!(&error, PortManagerError::Io { error, .. } if error.kind() == io::ErrorKind::BrokenPipe));
+            assert!(error.to_string().starts_with("Failed to write processes: "));
+        }
+    }
+    
+    #[test]
+    fn bytes_format_in_human_units() {
+        assert_eq!(format_bytes(0, false), "0 B");
+        assert_eq!(format_bytes(999, false), "999 B");
+        assert_eq!(format_bytes(1000, false), "1.0 KB");
+        assert_eq!(format_bytes(1536, false), "1.5 KB");
+        assert_eq!(format_bytes(999_999, false), "1.0 MB");
+        assert_eq!(format_bytes(2_500_000_000, false), "2.5 GB");
+        assert_eq!(format_bytes(u64::MAX, false), "18.4 EB");
+        
+        assert_eq!(format_bytes(1023, true), "1023 B");
+        assert_eq!(format_bytes(1024, true), "1.0 KiB");
+        assert_eq!(format_bytes(1536, true), "1.5 KiB");
+        assert_eq!(format_bytes(512 * 1024 * 1024, true), "512.0 MiB");
+        assert_eq!(format_bytes(u64::MAX, true), "16.0 EiB");
+    }
+    
+    #[test]
+    fn prometheus_labels_escape_special_characters() {
+        let labels = prometheus_labels(&[
+            ("process", r"C:\bin\node".to_string()),
+            ("command", r#"say "hi""#.to_string()),
+            ("note", "two\nlines".to_string()),
+        ]);
+        assert_eq!(labels, r#"process="C:\\bin\\node",command="say \"hi\"",note="two\nlines""#);
+    }
+    
+    #[test]
+    fn prometheus_escapes_process_names() {
+        let output = prometheus(&[listener(42, "my \"app\"\\v2\n", 3000)]);
+        assert!(output.contains(r#"port_manager_listener{port="3000",process="my \"app\"\\v2\n",pid="42","#), "{}", output);
+        assert!(output.contains(r#"port_manager_memory_bytes{pid="42",process="my \"app\"\\v2\n"} 1024"#), "{}", output);
+        // Every sample stays on one line
+        assert!(output.lines().all(|line| line.starts_with('#') || line.ends_with(" 1") || line.ends_with(" 1024") || line.ends_with(" 0.0")), "{}", output);
+    }
+    
+    #[test]
+    fn prometheus_counts_a_process_once_per_gauge() {
+        let output = prometheus(&[listener(42, "node", 3000), listener(42, "node", 3001)]);
+        assert_eq!(output.matches("port_manager_listener{").count(), 2);
+        assert_eq!(output.matches("port_manager_memory_bytes{").count(), 1);
+        // Threads aren't known, so there's no sample for them
+        assert!(!output.contains("port_manager_threads{"));
+    }
+    
+    #[test]
+    fn masked_output_contains_no_command_text() {
+        let process = PortProcess {
+            command: "node server.js --token=s3cr3t".to_string(),
+            ..listener(42, "node", 3000)
+        };
+        let options = WriteOptions { binary_units: false, hide_commands: true };
+        for format in [OutputFormat::List, OutputFormat::Json, OutputFormat::Csv, OutputFormat::Prometheus] {
+            let mut out = Vec::new();
+            write_processes(&mut out, format, std::slice::from_ref(&process), &options).unwrap();
+            let output = String::from_utf8(out).unwrap();
+            assert!(!output.contains("s3cr3t") && !output.contains("server.js"), "{:?}: {}", format, output);
+            if !matches!(format, OutputFormat::Prometheus) {
+                assert!(output.contains(HIDDEN_COMMAND), "{:?}: {}", format, output);
+            }
+        }
+        
+        // The command is still there when not masked
+        let mut out = Vec::new();
+        write_processes(&mut out, OutputFormat::Json, &[process], &WriteOptions { binary_units: false, hide_commands: false }).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("s3cr3t"));
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::error::{PortManagerError, Result};
+
+/// How long `kubectl` may wait for the cluster before giving up
+const REQUEST_TIMEOUT: &str = "2s";
+
+/// What a local Kubernetes cluster has to do with a port, as far as can be
+/// told from the listener's command line and the local kubeconfig
+#[derive(Debug, Clone, PartialEq)]
+pub enum KubernetesInfo {
+    /// A `kubectl port-forward` to a pod or service
+    PortForward { context: Option<String>, namespace: String, target: String },
+    /// The API server of a cluster in the kubeconfig
+    ApiServer { cluster: String },
+    /// A NodePort service of the current context
+    NodePort { context: Option<String>, namespace: String, service: String },
+    /// Nothing in the cluster maps to the port
+    NotFound,
+    /// The cluster couldn't be asked, e.g. without a kubeconfig
+    Unknown(String),
+}
+
+impl KubernetesInfo {
+    /// Text shown in the detail popup
+    pub fn label(&self) -> String {
+        let in_context = |context: &Option<String>| {
+            context.as_ref().map(|context| format!(" (context {})", context)).unwrap_or_default()
+        };
+        match self {
+            KubernetesInfo::PortForward { context, namespace, target } => {
+                format!("port-forward to {} in namespace {}{}", target, namespace, in_context(context))
+            }
+            KubernetesInfo::ApiServer { cluster } => format!("API server of cluster {}", cluster),
+            KubernetesInfo::NodePort { context, namespace, service } => {
+                format!("NodePort of service {}/{}{}", namespace, service, in_context(context))
+            }
+            KubernetesInfo::NotFound => "not from the cluster".to_string(),
+            KubernetesInfo::Unknown(reason) => format!("unknown ({})", reason),
+        }
+    }
+}
+
+/// Find out what a local cluster has to do with a listener. Port-forwards are
+/// read from the command line; the API server and NodePorts need `kubectl`.
+pub fn kubernetes_info(process_name: &str, command: &str, port: u16) -> KubernetesInfo {
+    if process_name == "kubectl" {
+        if let Some(info) = parse_port_forward(command) {
+            return info;
+        }
+    }
+    if !has_kubeconfig() {
+        return KubernetesInfo::Unknown("no kubeconfig".to_string());
+    }
+    
+    let found = api_server_cluster(port).and_then(|cluster| match cluster {
+        Some(cluster) => Ok(Some(KubernetesInfo::ApiServer { cluster })),
+        None => node_port_service(port),
+    });
+    match found {
+        Ok(Some(info)) => info,
+        Ok(None) => KubernetesInfo::NotFound,
+        Err(e) => KubernetesInfo::Unknown(e.to_string()),
+    }
+}
+
+/// Read the target of a `kubectl port-forward` command line, e.g.
+/// `kubectl port-forward -n web svc/api 8080:80`
+fn parse_port_forward(command: &str) -> Option<KubernetesInfo> {
+    let args: Vec<&str> = command.split_whitespace().collect();
+    let start = args.iter().position(|arg| *arg == "port-forward")?;
+    
+    let mut context = None;
+    let mut namespace = None;
+    let mut target = None;
+    let mut rest = args[start + 1..].iter();
+    while let Some(arg) = rest.next() {
+        if let Some((flag, value)) = arg.split_once('=') {
+            match flag {
+                "-n" | "--namespace" => namespace = Some(value.to_string()),
+                "--context" => context = Some(value.to_string()),
+                _ => {}
+            }
+            continue;
+        }
+        match *arg {
+            "-n" | "--namespace" => namespace = rest.next().map(|value| value.to_string()),
+            "--context" => context = rest.next().map(|value| value.to_string()),
+            // Flags with a value that isn't needed here
+            "--address" | "--pod-running-timeout" | "--kubeconfig" => {
+                rest.next();
+            }
+            flag if flag.starts_with('-') => {}
+            // The first positional argument is the resource; a bare name is a pod
+            resource if target.is_none() => {
+                target = Some(if resource.contains('/') { resource.to_string() } else { format!("pod/{}", resource) });
+            }
+            _ => {}
+        }
+    }
+    
+    Some(KubernetesInfo::PortForward {
+        context: context.or_else(current_context),
+        namespace: namespace.unwrap_or_else(|| "default".to_string()),
+        target: target?,
+    })
+}
+
+/// Check for a kubeconfig where `kubectl` looks for one
+fn has_kubeconfig() -> bool {
+    if let Some(paths) = std::env::var_os("KUBECONFIG") {
+        return std::env::split_paths(&paths).any(|path| path.is_file());
+    }
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(".kube").join("config"))
+        .is_some_and(|path| path.is_file())
+}
+
+/// Name of the kubeconfig's current context
+fn current_context() -> Option<String> {
+    let context = kubectl(&["config", "current-context"]).ok()?;
+    let context = context.trim();
+    (!context.is_empty()).then(|| context.to_string())
+}
+
+/// Find a cluster in the kubeconfig whose API server is on this machine's port.
+/// kind, k3d and minikube all publish the API server on a local port.
+fn api_server_cluster(port: u16) -> Result<Option<String>> {
+    let clusters = kubectl(&[
+        "config", "view", "-o",
+        r#"jsonpath={range .clusters[*]}{.name}{" "}{.cluster.server}{"\n"}{end}"#,
+    ])?;
+    
+    Ok(clusters.lines().find_map(|line| {
+        let (name, server) = line.split_once(' ')?;
+        // https://127.0.0.1:6443
+        let authority = server.split_once("://").map_or(server, |(_, rest)| rest);
+        let authority = authority.split('/').next()?;
+        let (host, server_port) = authority.rsplit_once(':')?;
+        let local = matches!(host, "127.0.0.1" | "localhost" | "0.0.0.0" | "[::1]");
+        (local && server_port.parse() == Ok(port)).then(|| name.to_string())
+    }))
+}
+
+/// Find a NodePort service of the current context on the port
+fn node_port_service(port: u16) -> Result<Option<KubernetesInfo>> {
+    let services = kubectl(&[
+        "get", "services", "--all-namespaces",
+        &format!("--request-timeout={}", REQUEST_TIMEOUT),
+        "-o", r#"jsonpath={range .items[*]}{.metadata.namespace}{" "}{.metadata.name}{" "}{.spec.ports[*].nodePort}{"\n"}{end}"#,
+    ])?;
+    
+    // default web 30080 30443
+    let found = services.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let namespace = fields.next()?;
+        let service = fields.next()?;
+        fields.any(|node_port| node_port.parse() == Ok(port))
+            .then(|| (namespace.to_string(), service.to_string()))
+    });
+    Ok(found.map(|(namespace, service)| KubernetesInfo::NodePort {
+        context: current_context(),
+        namespace,
+        service,
+    }))
+}
+
+/// Run `kubectl` and return its output
+fn kubectl(args: &[&str]) -> Result<String> {
+    let output = Command::new("kubectl")
+        .args(args)
+        .output()
+        .map_err(|e| PortManagerError::spawn("kubectl", e))?;
+    
+    if !output.status.success() {
+        // e.g. "The connection to the server localhost:8080 was refused"
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(PortManagerError::CommandFailed {
+            command: "kubectl".to_string(),
+            message: error.lines().next().unwrap_or("no output").trim().to_string(),
+        });
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -1,36 +1,362 @@
+mod audit;
+mod backend;
 mod config;
+mod diff;
+mod dns;
+mod enrich;
+mod error;
+mod firewall;
+mod format;
+mod kube;
+mod notify;
+mod process;
+mod serve;
+mod services;
+mod session;
+mod stats;
+mod systemd;
 
-use std::process::Command;
-use std::io;
-use std::time::Duration;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Result, Context};
-use clap::Parser;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use clap::{Parser, ValueEnum};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::ExecutableCommand;
 use ratatui::prelude::*;
 use ratatui::widgets::*;
 use ratatui::style::{Color, Style, Stylize};
-use sysinfo::System;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use sysinfo::{Pid, System};
 
-use config::Config;
+use audit::{audit_kill, KillRecord};
+use backend::{select_backend, Backend, BackendKind, CapturedLsofBackend, SshBackend, DEMO_ENV_VAR, LSOF_INPUT_ENV_VAR};
+use config::{AgeTier, AutoKillRules, BaselineEntry, BorderStyle, CleanupScript, CleanupStep, Config, ConfigSource, ConfirmStyle, DimStyle, ExitedProcesses, FilterEntry, FilterImport, FilterPreset, FilterRule, FilterSettings, HighlightStyle, KeyAction, KillPolicy, MatchMode, Palette, ProcessLayout, SearchField, SortColumn, TableColumn, DEFAULT_PROFILE};
+use firewall::{firewall_status, FirewallStatus};
+use format::{format_bytes, tsv_row, write_processes, OutputFormat, WriteOptions};
+use kube::{kubernetes_info, KubernetesInfo};
+use notify::{has_display, notify};
+use process::{accept_queue, count_open_fds, count_threads, exclude_own_processes, hide_loopback_only, kill_script, merge_dual_stack, protected_reason, socket_summary, sort_processes, take_ps_lookup_time, AcceptQueue, AddressFamily, DiscoveryMode, PortProcess, TcpState, HIDDEN_COMMAND};
+use serve::DEFAULT_SERVE_ADDR;
+use services::ServiceNames;
+use session::Session;
+use stats::KillStats;
+use systemd::{restart_command, systemd_unit, RestartCommand};
 
 /// CLI tool to manage processes running on ports
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Show processes running on this specific port
+    /// Show processes running on these ports, repeated or comma-separated,
+    /// e.g. --port 3000,8080
+    #[arg(short, long, value_delimiter = ',')]
+    port: Vec<u16>,
+    
+    /// Only show processes on ports in this range, e.g. 3000-3100
+    #[arg(long, value_name = "START-END", value_parser = parse_port_range, conflicts_with = "port")]
+    range: Option<RangeInclusive<u16>>,
+    
+    /// Only show processes in the network namespace with this inode (Linux only)
+    #[arg(long, value_name = "INODE")]
+    net_namespace: Option<u64>,
+    
+    /// Show processes with connections (in any state but LISTEN) instead of listeners
+    #[arg(long)]
+    connections: bool,
+    
+    /// Only show sockets in this TCP state, e.g. TIME_WAIT or CLOSE_WAIT.
+    /// States other than LISTEN imply --connections
+    #[arg(long, value_name = "STATE")]
+    state: Option<TcpState>,
+    
+    /// Only show listeners served from containers: processes in a Docker,
+    /// Podman or Kubernetes container, and Docker's port proxy (Linux only)
+    #[arg(long, conflicts_with = "host_only")]
+    containers_only: bool,
+    
+    /// Only show processes running directly on this machine, not in containers
+    #[arg(long)]
+    host_only: bool,
+    
+    /// Only show the N processes using the most resources
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+    
+    /// Resource to rank by when using --top
+    #[arg(long, value_enum, default_value_t = ResourceKey::Memory)]
+    top_by: ResourceKey,
+    
+    /// Hide processes started less than this long ago, e.g. 5m or 1h
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    older_than: Option<Duration>,
+    
+    /// Only show processes started within this long ago, e.g. 5m, to see what
+    /// a deploy or test run just started
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    since: Option<Duration>,
+    
+    /// Kill listeners matching the `auto_kill` rules in the config, then exit
+    #[arg(long)]
+    auto_kill: bool,
+    
+    /// Kill whatever is listening on this port, then exit (can be repeated)
+    #[arg(long, value_name = "PORT")]
+    kill_port: Vec<u16>,
+    
+    /// Kill listeners whose name matches, using the filter match mode, then exit (can be repeated)
+    #[arg(long, value_name = "NAME")]
+    kill_name: Vec<String>,
+    
+    /// Kill listeners matching the cleanup script saved under this name with
+    /// `j` in the TUI, then exit
+    #[arg(long, value_name = "NAME")]
+    run_cleanup: Option<String>,
+    
+    /// After killing listeners, also offer to kill processes that still have
+    /// connections on their ports
+    #[arg(long)]
+    kill_connections: bool,
+    
+    /// Don't ask before killing by name or killing connected processes
     #[arg(short, long)]
-    port: Option<u16>,
+    yes: bool,
+    
+    /// Exit with an error, naming them, if anything is listening that isn't in
+    /// the baseline set with E in the TUI
+    #[arg(long)]
+    check_baseline: bool,
+    
+    /// Exit with an error, naming the listener, if something is listening on this port
+    #[arg(long, value_name = "PORT")]
+    check_port: Option<u16>,
+    
+    /// Print only the PIDs listening on this port, one per line, exiting with an error if there are none
+    #[arg(long, value_name = "PORT", conflicts_with = "check_port")]
+    pid_on: Option<u16>,
+    
+    /// Color rows whose name, command or port contains this text, without hiding others
+    #[arg(long, value_name = "TEXT")]
+    highlight: Option<String>,
+    
+    /// Dim every row not on this port, without hiding any
+    #[arg(long, value_name = "PORT")]
+    focus: Option<u16>,
+    
+    /// Show processes hidden by the filtered names and PIDs, for this run only
+    #[arg(long)]
+    no_filters: bool,
+    
+    /// With --no-filters, also ignore the configured port range, showing
+    /// system services on privileged ports
+    #[arg(long, requires = "no_filters")]
+    include_system: bool,
+    
+    /// Wait until something listens on this port, print it, then exit
+    #[arg(long, value_name = "PORT", conflicts_with = "wait_until_free")]
+    wait_for: Option<u16>,
+    
+    /// Wait until nothing listens on this port any more, then exit
+    #[arg(long, value_name = "PORT")]
+    wait_until_free: Option<u16>,
+    
+    /// Give up waiting after this long, e.g. 30s, exiting with an error
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    timeout: Option<Duration>,
+    
+    /// Only print errors, reporting through the exit code (with --check-port, --pid-on, waits and kills)
+    #[arg(short, long)]
+    quiet: bool,
+    
+    /// Show what --auto-kill/--kill-port/--kill-name, or kills in the TUI, would kill without killing anything
+    #[arg(long)]
+    dry_run: bool,
+    
+    /// Use a named config profile (created with defaults if missing)
+    #[arg(long, value_name = "NAME", default_value = DEFAULT_PROFILE)]
+    profile: String,
+    
+    /// Read and save configuration at this path instead of the config directory
+    #[arg(long, value_name = "PATH", conflicts_with = "profile")]
+    config: Option<PathBuf>,
+    
+    /// Exit with an error if the config file doesn't parse, instead of backing
+    /// it up and starting with defaults
+    #[arg(long)]
+    strict_config: bool,
+    
+    /// How to discover processes (auto picks the first available)
+    #[arg(long, value_enum, default_value_t = BackendKind::Auto)]
+    backend: BackendKind,
+    
+    /// Refresh the process list once, print how long each step took, then exit
+    #[arg(long)]
+    time_refresh: bool,
+    
+    /// Parse lsof output captured in this file (`-` for standard input)
+    /// instead of running lsof, e.g. from `lsof -iTCP -P -n > ports.txt`.
+    /// Also PORT_MANAGER_LSOF_INPUT=<PATH>
+    #[arg(long, value_name = "PATH")]
+    lsof_input: Option<PathBuf>,
+    
+    /// Show the processes of another machine, running lsof, ps and kill there
+    /// over SSH, e.g. --host deploy@staging. Needs a key-based login.
+    #[arg(long, value_name = "USER@HOST", value_parser = parse_ssh_host, conflicts_with = "lsof_input")]
+    host: Option<String>,
+    
+    /// Show made-up processes instead of real ones (also PORT_MANAGER_DEMO=1)
+    #[arg(long, hide = true)]
+    demo: bool,
+    
+    /// Print the process list in this format and exit instead of starting the TUI
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+    
+    /// Serve the process list as JSON over HTTP on this address instead of starting the TUI
+    #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = DEFAULT_SERVE_ADDR)]
+    serve: Option<SocketAddr>,
+    
+    /// Print the process list every refresh interval instead of starting the TUI
+    #[arg(long, conflicts_with = "output")]
+    watch: bool,
+    
+    /// With --watch, print the full list once, then only the ports added,
+    /// removed or taken over since the previous refresh
+    #[arg(long, requires = "watch")]
+    deltas: bool,
+    
+    /// With --watch --deltas, print a line for refreshes that changed nothing
+    #[arg(long, requires = "deltas")]
+    heartbeat: bool,
+    
+    /// Write the --format output to this file instead of stdout
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+    
+    /// Write the process name filters to a JSON file to share, then exit
+    #[arg(long, value_name = "PATH", conflicts_with = "import_filters")]
+    export_filters: Option<PathBuf>,
+    
+    /// Merge the process name filters from a file written by --export-filters, then exit
+    #[arg(long, value_name = "PATH")]
+    import_filters: Option<PathBuf>,
+    
+    /// Show the ports added, removed or taken over between two --format json snapshots, then exit
+    #[arg(long, num_args = 2, value_names = ["BEFORE", "AFTER"])]
+    diff: Option<Vec<PathBuf>>,
 }
 
-/// Represents a process running on a port
-struct PortProcess {
-    pid: u32,
-    name: String,
-    port: u16,
-    command: String,
+/// Number of refreshes of connection counts kept per port
+const CONNECTION_HISTORY_LEN: usize = 60;
+
+/// How often non-interactive kills check whether a port has been freed
+const KILL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often `--watch` refreshes when `refresh_interval_ms` is 0
+const DEFAULT_WATCH_INTERVAL_MS: u64 = 2000;
+
+/// Shortest time between two kills by an armed auto-kill
+const ARMED_KILL_COOLDOWN: Duration = Duration::from_secs(2);
+
+/// Kills an armed auto-kill may make per minute before disarming itself
+const ARMED_KILL_LIMIT: usize = 10;
+
+/// How long the detail popup waits for a reverse lookup before showing the
+/// bare address
+const REVERSE_DNS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often --wait-for and --wait-until-free check the port
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of processes shown when top mode is toggled on without --top
+const DEFAULT_TOP_COUNT: usize = 10;
+
+/// File the filter view exports filters to and imports them from, in the
+/// current directory
+const FILTER_EXPORT_FILE: &str = "port-manager-filters.json";
+
+/// Number of filter changes that can be undone
+const UNDO_LIMIT: usize = 20;
+
+/// Fields the highlight pattern is matched against
+const HIGHLIGHT_FIELDS: [SearchField; 3] = [SearchField::Name, SearchField::Command, SearchField::Port];
+
+/// What has to be typed to confirm a clean slate kill
+const CLEAN_SLATE_CONFIRMATION: &str = "kill all";
+
+/// Minimum age of shown processes when hiding recent ones without --older-than
+const DEFAULT_OLDER_THAN: Duration = Duration::from_secs(5 * 60);
+
+/// Resource used to rank processes in top mode
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ResourceKey {
+    Memory,
+    Cpu,
+}
+
+impl ResourceKey {
+    fn label(&self) -> &'static str {
+        match self {
+            ResourceKey::Memory => "memory",
+            ResourceKey::Cpu => "CPU",
+        }
+    }
+}
+
+/// Where the processes shown run, when only one kind is shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Placement {
+    Container,
+    Host,
+}
+
+impl Placement {
+    fn label(&self) -> &'static str {
+        match self {
+            Placement::Container => "containers",
+            Placement::Host => "host processes",
+        }
+    }
+}
+
+/// Parse a `start-end` port range, both ends inclusive
+fn parse_port_range(value: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = value.split_once('-')
+        .ok_or_else(|| format!("expected START-END, got {:?}", value))?;
+    let parse = |port: &str| {
+        port.trim().parse::<u16>()
+            .map_err(|_| format!("{:?} is not a port between 0 and 65535", port.trim()))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        return Err(format!("range start {} is after its end {}", start, end));
+    }
+    Ok(start..=end)
+}
+
+/// Parse an SSH destination, refusing ones ssh would read as an option
+fn parse_ssh_host(value: &str) -> std::result::Result<String, String> {
+    if value.is_empty() || value.starts_with('-') || value.contains(char::is_whitespace) {
+        return Err(format!("{:?} is not an SSH destination like user@host", value));
+    }
+    Ok(value.to_string())
+}
+
+/// The backend chosen on the command line, captured lsof output when
+/// `--lsof-input` is given, or another machine with `--host`
+fn discovery_backend(args: &Args) -> Result<Box<dyn Backend>> {
+    Ok(match (&args.lsof_input, &args.host) {
+        (Some(path), _) => Box::new(CapturedLsofBackend::new(path.clone())?),
+        (None, Some(host)) => Box::new(SshBackend::new(host.clone())),
+        (None, None) => select_backend(args.backend)?,
+    })
 }
 
 /// UI view states
@@ -39,6 +365,129 @@ enum View {
     FilterManagement,
 }
 
+/// What an auto-kill armed with `Y` kills on sight
+#[derive(Debug, Clone, PartialEq)]
+enum ArmedTarget {
+    Port(u16),
+    Name(String),
+}
+
+impl ArmedTarget {
+    fn matches(&self, process: &PortProcess) -> bool {
+        match self {
+            ArmedTarget::Port(port) => process.unresolved_port.is_none() && process.port == *port,
+            ArmedTarget::Name(name) => process.name == *name,
+        }
+    }
+    
+    fn label(&self) -> String {
+        match self {
+            ArmedTarget::Port(port) => format!("port {}", port),
+            ArmedTarget::Name(name) => format!("\"{}\"", name),
+        }
+    }
+}
+
+/// An auto-kill armed for this session only, with when it last killed
+struct ArmedKill {
+    target: ArmedTarget,
+    /// Kills in the last minute, oldest first
+    recent_kills: VecDeque<Instant>,
+    total_kills: usize,
+}
+
+impl ArmedKill {
+    fn new(target: ArmedTarget) -> Self {
+        Self { target, recent_kills: VecDeque::new(), total_kills: 0 }
+    }
+}
+
+/// Result of killing one process in a bulk kill
+struct KillOutcome {
+    pid: u32,
+    name: String,
+    /// Every shown port the process held, all freed by the one kill
+    ports: Vec<u16>,
+    /// Why the kill failed, if it did
+    error: Option<String>,
+}
+
+/// Fields of the first-run setup popup, in the order Tab moves through them
+#[derive(Clone, Copy, PartialEq)]
+enum SetupField {
+    PortRange,
+    SystemPorts,
+    Filters,
+}
+
+impl SetupField {
+    fn next(self) -> Self {
+        match self {
+            SetupField::PortRange => SetupField::SystemPorts,
+            SetupField::SystemPorts => SetupField::Filters,
+            SetupField::Filters => SetupField::PortRange,
+        }
+    }
+    
+    fn previous(self) -> Self {
+        match self {
+            SetupField::PortRange => SetupField::Filters,
+            SetupField::SystemPorts => SetupField::PortRange,
+            SetupField::Filters => SetupField::SystemPorts,
+        }
+    }
+}
+
+/// Answers typed into the first-run setup popup, prefilled from the defaults
+struct SetupForm {
+    field: SetupField,
+    /// Port range as `start-end`
+    port_range: String,
+    /// Filter patterns separated by commas
+    filters: String,
+    /// Why the answers were rejected
+    error: Option<String>,
+}
+
+impl SetupForm {
+    fn new(config: &Config) -> Self {
+        Self {
+            field: SetupField::PortRange,
+            port_range: format!("{}-{}", config.min_port, config.max_port),
+            filters: config.filtered_process_names.iter()
+                .map(|filter| filter.pattern.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            error: None,
+        }
+    }
+    
+    /// Whether the typed range reaches below 1024
+    fn includes_system_ports(&self) -> bool {
+        parse_port_range(&self.port_range).is_ok_and(|range| *range.start() < 1024)
+    }
+    
+    /// Flip the system ports choice by moving the start of the typed range
+    fn toggle_system_ports(&mut self) {
+        let Ok(range) = parse_port_range(&self.port_range) else {
+            self.error = Some("Fix the port range first".to_string());
+            return;
+        };
+        let start = if *range.start() < 1024 { 1024.min(*range.end()) } else { 1 };
+        self.port_range = format!("{}-{}", start, range.end());
+        self.error = None;
+    }
+    
+    /// The text input focused, if the focused field is one
+    fn input_mut(&mut self) -> Option<&mut String> {
+        match self.field {
+            SetupField::PortRange => Some(&mut self.port_range),
+            SetupField::SystemPorts => None,
+            SetupField::Filters => Some(&mut self.filters),
+        }
+    }
+}
+
 /// Application state
 struct App {
     port_processes: Vec<PortProcess>,
@@ -46,641 +495,8179 @@ struct App {
     should_quit: bool,
     config: Config,
     current_view: View,
+    /// Position of the selected filter in the displayed list
     filter_selected_idx: Option<usize>,
+    /// Show filters in alphabetical order instead of their stored order
+    filters_sorted: bool,
+    /// Filter settings from before each recent change, newest last
+    undo_stack: VecDeque<FilterSettings>,
+    /// Filter settings undone since the last change, newest last
+    redo_stack: Vec<FilterSettings>,
+    /// Guided setup shown on the first launch, before any config existed
+    show_setup_popup: bool,
+    setup: SetupForm,
     show_add_filter_popup: bool,
     add_filter_input: String,
+    /// Why the last filter input was rejected
+    add_filter_error: Option<String>,
+    show_kill_confirm: bool,
+    /// PIDs the open kill confirmation applies to
+    kill_targets: Vec<u32>,
+    /// Also add the target's name to the filters once it's killed
+    kill_and_filter: bool,
+    /// How to restart the service the single kill target runs under, offered
+    /// instead of the kill
+    kill_restart: Option<RestartCommand>,
+    /// The pending kill is a clean slate, which needs the strong confirmation
+    clean_slate: bool,
+    /// Visible PIDs the pending clean slate leaves running
+    kill_kept: Vec<u32>,
+    /// Typed confirmation for bulk kills
+    kill_confirm_input: String,
+    kill_confirm_scroll: u16,
+    /// PIDs marked for a bulk kill
+    marked_pids: BTreeSet<u32>,
+    /// Make listening ports clickable links in terminals that support OSC 8
+    port_links: bool,
+    /// PIDs ignored with `I`, forgotten on exit since PIDs get reused
+    ignored_pids: BTreeSet<u32>,
+    /// Filter patterns switched off for now, with when they come back on
+    /// (never, until restart, when `None`)
+    snoozed_filters: HashMap<String, Option<Instant>>,
+    show_kill_results: bool,
+    /// Outcome of each kill in the last bulk kill
+    kill_results: Vec<KillOutcome>,
+    kill_results_scroll: u16,
+    /// Set when state changed and the screen needs to be redrawn
+    needs_redraw: bool,
+    /// When the process list was last refreshed
+    last_refresh: Instant,
+    /// Auto-refresh is paused while inspecting the list
+    refresh_paused: bool,
+    /// Refreshes in a row that found the same sockets, used to back off auto-refresh
+    unchanged_refreshes: u32,
+    /// Everything found by the last discovery, before filtering
+    discovered: Vec<PortProcess>,
+    /// Set while the live search is being typed
+    search_active: bool,
+    search_query: String,
+    /// Fields the live search matches, starting from the config
+    search_fields: Vec<SearchField>,
+    /// Set while the highlight pattern is being typed
+    highlight_active: bool,
+    /// Rows matching this are colored, but nothing is hidden
+    highlight_query: String,
+    /// Rows on other ports are dimmed, but nothing is hidden
+    focus_port: Option<u16>,
+    discovery_mode: DiscoveryMode,
+    backend: Box<dyn Backend>,
+    /// Only show processes on these ports, unless empty
+    port_filter: Vec<u16>,
+    /// Only show processes on ports in this range, instead of the configured range
+    port_range: Option<RangeInclusive<u16>>,
+    /// Only show processes in this network namespace
+    net_namespace: Option<u64>,
+    /// Kept between refreshes so CPU usage can be measured
+    system: System,
+    /// Only show the `top_count` heaviest processes
+    top_enabled: bool,
+    top_count: usize,
+    top_by: ResourceKey,
+    /// Ignore the filtered names and PIDs, and with `include_system` the port range
+    no_filters: bool,
+    include_system: bool,
+    /// Report kills instead of sending signals
+    dry_run: bool,
+    /// Index of the preset shown instead of the configured filters
+    active_preset: Option<usize>,
+    /// Hide processes younger than `min_age`
+    hide_recent: bool,
+    min_age: Duration,
+    /// Hide processes started longer ago than this
+    max_age: Option<Duration>,
+    /// Only show sockets in this TCP state
+    state_filter: Option<TcpState>,
+    /// Only show containerized or host processes
+    placement_filter: Option<Placement>,
+    /// Only show listeners missing from the baseline
+    unexpected_only: bool,
+    /// Auto-kill armed with `Y`, never saved so it has to be armed again
+    /// each session
+    armed_kill: Option<ArmedKill>,
+    /// Kills recorded since `J` started recording a cleanup script, or
+    /// `None` when not recording
+    recorded_cleanup: Option<Vec<CleanupStep>>,
+    /// Naming the recorded cleanup script before it's saved
+    show_cleanup_name_popup: bool,
+    cleanup_name_input: String,
+    /// Mask command lines in the UI
+    hide_commands: bool,
+    /// Show only executables in the Command column
+    short_commands: bool,
+    /// Characters scrolled off the start of the Command column
+    command_scroll: usize,
+    /// Hide processes that only listen on loopback addresses
+    hide_loopback: bool,
+    /// Back up and reset config files that don't parse instead of failing
+    recover_config: bool,
+    /// Where profiles are kept, the platform config directory unless set
+    config_dir: Option<PathBuf>,
+    show_profile_popup: bool,
+    profile_names: Vec<String>,
+    profile_selected_idx: usize,
+    show_details: bool,
+    /// Firewall verdict for the port in the detail popup, if checking is enabled
+    firewall: Option<FirewallStatus>,
+    /// Cluster details for the port in the detail popup, if enabled
+    kubernetes: Option<KubernetesInfo>,
+    /// Names of the services usually found on each port
+    services: ServiceNames,
+    /// Open file descriptors of the process in the detail popup, or why they
+    /// couldn't be counted
+    open_fds: Option<Result<usize, String>>,
+    /// Accept queue of the listener in the detail popup, or why it couldn't
+    /// be read
+    accept_queue: Option<Result<AcceptQueue, String>>,
+    /// Hostnames found for bind addresses, or `None` when there was none
+    hostnames: HashMap<IpAddr, Option<String>>,
+    /// Reverse lookups still running, with when they started
+    hostname_lookups: HashMap<IpAddr, Instant>,
+    /// Lookup threads send their results here
+    hostname_sender: mpsc::Sender<(IpAddr, Option<String>)>,
+    hostname_receiver: mpsc::Receiver<(IpAddr, Option<String>)>,
+    /// Output of the enrichment command for the process in the detail popup,
+    /// or why it failed. `None` while the command is running.
+    enrichment: Option<Result<String, String>>,
+    /// PID and port the running enrichment command was started for
+    enrichment_target: Option<(u32, u16)>,
+    /// The enrichment thread sends its result here
+    enrichment_sender: mpsc::Sender<((u32, u16), Result<String, String>)>,
+    enrichment_receiver: mpsc::Receiver<((u32, u16), Result<String, String>)>,
+    /// Show start times as timestamps, starting from the config's choice
+    absolute_times: bool,
+    /// Color rows by process age, starting from the config's choice
+    age_coloring: bool,
+    /// Group the table under a header per bind address
+    group_by_address: bool,
+    /// Group the table under a header per systemd unit
+    group_by_unit: bool,
+    /// Show where each detail came from in the detail popup
+    show_detail_sources: bool,
+    /// Recent established connection counts per listening port, oldest first
+    connection_history: HashMap<u16, VecDeque<u64>>,
+    /// When each discovered port was first seen this session, unlike the
+    /// start time of the process holding it
+    port_first_seen: HashMap<u16, Instant>,
+    /// CPU usage of each discovered PID at the last refresh
+    previous_cpu: HashMap<u32, f32>,
+    /// PIDs whose CPU usage jumped at the last refresh, shown until the next
+    cpu_spikes: HashSet<u32>,
+    /// Result of the last action, shown in place of the help until the next key press
+    status_message: Option<String>,
+    show_action_popup: bool,
+    /// Set while the kill stats are shown
+    show_stats_popup: bool,
+    /// Set while every process list key is listed
+    show_help_popup: bool,
+    /// Kill counts read when the stats were opened
+    kill_stats: KillStats,
+    action_selected_idx: usize,
+    show_config_popup: bool,
+    /// Raw config file contents shown in the config popup
+    config_text: String,
+    config_scroll: u16,
+    /// Why the edited config was not applied
+    config_error: Option<String>,
 }
 
 impl App {
-    fn new() -> Result<Self> {
+    fn new(args: &Args) -> Result<Self> {
+        let discovery_mode = if args.connections || args.state.is_some_and(|state| state != TcpState::Listen) {
+            DiscoveryMode::Connections
+        } else {
+            DiscoveryMode::Listeners
+        };
+        
+        let config = load_config(args)?;
+        let services = ServiceNames::load(config.services_file.as_deref())?;
+        let status_message = startup_message(&config);
+        let (hostname_sender, hostname_receiver) = mpsc::channel();
+        let (enrichment_sender, enrichment_receiver) = mpsc::channel();
+        
         Ok(Self {
             port_processes: Vec::new(),
             selected_idx: None,
             should_quit: false,
-            config: Config::load()?,
+            hide_commands: config.hide_commands,
+            short_commands: config.short_commands,
+            hide_loopback: config.hide_loopback_only,
+            search_fields: config.search_fields.clone(),
+            absolute_times: config.absolute_times,
+            age_coloring: config.age_coloring,
+            group_by_address: false,
+            group_by_unit: false,
+            port_links: config.port_links,
+            setup: SetupForm::new(&config),
+            config,
             current_view: View::ProcessList,
             filter_selected_idx: None,
+            filters_sorted: false,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            show_setup_popup: false,
             show_add_filter_popup: false,
             add_filter_input: String::new(),
+            add_filter_error: None,
+            show_kill_confirm: false,
+            kill_targets: Vec::new(),
+            kill_and_filter: false,
+            kill_restart: None,
+            clean_slate: false,
+            kill_kept: Vec::new(),
+            kill_confirm_input: String::new(),
+            kill_confirm_scroll: 0,
+            marked_pids: BTreeSet::new(),
+            ignored_pids: BTreeSet::new(),
+            snoozed_filters: HashMap::new(),
+            show_kill_results: false,
+            kill_results: Vec::new(),
+            kill_results_scroll: 0,
+            needs_redraw: true,
+            last_refresh: Instant::now(),
+            refresh_paused: false,
+            unchanged_refreshes: 0,
+            discovered: Vec::new(),
+            search_active: false,
+            highlight_active: false,
+            highlight_query: args.highlight.clone().unwrap_or_default(),
+            focus_port: args.focus,
+            search_query: String::new(),
+            discovery_mode,
+            backend: discovery_backend(args)?,
+            port_filter: args.port.clone(),
+            port_range: args.range.clone(),
+            net_namespace: args.net_namespace,
+            system: System::new(),
+            top_enabled: args.top.is_some(),
+            top_count: args.top.unwrap_or(DEFAULT_TOP_COUNT),
+            top_by: args.top_by,
+            no_filters: args.no_filters,
+            include_system: args.include_system,
+            dry_run: args.dry_run,
+            active_preset: None,
+            hide_recent: args.older_than.is_some(),
+            min_age: args.older_than.unwrap_or(DEFAULT_OLDER_THAN),
+            max_age: args.since,
+            state_filter: args.state,
+            placement_filter: match (args.containers_only, args.host_only) {
+                (true, _) => Some(Placement::Container),
+                (_, true) => Some(Placement::Host),
+                _ => None,
+            },
+            unexpected_only: false,
+            armed_kill: None,
+            recorded_cleanup: None,
+            show_cleanup_name_popup: false,
+            cleanup_name_input: String::new(),
+            recover_config: !args.strict_config,
+            config_dir: None,
+            command_scroll: 0,
+            show_profile_popup: false,
+            profile_names: Vec::new(),
+            profile_selected_idx: 0,
+            show_details: false,
+            firewall: None,
+            kubernetes: None,
+            services,
+            open_fds: None,
+            accept_queue: None,
+            hostnames: HashMap::new(),
+            hostname_lookups: HashMap::new(),
+            hostname_sender,
+            hostname_receiver,
+            enrichment: None,
+            enrichment_target: None,
+            enrichment_sender,
+            enrichment_receiver,
+            show_detail_sources: false,
+            connection_history: HashMap::new(),
+            port_first_seen: HashMap::new(),
+            previous_cpu: HashMap::new(),
+            cpu_spikes: HashSet::new(),
+            status_message,
+            show_action_popup: false,
+            show_stats_popup: false,
+            show_help_popup: false,
+            kill_stats: KillStats::default(),
+            action_selected_idx: 0,
+            show_config_popup: false,
+            config_text: String::new(),
+            config_scroll: 0,
+            config_error: None,
         })
     }
 
     /// Reload process list
     fn refresh_processes(&mut self) -> Result<()> {
-        let all_processes = get_port_processes()?;
+        self.wake_snoozed_filters();
+        let mut discovered = get_port_processes(
+            self.backend.as_ref(),
+            self.discovery_mode,
+            &mut self.system,
+            self.config.exited_processes,
+        )?;
+        if self.config.systemd_units && self.backend.is_local() {
+            for process in discovered.iter_mut().filter(|process| !process.orphaned) {
+                process.systemd_unit = systemd_unit(process.pid);
+            }
+        }
+        self.unchanged_refreshes = if same_sockets(&self.discovered, &discovered) {
+            self.unchanged_refreshes.saturating_add(1)
+        } else {
+            0
+        };
+        self.discovered = discovered;
+        self.last_refresh = Instant::now();
+        self.track_first_seen();
+        self.detect_cpu_spikes();
+        self.apply_view();
+        self.run_armed_kill();
+        
+        // Track connection counts for listeners, dropping ports that went away
+        if self.discovery_mode == DiscoveryMode::Listeners {
+            let counts = self.backend.connection_counts()?;
+            let visible_ports: BTreeSet<u16> = self.port_processes.iter().map(|p| p.port).collect();
+            self.connection_history.retain(|port, _| visible_ports.contains(port));
+            for port in visible_ports {
+                let history = self.connection_history.entry(port).or_default();
+                history.push_back(counts.get(&port).copied().unwrap_or(0));
+                if history.len() > CONNECTION_HISTORY_LEN {
+                    history.pop_front();
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Arm an auto-kill for the selected process's port, then switch it to
+    /// the process's name, then disarm it
+    fn cycle_armed_kill(&mut self) {
+        let selected = self.selected_process().map(|p| (p.port, p.name.clone()));
+        self.armed_kill = match (self.armed_kill.take(), selected) {
+            (None, Some((port, _))) => Some(ArmedKill::new(ArmedTarget::Port(port))),
+            (Some(armed), Some((port, name))) if armed.target == ArmedTarget::Port(port) => {
+                Some(ArmedKill::new(ArmedTarget::Name(name)))
+            }
+            _ => None,
+        };
+        self.status_message = Some(match &self.armed_kill {
+            Some(armed) => format!(
+                "Auto-kill armed for {}: every refresh kills it on sight. Y again to change or disarm",
+                armed.target.label(),
+            ),
+            None => "Auto-kill disarmed".to_string(),
+        });
+    }
+    
+    /// Start recording kills into a cleanup script, or stop and ask for a
+    /// name to save it under
+    fn toggle_cleanup_recording(&mut self) {
+        match &self.recorded_cleanup {
+            None => {
+                self.recorded_cleanup = Some(Vec::new());
+                let key = key_names(&self.config, KeyAction::RecordCleanup);
+                self.status_message = Some(format!("Recording kills into a cleanup script; {} again to save it", key));
+            }
+            Some(steps) if steps.is_empty() => {
+                self.recorded_cleanup = None;
+                self.status_message = Some("Stopped recording: nothing was killed".to_string());
+            }
+            Some(_) => self.show_cleanup_name_popup = true,
+        }
+    }
+    
+    /// Add the ports freed by successful kills to the cleanup script being
+    /// recorded, by name and port since PIDs change on every restart
+    fn record_cleanup_steps(&mut self, outcomes: &[KillOutcome]) {
+        let Some(steps) = &mut self.recorded_cleanup else {
+            return;
+        };
+        for outcome in outcomes.iter().filter(|outcome| outcome.error.is_none()) {
+            for &port in &outcome.ports {
+                let step = CleanupStep { name: Some(outcome.name.clone()), port: Some(port) };
+                if !steps.contains(&step) {
+                    steps.push(step);
+                }
+            }
+        }
+    }
+    
+    /// Save the recorded cleanup script under the typed name, replacing one
+    /// of the same name
+    fn save_cleanup_script(&mut self) -> Result<()> {
+        let name = self.cleanup_name_input.trim().to_string();
+        if name.is_empty() {
+            return Ok(());
+        }
+        let steps = self.recorded_cleanup.take().unwrap_or_default();
+        let count = steps.len();
+        self.config.save_cleanup_script(CleanupScript { name: name.clone(), steps })?;
+        self.close_cleanup_name_popup();
+        self.status_message = Some(format!("Saved cleanup script {} ({} step(s)); replay it with --run-cleanup {}", name, count, name));
+        Ok(())
+    }
+    
+    /// Discard the recording instead of saving it
+    fn discard_cleanup_recording(&mut self) {
+        self.recorded_cleanup = None;
+        self.close_cleanup_name_popup();
+        self.status_message = Some("Discarded the recorded cleanup script".to_string());
+    }
+    
+    /// Close the name prompt of the cleanup script
+    fn close_cleanup_name_popup(&mut self) {
+        self.show_cleanup_name_popup = false;
+        self.cleanup_name_input.clear();
+    }
+    
+    /// Kill whatever the armed auto-kill matches, filtered or not. Kills are
+    /// spaced out, and a process that keeps coming back disarms the rule
+    /// rather than being killed in a storm. Protected ports are left alone.
+    fn run_armed_kill(&mut self) {
+        let Some(armed) = &mut self.armed_kill else {
+            return;
+        };
+        let now = Instant::now();
+        armed.recent_kills.retain(|at| now.duration_since(*at) < Duration::from_secs(60));
+        if armed.recent_kills.back().is_some_and(|last| now.duration_since(*last) < ARMED_KILL_COOLDOWN) {
+            return;
+        }
+        
+        let mut targets: Vec<(u32, String, Vec<u16>)> = Vec::new();
+        for process in &self.discovered {
+            if process.orphaned || process.exited || !armed.target.matches(process)
+                || self.config.port_policy(process.port) == Some(KillPolicy::Protected) {
+                continue;
+            }
+            match targets.iter_mut().find(|(pid, _, _)| *pid == process.pid) {
+                Some((_, _, ports)) => ports.push(process.port),
+                None => targets.push((process.pid, process.name.clone(), vec![process.port])),
+            }
+        }
+        if targets.is_empty() {
+            return;
+        }
+        if armed.recent_kills.len() >= ARMED_KILL_LIMIT {
+            self.status_message = Some(format!(
+                "Auto-kill for {} disarmed after {} kills in a minute; it keeps coming back",
+                armed.target.label(), ARMED_KILL_LIMIT,
+            ));
+            self.armed_kill = None;
+            return;
+        }
+        armed.recent_kills.push_back(now);
+        armed.total_kills += targets.len();
+        
+        let names: Vec<String> = targets.iter().map(|(pid, name, _)| format!("{} (PID {})", name, pid)).collect();
+        if self.backend.is_demo() || self.dry_run {
+            self.status_message = Some(format!("Dry run: auto-kill would send SIG{} to {}", self.config.kill_signal, names.join(", ")));
+            return;
+        }
+        
+        let mut outcomes = Vec::new();
+        for (pid, name, ports) in targets {
+            let error = self.backend.send_signal(pid, &self.config.kill_signal).err().map(|e| format!("{:#}", e));
+            if self.config.audit_kills {
+                audit_kill(&KillRecord { pid, name: &name, ports: &ports, signal: &self.config.kill_signal, error: error.as_deref() });
+            }
+            outcomes.push(KillOutcome { pid, name, ports, error });
+        }
+        if self.config.kill_stats {
+            self.record_kill_stats(&outcomes);
+        }
+        self.status_message = Some(match outcomes.iter().find_map(|outcome| outcome.error.as_ref()) {
+            Some(error) => format!("Auto-kill failed: {}", error),
+            None => format!("Auto-killed {}", names.join(", ")),
+        });
+    }
+    
+    /// Note when ports show up, forgetting those that went away so a port
+    /// that comes back counts as new
+    fn track_first_seen(&mut self) {
+        let ports: BTreeSet<u16> = self.discovered.iter().map(|p| p.port).collect();
+        self.port_first_seen.retain(|port, _| ports.contains(port));
+        for port in ports {
+            self.port_first_seen.entry(port).or_insert(self.last_refresh);
+        }
+    }
+    
+    /// Note which processes' CPU usage rose by at least `cpu_spike_threshold`
+    /// points since the previous refresh. Processes seen for the first time
+    /// have nothing to compare against, so they never count as spiking.
+    fn detect_cpu_spikes(&mut self) {
+        let current: HashMap<u32, f32> = self.discovered.iter().map(|p| (p.pid, p.cpu_usage)).collect();
+        let threshold = self.config.cpu_spike_threshold;
+        self.cpu_spikes = current.iter()
+            .filter(|_| threshold > 0.0)
+            .filter(|(pid, cpu)| self.previous_cpu.get(pid).is_some_and(|previous| **cpu - previous >= threshold))
+            .map(|(pid, _)| *pid)
+            .collect();
+        self.previous_cpu = current;
+    }
+    
+    /// Rebuild the visible rows from the last discovered processes, so view
+    /// changes like searching don't have to wait for discovery
+    fn apply_view(&mut self) {
+        let mut all_processes = self.discovered.clone();
+        // Decide on loopback before merging, which only keeps one address per pair
+        if self.hide_loopback && self.discovery_mode == DiscoveryMode::Listeners {
+            all_processes = hide_loopback_only(all_processes);
+        }
+        if self.config.merge_dual_stack {
+            all_processes = merge_dual_stack(all_processes);
+        }
         
         // Filter processes based on configuration
-        self.port_processes = all_processes.into_iter()
+        let now = SystemTime::now();
+        let preset = self.active_preset().map(|preset| preset.matcher(self.config.match_mode));
+        let shown = all_processes.into_iter()
             .filter(|process| {
-                // Check if the port is within range
-                let port_in_range = process.port >= self.config.min_port && 
-                                    process.port <= self.config.max_port;
+                // Check if the port is within range. An explicit --port or --range
+                // replaces the configured range, and since the local side of a client
+                // connection is usually an ephemeral port, that only applies to listeners.
+                // Rows with an unknown port can't be checked against the range,
+                // so showing them at all is opt-in
+                let port_in_range = match (self.port_filter.as_slice(), &self.port_range) {
+                    _ if process.unresolved_port.is_some() => self.config.show_unresolved_ports,
+                    ([_, ..], _) => self.port_filter.contains(&process.port),
+                    ([], Some(range)) => range.contains(&process.port),
+                    ([], None) => self.discovery_mode == DiscoveryMode::Connections ||
+                            self.include_system ||
+                            (process.port >= self.config.min_port &&
+                             process.port <= self.config.max_port),
+                };
                 
                 // Check if the process name is in the filter list
-                let name_not_filtered = !self.config.filtered_process_names
-                    .iter()
-                    .any(|filtered| process.name.contains(filtered));
+                let name_not_filtered = self.no_filters || !self.config.is_filtered(&process.name, |pattern| self.snoozed_filters.contains_key(pattern));
+                
+                // Rules can combine the name with the command, port and user
+                let rules_not_matched = self.no_filters ||
+                    !self.config.is_rule_filtered(&process.name, &process.command, process.port, &process.user);
+                
+                let pid_not_filtered = self.no_filters ||
+                    !(self.ignored_pids.contains(&process.pid) || self.config.is_pid_filtered(process.pid));
+                
+                let matches_search = self.search_query.is_empty()
+                    || process.matches_search(&self.search_query, &self.search_fields, self.hide_commands);
+                
+                // Processes whose namespace can't be read never match a namespace filter
+                let in_namespace = self.net_namespace.is_none()
+                    || process.net_namespace == self.net_namespace;
+                
+                // Processes with an unknown start time are kept, since they may be old
+                let old_enough = !self.hide_recent
+                    || process.age(now).is_none_or(|age| age >= self.min_age);
+                
+                let new_enough = self.max_age
+                    .is_none_or(|max_age| process.started_within(now, max_age));
+                
+                let in_state = self.state_filter.is_none_or(|state| process.state == state);
                 
-                port_in_range && name_not_filtered
+                let in_baseline_view = !self.unexpected_only || self.is_unexpected(process);
+                
+                let in_placement = match self.placement_filter {
+                    Some(Placement::Container) => process.is_container(),
+                    Some(Placement::Host) => !process.is_container(),
+                    None => true,
+                };
+                
+                // An active preset stands in for the port range and filters
+                let passes_filters = match &preset {
+                    Some(in_preset) => in_preset(process.port, &process.name),
+                    None => port_in_range && name_not_filtered && rules_not_matched,
+                };
+                
+                passes_filters && pid_not_filtered && matches_search && in_namespace && old_enough && new_enough && in_state && in_placement && in_baseline_view
             })
             .collect();
+        // The preset's matcher borrows the config
+        drop(preset);
+        self.port_processes = shown;
+        
+        sort_processes(&mut self.port_processes, self.config.sort_column, self.config.sort_descending);
+        
+        // Keep only the heaviest processes in top mode, which ranks by resource
+        // rather than the sort column
+        if self.top_enabled {
+            match self.top_by {
+                ResourceKey::Memory => self.port_processes.sort_by_key(|p| std::cmp::Reverse(p.memory)),
+                ResourceKey::Cpu => self.port_processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage)),
+            }
+            self.port_processes.truncate(self.top_count);
+        }
+        
+        // Sink system processes to the bottom. The sort is stable, so each
+        // group keeps the order from above.
+        if self.config.system_processes_last {
+            self.port_processes.sort_by_key(PortProcess::is_system);
+        }
+        
+        // Gather processes bound to the same address, most exposed first. The
+        // sort is stable, so each group keeps the order from above.
+        if self.group_by_address {
+            self.port_processes.sort_by(|a, b| {
+                (a.bind_scope().0, &a.local_addr).cmp(&(b.bind_scope().0, &b.local_addr))
+            });
+        }
+        
+        // Gather processes by systemd unit, with those outside any unit last
+        if self.group_by_unit {
+            self.port_processes.sort_by(|a, b| {
+                (a.systemd_unit.is_none(), &a.systemd_unit).cmp(&(b.systemd_unit.is_none(), &b.systemd_unit))
+            });
+        }
+        
+        // Forget marks on processes that are no longer shown
+        let visible_pids: BTreeSet<u32> = self.port_processes.iter().map(|p| p.pid).collect();
+        self.marked_pids.retain(|pid| visible_pids.contains(pid));
         
         // Update process list selection
-        if !self.port_processes.is_empty() && self.selected_idx.is_none() {
+        if self.port_processes.is_empty() {
+            self.selected_idx = None;
+        } else if self.selected_idx.is_none() {
             self.selected_idx = Some(0);
+        } else if let Some(selected) = self.selected_idx {
+            self.selected_idx = Some(selected.min(self.port_processes.len() - 1));
         }
         
         // Update filter list selection if in filter view
         if !self.config.filtered_process_names.is_empty() && self.filter_selected_idx.is_none() {
             self.filter_selected_idx = Some(0);
         }
-        
-        Ok(())
     }
     
-    /// Toggle between views
-    fn toggle_view(&mut self) {
-        match self.current_view {
-            View::ProcessList => self.current_view = View::FilterManagement,
-            View::FilterManagement => self.current_view = View::ProcessList,
+    /// The preset being shown, if any
+    fn active_preset(&self) -> Option<&FilterPreset> {
+        self.active_preset.and_then(|index| self.config.presets.get(index))
+    }
+    
+    /// The UI state to save for the next launch
+    fn session(&self) -> Session {
+        Session {
+            filter_view: matches!(self.current_view, View::FilterManagement),
+            selected_pid: self.selected_process().map(|p| p.pid),
+            search_query: self.search_query.clone(),
+            preset: self.active_preset().map(|preset| preset.name.clone()),
+            refresh_paused: self.refresh_paused,
+            top_enabled: self.top_enabled,
+            hide_loopback: self.hide_loopback,
+            hide_recent: self.hide_recent,
+            group_by_address: self.group_by_address,
         }
     }
     
-    /// Toggle add filter popup
-    fn toggle_add_filter_popup(&mut self) {
-        self.show_add_filter_popup = !self.show_add_filter_popup;
-        if !self.show_add_filter_popup {
-            self.add_filter_input.clear();
+    /// Pick up where the last session left off. Call after a refresh, so the
+    /// selected process can be found again; if it has exited, or its preset
+    /// was removed, that part is skipped.
+    fn restore_session(&mut self, session: Session) {
+        if session.filter_view {
+            self.current_view = View::FilterManagement;
+        }
+        self.search_query = session.search_query;
+        self.active_preset = session.preset
+            .and_then(|name| self.config.presets.iter().position(|preset| preset.name == name));
+        self.refresh_paused = session.refresh_paused;
+        self.top_enabled = session.top_enabled;
+        self.hide_loopback = session.hide_loopback;
+        self.hide_recent = session.hide_recent;
+        self.group_by_address = session.group_by_address;
+        self.apply_view();
+        
+        if let Some(index) = session.selected_pid
+            .and_then(|pid| self.port_processes.iter().position(|p| p.pid == pid))
+        {
+            self.selected_idx = Some(index);
         }
     }
     
-    /// Add character to filter input
-    fn add_char_to_filter(&mut self, c: char) {
-        self.add_filter_input.push(c);
+    /// Show the next preset, going back to the configured filters after the last
+    fn cycle_preset(&mut self) {
+        if self.config.presets.is_empty() {
+            self.status_message = Some("No presets configured".to_string());
+            return;
+        }
+        
+        self.active_preset = match self.active_preset {
+            None => Some(0),
+            Some(index) if index + 1 < self.config.presets.len() => Some(index + 1),
+            Some(_) => None,
+        };
+        self.status_message = Some(match self.active_preset() {
+            Some(preset) => format!("Showing preset \"{}\"", preset.name),
+            None => "Showing configured filters".to_string(),
+        });
+        self.apply_view();
     }
     
-    /// Delete character from filter input
-    fn delete_char_from_filter(&mut self) {
-        self.add_filter_input.pop();
+    /// Start typing a live search
+    fn open_search(&mut self) {
+        self.search_active = true;
     }
     
-    /// Save the current filter input
-    fn save_filter(&mut self) -> Result<()> {
-        let filter = self.add_filter_input.trim().to_string();
-        if !filter.is_empty() {
-            self.config.add_filtered_process(filter)?;
-            self.refresh_processes()?;
-        }
-        self.toggle_add_filter_popup();
-        Ok(())
+    /// Stop typing, keeping the search applied
+    fn close_search(&mut self) {
+        self.search_active = false;
     }
     
-    /// Add current process to filter list
-    fn filter_selected_process(&mut self) -> Result<()> {
-        if let Some(selected) = self.selected_idx {
-            if let Some(process) = self.port_processes.get(selected) {
-                let process_name = process.name.clone();
-                self.config.add_filtered_process(process_name)?;
-                self.refresh_processes()?;
-            }
-        }
-        Ok(())
+    /// Stop typing and show every process again
+    fn clear_search(&mut self) {
+        self.search_active = false;
+        self.search_query.clear();
+        self.apply_view();
     }
-
-    /// Move selection up
-    fn previous(&mut self) {
-        match self.current_view {
-            View::ProcessList => {
-                if let Some(selected) = self.selected_idx {
-                    if selected > 0 {
-                        self.selected_idx = Some(selected - 1);
-                    }
-                }
-            },
-            View::FilterManagement => {
-                if let Some(selected) = self.filter_selected_idx {
-                    if selected > 0 {
-                        self.filter_selected_idx = Some(selected - 1);
-                    }
-                }
-            }
+    
+    /// Add a character to the search
+    fn add_char_to_search(&mut self, c: char) {
+        self.search_query.push(c);
+        self.apply_view();
+    }
+    
+    /// Delete a character from the search
+    fn delete_char_from_search(&mut self) {
+        self.search_query.pop();
+        self.apply_view();
+    }
+    
+    /// Switch between searching names only and searching every field
+    fn toggle_search_scope(&mut self) {
+        self.search_fields = if self.search_fields == [SearchField::Name] {
+            SearchField::ALL.to_vec()
+        } else {
+            vec![SearchField::Name]
+        };
+        self.apply_view();
+    }
+    
+    /// Start typing the highlight pattern
+    fn open_highlight(&mut self) {
+        self.highlight_active = true;
+    }
+    
+    /// Stop typing, keeping the highlight
+    fn close_highlight(&mut self) {
+        self.highlight_active = false;
+    }
+    
+    /// Stop typing and remove the highlight
+    fn clear_highlight(&mut self) {
+        self.highlight_active = false;
+        self.highlight_query.clear();
+    }
+    
+    /// Add a character to the highlight pattern
+    fn add_char_to_highlight(&mut self, c: char) {
+        self.highlight_query.push(c);
+    }
+    
+    /// Delete a character from the highlight pattern
+    fn delete_char_from_highlight(&mut self) {
+        self.highlight_query.pop();
+    }
+    
+    /// Focus the selected process's port, dimming the other rows, or stop
+    /// focusing if it's already focused
+    fn toggle_focus_port(&mut self) {
+        let Some(port) = self.selected_process().map(|p| p.port) else {
+            return;
+        };
+        self.focus_port = (self.focus_port != Some(port)).then_some(port);
+    }
+    
+    /// Check whether a row is dimmed because another port is focused
+    fn is_dimmed(&self, process: &PortProcess) -> bool {
+        self.focus_port.is_some_and(|port| process.port != port)
+    }
+    
+    /// Check whether a row should be highlighted
+    fn is_highlighted(&self, process: &PortProcess) -> bool {
+        !self.highlight_query.is_empty()
+            && process.matches_search(&self.highlight_query, &HIGHLIGHT_FIELDS, self.hide_commands)
+    }
+    
+    /// Time between auto-refreshes, doubling with each refresh that found
+    /// nothing new, up to `refresh_backoff_max_ms`
+    fn refresh_interval(&self) -> Duration {
+        let base = self.config.refresh_interval_ms;
+        let max = self.config.refresh_backoff_max_ms.max(base);
+        let factor = 1u64 << self.unchanged_refreshes.min(16);
+        Duration::from_millis(base.saturating_mul(factor).min(max))
+    }
+    
+    /// Go back to the base auto-refresh interval, e.g. when the user is active
+    fn reset_refresh_backoff(&mut self) {
+        self.unchanged_refreshes = 0;
+    }
+    
+    /// Check whether a popup or text input is open over the table
+    fn is_modal_open(&self) -> bool {
+        self.show_add_filter_popup || self.show_kill_confirm || self.show_details ||
+            self.show_profile_popup || self.show_action_popup || self.show_config_popup ||
+            self.show_stats_popup || self.show_help_popup || self.show_kill_results || self.show_setup_popup || self.search_active ||
+            self.highlight_active || self.show_cleanup_name_popup
+    }
+    
+    /// Check whether auto-refresh is on, not paused and due. It's held while a
+    /// popup or input is open, so the rows it refers to don't change under it.
+    fn auto_refresh_due(&self) -> bool {
+        self.config.refresh_interval_ms > 0
+            && !self.refresh_paused
+            && !self.is_modal_open()
+            && self.last_refresh.elapsed() >= self.refresh_interval()
+    }
+    
+    /// Pause or resume auto-refresh
+    fn toggle_refresh_paused(&mut self) {
+        if self.config.refresh_interval_ms == 0 {
+            self.status_message = Some("Auto-refresh is off; set `refresh_interval_ms` in the config to turn it on".to_string());
+            return;
         }
+        self.refresh_paused = !self.refresh_paused;
     }
-
-    /// Move selection down
-    fn next(&mut self) {
-        match self.current_view {
-            View::ProcessList => {
-                if let Some(selected) = self.selected_idx {
-                    if selected < self.port_processes.len().saturating_sub(1) {
-                        self.selected_idx = Some(selected + 1);
-                    }
-                }
-            },
-            View::FilterManagement => {
-                if let Some(selected) = self.filter_selected_idx {
-                    if selected < self.config.filtered_process_names.len().saturating_sub(1) {
-                        self.filter_selected_idx = Some(selected + 1);
-                    }
-                }
+    
+    /// Sort the table by the next column
+    fn cycle_sort_column(&mut self) -> Result<()> {
+        self.config.cycle_sort_column()?;
+        self.refresh_processes()
+    }
+    
+    /// Sort by port, highest first, or flip the direction when already
+    /// sorted by port. The shown rows are re-sorted without discovering them
+    /// again, and the selected row stays selected.
+    fn toggle_port_sort(&mut self) -> Result<()> {
+        let descending = self.config.sort_column != SortColumn::Port || !self.config.sort_descending;
+        self.config.set_sort(SortColumn::Port, descending)?;
+        
+        let selected = self.selected_process().map(|p| (p.pid, p.port, p.local_addr.clone()));
+        self.apply_view();
+        if let Some((pid, port, local_addr)) = selected {
+            if let Some(index) = self.port_processes.iter()
+                .position(|p| p.pid == pid && p.port == port && p.local_addr == local_addr)
+            {
+                self.selected_idx = Some(index);
             }
         }
+        
+        self.status_message = Some(if descending { "Sorted by port, highest first" } else { "Sorted by port, lowest first" }.to_string());
+        Ok(())
     }
-
-    /// Kill selected process
-    fn kill_selected(&mut self) -> Result<()> {
-        match self.current_view {
-            View::ProcessList => {
-                if let Some(selected) = self.selected_idx {
-                    if let Some(process) = self.port_processes.get(selected) {
-                        kill_process(process.pid)?;
-                        
-                        // Refresh the process list
-                        self.refresh_processes()?;
-                        
-                        // Adjust selection if needed
-                        if self.port_processes.is_empty() {
-                            self.selected_idx = None;
-                        } else if selected >= self.port_processes.len() {
-                            self.selected_idx = Some(self.port_processes.len() - 1);
-                        }
-                    }
-                }
-            },
-            View::FilterManagement => {
-                // In filter management view, remove the selected filter
-                if let Some(selected) = self.filter_selected_idx {
-                    if let Some(filter_name) = self.config.filtered_process_names.get(selected) {
-                        let filter_name = filter_name.clone();
-                        self.config.remove_filtered_process(&filter_name)?;
-                        
-                        // Adjust selection if needed
-                        if self.config.filtered_process_names.is_empty() {
-                            self.filter_selected_idx = None;
-                        } else if selected >= self.config.filtered_process_names.len() {
-                            self.filter_selected_idx = Some(self.config.filtered_process_names.len() - 1);
-                        }
-                        
-                        // Refresh process list with updated filters
-                        self.refresh_processes()?;
-                    }
-                }
+    
+    /// Flip the table's sort direction
+    fn toggle_sort_direction(&mut self) -> Result<()> {
+        self.config.toggle_sort_direction()?;
+        self.refresh_processes()
+    }
+    
+    /// Toggle showing only the heaviest processes
+    fn toggle_top(&mut self) -> Result<()> {
+        self.top_enabled = !self.top_enabled;
+        self.refresh_processes()
+    }
+    
+    /// Re-check just the selected process: whether it is alive, still on its
+    /// port, and its current CPU and memory. Faster than a full refresh, and
+    /// leaves the other rows alone.
+    fn rescan_selected(&mut self) -> Result<()> {
+        let Some(process) = self.selected_process() else {
+            return Ok(());
+        };
+        let (pid, port, name) = (process.pid, process.port, process.name.clone());
+        if process.orphaned {
+            self.status_message = Some(format!("No process to re-check for the orphaned socket on port {}; refresh to see if it's gone", port));
+            return Ok(());
+        }
+        
+        // Demo and remote processes aren't in the local process table, so
+        // the backend is asked whether they're still running, if it can tell
+        let local = self.backend.is_local();
+        let alive = if local { Some(self.system.refresh_process(Pid::from_u32(pid))) } else { self.backend.is_running(pid)? };
+        let info = self.system.process(Pid::from_u32(pid))
+            .filter(|_| local)
+            .map(|info| (info.cpu_usage(), info.memory(), info.status() == sysinfo::ProcessStatus::Stop));
+        let threads = if local { count_threads(pid) } else { None };
+        let on_port = alive != Some(false) && self.backend.holds_port(pid, port, self.discovery_mode, &self.system)?;
+        
+        for row in self.port_processes.iter_mut().chain(self.discovered.iter_mut()) {
+            if row.pid != pid {
+                continue;
+            }
+            row.exited = alive == Some(false);
+            if let Some((cpu_usage, memory, stopped)) = info {
+                row.cpu_usage = cpu_usage;
+                row.memory = memory;
+                row.stopped = stopped;
+                row.threads = threads;
             }
         }
+        
+        self.status_message = Some(match (alive, on_port) {
+            (Some(false), _) => format!("{} (PID {}) has exited", name, pid),
+            (None, false) => format!("{} (PID {}) is no longer on port {}; whether it's still running is unknown", name, pid, port),
+            (Some(true), false) => format!("{} (PID {}) is no longer on port {}", name, pid, port),
+            (_, true) => format!("{} (PID {}) is still on port {}", name, pid, port),
+        });
         Ok(())
     }
-}
-
-/// Get list of processes running on ports
-fn get_port_processes() -> Result<Vec<PortProcess>> {
-    let mut port_processes = Vec::new();
     
-    // On macOS, use `lsof` to find processes listening on ports
-    let output = Command::new("lsof")
-        .args(["-i", "-P", "-n", "-sTCP:LISTEN"])
-        .output()
-        .context("Failed to execute lsof command")?;
+    /// Toggle grouping the table by bind address
+    fn toggle_group_by_address(&mut self) {
+        self.group_by_address = !self.group_by_address;
+        self.group_by_unit = false;
+        self.apply_view();
+    }
     
-    if !output.status.success() {
-        return Err(anyhow::anyhow!("lsof command failed"));
+    /// Toggle grouping the table by systemd unit, which needs the units to
+    /// be looked up
+    fn toggle_group_by_unit(&mut self) {
+        if !self.config.systemd_units {
+            self.status_message = Some("Set systemd_units in the config to group by unit".to_string());
+            return;
+        }
+        self.group_by_unit = !self.group_by_unit;
+        self.group_by_address = false;
+        self.apply_view();
     }
     
-    let output_str = String::from_utf8(output.stdout)
-        .context("Failed to parse lsof output as UTF-8")?;
+    /// Step the state filter through the states seen in the last discovery,
+    /// then back to showing every state
+    fn cycle_state_filter(&mut self) {
+        let states: BTreeSet<TcpState> = self.discovered.iter().map(|p| p.state).collect();
+        self.state_filter = match self.state_filter {
+            None => states.first().copied(),
+            Some(current) => states.range(current..).find(|state| **state != current).copied(),
+        };
+        self.apply_view();
+        self.status_message = Some(match self.state_filter {
+            Some(state) => format!("Showing {} sockets", state.label()),
+            None if states.len() < 2 => "Every socket is in the same state".to_string(),
+            None => "Showing every state".to_string(),
+        });
+    }
     
-    // Load system info to get process details
-    let mut system = System::new();
-    system.refresh_processes();
+    /// Check whether a listener is missing from the baseline. Connections
+    /// aren't compared, since their local port is usually ephemeral.
+    fn is_unexpected(&self, process: &PortProcess) -> bool {
+        self.discovery_mode == DiscoveryMode::Listeners && self.config.is_unexpected(&process.name, process.port)
+    }
     
-    // Skip the header line
-    for line in output_str.lines().skip(1) {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 9 {
-            let process_name = parts[0].to_string();
-            let pid_str = parts[1];
-            
-            // Extract port from address (format is typically like: *:8080)
-            let addr_port = parts[8];
-            if let Some(port_str) = addr_port.split(':').last() {
-                if let (Ok(pid), Ok(port)) = (pid_str.parse::<u32>(), port_str.parse::<u16>()) {
-                    let command = {
-                        // Get command info via ps command
-                        let cmd_output = Command::new("ps")
-                            .args(["-o", "command=", "-p", &pid.to_string()])
-                            .output();
-                        
-                        if let Ok(output) = cmd_output {
-                            String::from_utf8_lossy(&output.stdout).trim().to_string()
-                        } else {
-                            String::new()
-                        }
-                    };
-                    
-                    port_processes.push(PortProcess {
-                        pid,
-                        name: process_name,
-                        port,
-                        command,
-                    });
-                }
-            }
+    /// Record every discovered listener, filtered or not, as expected, so
+    /// anything that starts listening later stands out
+    fn set_baseline(&mut self) -> Result<()> {
+        if self.discovery_mode != DiscoveryMode::Listeners {
+            self.status_message = Some("Baselines record listeners; restart without --connections to set one".to_string());
+            return Ok(());
         }
+        let listeners = self.discovered.iter()
+            .filter(|p| p.unresolved_port.is_none())
+            .map(|p| BaselineEntry { name: p.name.clone(), port: p.port });
+        let count = self.config.set_baseline(listeners)?;
+        self.unexpected_only = false;
+        self.apply_view();
+        self.status_message = Some(format!("Baseline set: {} listener(s) expected, others are flagged as unexpected", count));
+        Ok(())
     }
     
-    // Sort by port number
-    port_processes.sort_by_key(|p| p.port);
+    /// Toggle showing only the listeners missing from the baseline
+    fn toggle_unexpected_only(&mut self) {
+        if self.config.baseline.is_empty() {
+            self.status_message = Some("No baseline yet: press E to record the current listeners as expected".to_string());
+            return;
+        }
+        self.unexpected_only = !self.unexpected_only;
+        self.apply_view();
+    }
     
-    Ok(port_processes)
-}
-
-/// Kill a process by PID
-fn kill_process(pid: u32) -> Result<()> {
-    let output = Command::new("kill")
-        .arg("-9")
-        .arg(pid.to_string())
-        .output()
-        .context("Failed to execute kill command")?;
+    /// Step from showing everything to only containers, then only host
+    /// processes
+    fn cycle_placement_filter(&mut self) {
+        self.placement_filter = match self.placement_filter {
+            None => Some(Placement::Container),
+            Some(Placement::Container) => Some(Placement::Host),
+            Some(Placement::Host) => None,
+        };
+        self.apply_view();
+        self.status_message = Some(match self.placement_filter {
+            Some(placement) => format!("Showing only {}", placement.label()),
+            None => "Showing containers and host processes".to_string(),
+        });
+    }
     
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow::anyhow!("Failed to kill process: {}", error));
+    /// Toggle coloring rows by process age
+    fn toggle_age_coloring(&mut self) {
+        self.age_coloring = !self.age_coloring;
     }
     
-    Ok(())
-}
-
-/// Initialize the terminal for TUI
-fn init_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
-    enable_raw_mode().context("Failed to enable raw mode")?;
-    io::stdout()
-        .execute(EnterAlternateScreen)
-        .context("Failed to enter alternate screen")?;
+    /// The oldest age tier a process has reached, if age coloring is on
+    fn age_tier(&self, process: &PortProcess, now: SystemTime) -> Option<&AgeTier> {
+        if !self.age_coloring {
+            return None;
+        }
+        let age = process.age(now)?.as_secs();
+        self.config.age_tiers.iter()
+            .filter(|tier| age >= tier.min_age_secs)
+            .max_by_key(|tier| tier.min_age_secs)
+    }
     
-    let backend = CrosstermBackend::new(io::stdout());
-    let terminal = Terminal::new(backend).context("Failed to create terminal")?;
-    Ok(terminal)
-}
-
-/// Restore terminal to original state
-fn restore_terminal() -> Result<()> {
-    disable_raw_mode().context("Failed to disable raw mode")?;
-    io::stdout()
-        .execute(LeaveAlternateScreen)
-        .context("Failed to leave alternate screen")?;
-    Ok(())
-}
-
-/// Main UI rendering function
-fn ui(frame: &mut Frame, app: &App) {
-    // Render the current view
-    match app.current_view {
-        View::ProcessList => render_process_view(frame, app),
-        View::FilterManagement => render_filter_view(frame, app),
+    /// Color for a row under age coloring: that of the oldest tier the process
+    /// has reached. Invalid colors and unknown start times leave the row as is.
+    fn age_color(&self, process: &PortProcess, now: SystemTime) -> Option<Color> {
+        self.age_tier(process, now)
+            .and_then(|tier| tier.color.parse().ok())
+            .map(|color| self.color(color))
     }
     
-    // Render the add filter popup if active
-    if app.show_add_filter_popup {
-        render_add_filter_popup(frame, app);
+    /// Symbol for a row's age tier, so tiers can be told apart without color.
+    /// Only shown with the accessible palette or when `NO_COLOR` is set.
+    fn age_marker(&self, process: &PortProcess, now: SystemTime) -> Option<String> {
+        if self.config.palette != Palette::Accessible && !no_color() {
+            return None;
+        }
+        let tier = self.age_tier(process, now)?;
+        let rank = self.config.age_tiers.iter()
+            .filter(|other| other.min_age_secs < tier.min_age_secs)
+            .count();
+        Some(if self.config.use_glyphs {
+            ["○", "◐", "●"].get(rank).copied().unwrap_or("●").to_string()
+        } else {
+            (rank + 1).to_string()
+        })
     }
-}
-
-/// Render the process list view
-fn render_process_view(frame: &mut Frame, app: &App) {
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),  // Title
-            Constraint::Min(0),     // Table
-            Constraint::Length(1),  // Spacing
-            Constraint::Length(3),  // Help
-        ])
-        .split(frame.size());
     
-    // Title block
-    let title_block = Block::default()
-        .title("Port Manager")
-        .title_alignment(Alignment::Center)
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded);
+    /// Swap green and red for blue and orange under the accessible palette
+    fn color(&self, color: Color) -> Color {
+        match (self.config.palette, color) {
+            (Palette::Accessible, Color::Green | Color::LightGreen) => Color::LightBlue,
+            (Palette::Accessible, Color::Red | Color::LightRed) => ORANGE,
+            (_, color) => color,
+        }
+    }
     
-    let title_text = Paragraph::new("Monitor and manage processes running on ports")
-        .block(title_block)
-        .alignment(Alignment::Center);
+    /// Toggle hiding of recently started processes
+    fn toggle_hide_recent(&mut self) {
+        self.hide_recent = !self.hide_recent;
+        self.apply_view();
+    }
     
-    frame.render_widget(title_text, layout[0]);
+    /// Scroll the Command column sideways, stopping once the longest
+    /// visible command has scrolled fully into view
+    fn scroll_commands(&mut self, forward: bool) {
+        if forward {
+            let longest = self.port_processes
+                .iter()
+                .map(|p| self.table_command(p).chars().count())
+                .max()
+                .unwrap_or(0);
+            let max_scroll = longest.saturating_sub(1);
+            self.command_scroll = (self.command_scroll + COMMAND_SCROLL_STEP).min(max_scroll);
+        } else {
+            self.command_scroll = self.command_scroll.saturating_sub(COMMAND_SCROLL_STEP);
+        }
+    }
     
-    // Process table
-    let headers = vec!["PID", "Port", "Process Name", "Command"];
-    let header_cells = headers
-        .iter()
-        .map(|h| Cell::from(*h).style(Style::default().bold()));
-    let header = Row::new(header_cells).height(1).bottom_margin(1);
-    
-    let rows = app.port_processes.iter().map(|process| {
-        let cells = vec![
-            Cell::from(process.pid.to_string()),
-            Cell::from(process.port.to_string()),
-            Cell::from(process.name.clone()),
-            Cell::from(process.command.clone()),
-        ];
-        Row::new(cells).height(1)
-    });
+    /// Switch the resource used to rank processes in top mode
+    fn cycle_top_by(&mut self) -> Result<()> {
+        self.top_by = match self.top_by {
+            ResourceKey::Memory => ResourceKey::Cpu,
+            ResourceKey::Cpu => ResourceKey::Memory,
+        };
+        self.refresh_processes()
+    }
     
-    let table = Table::new(
-        rows,
-        [   
-            Constraint::Length(10),     // PID
-            Constraint::Length(10),     // Port
-            Constraint::Length(20),     // Process Name
-            Constraint::Percentage(60),  // Command
-        ],
-    )
-    .header(header)
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded)
-            .title("Processes")
-    )
-    .highlight_style(
-        Style::default()
-            .bg(Color::Blue)
-            .fg(Color::White)
-            .add_modifier(Modifier::BOLD)
-    )
-    .highlight_symbol(">> ");
+    /// The directory profiles are listed and loaded from
+    fn config_dir(&self) -> Result<PathBuf> {
+        match &self.config_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(Config::config_dir()?),
+        }
+    }
     
-    // Render table with selection
-    let table_state = &mut TableState::default().with_selected(app.selected_idx);
-    frame.render_stateful_widget(table, layout[1], table_state);
+    /// Open the profile switcher, selecting the active profile
+    fn open_profile_popup(&mut self) -> Result<()> {
+        self.profile_names = Config::list_profiles_in(&self.config_dir()?)?;
+        self.profile_selected_idx = self.profile_names
+            .iter()
+            .position(|name| name == self.config.profile())
+            .unwrap_or(0);
+        self.show_profile_popup = true;
+        Ok(())
+    }
     
-    // Help text
-    let help_text = "↑/↓: Navigate | Enter/k: Kill process | f: Filter process | F: Manage filters | r: Refresh | q: Quit";
-    let help = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::Gray))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-        )
-        .alignment(Alignment::Center);
+    /// Close the profile switcher without switching
+    fn close_profile_popup(&mut self) {
+        self.show_profile_popup = false;
+    }
     
-    frame.render_widget(help, layout[3]);
-}
-
-/// Render the filter management view
-fn render_filter_view(frame: &mut Frame, app: &App) {
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),  // Title
-            Constraint::Min(0),     // Filter list
-            Constraint::Length(1),  // Spacing
-            Constraint::Length(3),  // Help
-        ])
-        .split(frame.size());
+    /// Move the profile switcher selection up
+    fn previous_profile(&mut self) {
+        self.profile_selected_idx = self.profile_selected_idx.saturating_sub(1);
+    }
     
-    // Title block
-    let title_block = Block::default()
-        .title("Process Filters")
-        .title_alignment(Alignment::Center)
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded);
+    /// Move the profile switcher selection down
+    fn next_profile(&mut self) {
+        if self.profile_selected_idx + 1 < self.profile_names.len() {
+            self.profile_selected_idx += 1;
+        }
+    }
     
-    let title_text = Paragraph::new("Manage process name filters")
-        .block(title_block)
-        .alignment(Alignment::Center);
+    /// Load the profile selected in the switcher and refresh
+    fn switch_to_selected_profile(&mut self) -> Result<()> {
+        self.show_profile_popup = false;
+        if let Some(profile) = self.profile_names.get(self.profile_selected_idx) {
+            self.config = Config::load_in(&self.config_dir()?, profile, self.recover_config)?;
+            self.status_message = startup_message(&self.config);
+            self.hide_commands = self.config.hide_commands;
+            self.short_commands = self.config.short_commands;
+            self.hide_loopback = self.config.hide_loopback_only;
+            self.search_fields = self.config.search_fields.clone();
+            self.absolute_times = self.config.absolute_times;
+            self.age_coloring = self.config.age_coloring;
+            self.port_links = self.config.port_links;
+            self.filter_selected_idx = None;
+            self.active_preset = None;
+            self.clear_filter_history();
+            self.refresh_processes()?;
+        }
+        Ok(())
+    }
     
-    frame.render_widget(title_text, layout[0]);
+    /// Open the actions menu for the selected process
+    fn open_action_popup(&mut self) {
+        if self.selected_process().is_none() {
+            return;
+        }
+        if self.config.actions.is_empty() {
+            self.status_message = Some("No actions configured; add them to `actions` in the config".to_string());
+            return;
+        }
+        self.action_selected_idx = self.action_selected_idx.min(self.config.actions.len() - 1);
+        self.show_action_popup = true;
+    }
     
-    // Filter list
-    let filters = app.config.filtered_process_names.iter().enumerate()
-        .map(|(i, name)| {
-            ListItem::new(format!("{}. {}", i + 1, name))
-        })
-        .collect::<Vec<_>>();
+    /// Close the actions menu without running anything
+    fn close_action_popup(&mut self) {
+        self.show_action_popup = false;
+    }
     
-    let filter_list = List::new(filters)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .title("Filtered Process Names")
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::Blue)
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD)
-        )
-        .highlight_symbol(">> ");
+    /// Move the actions menu selection up
+    fn previous_action(&mut self) {
+        self.action_selected_idx = self.action_selected_idx.saturating_sub(1);
+    }
     
-    let mut filter_state = ListState::default();
-    filter_state.select(app.filter_selected_idx);
+    /// Move the actions menu selection down
+    fn next_action(&mut self) {
+        if self.action_selected_idx + 1 < self.config.actions.len() {
+            self.action_selected_idx += 1;
+        }
+    }
     
-    frame.render_stateful_widget(filter_list, layout[1], &mut filter_state);
+    /// The selected action's name and command, filled in for the selected process
+    fn selected_action_command(&self) -> Option<(String, String)> {
+        let process = self.selected_process()?;
+        let action = self.config.actions.get(self.action_selected_idx)?;
+        Some((action.name.clone(), action.render(process.pid, process.port, &process.name)))
+    }
     
-    // Help text
-    let help_text = "↑/↓: Navigate | Enter/Delete: Remove filter | a: Add new filter | F: Return to processes | q: Quit";
-    let help = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::Gray))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-        )
-        .alignment(Alignment::Center);
+    /// Show the raw config file
+    fn open_config_popup(&mut self) {
+        self.config_text = fs::read_to_string(self.config.config_path())
+            .unwrap_or_else(|e| format!("Failed to read {}: {}", self.config.config_path().display(), e));
+        self.config_scroll = 0;
+        self.config_error = None;
+        self.show_config_popup = true;
+    }
     
-    frame.render_widget(help, layout[3]);
-}
-
-/// Render a popup for adding a new filter
-fn render_add_filter_popup(frame: &mut Frame, app: &App) {
-    let popup_area = centered_rect(60, 20, frame.size());
+    /// Open the config directory in the file manager, showing its path in
+    /// case there's no desktop or the file manager can't be started
+    fn open_config_dir(&mut self) {
+        let dir = match Config::config_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                self.status_message = Some(format!("No config directory: {}", e));
+                return;
+            }
+        };
+        self.status_message = Some(if !has_display() {
+            format!("Config directory: {}", dir.display())
+        } else {
+            match open_in_file_manager(&dir) {
+                Ok(()) => format!("Opened {}", dir.display()),
+                Err(e) => format!("Config directory: {} ({:#})", dir.display(), e),
+            }
+        });
+    }
     
-    // Clear the area
-    frame.render_widget(Clear, popup_area);
+    /// Close the raw config popup
+    fn close_config_popup(&mut self) {
+        self.show_config_popup = false;
+    }
     
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),  // Title
-            Constraint::Length(3),  // Input
-            Constraint::Length(3),  // Help
-        ])
-        .split(popup_area);
+    /// Scroll the raw config popup
+    fn scroll_config(&mut self, down: bool) {
+        self.config_scroll = if down {
+            self.config_scroll.saturating_add(1)
+        } else {
+            self.config_scroll.saturating_sub(1)
+        };
+    }
     
-    // Title
-    let title = Paragraph::new("Add Process Filter")
-        .style(Style::default().fg(Color::White))
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-        );
+    /// Re-read the config file, keeping the current config if it no longer parses
+    fn reload_config(&mut self) -> Result<()> {
+        let reloaded = self.config.reload().and_then(|config| {
+            let services = ServiceNames::load(config.services_file.as_deref())?;
+            Ok((config, services))
+        });
+        // Show what is on disk now, even if it can't be applied
+        self.open_config_popup();
+        match reloaded {
+            Ok((config, services)) => {
+                self.config = config;
+                self.services = services;
+                self.hide_commands = self.config.hide_commands;
+                self.short_commands = self.config.short_commands;
+                self.hide_loopback = self.config.hide_loopback_only;
+                self.search_fields = self.config.search_fields.clone();
+                self.absolute_times = self.config.absolute_times;
+                self.age_coloring = self.config.age_coloring;
+                self.port_links = self.config.port_links;
+                self.filter_selected_idx = None;
+                self.active_preset = None;
+                self.clear_filter_history();
+                self.status_message = Some("Reloaded config".to_string());
+                self.refresh_processes()
+            }
+            Err(e) => {
+                self.config_error = Some(format!("Not applied: {:#}", e));
+                Ok(())
+            }
+        }
+    }
     
-    frame.render_widget(title, popup_layout[0]);
+    /// Toggle the detail popup for the selected process
+    fn toggle_details(&mut self) {
+        self.show_details = !self.show_details && self.selected_process().is_some();
+        // Reading the rules runs a command, so only do it when the popup opens.
+        // Both only know about this machine.
+        let local = self.backend.host().is_none();
+        self.firewall = match self.selected_process() {
+            Some(process) if self.show_details && local && self.config.check_firewall => Some(firewall_status(process.port)),
+            _ => None,
+        };
+        self.kubernetes = match self.selected_process() {
+            Some(process) if self.show_details && local && self.config.kubernetes => {
+                Some(kubernetes_info(&process.name, &process.command, process.port))
+            }
+            _ => None,
+        };
+        self.count_open_fds();
+        self.read_accept_queue();
+        self.lookup_hostname();
+        self.run_enrichment();
+    }
     
-    // Input
-    let input = Paragraph::new(app.add_filter_input.as_str())
-        .style(Style::default())
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .title("Process Name")
-        );
+    /// Run the configured enrichment command for the process in the detail
+    /// popup. It runs on its own thread, since the command may be slow; the
+    /// timeout is enforced there.
+    fn run_enrichment(&mut self) {
+        self.enrichment = None;
+        self.enrichment_target = None;
+        if !self.show_details || !self.backend.is_local() {
+            return;
+        }
+        let Some(enrichment) = self.config.enrichment.clone().filter(|e| !e.command.trim().is_empty()) else {
+            return;
+        };
+        let Some(process) = self.selected_process() else {
+            return;
+        };
+        
+        let target = (process.pid, process.port);
+        self.enrichment_target = Some(target);
+        let sender = self.enrichment_sender.clone();
+        std::thread::spawn(move || {
+            let timeout = Duration::from_millis(enrichment.timeout_ms);
+            let result = enrich::run_enrichment(&enrichment.command, target.0, target.1, timeout)
+                .map_err(|e| e.to_string());
+            if let Err(e) = &result {
+                tracing::debug!("Enrichment for PID {} failed: {}", target.0, e);
+            }
+            // The app may have exited already
+            let _ = sender.send((target, result));
+        });
+    }
     
-    frame.render_widget(input, popup_layout[1]);
+    /// Take the result of a finished enrichment command, dropping results for
+    /// a process the popup no longer shows. Returns whether anything changed.
+    fn collect_enrichment(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((target, result)) = self.enrichment_receiver.try_recv() {
+            if self.enrichment_target == Some(target) {
+                self.enrichment = Some(result);
+                changed = true;
+            }
+        }
+        changed
+    }
     
-    // Place cursor at the end of input
-    frame.set_cursor(
-        popup_layout[1].x + app.add_filter_input.len() as u16 + 1,
-        popup_layout[1].y + 1,
-    );
+    /// Start a reverse lookup of the bind address in the detail popup, unless
+    /// it's already known or on its way. It runs on its own thread, since
+    /// the resolver may wait on a DNS server.
+    fn lookup_hostname(&mut self) {
+        if !self.show_details || !self.config.reverse_dns {
+            return;
+        }
+        let Some(ip) = self.selected_process().and_then(PortProcess::external_bind_ip) else {
+            return;
+        };
+        if self.hostnames.contains_key(&ip) || self.hostname_lookups.contains_key(&ip) {
+            return;
+        }
+        
+        self.hostname_lookups.insert(ip, Instant::now());
+        let sender = self.hostname_sender.clone();
+        std::thread::spawn(move || {
+            let hostname = dns::reverse_lookup(ip).unwrap_or_else(|e| {
+                tracing::debug!("Reverse lookup of {} failed: {}", ip, e);
+                None
+            });
+            // The app may have exited already
+            let _ = sender.send((ip, hostname));
+        });
+    }
     
-    // Help
-    let help = Paragraph::new("Enter: Save | Esc: Cancel")
-        .style(Style::default().fg(Color::Gray))
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-        );
+    /// Cache the results of finished reverse lookups, giving up on ones that
+    /// took too long. Returns whether anything changed.
+    fn collect_hostnames(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((ip, hostname)) = self.hostname_receiver.try_recv() {
+            // A late answer for a lookup that timed out is still worth keeping
+            self.hostname_lookups.remove(&ip);
+            self.hostnames.insert(ip, hostname);
+            changed = true;
+        }
+        
+        let timed_out: Vec<IpAddr> = self.hostname_lookups.iter()
+            .filter(|(_, started)| started.elapsed() >= REVERSE_DNS_TIMEOUT)
+            .map(|(ip, _)| *ip)
+            .collect();
+        for ip in timed_out {
+            self.hostname_lookups.remove(&ip);
+            self.hostnames.insert(ip, None);
+            changed = true;
+        }
+        changed
+    }
     
-    frame.render_widget(help, popup_layout[2]);
-}
-
-/// Helper function to create a centered rect using up certain percentage of the available rect
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
-}
-
-fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
-    // Initial refresh
-    app.refresh_processes()?;
+    /// Count the open file descriptors of the process in the detail popup.
+    /// This reads /proc or runs lsof, so it's only done for that one process.
+    fn count_open_fds(&mut self) {
+        self.open_fds = match self.selected_process() {
+            Some(process) if self.show_details && self.backend.is_local() => {
+                Some(count_open_fds(process.pid).map_err(|e| e.to_string()))
+            }
+            _ => None,
+        };
+    }
     
-    loop {
-        terminal.draw(|frame| ui(frame, app))?;
+    /// Read the accept queue of the listener in the detail popup. This runs
+    /// ss or netstat, so it's only done for that one socket.
+    fn read_accept_queue(&mut self) {
+        self.accept_queue = match self.selected_process() {
+            Some(process) if self.show_details && self.backend.is_local() && process.state == TcpState::Listen && !process.orphaned => {
+                Some(accept_queue(process.port, &process.local_addr).map_err(|e| e.to_string()))
+            }
+            _ => None,
+        };
+    }
+    
+    /// Toggle the discovery and sysinfo comparison in the detail popup
+    fn toggle_detail_sources(&mut self) {
+        self.show_detail_sources = !self.show_detail_sources;
+    }
+    
+    /// Switch start times between timestamps and how long ago they were
+    fn toggle_absolute_times(&mut self) {
+        self.absolute_times = !self.absolute_times;
+    }
+    
+    /// Toggle masking of command lines
+    fn toggle_hide_commands(&mut self) {
+        self.hide_commands = !self.hide_commands;
+        // Hidden commands aren't searched
+        if !self.search_query.is_empty() {
+            self.apply_view();
+        }
+    }
+    
+    /// Toggle between whole command lines and executables in the table
+    fn toggle_short_commands(&mut self) {
+        self.short_commands = !self.short_commands;
+        self.command_scroll = 0;
+    }
+    
+    /// What the Command column shows for a process
+    fn table_command<'a>(&self, process: &'a PortProcess) -> &'a str {
+        if self.short_commands && !self.hide_commands {
+            process.executable()
+        } else {
+            process.display_command(self.hide_commands)
+        }
+    }
+    
+    /// Toggle hiding of processes that only listen on loopback
+    fn toggle_hide_loopback(&mut self) -> Result<()> {
+        self.hide_loopback = !self.hide_loopback;
+        self.refresh_processes()
+    }
+    
+    /// Toggle between views
+    fn toggle_view(&mut self) {
+        match self.current_view {
+            View::ProcessList => self.current_view = View::FilterManagement,
+            View::FilterManagement => self.current_view = View::ProcessList,
+        }
+    }
+    
+    /// Open the guided setup, prefilled with the defaults just written
+    fn open_setup_popup(&mut self) {
+        self.setup = SetupForm::new(&self.config);
+        self.show_setup_popup = true;
+    }
+    
+    /// Keep the defaults without changing anything
+    fn skip_setup(&mut self) {
+        self.show_setup_popup = false;
+        self.status_message = Some(format!("Using the defaults; edit {} to change them", self.config.config_path().display()));
+    }
+    
+    /// Save the setup answers to the config, keeping the popup open if they're invalid
+    fn save_setup(&mut self) -> Result<()> {
+        let range = match parse_port_range(&self.setup.port_range) {
+            Ok(range) => range,
+            Err(e) => {
+                self.setup.error = Some(format!("Port range: {}", e));
+                return Ok(());
+            }
+        };
         
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    // Handle popup first if it's active
-                    if app.show_add_filter_popup {
-                        match key.code {
-                            KeyCode::Esc => {
-                                app.toggle_add_filter_popup();
-                            }
-                            KeyCode::Char(c) => {
-                                app.add_char_to_filter(c);
-                            }
-                            KeyCode::Backspace => {
-                                app.delete_char_from_filter();
-                            }
-                            KeyCode::Enter => {
-                                app.save_filter()?;
-                            }
-                            _ => {}
-                        }
-                    } else {
-                        match app.current_view {
-                            View::ProcessList => match key.code {
-                                KeyCode::Char('q') => {
-                                    app.should_quit = true;
-                                }
-                                KeyCode::Char('r') => {
-                                    app.refresh_processes()?;
-                                }
-                                KeyCode::Char('f') => {
-                                    app.filter_selected_process()?;
-                                }
-                                KeyCode::Char('F') => {
-                                    app.toggle_view();
-                                }
-                                KeyCode::Up => {
-                                    app.previous();
-                                }
-                                KeyCode::Down => {
-                                    app.next();
-                                }
-                                KeyCode::Enter | KeyCode::Char('k') => {
-                                    app.kill_selected()?;
-                                }
-                                _ => {}
-                            },
-                            View::FilterManagement => match key.code {
-                                KeyCode::Char('q') => {
-                                    app.should_quit = true;
-                                }
-                                KeyCode::Char('a') => {
-                                    app.toggle_add_filter_popup();
-                                }
-                                KeyCode::Char('F') => {
-                                    app.toggle_view();
-                                }
-                                KeyCode::Up => {
-                                    app.previous();
-                                }
-                                KeyCode::Down => {
-                                    app.next();
-                                }
-                                KeyCode::Enter | KeyCode::Delete => {
-                                    app.kill_selected()?;
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                }
+        let mut filters: Vec<FilterEntry> = Vec::new();
+        for pattern in self.setup.filters.split(',').map(str::trim).filter(|pattern| !pattern.is_empty()) {
+            if let Err(e) = self.config.match_mode.validate(pattern) {
+                self.setup.error = Some(format!("Filter {:?}: {:#}", pattern, e));
+                return Ok(());
+            }
+            if !filters.iter().any(|filter| filter.pattern == pattern) {
+                filters.push(FilterEntry::new(pattern.to_string()));
             }
         }
         
-        if app.should_quit {
-            break;
+        self.config.set_filter_settings(FilterSettings {
+            filtered_process_names: filters,
+            match_mode: self.config.match_mode,
+            min_port: *range.start(),
+            max_port: *range.end(),
+        })?;
+        self.show_setup_popup = false;
+        self.status_message = Some(format!("Saved setup to {}", self.config.config_path().display()));
+        self.refresh_processes()
+    }
+    
+    /// Toggle add filter popup
+    fn toggle_add_filter_popup(&mut self) {
+        self.show_add_filter_popup = !self.show_add_filter_popup;
+        self.add_filter_error = None;
+        if !self.show_add_filter_popup {
+            self.add_filter_input.clear();
         }
     }
     
-    Ok(())
-}
-
-fn main() -> Result<()> {
-    // Setup logging
-    tracing_subscriber::fmt::init();
+    /// How many shown processes the typed filter would hide, counted from the
+    /// current rows so it follows them when they're refreshed
+    fn filter_input_matches(&self) -> Option<usize> {
+        let pattern = self.add_filter_input.trim();
+        if pattern.is_empty() {
+            return None;
+        }
+        let matcher = self.config.match_mode.compile(pattern).ok()?;
+        Some(self.port_processes.iter().filter(|p| matcher.matches(&p.name)).count())
+    }
     
-    // Parse command line arguments
-    let _args = Args::parse();
+    /// Add character to filter input
+    fn add_char_to_filter(&mut self, c: char) {
+        self.add_filter_input.push(c);
+    }
     
-    // Initialize terminal
-    let mut terminal = init_terminal()?;
+    /// Delete character from filter input
+    fn delete_char_from_filter(&mut self) {
+        self.add_filter_input.pop();
+    }
     
-    // Create app state
-    let mut app = App::new()?;
+    /// Save the current filter input, keeping the popup open if it's invalid
+    fn save_filter(&mut self) -> Result<()> {
+        let filter = self.add_filter_input.trim().to_string();
+        if let Err(e) = self.config.match_mode.validate(&filter) {
+            self.add_filter_error = Some(format!("{:#}", e));
+            return Ok(());
+        }
+        if !filter.is_empty() {
+            self.record_filter_change();
+            self.config.add_filtered_process(filter)?;
+            self.refresh_processes()?;
+        }
+        self.toggle_add_filter_popup();
+        Ok(())
+    }
     
-    // Run the application
-    let result = run_app(&mut terminal, &mut app);
+    /// Get the currently selected process
+    fn selected_process(&self) -> Option<&PortProcess> {
+        self.selected_idx.and_then(|idx| self.port_processes.get(idx))
+    }
     
-    // Ensure terminal is restored even if there's an error
-    restore_terminal()?;
+    /// Mark or unmark the selected process for a bulk kill
+    fn toggle_mark(&mut self) {
+        if let Some(pid) = self.selected_process().map(|p| p.pid) {
+            if !self.marked_pids.remove(&pid) {
+                self.marked_pids.insert(pid);
+            }
+        }
+    }
     
-    // Return the result from running the app
-    result
+    /// PIDs a kill would target: the marked processes, or else the selected one
+    fn kill_candidates(&self) -> Vec<u32> {
+        if self.marked_pids.is_empty() {
+            self.selected_process().map(|p| vec![p.pid]).unwrap_or_default()
+        } else {
+            self.marked_pids.iter().copied().collect()
+        }
+    }
+    
+    /// Check whether the pending kill is big enough to need a typed confirmation
+    fn needs_typed_confirmation(&self) -> bool {
+        self.clean_slate || self.kill_targets.len() > self.config.bulk_kill_threshold
+    }
+    
+    /// Block around the main views' title, list and help, without a border
+    /// in dense mode
+    fn view_block(&self) -> Block<'static> {
+        if self.config.dense {
+            Block::default()
+        } else {
+            bordered(self.config.border_style)
+        }
+    }
+    
+    /// Check whether the main views have borders around their parts
+    fn view_borders(&self) -> bool {
+        !self.config.dense && self.config.border_style != BorderStyle::None
+    }
+    
+    /// Check whether the pending kill is confirmed in the footer rather than
+    /// a popup. Typed confirmations need the popup's input box.
+    fn inline_kill_confirm(&self) -> bool {
+        self.show_kill_confirm && self.config.confirm_style == ConfirmStyle::Inline && !self.needs_typed_confirmation()
+    }
+    
+    /// Strictest kill policy of the ports a process listens on, if any of
+    /// them is in a `port_policies` range
+    fn kill_policy(&self, pid: u32) -> Option<KillPolicy> {
+        self.port_processes.iter()
+            .filter(|p| p.pid == pid)
+            .filter_map(|p| self.config.port_policy(p.port))
+            .max()
+    }
+    
+    /// Leave out processes on protected ports, saying which were refused
+    fn without_protected(&mut self, targets: Vec<u32>) -> Vec<u32> {
+        let (protected, allowed): (Vec<u32>, Vec<u32>) = targets.into_iter()
+            .partition(|pid| self.kill_policy(*pid) == Some(KillPolicy::Protected));
+        if let Some(process) = protected.first().and_then(|pid| self.port_processes.iter().find(|p| p.pid == *pid)) {
+            let others = if protected.len() > 1 { format!(" and {} other(s)", protected.len() - 1) } else { String::new() };
+            self.status_message = Some(format!(
+                "Refusing to kill {} (PID {}){}: port {} is protected by port_policies",
+                process.name, process.pid, others, process.port,
+            ));
+        }
+        allowed
+    }
+    
+    /// Kill the target processes, asking for confirmation first if configured
+    /// or if a port policy says so. Bulk kills over the threshold always ask.
+    fn request_kill(&mut self) -> Result<()> {
+        let targets = self.without_protected(self.kill_candidates());
+        if targets.is_empty() {
+            return Ok(());
+        }
+        
+        let default = if self.config.confirm_kill { KillPolicy::AlwaysConfirm } else { KillPolicy::NeverConfirm };
+        let confirm = targets.iter().any(|pid| self.kill_policy(*pid).unwrap_or(default) == KillPolicy::AlwaysConfirm);
+        if confirm || targets.len() > self.config.bulk_kill_threshold {
+            self.kill_restart = match targets[..] {
+                [pid] if self.config.systemd_units && self.backend.is_local() => restart_command(pid),
+                _ => None,
+            };
+            self.kill_targets = targets;
+            self.kill_confirm_input.clear();
+            self.kill_confirm_scroll = 0;
+            self.show_kill_confirm = true;
+            Ok(())
+        } else {
+            self.kill_pids(&targets)
+        }
+    }
+    
+    /// Kill the target processes straight away, even with confirmation on. Bulk
+    /// kills over the threshold and ports whose policy always confirms still ask.
+    fn kill_without_confirmation(&mut self) -> Result<()> {
+        let targets = self.without_protected(self.kill_candidates());
+        let always_confirm = targets.iter().any(|pid| self.kill_policy(*pid) == Some(KillPolicy::AlwaysConfirm));
+        if always_confirm || targets.len() > self.config.bulk_kill_threshold {
+            return self.request_kill();
+        }
+        if targets.is_empty() {
+            return Ok(());
+        }
+        
+        self.kill_pids(&targets)?;
+        // Keep any message about other ports the kill freed
+        if !self.show_kill_results && self.status_message.is_none() {
+            self.status_message = Some(format!("Killed {} process(es) without confirmation", targets.len()));
+        }
+        Ok(())
+    }
+    
+    /// Kill every visible process except protected and system processes and
+    /// those on the keep list, for resetting a dev machine. Always asks for
+    /// the strong typed confirmation, listing what is killed and kept.
+    fn request_clean_slate(&mut self) {
+        let mut targets = Vec::new();
+        let mut kept = Vec::new();
+        let keep = self.config.kept_processes();
+        for process in &self.port_processes {
+            if targets.contains(&process.pid) || kept.contains(&process.pid) {
+                continue;
+            }
+            if KillRisk::of(process) == KillRisk::System || keep.matches(&process.name)
+                || self.kill_policy(process.pid) == Some(KillPolicy::Protected) {
+                kept.push(process.pid);
+            } else {
+                targets.push(process.pid);
+            }
+        }
+        
+        if targets.is_empty() {
+            self.status_message = Some(format!("Nothing to kill: all {} visible process(es) are protected or kept", kept.len()));
+            return;
+        }
+        
+        self.kill_targets = targets;
+        self.kill_kept = kept;
+        self.clean_slate = true;
+        self.kill_confirm_input.clear();
+        self.kill_confirm_scroll = 0;
+        self.show_kill_confirm = true;
+    }
+    
+    /// Kill the selected process and add its name to the filters, so it stays
+    /// hidden if it respawns. Always asks first, since the filter is saved.
+    fn request_kill_and_filter(&mut self) {
+        let Some(pid) = self.selected_process().map(|p| p.pid) else {
+            return;
+        };
+        if self.without_protected(vec![pid]).is_empty() {
+            return;
+        }
+        
+        self.kill_targets = vec![pid];
+        self.kill_and_filter = true;
+        self.kill_confirm_input.clear();
+        self.kill_confirm_scroll = 0;
+        self.show_kill_confirm = true;
+    }
+    
+    /// Confirm the pending kill. Bulk kills only go ahead once "yes" or the
+    /// number of processes has been typed.
+    fn confirm_kill(&mut self) -> Result<()> {
+        if self.clean_slate {
+            if self.kill_confirm_input.trim() != CLEAN_SLATE_CONFIRMATION {
+                return Ok(());
+            }
+        } else if self.needs_typed_confirmation() {
+            let input = self.kill_confirm_input.trim();
+            if input != "yes" && input != self.kill_targets.len().to_string() {
+                return Ok(());
+            }
+        }
+        
+        self.show_kill_confirm = false;
+        self.clean_slate = false;
+        self.kill_kept.clear();
+        self.kill_restart = None;
+        let targets = std::mem::take(&mut self.kill_targets);
+        if !std::mem::take(&mut self.kill_and_filter) {
+            return self.kill_pids(&targets);
+        }
+        
+        // Look the name up before the kill removes the row
+        let Some(process) = self.port_processes.iter().find(|p| targets.contains(&p.pid)) else {
+            return Ok(());
+        };
+        let (pid, name) = (process.pid, process.name.clone());
+        self.kill_pids(&targets)?;
+        
+        self.record_filter_change();
+        self.config.add_filtered_process(name.clone())?;
+        self.refresh_processes()?;
+        let killed = if self.backend.is_demo() {
+            "Left running (demo mode)"
+        } else if self.dry_run {
+            "Left running (dry run)"
+        } else {
+            "Killed"
+        };
+        self.status_message = Some(format!("{} {} (PID {}) and added \"{}\" to the filters", killed, name, pid, name));
+        Ok(())
+    }
+    
+    /// Cancel the pending kill
+    fn cancel_kill(&mut self) {
+        self.show_kill_confirm = false;
+        self.kill_and_filter = false;
+        self.clean_slate = false;
+        self.kill_kept.clear();
+        self.kill_targets.clear();
+        self.kill_restart = None;
+    }
+    
+    /// Restart the pending kill target's service instead of killing it, so
+    /// its supervisor doesn't just start it again
+    fn restart_service(&mut self) -> Result<()> {
+        let Some(restart) = self.kill_restart.take() else {
+            return Ok(());
+        };
+        self.cancel_kill();
+        if self.backend.is_demo() || self.dry_run {
+            self.status_message = Some(format!("Dry run: would run {}", restart.command_line()));
+            return Ok(());
+        }
+        
+        self.status_message = Some(match restart.run() {
+            Ok(()) => format!("Restarted {} with {}", restart.service, restart.command_line()),
+            Err(e) => format!("Failed to restart {}: {}", restart.service, e),
+        });
+        self.refresh_processes()
+    }
+    
+    /// Scroll the kill confirmation's process list
+    fn scroll_kill_confirm(&mut self, down: bool) {
+        self.kill_confirm_scroll = if down {
+            self.kill_confirm_scroll.saturating_add(1)
+        } else {
+            self.kill_confirm_scroll.saturating_sub(1)
+        };
+    }
+    
+    /// Kill each PID, carrying on past failures. A failed single kill is
+    /// returned as an error, while bulk kills show a summary of every outcome.
+    fn kill_pids(&mut self, pids: &[u32]) -> Result<()> {
+        if self.backend.is_demo() {
+            self.marked_pids.clear();
+            self.status_message = Some(format!("Demo mode: {} process(es) left running", pids.len()));
+            return Ok(());
+        }
+        if self.dry_run {
+            let names: Vec<String> = pids.iter()
+                .map(|pid| match self.port_processes.iter().find(|p| p.pid == *pid) {
+                    Some(process) => format!("{} ({})", process.name, pid),
+                    None => pid.to_string(),
+                })
+                .collect();
+            self.marked_pids.clear();
+            self.status_message = Some(format!("Dry run: would send SIG{} to {}", self.config.kill_signal, names.join(", ")));
+            return Ok(());
+        }
+        
+        // Remember the port of the row the kill was started from, so other rows
+        // vanishing along with it can be explained
+        let selected_port = self.selected_process().map(|p| p.port);
+        
+        let mut outcomes = Vec::new();
+        for &pid in pids {
+            let name = self.port_processes.iter()
+                .find(|p| p.pid == pid)
+                .map(|p| p.name.clone())
+                .unwrap_or_default();
+            let mut ports: Vec<u16> = self.port_processes.iter()
+                .filter(|p| p.pid == pid)
+                .map(|p| p.port)
+                .collect();
+            ports.dedup();
+            let error = self.backend.send_signal(pid, &self.config.kill_signal).err().map(|e| format!("{:#}", e));
+            if self.config.audit_kills {
+                audit_kill(&KillRecord { pid, name: &name, ports: &ports, signal: &self.config.kill_signal, error: error.as_deref() });
+            }
+            outcomes.push(KillOutcome { pid, name, ports, error });
+        }
+        
+        self.marked_pids.clear();
+        if self.config.kill_stats {
+            self.record_kill_stats(&outcomes);
+        }
+        self.record_cleanup_steps(&outcomes);
+        
+        // Refresh the process list; this also keeps the selection in bounds
+        self.refresh_processes()?;
+        
+        if outcomes.len() > 1 {
+            self.kill_results = outcomes;
+            self.kill_results_scroll = 0;
+            self.show_kill_results = true;
+        } else if let Some(outcome) = outcomes.pop() {
+            if let Some(error) = outcome.error {
+                return Err(anyhow::anyhow!(error));
+            }
+            
+            // Killing one row's process also frees every other port it held
+            let others: Vec<String> = outcome.ports.iter()
+                .filter(|&&port| Some(port) != selected_port)
+                .map(u16::to_string)
+                .collect();
+            if !others.is_empty() && outcome.ports.len() > 1 {
+                self.status_message = Some(format!(
+                    "Killed {} (PID {}), which also freed port(s) {}",
+                    outcome.name, outcome.pid, others.join(", "),
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Count successful kills in the local stats file. Failing to save is
+    /// reported but doesn't undo the kills.
+    fn record_kill_stats(&mut self, outcomes: &[KillOutcome]) {
+        let config_path = self.config.config_path();
+        let result = KillStats::load(config_path).and_then(|mut stats| {
+            for outcome in outcomes.iter().filter(|outcome| outcome.error.is_none()) {
+                stats.record(&outcome.name, &outcome.ports);
+            }
+            stats.save(config_path)
+        });
+        if let Err(e) = result {
+            self.status_message = Some(format!("Failed to update kill stats: {}", e));
+        }
+    }
+    
+    /// Show the most-killed processes and ports from the local stats file
+    fn open_stats_popup(&mut self) {
+        if !self.config.kill_stats {
+            self.status_message = Some("Kill stats are off; set `kill_stats` in the config to count kills locally".to_string());
+            return;
+        }
+        match KillStats::load(self.config.config_path()) {
+            Ok(stats) => {
+                self.kill_stats = stats;
+                self.show_stats_popup = true;
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+    
+    /// Close the kill stats
+    fn close_stats_popup(&mut self) {
+        self.show_stats_popup = false;
+    }
+    
+    /// Show or hide the list of every process list key
+    fn toggle_help_popup(&mut self) {
+        self.show_help_popup = !self.show_help_popup;
+    }
+    
+    /// Delete the kill stats file and start counting again
+    fn clear_kill_stats(&mut self) {
+        match KillStats::clear(self.config.config_path()) {
+            Ok(()) => {
+                self.kill_stats = KillStats::default();
+                self.status_message = Some("Cleared kill stats".to_string());
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+    
+    /// Close the bulk kill summary
+    fn close_kill_results(&mut self) {
+        self.show_kill_results = false;
+        self.kill_results.clear();
+    }
+    
+    /// Scroll the bulk kill summary
+    fn scroll_kill_results(&mut self, down: bool) {
+        self.kill_results_scroll = if down {
+            self.kill_results_scroll.saturating_add(1)
+        } else {
+            self.kill_results_scroll.saturating_sub(1)
+        };
+    }
+    
+    /// Suspend (SIGSTOP) or resume (SIGCONT) the selected process
+    fn set_selected_suspended(&mut self, suspend: bool) -> Result<()> {
+        let Some(process) = self.selected_process() else {
+            return Ok(());
+        };
+        let (pid, name) = (process.pid, process.name.clone());
+        
+        if self.backend.is_demo() {
+            self.status_message = Some(format!("Demo mode: {} (PID {}) left as it is", name, pid));
+            return Ok(());
+        }
+        
+        // Stopping init would hang the machine. Stopping ourselves is refused
+        // by `send_signal`.
+        if suspend && pid == 1 {
+            self.status_message = Some(format!("Refusing to suspend {} (PID {})", name, pid));
+            return Ok(());
+        }
+        
+        let (signal, done) = if suspend { ("STOP", "Suspended") } else { ("CONT", "Resumed") };
+        self.status_message = Some(match self.backend.send_signal(pid, signal) {
+            Ok(()) => format!("{} {} (PID {})", done, name, pid),
+            Err(e) => format!("{:#}", e),
+        });
+        
+        // Pick up the new state
+        self.refresh_processes()
+    }
+    
+    /// Write a kill script for the visible processes to the current directory
+    fn export_kill_script(&mut self) {
+        if self.port_processes.is_empty() {
+            self.status_message = Some("No processes to export".to_string());
+            return;
+        }
+        
+        let now = SystemTime::now();
+        let timestamp = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let path = PathBuf::from(format!("port-manager-kill-{}.sh", timestamp));
+        let script = kill_script(
+            &self.port_processes,
+            &self.config.kill_signal,
+            &humantime::format_rfc3339_seconds(now).to_string(),
+        );
+        
+        self.status_message = Some(match write_script(&path, &script) {
+            Ok(()) => format!("Wrote kill script for {} process(es) to {}", self.port_processes.len(), path.display()),
+            Err(e) => format!("Failed to export kill script: {:#}", e),
+        });
+    }
+    
+    /// Copy the current view as a plain fixed-width table for bug reports,
+    /// writing it to a file instead when there is no clipboard
+    fn share_view(&mut self) {
+        let now = SystemTime::now();
+        let snapshot = view_snapshot(self, &humantime::format_rfc3339_seconds(now).to_string());
+        if copy_to_clipboard(&snapshot).is_ok() {
+            self.status_message = Some(format!("Copied {} process(es) as a text table", self.port_processes.len()));
+            return;
+        }
+        
+        let timestamp = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let path = PathBuf::from(format!("port-manager-view-{}.txt", timestamp));
+        self.status_message = Some(match fs::write(&path, &snapshot) {
+            Ok(()) => format!("No clipboard; wrote the view to {}", path.display()),
+            Err(e) => format!("Failed to write {}: {}", path.display(), e),
+        });
+    }
+    
+    /// Copy what a maintainer needs to reproduce a problem: versions, the
+    /// config, the view and the backend's raw output. Written to a file
+    /// instead when there is no clipboard.
+    fn copy_diagnostics(&mut self) {
+        let now = SystemTime::now();
+        let bundle = diagnostic_bundle(self, &humantime::format_rfc3339_seconds(now).to_string());
+        if copy_to_clipboard(&bundle).is_ok() {
+            self.status_message = Some("Copied a diagnostic bundle for a bug report".to_string());
+            return;
+        }
+        
+        let timestamp = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let path = PathBuf::from(format!("port-manager-diagnostics-{}.txt", timestamp));
+        self.status_message = Some(match fs::write(&path, &bundle) {
+            Ok(()) => format!("No clipboard; wrote the diagnostic bundle to {}", path.display()),
+            Err(e) => format!("Failed to write {}: {}", path.display(), e),
+        });
+    }
+    
+    /// Copy the selected process as a tab-separated line
+    fn copy_selected_row(&mut self) {
+        let Some(process) = self.selected_process() else {
+            return;
+        };
+        let options = WriteOptions {
+            binary_units: self.config.binary_units,
+            hide_commands: self.hide_commands,
+        };
+        let row = tsv_row(process, &options);
+        
+        self.status_message = Some(match copy_to_clipboard(&row) {
+            Ok(()) => format!("Copied {} (PID {}) as TSV", process.name, process.pid),
+            Err(e) => format!("Failed to copy: {:#}", e),
+        });
+    }
+    
+    /// Switch how filters are matched against process names
+    fn cycle_match_mode(&mut self) -> Result<()> {
+        self.record_filter_change();
+        self.config.cycle_match_mode()?;
+        self.refresh_processes()
+    }
+    
+    /// Remember the filter settings before changing them, so the change can
+    /// be undone. A new change can't be redone over.
+    fn record_filter_change(&mut self) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(self.config.filter_settings());
+        self.redo_stack.clear();
+    }
+    
+    /// Forget undo history, e.g. when switching to settings it doesn't apply to
+    fn clear_filter_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+    
+    /// Revert the last filter change
+    fn undo_filter_change(&mut self) -> Result<()> {
+        let Some(previous) = self.undo_stack.pop_back() else {
+            self.status_message = Some("Nothing to undo".to_string());
+            return Ok(());
+        };
+        self.redo_stack.push(self.config.filter_settings());
+        self.config.set_filter_settings(previous)?;
+        self.after_filter_history_change("Undid filter change")
+    }
+    
+    /// Re-apply the last undone filter change
+    fn redo_filter_change(&mut self) -> Result<()> {
+        let Some(next) = self.redo_stack.pop() else {
+            self.status_message = Some("Nothing to redo".to_string());
+            return Ok(());
+        };
+        self.undo_stack.push_back(self.config.filter_settings());
+        self.config.set_filter_settings(next)?;
+        self.after_filter_history_change("Redid filter change")
+    }
+    
+    /// Keep the filter selection in bounds and show the restored filters
+    fn after_filter_history_change(&mut self, message: &str) -> Result<()> {
+        let count = self.config.filtered_process_names.len();
+        self.filter_selected_idx = match self.filter_selected_idx {
+            _ if count == 0 => None,
+            Some(selected) => Some(selected.min(count - 1)),
+            None => Some(0),
+        };
+        self.refresh_processes()?;
+        self.status_message = Some(message.to_string());
+        Ok(())
+    }
+    
+    /// Indexes into the stored filter list, in the order they are displayed
+    fn filter_display_order(&self) -> Vec<usize> {
+        let filters = &self.config.filtered_process_names;
+        let mut order: Vec<usize> = (0..filters.len()).collect();
+        if self.filters_sorted {
+            order.sort_by_key(|&i| filters[i].pattern.to_lowercase());
+        }
+        order
+    }
+    
+    /// Stored index of the selected filter
+    fn selected_filter_index(&self) -> Option<usize> {
+        self.filter_selected_idx
+            .and_then(|selected| self.filter_display_order().get(selected).copied())
+    }
+    
+    /// Write the filters to `FILTER_EXPORT_FILE` in the current directory
+    fn export_filters(&mut self) {
+        let path = Path::new(FILTER_EXPORT_FILE);
+        self.status_message = Some(match self.config.export_filters(path) {
+            Ok(count) => format!("Exported {} filter(s) to {}", count, path.display()),
+            Err(e) => format!("Failed to export filters: {}", e),
+        });
+    }
+    
+    /// Merge the filters from `FILTER_EXPORT_FILE` in the current directory
+    fn import_filters(&mut self) -> Result<()> {
+        let path = Path::new(FILTER_EXPORT_FILE);
+        let before = self.config.filter_settings();
+        match self.config.import_filters(path) {
+            Ok(import) => {
+                // Recorded after the fact, since nothing changes when every filter is a duplicate
+                if import.added > 0 {
+                    if self.undo_stack.len() == UNDO_LIMIT {
+                        self.undo_stack.pop_front();
+                    }
+                    self.undo_stack.push_back(before);
+                    self.redo_stack.clear();
+                }
+                self.status_message = Some(import_summary(&import, path, self.config.match_mode));
+                self.refresh_processes()?;
+            }
+            Err(e) => self.status_message = Some(format!("Failed to import filters: {}", e)),
+        }
+        Ok(())
+    }
+    
+    /// Turn the selected filter off without removing it, or back on
+    fn toggle_selected_filter_enabled(&mut self) -> Result<()> {
+        let Some(index) = self.selected_filter_index() else {
+            return Ok(());
+        };
+        if let Some(enabled) = self.config.toggle_filter_enabled(index)? {
+            let pattern = &self.config.filtered_process_names[index].pattern;
+            self.status_message = Some(format!("Filter \"{}\" is {}", pattern, if enabled { "on" } else { "off" }));
+            self.apply_view();
+        }
+        Ok(())
+    }
+    
+    /// Add the selected filter to the quick toggle, or take it out
+    fn toggle_selected_filter_quick(&mut self) -> Result<()> {
+        let Some(index) = self.selected_filter_index() else {
+            return Ok(());
+        };
+        if let Some(quick) = self.config.toggle_filter_toggleable(index)? {
+            let pattern = &self.config.filtered_process_names[index].pattern;
+            self.status_message = Some(if quick {
+                format!("V now switches \"{}\" on and off", pattern)
+            } else {
+                format!("\"{}\" is no longer in the quick toggle", pattern)
+            });
+        }
+        Ok(())
+    }
+    
+    /// Switch the default filters and those marked for the quick toggle off,
+    /// or back on, to briefly see what they hide
+    fn toggle_quick_filters(&mut self) -> Result<()> {
+        self.status_message = Some(match self.config.toggle_quick_filters()? {
+            Some(true) => "Quick toggle filters are on again".to_string(),
+            Some(false) => {
+                let patterns: Vec<&str> = self.config.filtered_process_names.iter()
+                    .filter(|filter| filter.is_quick_toggle())
+                    .map(|filter| filter.pattern.as_str())
+                    .collect();
+                format!("Showing processes hidden by {}; press V to hide them again", patterns.join(", "))
+            }
+            None => "No quick toggle filters; mark one with T in the filter view".to_string(),
+        });
+        self.apply_view();
+        Ok(())
+    }
+    
+    /// Snooze the selected filter for `snooze_minutes`, or wake it if it's
+    /// already snoozed. Snoozes aren't saved, so a restart wakes them all.
+    fn toggle_snooze_selected_filter(&mut self) {
+        let Some(filter) = self.selected_filter_index().and_then(|index| self.config.filtered_process_names.get(index)) else {
+            return;
+        };
+        let pattern = filter.pattern.clone();
+        
+        if self.snoozed_filters.remove(&pattern).is_some() {
+            self.status_message = Some(format!("Filter \"{}\" is active again", pattern));
+        } else {
+            let minutes = self.config.snooze_minutes;
+            let until = (minutes > 0).then(|| Instant::now() + Duration::from_secs(minutes * 60));
+            self.snoozed_filters.insert(pattern.clone(), until);
+            self.status_message = Some(match until {
+                Some(_) => format!("Snoozed \"{}\" for {} minute(s)", pattern, minutes),
+                None => format!("Snoozed \"{}\" until restart", pattern),
+            });
+        }
+        self.apply_view();
+    }
+    
+    /// Turn snoozed filters back on once their time is up, and forget snoozes
+    /// of filters that were removed
+    fn wake_snoozed_filters(&mut self) {
+        let now = Instant::now();
+        let filters = &self.config.filtered_process_names;
+        let mut woken = Vec::new();
+        self.snoozed_filters.retain(|pattern, until| {
+            let expired = until.is_some_and(|until| until <= now);
+            if expired {
+                woken.push(pattern.clone());
+            }
+            !expired && filters.iter().any(|filter| filter.pattern == *pattern)
+        });
+        if !woken.is_empty() {
+            woken.sort();
+            self.status_message = Some(format!("Snooze over, filtering again: {}", woken.join(", ")));
+        }
+    }
+    
+    /// Toggle alphabetical display of filters, keeping the same filter selected
+    fn toggle_filters_sorted(&mut self) {
+        let selected = self.selected_filter_index();
+        self.filters_sorted = !self.filters_sorted;
+        if let Some(selected) = selected {
+            self.filter_selected_idx = self.filter_display_order().iter().position(|&i| i == selected);
+        }
+    }
+    
+    /// Move the selected filter up or down the list, keeping it selected
+    fn move_selected_filter(&mut self, up: bool) -> Result<()> {
+        // Moving is meaningless when the display order isn't the stored order
+        if self.filters_sorted {
+            self.status_message = Some("Turn off alphabetical order (s) to reorder filters".to_string());
+            return Ok(());
+        }
+        if let Some(selected) = self.filter_selected_idx {
+            self.record_filter_change();
+            self.filter_selected_idx = Some(self.config.move_filtered_process(selected, up)?);
+        }
+        Ok(())
+    }
+    
+    /// Add current process to filter list
+    fn filter_selected_process(&mut self) -> Result<()> {
+        if let Some(selected) = self.selected_idx {
+            if let Some(process) = self.port_processes.get(selected) {
+                let process_name = process.name.clone();
+                self.record_filter_change();
+                self.config.add_filtered_process(process_name)?;
+                self.refresh_processes()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Toggle making listening ports clickable links
+    fn toggle_port_links(&mut self) {
+        self.port_links = !self.port_links;
+        self.status_message = Some(if self.port_links {
+            "Ports are links to http://localhost:<port> (needs OSC 8 support)".to_string()
+        } else {
+            "Ports are plain text".to_string()
+        });
+    }
+    
+    /// Run a process list action bound in `keybindings`
+    fn run_key_action(&mut self, action: KeyAction) -> Result<()> {
+        match action {
+            KeyAction::Up => self.previous(),
+            KeyAction::Down => self.next(),
+            KeyAction::Search => self.open_search(),
+            KeyAction::Mark => self.toggle_mark(),
+            KeyAction::Kill => self.request_kill()?,
+            KeyAction::KillNow => self.kill_without_confirmation()?,
+            KeyAction::Details => self.toggle_details(),
+            KeyAction::Filter => self.filter_selected_process()?,
+            KeyAction::ToggleView => self.toggle_view(),
+            KeyAction::Refresh => self.refresh_processes()?,
+            KeyAction::RecordCleanup => self.toggle_cleanup_recording(),
+            KeyAction::Quit => self.should_quit = true,
+        }
+        Ok(())
+    }
+    
+    /// Hide the selected process's PID for the rest of the session
+    fn ignore_selected_pid(&mut self) {
+        let Some((pid, name)) = self.selected_process().map(|p| (p.pid, p.name.clone())) else {
+            return;
+        };
+        self.ignored_pids.insert(pid);
+        self.apply_view();
+        self.status_message = Some(format!("Ignoring PID {} ({}) until port-manager exits", pid, name));
+    }
+    
+    /// Show PIDs ignored during this session again
+    fn clear_ignored_pids(&mut self) {
+        if self.ignored_pids.is_empty() {
+            return;
+        }
+        let count = self.ignored_pids.len();
+        self.ignored_pids.clear();
+        self.apply_view();
+        self.status_message = Some(format!("Stopped ignoring {} PID(s)", count));
+    }
+    
+    /// Move selection up
+    fn previous(&mut self) {
+        match self.current_view {
+            View::ProcessList => {
+                if let Some(selected) = self.selected_idx {
+                    if selected > 0 {
+                        self.selected_idx = Some(selected - 1);
+                    }
+                }
+            },
+            View::FilterManagement => {
+                if let Some(selected) = self.filter_selected_idx {
+                    if selected > 0 {
+                        self.filter_selected_idx = Some(selected - 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Move selection down
+    fn next(&mut self) {
+        match self.current_view {
+            View::ProcessList => {
+                if let Some(selected) = self.selected_idx {
+                    if selected < self.port_processes.len().saturating_sub(1) {
+                        self.selected_idx = Some(selected + 1);
+                    }
+                }
+            },
+            View::FilterManagement => {
+                if let Some(selected) = self.filter_selected_idx {
+                    if selected < self.config.filtered_process_names.len().saturating_sub(1) {
+                        self.filter_selected_idx = Some(selected + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Move the selection to the next row with the selected process's name,
+    /// wrapping around to the top
+    fn select_next_same_name(&mut self) {
+        let Some(selected) = self.selected_idx else {
+            return;
+        };
+        let name = self.port_processes[selected].name.clone();
+        let matches: Vec<usize> = self.port_processes.iter()
+            .enumerate()
+            .filter(|(_, p)| p.name == name)
+            .map(|(index, _)| index)
+            .collect();
+        
+        let next = matches.iter()
+            .position(|&index| index > selected)
+            .unwrap_or(0);
+        self.selected_idx = Some(matches[next]);
+        self.status_message = Some(if matches.len() == 1 {
+            format!("No other {} rows", name)
+        } else {
+            format!("{} {}/{}", name, next + 1, matches.len())
+        });
+    }
+    
+    /// Kill selected process
+    fn kill_selected(&mut self) -> Result<()> {
+        match self.current_view {
+            View::ProcessList => {
+                let targets = self.kill_candidates();
+                self.kill_pids(&targets)?;
+            },
+            View::FilterManagement => {
+                // In filter management view, remove the selected filter
+                if let (Some(selected), Some(index)) = (self.filter_selected_idx, self.selected_filter_index()) {
+                    if let Some(filter) = self.config.filtered_process_names.get(index) {
+                        let filter_name = filter.pattern.clone();
+                        self.record_filter_change();
+                        self.config.remove_filtered_process(&filter_name)?;
+                        
+                        // Adjust selection if needed
+                        if self.config.filtered_process_names.is_empty() {
+                            self.filter_selected_idx = None;
+                        } else if selected >= self.config.filtered_process_names.len() {
+                            self.filter_selected_idx = Some(self.config.filtered_process_names.len() - 1);
+                        }
+                        
+                        // Refresh process list with updated filters
+                        self.refresh_processes()?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describe a filter import, warning when the file was written for another
+/// match mode since its patterns may hide different processes here
+fn import_summary(import: &FilterImport, path: &Path, match_mode: MatchMode) -> String {
+    let mut summary = format!(
+        "Imported {} new filter(s) from {} ({} already present)",
+        import.added,
+        path.display(),
+        import.duplicates
+    );
+    if import.match_mode != match_mode {
+        summary.push_str(&format!(
+            "; they were exported with {} matching but this config uses {}",
+            import.match_mode.label(),
+            match_mode.label()
+        ));
+    }
+    summary
+}
+
+/// Load the config chosen on the command line
+fn load_config(args: &Args) -> Result<Config> {
+    let config = match &args.config {
+        Some(path) => Config::load_override(path, !args.strict_config)?,
+        None => Config::load(&args.profile, !args.strict_config)?,
+    };
+    Ok(config)
+}
+
+/// Startup message telling the user their config was reset or comes from
+/// another machine, since the log isn't visible once the TUI is up
+fn startup_message(config: &Config) -> Option<String> {
+    if config.source() == ConfigSource::Recovered {
+        return Some(format!(
+            "Config could not be parsed; backed up to {} and reset to defaults",
+            Config::backup_path(config.config_path()).display()
+        ));
+    }
+    let unknown_columns = config.unknown_columns();
+    if !unknown_columns.is_empty() {
+        return Some(format!("Skipped unknown column(s) in config: {}", unknown_columns.join(", ")));
+    }
+    let invalid_filters = config.invalid_filters();
+    if !invalid_filters.is_empty() {
+        return Some(format!(
+            "Invalid filter pattern(s) for {} matching, which never match: {}",
+            config.match_mode.label(), invalid_filters.join(", "),
+        ));
+    }
+    config.host_mismatch().map(|(created_on, current)| {
+        format!(
+            "Config was created on {}, not this machine ({}); check its port range and filters",
+            created_on, current
+        )
+    })
+}
+
+/// Write a script to disk, making it executable where supported
+fn write_script(path: &std::path::Path, script: &str) -> Result<()> {
+    fs::write(path, script).context("Failed to write script")?;
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .context("Failed to make script executable")?;
+    }
+    
+    Ok(())
+}
+
+/// Check whether two discoveries found the same sockets. Resource usage is
+/// ignored since it changes on every refresh.
+fn same_sockets(a: &[PortProcess], b: &[PortProcess]) -> bool {
+    let sockets = |processes: &[PortProcess]| -> BTreeSet<(u32, u16, String, Option<String>)> {
+        processes.iter()
+            .map(|p| (p.pid, p.port, p.local_addr.clone(), p.remote.clone()))
+            .collect()
+    };
+    a.len() == b.len() && sockets(a) == sockets(b)
+}
+
+/// Clipboard commands to try, in order, with their arguments
+const CLIPBOARD_COMMANDS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Copy text to the system clipboard using the first clipboard command found
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    for (program, args) in CLIPBOARD_COMMANDS {
+        let child = std::process::Command::new(program)
+            .args(*args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue;
+        };
+        
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())
+                .with_context(|| format!("Failed to write to {}", program))?;
+        }
+        let status = child.wait().with_context(|| format!("Failed to run {}", program))?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("{} exited with {}", program, status));
+        }
+        return Ok(());
+    }
+    
+    Err(anyhow::anyhow!("no clipboard command found (tried pbcopy, wl-copy, xclip, xsel)"))
+}
+
+/// Get list of processes running on ports
+fn get_port_processes(
+    backend: &dyn Backend,
+    mode: DiscoveryMode,
+    system: &mut System,
+    exited: ExitedProcesses,
+) -> Result<Vec<PortProcess>> {
+    timed_port_processes(backend, mode, system, exited).map(|(processes, _)| processes)
+}
+
+/// Time spent in each step of a refresh
+struct RefreshTimings {
+    /// Scanning the process table with sysinfo
+    sysinfo: Duration,
+    /// Running the backend, excluding `ps` lookups
+    discovery: Duration,
+    /// Looking up users and commands with `ps`, and how many lookups ran
+    ps: Duration,
+    ps_count: u64,
+    /// Checking that each PID is still alive
+    recheck: Duration,
+}
+
+/// Like `get_port_processes`, also timing each step
+fn timed_port_processes(
+    backend: &dyn Backend,
+    mode: DiscoveryMode,
+    system: &mut System,
+    exited: ExitedProcesses,
+) -> Result<(Vec<PortProcess>, RefreshTimings)> {
+    // Load system info to get process details
+    let started = Instant::now();
+    system.refresh_processes();
+    let sysinfo = started.elapsed();
+    
+    take_ps_lookup_time();
+    let started = Instant::now();
+    let mut port_processes = backend.discover(mode, system)
+        .with_context(|| format!("Failed to discover processes using {}", backend.name()))?;
+    let (ps, ps_count) = take_ps_lookup_time();
+    let discovery = started.elapsed().saturating_sub(ps);
+    
+    let started = Instant::now();
+    // A process can exit between discovery and the per-PID lookups, leaving a
+    // row with a stale PID and no command. Check each PID once more.
+    let mut alive: HashMap<u32, bool> = HashMap::new();
+    for process in port_processes.iter_mut().filter(|p| backend.is_local() && !p.orphaned) {
+        let exists = *alive.entry(process.pid)
+            .or_insert_with(|| system.refresh_process(Pid::from_u32(process.pid)));
+        process.exited = !exists;
+    }
+    if exited == ExitedProcesses::Drop {
+        port_processes.retain(|p| !p.exited);
+    }
+    if backend.is_local() {
+        port_processes = exclude_own_processes(port_processes, system);
+    }
+    let recheck = started.elapsed();
+    
+    // Sort by port number
+    port_processes.sort_by_key(|p| p.port);
+    
+    let timings = RefreshTimings { sysinfo, discovery, ps, ps_count, recheck };
+    tracing::debug!(
+        "Refresh took {:?} in sysinfo, {:?} in {}, {:?} in {} ps lookup(s), {:?} rechecking PIDs",
+        timings.sysinfo, timings.discovery, backend.name(), timings.ps, timings.ps_count, timings.recheck,
+    );
+    
+    Ok((port_processes, timings))
+}
+
+/// Refresh once and print where the time went, to explain slow refreshes
+fn run_time_refresh(backend: &dyn Backend, mode: DiscoveryMode) -> Result<()> {
+    let mut system = System::new();
+    let started = Instant::now();
+    let (processes, timings) = timed_port_processes(backend, mode, &mut system, ExitedProcesses::Drop)?;
+    let total = started.elapsed();
+    
+    println!("Found {} process(es) in {:?} using {}", processes.len(), total, backend.name());
+    println!("  {:<20} {:?}", "sysinfo scan", timings.sysinfo);
+    println!("  {:<20} {:?}", backend.name(), timings.discovery);
+    println!("  {:<20} {:?} ({} lookups)", "ps", timings.ps, timings.ps_count);
+    println!("  {:<20} {:?}", "PID recheck", timings.recheck);
+    
+    Ok(())
+}
+
+/// How a non-interactive kill runs
+struct KillOptions {
+    /// Only show what would be killed
+    dry_run: bool,
+    /// Only print errors
+    quiet: bool,
+    /// Ask before killing, since the rules may match more than expected
+    confirm: bool,
+    /// Also kill processes with connections on the ports once the listeners are gone
+    kill_connections: bool,
+    /// Kill connected processes without asking
+    yes: bool,
+}
+
+/// The cleanup script saved under `name`, or an error listing the saved ones
+fn find_cleanup_script<'a>(config: &'a Config, name: &str) -> Result<&'a CleanupScript> {
+    config.cleanup_script(name).ok_or_else(|| {
+        let saved: Vec<&str> = config.cleanup_scripts.iter().map(|script| script.name.as_str()).collect();
+        anyhow::anyhow!(
+            "No cleanup script named {:?} (saved: {})",
+            name,
+            if saved.is_empty() { "none, record one with j in the TUI".to_string() } else { saved.join(", ") },
+        )
+    })
+}
+
+/// Listeners the rules match, split into those of processes holding a
+/// protected port, which are never killed, and the rest
+fn auto_kill_targets<'a>(
+    processes: &'a [PortProcess],
+    config: &Config,
+    matches: impl Fn(&PortProcess) -> bool,
+) -> (Vec<&'a PortProcess>, Vec<&'a PortProcess>) {
+    processes.iter()
+        .filter(|p| matches(p))
+        .partition(|p| processes.iter().any(|q| q.pid == p.pid && config.port_policy(q.port) == Some(KillPolicy::Protected)))
+}
+
+/// What `--auto-kill`, `--kill-port` and `--kill-name` ask to kill, and how
+fn cli_kill_rules(args: &Args, config: &Config) -> (AutoKillRules, KillOptions) {
+    let mut rules = if args.auto_kill {
+        config.auto_kill.clone()
+    } else {
+        AutoKillRules::default()
+    };
+    rules.ports.extend(&args.kill_port);
+    rules.names.extend(args.kill_name.iter().cloned());
+    let options = KillOptions {
+        dry_run: args.dry_run,
+        quiet: args.quiet,
+        // Names can match far more than intended, so ask unless told not to
+        confirm: !args.kill_name.is_empty() && !args.yes,
+        kill_connections: args.kill_connections,
+        yes: args.yes,
+    };
+    (rules, options)
+}
+
+/// Kill every listener `matches` accepts and report what was cleared.
+/// Success is only reported once the port is actually free, since scripts
+/// usually rebind it straight away. `rules` names what was matched against,
+/// e.g. "the auto-kill rules".
+fn run_auto_kill(
+    backend: &dyn Backend,
+    config: &Config,
+    rules: &str,
+    matches: impl Fn(&PortProcess) -> bool,
+    options: &KillOptions,
+) -> Result<()> {
+    let mut system = System::new();
+    let processes = get_port_processes(backend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop)?;
+    let (protected, targets) = auto_kill_targets(&processes, config, matches);
+    
+    if protected.is_empty() && targets.is_empty() {
+        if !options.quiet {
+            println!("Nothing matched {}", rules);
+        }
+        return Ok(());
+    }
+    
+    for process in &protected {
+        eprintln!("Refusing to kill {} (PID {}) on port {}: protected by port_policies", process.name, process.pid, process.port);
+    }
+    if targets.is_empty() {
+        return Err(anyhow::anyhow!("Refused to kill {} listener(s) on protected ports", protected.len()));
+    }
+    
+    // Demo processes don't exist, so there is nothing to signal
+    if options.dry_run || backend.is_demo() {
+        for process in &targets {
+            println!("Would kill {} (PID {}) on port {}", process.name, process.pid, process.port);
+        }
+        return Ok(());
+    }
+    
+    if options.confirm && !confirm_on_terminal(&targets, "listener(s)")? {
+        if !options.quiet {
+            println!("Nothing killed");
+        }
+        return Ok(());
+    }
+    
+    // A process listening on several matching ports only needs signalling once
+    let mut pids: Vec<u32> = targets.iter().map(|p| p.pid).collect();
+    pids.sort_unstable();
+    pids.dedup();
+    
+    let mut signalled = Vec::new();
+    for pid in pids {
+        let result = backend.send_signal(pid, &config.kill_signal);
+        if config.audit_kills {
+            let name = targets.iter().find(|p| p.pid == pid).map(|p| p.name.as_str()).unwrap_or_default();
+            let ports: Vec<u16> = targets.iter().filter(|p| p.pid == pid).map(|p| p.port).collect();
+            let error = result.as_ref().err().map(|e| e.to_string());
+            audit_kill(&KillRecord { pid, name, ports: &ports, signal: &config.kill_signal, error: error.as_deref() });
+        }
+        match result {
+            Ok(()) => signalled.push(pid),
+            Err(e) => eprintln!("Failed to kill PID {}: {}", pid, e),
+        }
+    }
+    
+    // Wait until the ports are released, escalating to SIGKILL halfway
+    // through the timeout if the configured signal was ignored
+    let targets_len = targets.len();
+    let mut target_ports: Vec<u16> = targets.iter().map(|p| p.port).collect();
+    let mut pending: Vec<&PortProcess> = targets.into_iter()
+        .filter(|p| signalled.contains(&p.pid))
+        .collect();
+    let timeout = Duration::from_millis(config.kill_timeout_ms);
+    let started = Instant::now();
+    let mut escalated = config.kill_signal.eq_ignore_ascii_case("KILL");
+    // Only ports seen free again count, not those whose holder couldn't be signalled
+    let mut freed = 0;
+    
+    loop {
+        let listening = get_port_processes(backend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop)?;
+        pending.retain(|target| {
+            let still_listening = listening.iter().any(|p| p.pid == target.pid && p.port == target.port);
+            if !still_listening {
+                freed += 1;
+                if !options.quiet {
+                    println!("Killed {} (PID {}), port {} is free", target.name, target.pid, target.port);
+                }
+            }
+            still_listening
+        });
+        
+        if pending.is_empty() || started.elapsed() >= timeout {
+            break;
+        }
+        
+        if !escalated && started.elapsed() >= timeout / 2 {
+            let mut stubborn: Vec<u32> = pending.iter().map(|p| p.pid).collect();
+            stubborn.sort_unstable();
+            stubborn.dedup();
+            for pid in stubborn {
+                if !options.quiet {
+                    println!("PID {} ignored SIG{}, sending SIGKILL", pid, config.kill_signal);
+                }
+                let result = backend.send_signal(pid, "KILL");
+                if config.audit_kills {
+                    let name = pending.iter().find(|p| p.pid == pid).map(|p| p.name.as_str()).unwrap_or_default();
+                    let ports: Vec<u16> = pending.iter().filter(|p| p.pid == pid).map(|p| p.port).collect();
+                    let error = result.as_ref().err().map(|e| e.to_string());
+                    audit_kill(&KillRecord { pid, name, ports: &ports, signal: "KILL", error: error.as_deref() });
+                }
+                if let Err(e) = result {
+                    eprintln!("Failed to kill PID {}: {}", pid, e);
+                }
+            }
+            escalated = true;
+        }
+        
+        std::thread::sleep(KILL_POLL_INTERVAL);
+    }
+    
+    // A port can stay unusable after its listener is gone while other
+    // processes, e.g. children that inherited the socket, hold connections on it
+    let mut connection_failures = 0;
+    if options.kill_connections {
+        target_ports.sort_unstable();
+        target_ports.dedup();
+        connection_failures = kill_connected_processes(backend, &target_ports, &signalled, options, config.audit_kills)?;
+    }
+    
+    if !options.quiet && targets_len > 1 {
+        println!("Freed {} of {} port(s)", freed, targets_len);
+    }
+    
+    for target in &pending {
+        eprintln!(
+            "Port {} is still in use by {} (PID {}) after {}ms",
+            target.port, target.name, target.pid, config.kill_timeout_ms
+        );
+    }
+    
+    let mut problems = Vec::new();
+    if freed < targets_len {
+        problems.push(format!("{} of {} port(s) still in use", targets_len - freed, targets_len));
+    }
+    if !protected.is_empty() {
+        problems.push(format!("{} listener(s) on protected ports skipped", protected.len()));
+    }
+    if connection_failures > 0 {
+        problems.push(format!("{} connected process(es) not killed", connection_failures));
+    }
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!("Failed to free every port: {}", problems.join(", ")));
+    }
+    
+    Ok(())
+}
+
+/// Kill processes other than `already_killed` with connections on
+/// the given ports, after listing them and asking unless `--yes` was given.
+/// Returns how many kills failed.
+fn kill_connected_processes(backend: &dyn Backend, ports: &[u16], already_killed: &[u32], options: &KillOptions, audit_kills: bool) -> Result<usize> {
+    let mut system = System::new();
+    let connected = get_port_processes(backend, DiscoveryMode::Connections, &mut system, ExitedProcesses::Drop)?;
+    let mut holders: Vec<&PortProcess> = connected.iter()
+        .filter(|p| ports.contains(&p.port) && !already_killed.contains(&p.pid))
+        .collect();
+    holders.sort_by_key(|p| p.pid);
+    holders.dedup_by_key(|p| p.pid);
+    
+    if holders.is_empty() {
+        return Ok(0);
+    }
+    
+    if !options.quiet {
+        println!("These processes still have connections on the port(s):");
+    }
+    if !options.yes && !confirm_on_terminal(&holders, "connected process(es)")? {
+        if !options.quiet {
+            println!("Left connected processes running");
+        }
+        return Ok(0);
+    }
+    
+    let mut failures = 0;
+    for process in holders {
+        let result = backend.send_signal(process.pid, "KILL");
+        if audit_kills {
+            let error = result.as_ref().err().map(|e| e.to_string());
+            audit_kill(&KillRecord { pid: process.pid, name: &process.name, ports: &[process.port], signal: "KILL", error: error.as_deref() });
+        }
+        match result {
+            Ok(()) if !options.quiet => {
+                println!("Killed {} (PID {}), which had a connection on port {}", process.name, process.pid, process.port);
+            }
+            Ok(()) => {}
+            Err(e) => {
+                eprintln!("Failed to kill PID {}: {}", process.pid, e);
+                failures += 1;
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// Print the filtered process list once, to `--output` or stdout
+fn run_snapshot(args: &Args, format: OutputFormat) -> Result<()> {
+    let mut app = App::new(args)?;
+    app.refresh_processes()?;
+    
+    let options = WriteOptions {
+        binary_units: app.config.binary_units,
+        hide_commands: app.hide_commands,
+    };
+    
+    match &args.output {
+        Some(path) => {
+            if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+            }
+            let file = fs::File::create(path)
+                .with_context(|| format!("Failed to open {} for writing", path.display()))?;
+            let mut out = io::BufWriter::new(file);
+            write_processes(&mut out, format, &app.port_processes, &options)?;
+            out.flush().with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        None => {
+            let mut out = io::stdout().lock();
+            write_processes(&mut out, format, &app.port_processes, &options)?;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Print the process list on every refresh until interrupted, or with
+/// `--deltas` only what changed, one timestamped line per port
+fn run_watch(args: &Args, format: OutputFormat) -> Result<()> {
+    let mut app = App::new(args)?;
+    let options = WriteOptions {
+        binary_units: app.config.binary_units,
+        hide_commands: app.hide_commands,
+    };
+    // Refreshing only on demand makes no sense here
+    let interval = match app.config.refresh_interval_ms {
+        0 => DEFAULT_WATCH_INTERVAL_MS,
+        ms => ms,
+    };
+    
+    let mut previous: Option<Vec<diff::SnapshotEntry>> = None;
+    loop {
+        app.refresh_processes()?;
+        let current: Vec<diff::SnapshotEntry> = app.port_processes.iter().map(diff::SnapshotEntry::from).collect();
+        let mut out = io::stdout().lock();
+        match &previous {
+            Some(previous) if args.deltas => {
+                let changes = diff::diff_snapshots(previous, &current);
+                let timestamp = humantime::format_rfc3339_seconds(SystemTime::now());
+                for change in &changes {
+                    writeln!(out, "{} {}", timestamp, change).context("Failed to write to stdout")?;
+                }
+                if changes.is_empty() && args.heartbeat {
+                    writeln!(out, "{} no changes", timestamp).context("Failed to write to stdout")?;
+                }
+            }
+            _ => {
+                if previous.is_some() {
+                    writeln!(out).context("Failed to write to stdout")?;
+                }
+                write_processes(&mut out, format, &app.port_processes, &options)?;
+            }
+        }
+        out.flush().context("Failed to write to stdout")?;
+        drop(out);
+        
+        previous = Some(current);
+        std::thread::sleep(Duration::from_millis(interval));
+    }
+}
+
+/// Print the differences between two snapshot files, one port per line
+fn run_diff(before: &Path, after: &Path) -> Result<()> {
+    let changes = diff::diff_snapshots(&diff::load_snapshot(before)?, &diff::load_snapshot(after)?);
+    if changes.is_empty() {
+        println!("No differences between {} and {}", before.display(), after.display());
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+    Ok(())
+}
+
+/// List the listeners about to be killed and ask on the terminal whether to go
+/// ahead. Without a terminal to ask on, `--yes` is required.
+fn confirm_on_terminal(targets: &[&PortProcess], what: &str) -> Result<bool> {
+    use std::io::IsTerminal;
+    
+    if !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!("Refusing to kill {} without --yes when not run from a terminal", what));
+    }
+    
+    for process in targets {
+        println!("{} (PID {}) on port {}", process.name, process.pid, process.port);
+    }
+    print!("Kill {} {}? [y/N] ", targets.len(), what);
+    io::stdout().flush().context("Failed to write to stdout")?;
+    
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).context("Failed to read from stdin")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Serve the filtered process list as JSON, refreshing it for every request.
+/// Requests take turns with the app, so CPU usage is measured between them.
+fn run_server(args: &Args, addr: SocketAddr) -> Result<()> {
+    let app = Mutex::new(App::new(args)?);
+    serve::serve(addr, move || -> Result<Vec<u8>> {
+        let mut app = app.lock().map_err(|_| anyhow::anyhow!("Process list lock poisoned"))?;
+        app.refresh_processes()?;
+        
+        let options = WriteOptions {
+            binary_units: app.config.binary_units,
+            hide_commands: app.hide_commands,
+        };
+        let mut body = Vec::new();
+        write_processes(&mut body, OutputFormat::Json, &app.port_processes, &options)?;
+        Ok(body)
+    })?;
+    Ok(())
+}
+
+/// Check whether anything is listening on a port, printing the listeners
+/// unless `quiet` is set. Display filters don't apply, so nothing is missed.
+fn run_check_port(backend: &dyn Backend, port: u16, quiet: bool) -> Result<bool> {
+    let mut system = System::new();
+    let listeners: Vec<PortProcess> = get_port_processes(backend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop)?
+        .into_iter()
+        .filter(|p| p.port == port)
+        .collect();
+    
+    if !quiet {
+        if listeners.is_empty() {
+            println!("Port {} is free", port);
+        }
+        for process in &listeners {
+            println!("Port {} is in use by {} (PID {})", port, process.name, process.pid);
+        }
+    }
+    
+    Ok(listeners.is_empty())
+}
+
+/// Check every listener against the baseline, printing the unexpected ones.
+/// Display filters don't apply, so nothing is missed. Returns whether all
+/// listeners were expected.
+fn run_check_baseline(backend: &dyn Backend, config: &Config, quiet: bool) -> Result<bool> {
+    if config.baseline.is_empty() {
+        return Err(anyhow::anyhow!("No baseline set; press E in the TUI to record the current listeners as expected"));
+    }
+    
+    let mut system = System::new();
+    let listeners = get_port_processes(backend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop)?;
+    let mut unexpected: Vec<&PortProcess> = listeners.iter()
+        .filter(|p| p.unresolved_port.is_none() && config.is_unexpected(&p.name, p.port))
+        .collect();
+    // A listener on both IPv4 and IPv6 is one finding
+    unexpected.dedup_by_key(|p| (p.port, p.pid));
+    
+    if !quiet {
+        if unexpected.is_empty() {
+            println!("All {} listener(s) are in the baseline", listeners.len());
+        }
+        for process in &unexpected {
+            println!("Unexpected listener on port {}: {} (PID {})", process.port, process.name, process.pid);
+        }
+    }
+    Ok(unexpected.is_empty())
+}
+
+/// Write the PID of each process listening on a port to `out`, once each and
+/// nothing else, unless `quiet` is set. Returns whether anything was listening.
+fn run_pid_on(backend: &dyn Backend, port: u16, quiet: bool, out: &mut dyn Write) -> Result<bool> {
+    let mut system = System::new();
+    let pids: BTreeSet<u32> = get_port_processes(backend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop)?
+        .into_iter()
+        .filter(|p| p.port == port)
+        .map(|p| p.pid)
+        .collect();
+    
+    if !quiet {
+        for pid in &pids {
+            writeln!(out, "{}", pid)?;
+        }
+    }
+    
+    Ok(!pids.is_empty())
+}
+
+/// Poll until something is listening on a port (or, with `until_free`, until
+/// nothing is), printing the listeners unless `quiet` is set. A port that
+/// changes while waiting is also passed to `on_change`, e.g. to raise a
+/// desktop notification. Returns false if the timeout ran out first.
+fn run_wait(
+    backend: &dyn Backend,
+    port: u16,
+    until_free: bool,
+    timeout: Option<Duration>,
+    quiet: bool,
+    on_change: Option<&dyn Fn(&str)>,
+) -> Result<bool> {
+    let mut system = System::new();
+    let started = Instant::now();
+    // A port that was already as wanted didn't change, so nobody needs alerting
+    let mut changed = false;
+    
+    loop {
+        let listeners: Vec<PortProcess> = get_port_processes(backend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop)?
+            .into_iter()
+            .filter(|p| p.port == port)
+            .collect();
+        
+        if until_free && listeners.is_empty() {
+            if !quiet {
+                println!("Port {} is free", port);
+            }
+            if let Some(on_change) = on_change.filter(|_| changed) {
+                on_change(&format!("Port {} is free", port));
+            }
+            return Ok(true);
+        }
+        if !until_free && !listeners.is_empty() {
+            if !quiet {
+                for process in &listeners {
+                    println!("Port {} is in use by {} (PID {})", port, process.name, process.pid);
+                }
+            }
+            if let Some(on_change) = on_change.filter(|_| changed) {
+                let process = &listeners[0];
+                on_change(&format!("Port {} is in use by {} (PID {})", port, process.name, process.pid));
+            }
+            return Ok(true);
+        }
+        changed = true;
+        
+        if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            if !quiet {
+                let waiting_for = if until_free { "to be freed" } else { "to be listened on" };
+                eprintln!("Timed out waiting for port {} {}", port, waiting_for);
+            }
+            return Ok(false);
+        }
+        
+        std::thread::sleep(WAIT_POLL_INTERVAL);
+    }
+}
+
+/// Run the TUI until it's quit or `shutdown` is set, e.g. by a termination
+/// signal, then put the terminal back with `restore` and save the session
+fn run_tui<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    shutdown: &AtomicBool,
+    restore: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    let result = run_app(terminal, app, shutdown);
+    
+    // Ensure terminal is restored even if there's an error
+    restore()?;
+    
+    // Failing to save the session shouldn't hide an error from the app
+    if app.config.restore_session {
+        if let Err(e) = app.session().save(app.config.config_path()) {
+            eprintln!("Failed to save the session: {}", e);
+        }
+    }
+    
+    // Return the result from running the app
+    result
+}
+
+/// Turn on raw mode with `enable_raw`, or if the terminal can't do it, write
+/// the process list to `out` instead. Returns whether the TUI can start.
+fn enter_raw_mode_or_list(
+    app: &mut App,
+    enable_raw: impl FnOnce() -> io::Result<()>,
+    out: &mut dyn Write,
+) -> Result<bool> {
+    let Err(e) = enable_raw() else {
+        return Ok(true);
+    };
+    tracing::warn!("Raw mode is unavailable ({}), printing the process list instead", e);
+    
+    app.refresh_processes()?;
+    let options = WriteOptions {
+        binary_units: app.config.binary_units,
+        hide_commands: app.hide_commands,
+    };
+    write_processes(out, OutputFormat::List, &app.port_processes, &options)?;
+    Ok(false)
+}
+
+/// Initialize the terminal for TUI once raw mode is on
+fn init_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    io::stdout()
+        .execute(EnterAlternateScreen)
+        .context("Failed to enter alternate screen")?;
+    
+    let backend = CrosstermBackend::new(io::stdout());
+    let terminal = Terminal::new(backend).context("Failed to create terminal")?;
+    Ok(terminal)
+}
+
+/// Restore terminal to original state
+fn restore_terminal() -> Result<()> {
+    disable_raw_mode().context("Failed to disable raw mode")?;
+    io::stdout()
+        .execute(LeaveAlternateScreen)
+        .context("Failed to leave alternate screen")?;
+    Ok(())
+}
+
+/// Run the selected action with the TUI suspended, so interactive commands
+/// like `strace` get the terminal, then wait for Enter before returning
+fn run_selected_action<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    app.show_action_popup = false;
+    let Some((name, command)) = app.selected_action_command() else {
+        return Ok(());
+    };
+    
+    restore_terminal()?;
+    println!("$ {}", command);
+    
+    let status = std::process::Command::new("sh")
+        .args(["-c", &command])
+        .status();
+    
+    app.status_message = Some(match &status {
+        Ok(status) if status.success() => format!("Action \"{}\" finished", name),
+        Ok(status) => format!("Action \"{}\" failed ({})", name, status),
+        Err(e) => format!("Failed to run action \"{}\": {}", name, e),
+    });
+    if let Err(e) = &status {
+        eprintln!("Failed to run command: {}", e);
+    }
+    
+    println!("\nPress Enter to return to port-manager");
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).context("Failed to read from stdin")?;
+    
+    // Ctrl-C while the action runs reaches us as well, but was meant for the action
+    shutdown.store(false, Ordering::Relaxed);
+    
+    resume_terminal(terminal)?;
+    
+    // The action may well have changed what is running
+    app.refresh_processes()
+}
+
+/// System monitors tried in order to inspect a process, with `{pid}` replaced
+#[cfg(target_os = "macos")]
+const MONITOR_COMMANDS: &[(&str, &[&str])] = &[("top", &["-pid", "{pid}"])];
+#[cfg(not(target_os = "macos"))]
+const MONITOR_COMMANDS: &[(&str, &[&str])] = &[("htop", &["-p", "{pid}"]), ("top", &["-p", "{pid}"])];
+
+/// Open the selected process in the first installed system monitor, with the
+/// TUI suspended until the monitor is quit
+fn open_system_monitor<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    let Some(pid) = app.selected_process().map(|p| p.pid) else {
+        return Ok(());
+    };
+    if app.backend.is_demo() {
+        app.status_message = Some(format!("Demo mode: PID {} isn't a real process", pid));
+        return Ok(());
+    }
+    if !app.backend.is_local() {
+        app.status_message = Some(format!("PID {} runs on another machine; open a monitor there", pid));
+        return Ok(());
+    }
+    
+    restore_terminal()?;
+    let mut result = None;
+    for (program, args) in MONITOR_COMMANDS {
+        let args = args.iter().map(|arg| arg.replace("{pid}", &pid.to_string()));
+        match std::process::Command::new(program).args(args).status() {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            status => {
+                result = Some((program, status));
+                break;
+            }
+        }
+    }
+    
+    // Ctrl-C in the monitor reaches us as well, but was meant for the monitor
+    shutdown.store(false, Ordering::Relaxed);
+    resume_terminal(terminal)?;
+    
+    app.status_message = match result {
+        None => {
+            let programs: Vec<&str> = MONITOR_COMMANDS.iter().map(|(program, _)| *program).collect();
+            Some(format!("No system monitor found (tried {})", programs.join(", ")))
+        }
+        Some((program, Err(e))) => Some(format!("Failed to run {}: {}", program, e)),
+        Some((program, Ok(status))) if !status.success() => Some(format!("{} exited with {}", program, status)),
+        Some(_) => None,
+    };
+    Ok(())
+}
+
+/// Program that opens a directory in the desktop's file manager
+#[cfg(target_os = "macos")]
+const FILE_MANAGER_COMMAND: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const FILE_MANAGER_COMMAND: &str = "xdg-open";
+
+/// Open a directory in the file manager without waiting for it, so the TUI
+/// keeps running
+fn open_in_file_manager(dir: &Path) -> Result<()> {
+    let mut child = std::process::Command::new(FILE_MANAGER_COMMAND)
+        .arg(dir)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", FILE_MANAGER_COMMAND))?;
+    // Reap it in the background; xdg-open returns once the file manager is up
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Open the config file in `$VISUAL`/`$EDITOR` with the TUI suspended, then
+/// apply it only if it still parses
+fn edit_config<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    
+    restore_terminal()?;
+    
+    // Go through the shell so editors with arguments like `code -w` work
+    let status = std::process::Command::new("sh")
+        .args(["-c", &format!("{} \"$1\"", editor), "sh"])
+        .arg(app.config.config_path())
+        .status();
+    
+    resume_terminal(terminal)?;
+    
+    match status {
+        Ok(status) if status.success() => app.reload_config(),
+        Ok(status) => {
+            app.config_error = Some(format!("Editor exited with {}", status));
+            Ok(())
+        }
+        Err(e) => {
+            app.config_error = Some(format!("Failed to run editor \"{}\": {}", editor, e));
+            Ok(())
+        }
+    }
+}
+
+/// Return to the TUI after handing the terminal to another program
+fn resume_terminal<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>) -> Result<()> {
+    enable_raw_mode().context("Failed to enable raw mode")?;
+    io::stdout()
+        .execute(EnterAlternateScreen)
+        .context("Failed to enter alternate screen")?;
+    terminal.clear().context("Failed to clear terminal")?;
+    Ok(())
+}
+
+/// Main UI rendering function
+fn ui(frame: &mut Frame, app: &App) -> Vec<PortLink> {
+    // Render the current view
+    let links = match app.current_view {
+        View::ProcessList => render_process_view(frame, app),
+        View::FilterManagement => {
+            render_filter_view(frame, app);
+            Vec::new()
+        }
+    };
+    
+    // Render the add filter popup if active
+    if app.show_add_filter_popup {
+        render_add_filter_popup(frame, app);
+    }
+    
+    // Render the cleanup script name prompt if active
+    if app.show_cleanup_name_popup {
+        render_cleanup_name_popup(frame, app);
+    }
+    
+    // Render the kill confirmation popup if active
+    if app.show_kill_confirm && !app.inline_kill_confirm() {
+        render_kill_confirm_popup(frame, app);
+    }
+    
+    // Render the detail popup if active
+    if app.show_details {
+        render_details_popup(frame, app);
+    }
+    
+    // Render the profile switcher if active
+    if app.show_profile_popup {
+        render_profile_popup(frame, app);
+    }
+    
+    // Render the actions menu if active
+    if app.show_action_popup {
+        render_action_popup(frame, app);
+    }
+    
+    // Render the raw config if active
+    if app.show_config_popup {
+        render_config_popup(frame, app);
+    }
+    
+    // Render the kill stats if active
+    if app.show_stats_popup {
+        render_stats_popup(frame, app);
+    }
+    
+    // Render the key list if active
+    if app.show_help_popup {
+        render_help_popup(frame, app);
+    }
+    
+    // Render the bulk kill summary if active
+    if app.show_kill_results {
+        render_kill_results_popup(frame, app);
+    }
+    
+    // Render the first-run setup over everything else
+    if app.show_setup_popup {
+        render_setup_popup(frame, app);
+    }
+    
+    // Links are drawn over the finished frame, so they would show through
+    // popups and move the cursor away from text inputs
+    if app.is_modal_open() {
+        Vec::new()
+    } else {
+        links
+    }
+}
+
+/// Window title, naming the profile when it isn't the default and the
+/// machine when it isn't this one
+fn app_title(app: &App) -> String {
+    let mut title = "Port Manager".to_string();
+    if app.config.profile() != DEFAULT_PROFILE {
+        title.push_str(&format!(" [{}]", app.config.profile()));
+    }
+    if let Some(host) = app.backend.host() {
+        title.push_str(&format!(" on {}", host));
+    }
+    title
+}
+
+/// One line of session info shown under the table in both views
+fn status_footer(app: &App) -> String {
+    let refresh = if app.refresh_paused {
+        "refresh PAUSED".to_string()
+    } else {
+        format!("refreshed {}s ago", app.last_refresh.elapsed().as_secs())
+    };
+    let sort = if app.top_enabled {
+        format!("top {} by {}", app.top_count, app.top_by.label())
+    } else {
+        format!(
+            "sorted by {} {}",
+            app.config.sort_column.label(),
+            if app.config.sort_descending { "▼" } else { "▲" }
+        )
+    };
+    let mut footer = match &app.armed_kill {
+        Some(armed) => format!("AUTO-KILL ARMED for {} ({} killed) | ", armed.target.label(), armed.total_kills),
+        None => String::new(),
+    };
+    footer.push_str(&format!(
+        "Backend: {} | {} of {} shown | {} | {}",
+        app.backend.name(),
+        app.port_processes.len(),
+        app.discovered.len(),
+        refresh,
+        sort,
+    ));
+    footer.push_str(&format!(" | {}", resource_summary(app)));
+    if let Some(preset) = app.active_preset() {
+        footer.push_str(&format!(" | preset: {}", preset.name));
+    }
+    if let Some(steps) = &app.recorded_cleanup {
+        footer.push_str(&format!(" | recording cleanup ({} step(s))", steps.len()));
+    }
+    if !app.snoozed_filters.is_empty() {
+        footer.push_str(&format!(" | {} filter(s) snoozed", app.snoozed_filters.len()));
+    }
+    if app.config.palette != Palette::Standard {
+        footer.push_str(&format!(" | {} palette", app.config.palette.label()));
+    }
+    footer
+}
+
+/// Dim, unless an auto-kill is armed: it kills without asking, so it must
+/// not go unnoticed
+fn status_footer_style(app: &App) -> Style {
+    match app.armed_kill {
+        Some(_) => Style::default().fg(app.color(Color::Red)).bold(),
+        None => Style::default().fg(Color::DarkGray),
+    }
+}
+
+/// Total memory and CPU of the shown processes, counting each PID once even
+/// when it listens on several ports
+fn resource_summary(app: &App) -> String {
+    let mut seen = BTreeSet::new();
+    let mut memory = 0;
+    let mut cpu = 0.0;
+    let mut missing = 0;
+    for process in &app.port_processes {
+        if !seen.insert(process.pid) {
+            continue;
+        }
+        if process.has_resource_data() {
+            memory += process.memory;
+            cpu += process.cpu_usage;
+        } else {
+            missing += 1;
+        }
+    }
+    
+    let mut summary = format!("total {}, {:.1}% CPU", format_bytes(memory, app.config.binary_units), cpu);
+    if missing > 0 {
+        summary.push_str(&format!(" ({} without data)", missing));
+    }
+    summary
+}
+
+/// Render the process list view
+fn render_process_view(frame: &mut Frame, app: &App) -> Vec<PortLink> {
+    // The search and highlight inputs take the help's place, with a title
+    let typing = app.search_active || app.highlight_active;
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(view_block_height(app, 1, true)),     // Title
+            Constraint::Min(0),                                      // Table
+            Constraint::Length(1),                                   // Status footer
+            Constraint::Length(view_block_height(app, 1, typing)),   // Help
+        ])
+        .split(frame.size());
+    
+    // Title block
+    let title_block = app.view_block()
+        .title(app_title(app))
+        .title_alignment(Alignment::Center);
+    
+    let (subtitle, table_title) = match app.discovery_mode {
+        DiscoveryMode::Listeners => ("Monitor and manage processes listening on TCP ports (UDP isn't shown)", "Processes"),
+        DiscoveryMode::Connections => ("Processes with TCP connections (not listeners)", "Connections"),
+    };
+    let show_remote = app.discovery_mode == DiscoveryMode::Connections;
+    let mut table_title = table_title.to_string();
+    match app.port_filter.as_slice() {
+        [] => {}
+        [port] => table_title.push_str(&format!(" on port {}", port)),
+        ports => {
+            let ports: Vec<String> = ports.iter().map(u16::to_string).collect();
+            table_title.push_str(&format!(" on ports {}", ports.join(", ")));
+        }
+    }
+    if let Some(range) = &app.port_range {
+        table_title.push_str(&format!(" on ports {}-{}", range.start(), range.end()));
+    }
+    if let Some(namespace) = app.net_namespace {
+        table_title.push_str(&format!(" in net namespace {}", namespace));
+    }
+    if app.include_system {
+        table_title.push_str(" (filters and port range off)");
+    } else if app.no_filters {
+        table_title.push_str(" (filters off)");
+    }
+    if app.top_enabled {
+        table_title.push_str(&format!(" (top {} by {})", app.top_count, app.top_by.label()));
+    }
+    if app.hide_recent {
+        table_title.push_str(&format!(" (older than {})", humantime::format_duration(app.min_age)));
+    }
+    if let Some(max_age) = app.max_age {
+        table_title.push_str(&format!(" (started within {})", humantime::format_duration(max_age)));
+    }
+    if let Some(state) = app.state_filter {
+        table_title.push_str(&format!(" in state {}", state.label()));
+    }
+    if let Some(placement) = app.placement_filter {
+        table_title.push_str(&format!(" (only {})", placement.label()));
+    }
+    if app.unexpected_only {
+        table_title.push_str(" (only unexpected)");
+    } else {
+        let unexpected = app.port_processes.iter().filter(|p| app.is_unexpected(p)).count();
+        if unexpected > 0 {
+            table_title.push_str(&format!(" - {} unexpected", unexpected));
+        }
+    }
+    if app.hide_loopback && !show_remote {
+        table_title.push_str(" (loopback-only hidden)");
+    }
+    if !app.search_query.is_empty() {
+        table_title.push_str(&format!(" matching \"{}\"", app.search_query));
+    }
+    if app.group_by_address {
+        table_title.push_str(" grouped by address");
+    }
+    if app.group_by_unit {
+        table_title.push_str(" grouped by unit");
+    }
+    if !app.highlight_query.is_empty() {
+        table_title.push_str(&format!(" highlighting \"{}\"", app.highlight_query));
+    }
+    if let Some(port) = app.focus_port {
+        table_title.push_str(&format!(" focusing port {}", port));
+    }
+    if !app.ignored_pids.is_empty() {
+        table_title.push_str(&format!(" - {} PID(s) ignored", app.ignored_pids.len()));
+    }
+    if !app.marked_pids.is_empty() {
+        table_title.push_str(&format!(" - {} marked", app.marked_pids.len()));
+    }
+    
+    let title_text = Paragraph::new(subtitle)
+        .block(title_block)
+        .alignment(Alignment::Center);
+    
+    frame.render_widget(title_text, layout[0]);
+    
+    let footer = Paragraph::new(status_footer(app))
+        .style(status_footer_style(app))
+        .alignment(Alignment::Center);
+    frame.render_widget(footer, layout[2]);
+    
+    // Process table, with an arrow on the sorted column unless top mode
+    // is ranking by resource instead
+    let sort_column = (!app.top_enabled).then_some(app.config.sort_column);
+    let arrow = if app.config.sort_descending { " ▼" } else { " ▲" };
+    let columns = table_columns(app);
+    let command_header = if app.command_scroll > 0 {
+        format!("Command (+{})", app.command_scroll)
+    } else {
+        "Command".to_string()
+    };
+    let header_cells = columns
+        .iter()
+        .map(|column| {
+            let title = if column.sort_column().is_some() && column.sort_column() == sort_column {
+                format!("{}{}", column.title(), arrow)
+            } else if *column == TableColumn::Command {
+                command_header.clone()
+            } else {
+                column.title().to_string()
+            };
+            Cell::from(title).style(Style::default().bold())
+        });
+    let header_margin = if app.config.dense { 0 } else { 1 };
+    let header = Row::new(header_cells).height(1).bottom_margin(header_margin);
+    
+    let now = SystemTime::now();
+    let window = row_window(app.port_processes.len(), app.selected_idx, app.config.max_rows);
+    let hidden_rows = app.port_processes.len() - window.len();
+    let shown = &app.port_processes[window.clone()];
+    let rows = shown.iter().map(|process| {
+        let cells = columns.iter().map(|column| match column {
+            TableColumn::Indicators => Cell::from(row_indicators(process, app.is_unexpected(process), app.config.use_glyphs) + &app.age_marker(process, now).unwrap_or_default()),
+            TableColumn::Pid => Cell::from(process.pid.to_string()),
+            TableColumn::Port => Cell::from(port_label(process)),
+            TableColumn::Remote => Cell::from(process.remote.clone().unwrap_or_default()),
+            TableColumn::State => Cell::from(process.state.label()),
+            // Processes that exited mid-refresh are kept only when marking is configured
+            TableColumn::Name if process.exited => {
+                Cell::from(format!("{} (exited)", process.name)).style(Style::default().fg(Color::DarkGray))
+            }
+            TableColumn::Name if process.orphaned => {
+                Cell::from(process.name.clone()).style(Style::default().fg(app.color(Color::Red)))
+            }
+            TableColumn::Name if app.is_unexpected(process) => {
+                Cell::from(process.name.clone()).style(Style::default().fg(Color::Magenta).bold())
+            }
+            TableColumn::Name => Cell::from(process.name.clone()),
+            TableColumn::Cpu => Cell::from(format!("{:.1}", process.cpu_usage)),
+            TableColumn::Memory => Cell::from(format_bytes(process.memory, app.config.binary_units)),
+            TableColumn::Threads => Cell::from(thread_label(process)),
+            TableColumn::Command => Cell::from(scrolled_command(app.table_command(process), app.command_scroll)),
+        });
+        Row::new(cells).height(1).style(row_style(app, process, now))
+    });
+    
+    // When grouped, a header row goes before each group's processes, so the
+    // selection has to skip past the headers above it
+    let mut table_rows = Vec::new();
+    let mut row_processes = Vec::new();
+    let mut selected_row = app.selected_idx.map(|selected| selected - window.start);
+    // Group headers and the row count go under the name and the last column
+    let name_column = columns.iter().position(|column| *column == TableColumn::Name).unwrap_or(0);
+    let last_column = columns.len() - 1;
+    let same_group = |a: &PortProcess, b: &PortProcess| if app.group_by_unit {
+        a.systemd_unit == b.systemd_unit
+    } else {
+        a.local_addr == b.local_addr
+    };
+    for (index, (process, row)) in shown.iter().zip(rows).enumerate() {
+        let index = index + window.start;
+        let starts_group = index == 0 || !same_group(&app.port_processes[index - 1], process);
+        if (app.group_by_address || app.group_by_unit) && starts_group {
+            let group: Vec<&PortProcess> = app.port_processes[index..].iter()
+                .take_while(|p| same_group(p, process))
+                .collect();
+            // A process can hold several sockets in a group
+            let processes = group.iter().map(|p| p.pid).collect::<HashSet<_>>().len();
+            let mut cells = vec![Cell::from(""); columns.len()];
+            if app.group_by_unit {
+                cells[name_column] = Cell::from(process.systemd_unit.clone().unwrap_or_else(|| "(no unit)".to_string()));
+                cells[last_column] = Cell::from(format!("{} process(es), {}", processes, socket_summary(group.iter().copied())));
+            } else {
+                cells[name_column] = Cell::from(process.local_addr.clone());
+                cells[last_column] = Cell::from(format!(
+                    "{}, {} process(es), {}",
+                    process.bind_scope().1,
+                    processes,
+                    socket_summary(group.iter().copied()),
+                ));
+            }
+            table_rows.push(Row::new(cells).height(1).style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
+            row_processes.push(None);
+            if app.selected_idx.is_some_and(|selected| selected >= index) {
+                selected_row = selected_row.map(|row| row + 1);
+            }
+        }
+        table_rows.push(row);
+        row_processes.push(Some(process));
+    }
+    if hidden_rows > 0 {
+        let mut cells = vec![Cell::from(""); columns.len()];
+        cells[name_column] = Cell::from(format!("… {} more", hidden_rows));
+        cells[last_column] = Cell::from("(adjust filters or search)");
+        table_rows.push(Row::new(cells).height(1).style(Style::default().fg(Color::DarkGray)));
+        row_processes.push(None);
+    }
+    
+    // Size the PID and Port columns to their widest value so nothing clips
+    // Leave room for the sort arrow in the header
+    let pid_width = column_width("PID ▲", shown.iter().map(|p| p.pid.to_string()));
+    let port_width = column_width("Port ▲", shown.iter().map(port_label));
+    
+    let widths: Vec<Constraint> = columns.iter()
+        .map(|column| match column {
+            TableColumn::Indicators => Constraint::Length(if app.config.use_glyphs { 12 } else { 6 }),
+            TableColumn::Pid => Constraint::Length(pid_width),
+            TableColumn::Port => Constraint::Length(port_width),
+            TableColumn::Remote => Constraint::Length(24),
+            TableColumn::State => Constraint::Length(11),
+            TableColumn::Name => Constraint::Length(20),
+            TableColumn::Cpu => Constraint::Length(7),
+            TableColumn::Memory => Constraint::Length(10),
+            TableColumn::Threads => Constraint::Length(10),
+            TableColumn::Command => Constraint::Percentage(60),
+        })
+        .collect();
+    
+    let table_block = app.view_block().title(table_title.clone());
+    let table_inner = table_block.inner(layout[1]);
+    let table = Table::new(table_rows, widths.clone())
+    .header(header)
+    .block(table_block)
+    .highlight_style(selection_style(app.config.highlight_style))
+    .highlight_symbol(">> ");
+    
+    // Render table with selection, or cards when the terminal is too narrow
+    // for the columns
+    let narrow = match app.config.process_layout {
+        ProcessLayout::Auto => frame.size().width < NARROW_LAYOUT_WIDTH,
+        ProcessLayout::Table => false,
+        ProcessLayout::Cards => true,
+    };
+    let mut links = Vec::new();
+    if narrow {
+        let mut cards = process_cards(app, shown, now);
+        if hidden_rows > 0 {
+            let more = format!("… {} more (adjust filters or search)", hidden_rows);
+            cards.push(ListItem::new(Line::styled(more, Style::default().fg(Color::DarkGray))));
+        }
+        let cards = List::new(cards)
+            .block(app.view_block().title(table_title))
+            .highlight_style(selection_style(app.config.highlight_style))
+            .highlight_symbol("> ");
+        let list_state = &mut ListState::default().with_selected(app.selected_idx.map(|selected| selected - window.start));
+        frame.render_stateful_widget(cards, layout[1], list_state);
+    } else {
+        let table_state = &mut TableState::default().with_selected(selected_row);
+        frame.render_stateful_widget(table, layout[1], table_state);
+        
+        // Only listeners can be opened in a browser
+        let port_column = columns.iter().position(|column| *column == TableColumn::Port);
+        if let Some(port_column) = port_column.filter(|_| app.port_links && !show_remote) {
+            let header_rows = 1 + header_margin;
+            links = port_links(table_inner, header_rows, &widths, port_column, selected_row.is_some(), &row_processes[table_state.offset()..]);
+        }
+    }
+    
+    // Help text, replaced by the result of the last action unless a kill is
+    // waiting to be confirmed, or by the confirmation itself when inline
+    let help = match &app.status_message {
+        _ if app.inline_kill_confirm() => inline_kill_prompt(app),
+        Some(message) if !app.show_kill_confirm => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        _ => Paragraph::new(process_view_help(app)).style(Style::default().fg(Color::Gray)),
+    };
+    let help = help
+        .block(app.view_block())
+        .alignment(Alignment::Center);
+    
+    // The search input takes the help's place while it is being typed
+    if app.search_active {
+        let fields = app.search_fields.iter().map(SearchField::label).collect::<Vec<_>>().join(", ");
+        let block = app.view_block()
+            .title(format!("Search {} | Tab: Names only/everything | Enter: Keep | Esc: Clear", fields));
+        let (x, y) = input_cursor(&block, layout[3], app.search_query.chars().count() + 1);
+        frame.render_widget(Paragraph::new(format!("/{}", app.search_query)).block(block), layout[3]);
+        frame.set_cursor(x, y);
+    } else if app.highlight_active {
+        let block = app.view_block()
+            .title("Highlight name, command, port | Enter: Keep | Esc: Clear");
+        let (x, y) = input_cursor(&block, layout[3], app.highlight_query.chars().count() + 1);
+        frame.render_widget(Paragraph::new(format!("*{}", app.highlight_query)).block(block), layout[3]);
+        frame.set_cursor(x, y);
+    } else {
+        frame.render_widget(help, layout[3]);
+    }
+    
+    links
+}
+
+/// Orange from the 256-color palette, which stands out from blue for
+/// color-blind users
+const ORANGE: Color = Color::Indexed(208);
+
+/// Check whether the user asked for no color. Per https://no-color.org, any
+/// non-empty value counts.
+fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// A block with the configured border, used by every view and popup
+fn bordered(style: BorderStyle) -> Block<'static> {
+    let border_type = match style {
+        BorderStyle::Rounded => BorderType::Rounded,
+        BorderStyle::Plain => BorderType::Plain,
+        BorderStyle::Double => BorderType::Double,
+        BorderStyle::None => return Block::default(),
+    };
+    Block::default().borders(Borders::ALL).border_type(border_type)
+}
+
+/// Rows a main view's title or help box takes for its content lines. A
+/// title needs a line of its own when there's no border to put it on.
+fn view_block_height(app: &App, lines: u16, titled: bool) -> u16 {
+    if app.view_borders() {
+        lines + 2
+    } else {
+        lines + u16::from(titled)
+    }
+}
+
+/// Where the cursor goes after `typed` characters in an input box. The
+/// block must have a title for the input line to be below it.
+fn input_cursor(block: &Block, area: Rect, typed: usize) -> (u16, u16) {
+    let inner = block.inner(area);
+    (inner.x + typed as u16, inner.y)
+}
+
+/// Style of the selected row in tables and lists
+fn selection_style(highlight: HighlightStyle) -> Style {
+    let highlight = match highlight {
+        HighlightStyle::Auto if no_color() => HighlightStyle::Inverse,
+        HighlightStyle::Auto => HighlightStyle::Blue,
+        other => other,
+    };
+    match highlight {
+        HighlightStyle::Auto | HighlightStyle::Blue => Style::default()
+            .bg(Color::Blue)
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD),
+        HighlightStyle::Inverse => Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        HighlightStyle::Underline => Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD),
+        HighlightStyle::Arrow => Style::default(),
+    }
+}
+
+/// Characters the Command column moves per Left/Right press
+const COMMAND_SCROLL_STEP: usize = 8;
+
+/// Command with its first `offset` characters scrolled away. Only this cell
+/// scrolls, so the other columns stay put and the row highlight still spans
+/// the whole row; a leading `…` shows there is more to the left.
+fn scrolled_command(command: &str, offset: usize) -> String {
+    if offset == 0 {
+        return command.to_string();
+    }
+    let rest: String = command.chars().skip(offset + 1).collect();
+    format!("…{}", rest)
+}
+
+/// Widest an auto-sized column may grow, so the command column keeps its space
+const MAX_AUTO_COLUMN_WIDTH: u16 = 14;
+
+/// Help line for the process view, with only the keys that make sense right
+/// now: the confirmation's keys while one is open, ways to bring processes
+/// back when none are shown, and actions on the selected process (or the
+/// marked ones) otherwise. `?` lists the rest.
+fn process_view_help(app: &App) -> String {
+    if app.show_kill_confirm {
+        return kill_confirm_help(app).to_string();
+    }
+
+    let key = |action| key_help(&app.config, action);
+    let navigate = format!("{}/{}: Navigate", key_names(&app.config, KeyAction::Up), key_names(&app.config, KeyAction::Down));
+    if app.port_processes.is_empty() {
+        return format!(
+            "No processes shown | {} | {} | V: Quick toggle filters | u: Undo filter change | {} | ?: All keys | {}",
+            key(KeyAction::Search),
+            key(KeyAction::ToggleView),
+            key(KeyAction::Refresh),
+            key(KeyAction::Quit),
+        );
+    }
+
+    if !app.marked_pids.is_empty() {
+        return format!(
+            "{} marked | {} | {} | {} | {} | ?: All keys | {}",
+            app.marked_pids.len(),
+            key(KeyAction::Kill),
+            key(KeyAction::KillNow),
+            key(KeyAction::Mark),
+            navigate,
+            key(KeyAction::Quit),
+        );
+    }
+
+    if app.selected_process().is_none() {
+        return format!(
+            "{} | {} | {} | ?: All keys | {}",
+            navigate,
+            key(KeyAction::Search),
+            key(KeyAction::Refresh),
+            key(KeyAction::Quit),
+        );
+    }
+
+    format!(
+        "{} | {} | c: Copy row | {} | {} | ?: All keys | {}",
+        key(KeyAction::Kill),
+        key(KeyAction::Details),
+        key(KeyAction::Mark),
+        navigate,
+        key(KeyAction::Quit),
+    )
+}
+
+/// Every process list key, listed by the `?` popup
+fn process_view_keys(app: &App) -> Vec<String> {
+    let key = |action| key_help(&app.config, action);
+    vec![
+        key(KeyAction::Kill),
+        key(KeyAction::KillNow),
+        key(KeyAction::Details),
+        "c: Copy row".to_string(),
+        key(KeyAction::Filter),
+        key(KeyAction::Mark),
+        "a: Actions".to_string(),
+        "B: Kill and filter".to_string(),
+        "M: System monitor".to_string(),
+        "z/Z: Suspend/resume".to_string(),
+        "R: Re-check selected".to_string(),
+        "I: Ignore PID".to_string(),
+        format!("{}/{}: Navigate", key_names(&app.config, KeyAction::Up), key_names(&app.config, KeyAction::Down)),
+        "n: Next with same name".to_string(),
+        key(KeyAction::Search),
+        "H: Highlight".to_string(),
+        if app.focus_port.is_some() { "D/Esc: Unfocus" } else { "D: Focus port" }.to_string(),
+        "←/→: Scroll commands".to_string(),
+        key(KeyAction::ToggleView),
+        "s/S: Sort column/direction".to_string(),
+        "O: Port order".to_string(),
+        "t/T: Top mode/key".to_string(),
+        "h: Hide commands".to_string(),
+        "e: Executables only".to_string(),
+        "l: Hide loopback-only".to_string(),
+        "L: Port links".to_string(),
+        "o: Hide recent".to_string(),
+        "A: Age colors".to_string(),
+        "g/G: Group by address/unit".to_string(),
+        "C: Cycle TCP states".to_string(),
+        "W: Containers/host only".to_string(),
+        "E: Set baseline".to_string(),
+        "Q: Unexpected only".to_string(),
+        "Y: Arm auto-kill (port/name/off)".to_string(),
+        key(KeyAction::RecordCleanup),
+        "v: Cycle presets".to_string(),
+        "V: Quick toggle filters".to_string(),
+        "w: Share view".to_string(),
+        "b: Diagnostics".to_string(),
+        "x: Export kill script".to_string(),
+        "X: Clean slate".to_string(),
+        "N: Kill stats".to_string(),
+        "P: Profiles".to_string(),
+        key(KeyAction::Refresh),
+        "p: Pause refresh".to_string(),
+        "u/U: Undo/redo filter change".to_string(),
+        key(KeyAction::Quit),
+    ]
+}
+
+/// Keys for the open kill confirmation
+fn kill_confirm_help(app: &App) -> &'static str {
+    match (app.needs_typed_confirmation(), app.kill_targets.len() > 1) {
+        (true, _) => "Enter: Kill | ↑/↓: Scroll | Esc: Cancel",
+        (false, true) => "y/Enter: Kill | ↑/↓: Scroll | n/Esc: Cancel",
+        (false, false) if app.kill_restart.is_some() => "y/Enter: Kill | r: Restart service | n/Esc: Cancel",
+        (false, false) => "y/Enter: Kill | n/Esc: Cancel",
+    }
+}
+
+/// Keys bound to an action as shown in the help line, e.g. `Enter/i`
+fn key_names(config: &Config, action: KeyAction) -> String {
+    let enter = config.enter_runs(action).then(|| "enter".to_string());
+    enter.iter().chain(config.keys_for(action).iter())
+        .map(|key| match key.to_ascii_lowercase().as_str() {
+            "up" => "↑".to_string(),
+            "down" => "↓".to_string(),
+            "enter" => "Enter".to_string(),
+            "space" => "Space".to_string(),
+            _ => key.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Help line entry for an action, e.g. `k: Kill process`
+fn key_help(config: &Config, action: KeyAction) -> String {
+    format!("{}: {}", key_names(config, action), action.label())
+}
+
+/// A port in the table to make into a terminal hyperlink
+struct PortLink {
+    area: Rect,
+    url: String,
+}
+
+/// Where each visible listener's port is drawn in the process table, worked
+/// out the same way `Table` lays out its columns
+fn port_links(inner: Rect, header_rows: u16, widths: &[Constraint], port_column: usize, selected: bool, rows: &[Option<&PortProcess>]) -> Vec<PortLink> {
+    let selection_width = if selected { 3 } else { 0 };  // ">> "
+    let mut constraints = vec![Constraint::Length(selection_width)];
+    for (index, width) in widths.iter().enumerate() {
+        if index > 0 {
+            constraints.push(Constraint::Length(1));
+        }
+        constraints.push(*width);
+    }
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(inner);
+    // The selection, then each column after the spacing before it
+    let port_column = columns[1 + 2 * port_column];
+    
+    // Rows start below the header and its margin
+    (inner.y + header_rows..inner.bottom())
+        .zip(rows)
+        .filter_map(|(y, process)| {
+            let process = (*process).filter(|p| p.unresolved_port.is_none())?;
+            let width = (port_label(process).chars().count() as u16).min(port_column.width);
+            let scheme = if matches!(process.port, 443 | 8443) { "https" } else { "http" };
+            Some(PortLink {
+                area: Rect::new(port_column.x, y, width, 1),
+                url: format!("{}://localhost:{}", scheme, process.port),
+            })
+        })
+        .collect()
+}
+
+/// Copies of the cells under each link, wrapped in OSC 8 hyperlink escapes.
+/// Drawn straight to the terminal after a frame, they leave the buffer that
+/// ratatui diffs against untouched, since the escapes would break its widths.
+fn port_link_cells(buffer: &Buffer, links: &[PortLink]) -> Vec<(u16, u16, ratatui::buffer::Cell)> {
+    let mut cells = Vec::new();
+    for link in links {
+        for x in link.area.left()..link.area.right() {
+            let mut cell = buffer.get(x, link.area.y).clone();
+            let mut symbol = cell.symbol().to_string();
+            if x == link.area.left() {
+                symbol = format!("\x1b]8;;{}\x1b\\{}", link.url, symbol);
+            }
+            if x + 1 == link.area.right() {
+                symbol.push_str("\x1b]8;;\x1b\\");
+            }
+            cell.set_symbol(&symbol);
+            cells.push((x, link.area.y, cell));
+        }
+    }
+    cells
+}
+
+/// Dim rows off the focused port, highlight processes marked for a bulk kill
+/// or matching the highlight pattern, dim suspended ones, and color the rest
+/// by age if enabled. The selection style is applied on top, so it still
+/// stands out.
+fn row_style(app: &App, process: &PortProcess, now: SystemTime) -> Style {
+    if app.is_dimmed(process) {
+        match app.config.focus_dim_style {
+            DimStyle::Gray => Style::default().fg(Color::DarkGray),
+            DimStyle::Faint => row_style_undimmed(app, process, now).add_modifier(Modifier::DIM),
+        }
+    } else {
+        row_style_undimmed(app, process, now)
+    }
+}
+
+/// Style of a row when no other port is focused
+fn row_style_undimmed(app: &App, process: &PortProcess, now: SystemTime) -> Style {
+    if app.marked_pids.contains(&process.pid) {
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+    } else if app.is_highlighted(process) {
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+    } else if app.cpu_spikes.contains(&process.pid) {
+        Style::default().fg(app.color(Color::Red)).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+    } else if process.stopped {
+        Style::default().fg(Color::Magenta).add_modifier(Modifier::ITALIC)
+    } else if let Some(color) = app.age_color(process, now) {
+        Style::default().fg(color)
+    } else {
+        Style::default()
+    }
+}
+
+/// Terminals narrower than this show processes as cards in the auto layout
+const NARROW_LAYOUT_WIDTH: u16 = 80;
+
+/// Each process as a small card for narrow terminals: the name, then the
+/// port, PID and usage, then the command
+fn process_cards(app: &App, processes: &[PortProcess], now: SystemTime) -> Vec<ListItem<'static>> {
+    let label = Style::default().fg(Color::Gray);
+    processes.iter()
+        .map(|process| {
+            let indicators = row_indicators(process, app.is_unexpected(process), app.config.use_glyphs) + &app.age_marker(process, now).unwrap_or_default();
+            let mut name = vec![Span::styled(process.name.clone(), Style::default().bold())];
+            if process.exited {
+                name.push(Span::raw(" (exited)"));
+            }
+            if !indicators.is_empty() {
+                name.push(Span::styled(format!(" {}", indicators), label));
+            }
+            
+            let mut usage = format!(
+                "  :{}  PID {}  {:.1}%  {}",
+                port_label(process),
+                process.pid,
+                process.cpu_usage,
+                format_bytes(process.memory, app.config.binary_units),
+            );
+            if let Some(remote) = &process.remote {
+                usage.push_str(&format!("  → {} {}", remote, process.state.label()));
+            }
+            
+            ListItem::new(vec![
+                Line::from(name),
+                Line::from(usage),
+                Line::styled(format!("  {}", scrolled_command(app.table_command(process), app.command_scroll)), label),
+            ])
+            .style(row_style(app, process, now))
+        })
+        .collect()
+}
+
+/// Rows of the process list to render when at most `max_rows` are: the page
+/// of that many rows holding the selection, so every row can still be reached
+fn row_window(len: usize, selected: Option<usize>, max_rows: Option<usize>) -> Range<usize> {
+    match max_rows {
+        Some(max_rows) if max_rows > 0 && len > max_rows => {
+            let start = selected.unwrap_or(0) / max_rows * max_rows;
+            start..len.min(start + max_rows)
+        }
+        _ => 0..len,
+    }
+}
+
+/// Thread count as shown in the table, or "-" where it can't be read
+fn thread_label(process: &PortProcess) -> String {
+    process.threads.map_or_else(|| "-".to_string(), |threads| threads.to_string())
+}
+
+/// Columns of the process table, leaving out the remote side of connections
+/// when listing listeners unless nothing else is configured
+fn table_columns(app: &App) -> Vec<TableColumn> {
+    let columns = app.config.table_columns();
+    if app.discovery_mode == DiscoveryMode::Connections {
+        return columns;
+    }
+    let listener_columns: Vec<TableColumn> = columns.iter()
+        .copied()
+        .filter(|column| !matches!(column, TableColumn::Remote | TableColumn::State))
+        .collect();
+    if listener_columns.is_empty() { columns } else { listener_columns }
+}
+
+/// Port as shown in the table, flagging merged dual-stack listeners
+fn port_label(process: &PortProcess) -> String {
+    if process.dual_stack {
+        format!("{} v4/v6", process.port)
+    } else {
+        process.port_text()
+    }
+}
+
+/// Markers for a row's characteristics: a privileged port, a socket reachable
+/// from other machines, and a process that is suspended or has exited. Glyphs
+/// are two cells wide in most terminals, ASCII letters one.
+fn row_indicators(process: &PortProcess, unexpected: bool, use_glyphs: bool) -> String {
+    let markers = [
+        (process.is_privileged_port(), "🔒", "P"),
+        (!process.is_loopback(), "🌐", "E"),
+        (process.stopped, "💤", "S"),
+        (process.exited, "💀", "X"),
+        (process.is_container(), "📦", "C"),
+        (unexpected, "🆕", "N"),
+    ];
+    markers
+        .iter()
+        .filter(|(applies, _, _)| *applies)
+        .map(|(_, glyph, ascii)| if use_glyphs { *glyph } else { *ascii })
+        .collect()
+}
+
+/// The process table as plain text: a header naming when and how it was
+/// taken, then the same columns as the TUI table, aligned with spaces.
+/// Indicators use the ASCII letters so every row lines up.
+fn view_snapshot(app: &App, generated_at: &str) -> String {
+    let show_remote = app.discovery_mode == DiscoveryMode::Connections;
+    let columns = table_columns(app);
+    let headers: Vec<&str> = columns.iter().map(TableColumn::title).collect();
+    
+    let rows: Vec<Vec<String>> = app.port_processes.iter()
+        .map(|process| {
+            columns.iter()
+                .map(|column| match column {
+                    TableColumn::Indicators => row_indicators(process, app.is_unexpected(process), false),
+                    TableColumn::Pid => process.pid.to_string(),
+                    TableColumn::Port => port_label(process),
+                    TableColumn::Remote => process.remote.clone().unwrap_or_default(),
+                    TableColumn::State => process.state.label().to_string(),
+                    TableColumn::Name if process.exited => format!("{} (exited)", process.name),
+                    TableColumn::Name => process.name.clone(),
+                    TableColumn::Cpu => format!("{:.1}", process.cpu_usage),
+                    TableColumn::Memory => format_bytes(process.memory, app.config.binary_units),
+                    TableColumn::Threads => thread_label(process),
+                    TableColumn::Command => app.table_command(process).to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    
+    let mut out = format!(
+        "port-manager {} - {} process(es) via {} at {}\n\n",
+        if show_remote { "connections" } else { "listeners" },
+        rows.len(),
+        app.backend.name(),
+        generated_at,
+    );
+    out.push_str(&text_table(&headers, &rows));
+    out
+}
+
+/// A plain text bug report: versions and platform, the active config, the
+/// current view and the backend's raw output. With commands hidden, commands
+/// in the config are masked too, since they may hold tokens.
+fn diagnostic_bundle(app: &App, generated_at: &str) -> String {
+    let mut config = serde_json::to_value(&app.config).unwrap_or_default();
+    if app.hide_commands {
+        mask_commands(&mut config);
+    }
+    let config = serde_json::to_string_pretty(&config).unwrap_or_default();
+    let raw_output = app.backend.raw_output(app.discovery_mode)
+        .unwrap_or_else(|e| format!("(failed: {})", e));
+    
+    format!(
+        "== port-manager diagnostics ==\n\
+         Version:   {}\n\
+         Platform:  {} {}\n\
+         Generated: {}\n\
+         Backend:   {}\n\
+         Config:    {} (profile {})\n\
+         \n== Config ==\n{}\n\
+         \n== View ==\n{}\
+         \n== Raw {} output ==\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        generated_at,
+        app.backend.name(),
+        app.config.config_path().display(),
+        app.config.profile(),
+        config,
+        view_snapshot(app, generated_at),
+        app.backend.name(),
+        raw_output.trim_end(),
+    )
+}
+
+/// Replace every `command` string in a config, e.g. in actions and enrichment
+fn mask_commands(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if key == "command" && field.is_string() {
+                    *field = serde_json::Value::from(HIDDEN_COMMAND);
+                } else {
+                    mask_commands(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_commands),
+        _ => {}
+    }
+}
+
+/// Align rows under headers, padding every column but the last to its widest
+/// value with two spaces between columns
+fn text_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain(std::iter::once(headers[column].chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    
+    let line = |cells: Vec<&str>| -> String {
+        let padded: Vec<String> = cells.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    
+    let rules: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    let mut out = line(headers.to_vec());
+    out.push_str(&line(rules.iter().map(String::as_str).collect()));
+    for row in rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+/// Width needed to show a column header and all of its values, plus padding
+fn column_width(header: &str, values: impl Iterator<Item = String>) -> u16 {
+    let widest = values
+        .map(|value| value.chars().count())
+        .chain(std::iter::once(header.chars().count()))
+        .max()
+        .unwrap_or(0);
+    (widest as u16 + 2).min(MAX_AUTO_COLUMN_WIDTH)
+}
+
+/// Render the filter management view
+fn render_filter_view(frame: &mut Frame, app: &App) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(view_block_height(app, 1, true)),   // Title
+            Constraint::Min(0),                                    // Filter list
+            Constraint::Length(1),                                 // Status footer
+            Constraint::Length(view_block_height(app, 1, false)),  // Help
+        ])
+        .split(frame.size());
+    
+    // Title block
+    let title_block = app.view_block()
+        .title("Process Filters")
+        .title_alignment(Alignment::Center);
+    
+    // Show where filters are saved, since users often need to find the file
+    let config_line = format!(
+        "Config: {} ({})",
+        app.config.config_path().display(),
+        app.config.source().label()
+    );
+    let title_text = Paragraph::new(config_line)
+        .block(title_block)
+        .alignment(Alignment::Center);
+    
+    frame.render_widget(title_text, layout[0]);
+    
+    let footer = Paragraph::new(status_footer(app))
+        .style(status_footer_style(app))
+        .alignment(Alignment::Center);
+    frame.render_widget(footer, layout[2]);
+    
+    // Filter list, numbered by stored position even when shown alphabetically
+    let mut filters = app.filter_display_order().into_iter()
+        .map(|i| {
+            let filter = &app.config.filtered_process_names[i];
+            let mode = app.config.match_mode;
+            let (mut text, mut style) = if filter.matcher.is_some() {
+                (format!("{}. {} ({})", i + 1, filter.pattern, mode.label()), Style::default())
+            } else {
+                (format!("{}. {} (invalid {})", i + 1, filter.pattern, mode.label()), Style::default().fg(app.color(Color::Red)))
+            };
+            if filter.is_quick_toggle() {
+                text.push_str("  [quick]");
+            }
+            // Filters turned off or snoozed are dimmed, and snoozes say when they come back on
+            if !filter.enabled {
+                text.push_str("  [off]");
+                style = Style::default().fg(Color::DarkGray);
+            } else if let Some(until) = app.snoozed_filters.get(&filter.pattern) {
+                match until {
+                    Some(until) => {
+                        let minutes = until.saturating_duration_since(Instant::now()).as_secs().div_ceil(60);
+                        text.push_str(&format!("  [snoozed, {}m left]", minutes));
+                    }
+                    None => text.push_str("  [snoozed until restart]"),
+                }
+                style = Style::default().fg(Color::DarkGray);
+            }
+            let mut spans = vec![Span::styled(text, style)];
+            if let Some(note) = &filter.note {
+                spans.push(Span::styled(format!("  # {}", note), Style::default().fg(Color::Gray)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect::<Vec<_>>();
+    
+    // Rules aren't listed, but a broken command regex would silently never match
+    for pattern in app.config.filter_rules.iter().flat_map(FilterRule::invalid_patterns) {
+        filters.push(ListItem::new(Line::styled(
+            format!("rule: command {} (invalid regex, never matches)", pattern),
+            Style::default().fg(app.color(Color::Red)),
+        )));
+    }
+    
+    let filter_list = List::new(filters)
+        .block(
+            app.view_block()
+                .title(match (app.filters_sorted, app.config.filter_rules.len()) {
+                    (true, 0) => "Filtered Process Names (A-Z)".to_string(),
+                    (false, 0) => "Filtered Process Names".to_string(),
+                    // Rules are only edited in the config file
+                    (true, rules) => format!("Filtered Process Names (A-Z), plus {} rule(s) from the config", rules),
+                    (false, rules) => format!("Filtered Process Names, plus {} rule(s) from the config", rules),
+                })
+        )
+        .highlight_style(selection_style(app.config.highlight_style))
+        .highlight_symbol(">> ");
+    
+    let mut filter_state = ListState::default();
+    filter_state.select(app.filter_selected_idx);
+    
+    frame.render_stateful_widget(filter_list, layout[1], &mut filter_state);
+    
+    // Help text
+    let help_text = "↑/↓: Navigate | Enter/Delete: Remove filter | a: Add new filter | m: Match mode | I: Clear ignored PIDs | K/J: Move up/down | s: Sort A-Z | Space: On/off | T: Quick toggle | z: Snooze/wake | E/L: Export/load filters file | u/U: Undo/redo | c: Raw config | F: Return to processes | q: Quit";
+    let help = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::Gray)),
+    };
+    let help = help
+        .block(app.view_block())
+        .alignment(Alignment::Center);
+    
+    frame.render_widget(help, layout[3]);
+}
+
+/// Render a popup for adding a new filter
+fn render_add_filter_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(60, 20, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Title
+            Constraint::Length(3),  // Input
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    // Title
+    let title = Paragraph::new(format!("Add Process Filter ({})", app.config.match_mode.label()))
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(title, popup_layout[0]);
+    
+    // Input
+    let block = bordered(app.config.border_style)
+        .title(match app.filter_input_matches() {
+            Some(count) => format!("Process Name (hides {} of {} shown)", count, app.port_processes.len()),
+            None => "Process Name".to_string(),
+        });
+    let (x, y) = input_cursor(&block, popup_layout[1], app.add_filter_input.len());
+    let input = Paragraph::new(app.add_filter_input.as_str())
+        .style(Style::default())
+        .block(block);
+    
+    frame.render_widget(input, popup_layout[1]);
+    
+    // Place cursor at the end of input
+    frame.set_cursor(x, y);
+    
+    // Help, replaced by the validation error when the input was rejected
+    let help = match &app.add_filter_error {
+        Some(error) => Paragraph::new(error.as_str()).style(Style::default().fg(app.color(Color::Red))),
+        None => Paragraph::new("Enter: Save | Esc: Cancel").style(Style::default().fg(Color::Gray)),
+    };
+    let help = help
+        .wrap(Wrap { trim: true })
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[2]);
+}
+
+/// Render a popup asking for the name to save the recorded cleanup script under
+fn render_cleanup_name_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(60, 20, frame.size());
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Title
+            Constraint::Length(3),  // Input
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    let steps = app.recorded_cleanup.as_ref().map_or(0, Vec::len);
+    let title = Paragraph::new(format!("Save Cleanup Script ({} step(s) recorded)", steps))
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Center)
+        .block(bordered(app.config.border_style));
+    frame.render_widget(title, popup_layout[0]);
+    
+    let block = bordered(app.config.border_style)
+        .title(match app.config.cleanup_script(app.cleanup_name_input.trim()) {
+            Some(_) => "Name (replaces the saved script)",
+            None => "Name",
+        });
+    let (x, y) = input_cursor(&block, popup_layout[1], app.cleanup_name_input.len());
+    let input = Paragraph::new(app.cleanup_name_input.as_str()).block(block);
+    frame.render_widget(input, popup_layout[1]);
+    frame.set_cursor(x, y);
+    
+    let help = Paragraph::new("Enter: Save | Esc: Discard recording")
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(bordered(app.config.border_style));
+    frame.render_widget(help, popup_layout[2]);
+}
+
+/// Render a popup asking for confirmation before killing the selected process
+/// How risky a kill looks, from least to most
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum KillRisk {
+    /// An ordinary user process
+    Normal,
+    /// A process on a port below 1024, usually a service
+    PrivilegedPort,
+    /// A system process, or one port-manager refuses to signal
+    System,
+}
+
+impl KillRisk {
+    /// Judge a process by its protection, owner and port
+    fn of(process: &PortProcess) -> Self {
+        if protected_reason(process.pid).is_some() || process.is_system() {
+            KillRisk::System
+        } else if process.is_privileged_port() {
+            KillRisk::PrivilegedPort
+        } else {
+            KillRisk::Normal
+        }
+    }
+    
+    /// Line explaining the risk, so it doesn't rely on color alone
+    fn warning(&self) -> &'static str {
+        match self {
+            KillRisk::Normal => "Risk: low (user process)",
+            KillRisk::PrivilegedPort => "Risk: medium (privileged port, likely a service)",
+            KillRisk::System => "Risk: high (system or protected process)",
+        }
+    }
+    
+    /// Style of the warning and popup border: green, yellow or red, with
+    /// bold standing in for color under NO_COLOR
+    fn style(&self, app: &App) -> Style {
+        if no_color() {
+            return match self {
+                KillRisk::Normal => Style::default(),
+                _ => Style::default().add_modifier(Modifier::BOLD),
+            };
+        }
+        let color = match self {
+            KillRisk::Normal => Color::Green,
+            KillRisk::PrivilegedPort => Color::Yellow,
+            KillRisk::System => Color::Red,
+        };
+        Style::default().fg(app.color(color))
+    }
+}
+
+/// One-line kill confirmation for the footer, e.g.
+/// `Kill node (PID 1234, :3000)? y/n`, colored by risk like the popup border
+fn inline_kill_prompt(app: &App) -> Paragraph<'static> {
+    let targets: Vec<&PortProcess> = app.kill_targets.iter()
+        .filter_map(|pid| app.port_processes.iter().find(|p| p.pid == *pid))
+        .collect();
+    let risk = targets.iter().copied().map(KillRisk::of).max().unwrap_or(KillRisk::Normal);
+    let verb = if app.kill_and_filter { "Kill and filter" } else { "Kill" };
+    
+    let mut prompt = match &targets[..] {
+        [process] => format!("{} {} (PID {}, :{})? y/n", verb, process.name, process.pid, process.port_text()),
+        _ => format!(
+            "{} {} processes ({})? y/n",
+            verb,
+            targets.len(),
+            targets.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", "),
+        ),
+    };
+    if let Some(restart) = &app.kill_restart {
+        prompt.push_str(&format!(" | r: Restart {} instead", restart.service));
+    }
+    Paragraph::new(prompt).style(risk.style(app).add_modifier(Modifier::BOLD))
+}
+
+fn render_kill_confirm_popup(frame: &mut Frame, app: &App) {
+    let targets: Vec<&PortProcess> = app.kill_targets.iter()
+        .filter_map(|pid| app.port_processes.iter().find(|p| p.pid == *pid))
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+    
+    let typed = app.needs_typed_confirmation();
+    let popup_area = centered_rect(60, if targets.len() > 1 { 60 } else { 40 }, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),                                // Process details
+            Constraint::Length(if typed { 3 } else { 0 }),     // Typed confirmation
+            Constraint::Length(3),                             // Help
+        ])
+        .split(popup_area);
+    
+    let risk = targets.iter().copied().map(KillRisk::of).max().unwrap_or(KillRisk::Normal);
+    let risk_style = risk.style(app);
+    
+    let label = Style::default().fg(Color::Gray);
+    let (title, details) = if let ([process], false) = (&targets[..], app.clean_slate) {
+        // Show enough detail to tell apart processes with generic names like `node`
+        let mut details = vec![
+            Line::styled(risk.warning(), risk_style),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Process: ", label),
+                Span::styled(process.name.clone(), Style::default().bold()),
+            ]),
+            Line::from(vec![
+                Span::styled("Port:    ", label),
+                Span::raw(process.port_text()),
+            ]),
+            Line::from(vec![
+                Span::styled("PID:     ", label),
+                Span::raw(process.pid.to_string()),
+            ]),
+            Line::from(""),
+            Line::from(Span::styled("Command:", label)),
+            Line::from(process.display_command(app.hide_commands).to_string()),
+        ];
+        if let Some(restart) = &app.kill_restart {
+            details.extend([
+                Line::from(""),
+                Line::styled(
+                    format!("This runs under {}, which may start it again after a kill. Press r to restart it instead with:", restart.service),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Line::from(restart.command_line()),
+            ]);
+        }
+        if app.kill_and_filter {
+            details.extend([
+                Line::from(""),
+                Line::from(format!(
+                    "\"{}\" will also be added to the filters, hiding it and anything else matching it if it restarts",
+                    process.name,
+                )),
+            ]);
+            ("Kill and Filter Process?".to_string(), details)
+        } else {
+            ("Kill Process?".to_string(), details)
+        }
+    } else {
+        // List every affected process so nothing is killed by surprise, with
+        // the riskiest kill summed up first
+        let mut details = vec![Line::styled(risk.warning(), risk_style), Line::from("")];
+        details.extend(targets.iter().map(|process| {
+            let process_risk = KillRisk::of(process);
+            let name_style = if process_risk == KillRisk::Normal {
+                Style::default().bold()
+            } else {
+                process_risk.style(app).add_modifier(Modifier::BOLD)
+            };
+            Line::from(vec![
+                Span::styled(format!("{:>7} ", process.pid), label),
+                Span::styled(format!("{} ", process.name), name_style),
+                Span::raw(format!(":{} ", process.port_text())),
+                Span::styled(process.display_command(app.hide_commands).to_string(), label),
+            ])
+        }));
+        if app.clean_slate {
+            // Show what survives too, so a missing keep list entry is caught
+            let kept: Vec<&PortProcess> = app.kill_kept.iter()
+                .filter_map(|pid| app.port_processes.iter().find(|p| p.pid == *pid))
+                .collect();
+            details.extend([Line::from(""), Line::from(Span::styled(format!("Kept ({}):", kept.len()), label))]);
+            details.extend(kept.iter().map(|process| {
+                let reason = if KillRisk::of(process) == KillRisk::System {
+                    "protected"
+                } else if app.kill_policy(process.pid) == Some(KillPolicy::Protected) {
+                    "protected port"
+                } else {
+                    "keep list"
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:>7} ", process.pid), label),
+                    Span::raw(format!("{} :{} ", process.name, process.port_text())),
+                    Span::styled(format!("({})", reason), label),
+                ])
+            }));
+            (format!("Clean Slate: Kill {} Process(es)?", targets.len()), details)
+        } else {
+            (format!("Kill {} Processes?", targets.len()), details)
+        }
+    };
+    
+    // Wrap the command within the popup width
+    let body = Paragraph::new(details)
+        .wrap(Wrap { trim: false })
+        .scroll((app.kill_confirm_scroll, 0))
+        .block(
+            bordered(app.config.border_style)
+                .border_style(risk_style)
+                .title(title)
+        );
+    
+    frame.render_widget(body, popup_layout[0]);
+    
+    if typed {
+        let block = bordered(app.config.border_style)
+            .title(if app.clean_slate {
+                format!("Type \"{}\" to confirm", CLEAN_SLATE_CONFIRMATION)
+            } else {
+                format!("Type \"yes\" or {} to confirm", targets.len())
+            });
+        let (x, y) = input_cursor(&block, popup_layout[1], app.kill_confirm_input.len());
+        let input = Paragraph::new(app.kill_confirm_input.as_str()).block(block);
+        
+        frame.render_widget(input, popup_layout[1]);
+        
+        // Place cursor at the end of input
+        frame.set_cursor(x, y);
+    }
+    
+    // Help
+    let help = Paragraph::new(kill_confirm_help(app))
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[2]);
+}
+
+/// Render the outcome of each kill in the last bulk kill
+fn render_kill_results_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(60, 60, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Outcomes
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    let label = Style::default().fg(Color::Gray);
+    let outcomes = app.kill_results.iter()
+        .map(|outcome| {
+            let (status, style) = match &outcome.error {
+                None if outcome.ports.len() > 1 => {
+                    let ports: Vec<String> = outcome.ports.iter().map(u16::to_string).collect();
+                    (format!("killed, freeing ports {}", ports.join(", ")), Style::default().fg(app.color(Color::Green)))
+                }
+                None => ("killed".to_string(), Style::default().fg(app.color(Color::Green))),
+                Some(error) => (format!("failed: {}", error), Style::default().fg(app.color(Color::Red))),
+            };
+            Line::from(vec![
+                Span::styled(format!("{:>7} ", outcome.pid), label),
+                Span::styled(format!("{} ", outcome.name), Style::default().bold()),
+                Span::styled(status, style),
+            ])
+        })
+        .collect::<Vec<_>>();
+    
+    let failed = app.kill_results.iter().filter(|outcome| outcome.error.is_some()).count();
+    let title = format!("Killed {} of {} ({} failed)", app.kill_results.len() - failed, app.kill_results.len(), failed);
+    
+    let body = Paragraph::new(outcomes)
+        .wrap(Wrap { trim: false })
+        .scroll((app.kill_results_scroll, 0))
+        .block(
+            bordered(app.config.border_style)
+                .title(title)
+        );
+    
+    frame.render_widget(body, popup_layout[0]);
+    
+    // Help
+    let help = Paragraph::new("↑/↓: Scroll | Any other key: Close")
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[1]);
+}
+
+/// Every socket the selected process holds, before dual-stack merging, e.g.
+/// "2×TCP, 1×UDP, v4+v6: *:8080 TCP v4, *:8080 TCP v6, 127.0.0.1:5353 UDP v4"
+fn socket_breakdown(app: &App, process: &PortProcess) -> String {
+    let mut sockets: Vec<&PortProcess> = app.discovered.iter()
+        .filter(|socket| socket.pid == process.pid && (!process.orphaned || socket.port == process.port))
+        .collect();
+    if sockets.is_empty() {
+        sockets.push(process);
+    }
+    let list: Vec<String> = sockets.iter()
+        .map(|socket| {
+            let family = match (socket.dual_stack, socket.family) {
+                (true, _) => "v4+v6",
+                (false, AddressFamily::Ipv4) => "v4",
+                (false, AddressFamily::Ipv6) => "v6",
+            };
+            format!("{}:{} {} {}", socket.local_addr, socket.port_text(), socket.protocol.label(), family)
+        })
+        .collect();
+    format!("{}: {}", socket_summary(sockets.iter().copied()), list.join(", "))
+}
+
+/// Render a popup with details of the selected process
+fn render_details_popup(frame: &mut Frame, app: &App) {
+    let Some(process) = app.selected_process() else {
+        return;
+    };
+    
+    let popup_area = centered_rect(70, 60, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Process details
+            Constraint::Length(5),  // Connection sparkline
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    let label = Style::default().fg(Color::Gray);
+    let mut details = vec![
+        Line::from(vec![
+            Span::styled("Process: ", label),
+            Span::styled(process.name.clone(), Style::default().bold()),
+        ]),
+        Line::from(vec![
+            Span::styled("PID:     ", label),
+            Span::raw(if process.orphaned { "none".to_string() } else { process.pid.to_string() }),
+        ]),
+        Line::from(vec![
+            Span::styled("Port:    ", label),
+            Span::raw(match &process.unresolved_port {
+                Some(token) => format!("? (lsof printed {:?}, which isn't a known service)", token),
+                None => match app.services.name(process.port) {
+                    Some(service) => format!("{} ({})", process.port, service),
+                    None => process.port.to_string(),
+                },
+            }),
+        ]),
+    ];
+    if process.orphaned {
+        details.push(Line::styled(
+            "No process owns this socket, so a kill can't free it. It may be held by the kernel after its process died; check `ss -tlnpe` or reboot.",
+            Style::default().fg(app.color(Color::Red)),
+        ));
+    }
+    if let Some(firewall) = &app.firewall {
+        let color = match firewall {
+            FirewallStatus::Open => app.color(Color::Green),
+            FirewallStatus::Firewalled => app.color(Color::Red),
+            FirewallStatus::Unknown(_) => Color::DarkGray,
+        };
+        details.push(Line::from(vec![
+            Span::styled("Firewall:", label),
+            Span::styled(format!(" {}", firewall.label()), Style::default().fg(color)),
+        ]));
+    }
+    details.extend([
+        Line::from(vec![
+            Span::styled("User:    ", label),
+            Span::raw(process.user.clone()),
+        ]),
+        Line::from(vec![
+            Span::styled("Address: ", label),
+            Span::raw(process.local_addr.clone()),
+        ]),
+    ]);
+    if let Some(ip) = process.external_bind_ip().filter(|_| app.config.reverse_dns) {
+        let host = if app.hostname_lookups.contains_key(&ip) {
+            Span::styled("looking up…", Style::default().fg(Color::DarkGray))
+        } else {
+            match app.hostnames.get(&ip) {
+                Some(Some(hostname)) => Span::raw(hostname.clone()),
+                _ => Span::styled(format!("{} (no hostname)", ip), Style::default().fg(Color::DarkGray)),
+            }
+        };
+        details.push(Line::from(vec![Span::styled("Host:    ", label), host]));
+    }
+    if app.config.systemd_units && !process.orphaned {
+        let unit = match &process.systemd_unit {
+            Some(unit) => Span::raw(format!("{} (restart with systemctl restart {})", unit, unit)),
+            None => Span::styled("none (not under systemd)", Style::default().fg(Color::DarkGray)),
+        };
+        details.push(Line::from(vec![Span::styled("Unit:    ", label), unit]));
+    }
+    if let Some(id) = &process.container_id {
+        details.push(Line::from(vec![Span::styled("Container:", label), Span::raw(format!(" {}", id))]));
+    }
+    if let Some(kubernetes) = &app.kubernetes {
+        let style = match kubernetes {
+            KubernetesInfo::NotFound | KubernetesInfo::Unknown(_) => Style::default().fg(Color::DarkGray),
+            _ => Style::default(),
+        };
+        details.push(Line::from(vec![
+            Span::styled("K8s:     ", label),
+            Span::styled(kubernetes.label(), style),
+        ]));
+    }
+    details.extend([
+        Line::from(vec![
+            Span::styled("Family:  ", label),
+            Span::raw(match (process.dual_stack, process.family) {
+                (true, _) => "IPv4 and IPv6 (dual-stack)",
+                (false, AddressFamily::Ipv4) => "IPv4",
+                (false, AddressFamily::Ipv6) => "IPv6",
+            }),
+        ]),
+        Line::from(vec![
+            Span::styled("Sockets: ", label),
+            Span::raw(socket_breakdown(app, process)),
+        ]),
+        Line::from(vec![
+            Span::styled("Net ns:  ", label),
+            Span::raw(process.net_namespace.map_or_else(|| "unknown".to_string(), |inode| inode.to_string())),
+        ]),
+        Line::from(vec![
+            Span::styled("FD:      ", label),
+            Span::raw(process.fd.clone().unwrap_or_else(|| "unknown".to_string())),
+        ]),
+        Line::from(vec![
+            Span::styled("Inode:   ", label),
+            Span::raw(process.socket_inode.map_or_else(|| "unknown".to_string(), |inode| inode.to_string())),
+        ]),
+        Line::from(vec![
+            Span::styled("Queues:  ", label),
+            match (process.recv_queue, process.send_queue) {
+                // A listener with a backlog isn't accepting connections fast enough
+                (Some(recv), Some(send)) => Span::styled(
+                    format!("Recv-Q {}, Send-Q {}", recv, send),
+                    if recv > 0 { Style::default().fg(app.color(Color::Yellow)) } else { Style::default() },
+                ),
+                _ => Span::raw("-"),
+            },
+        ]),
+    ]);
+    if let Some(queue) = &app.accept_queue {
+        details.push(Line::from(vec![
+            Span::styled("Backlog: ", label),
+            match queue {
+                Ok(queue) => Span::styled(
+                    format!("{} of {} waiting to be accepted", queue.pending, queue.backlog),
+                    if queue.nearly_full() {
+                        Style::default().fg(app.color(Color::Red))
+                    } else if queue.pending > 0 {
+                        Style::default().fg(app.color(Color::Yellow))
+                    } else {
+                        Style::default()
+                    },
+                ),
+                Err(error) => Span::styled(format!("unknown ({})", error), Style::default().fg(Color::DarkGray)),
+            },
+        ]));
+    }
+    details.extend([
+        Line::from(vec![
+            Span::styled("State:   ", label),
+            Span::raw(if process.stopped { "suspended" } else { "running" }),
+        ]),
+        Line::from(vec![
+            Span::styled("Started: ", label),
+            Span::raw(process.started_label(SystemTime::now(), app.absolute_times)
+                .unwrap_or_else(|| "unknown".to_string())),
+        ]),
+        Line::from(vec![
+            Span::styled("Seen:    ", label),
+            Span::raw(match app.port_first_seen.get(&process.port) {
+                Some(seen) => format!(
+                    "port first seen {} ago this session",
+                    humantime::format_duration(Duration::from_secs(seen.elapsed().as_secs())),
+                ),
+                None => "unknown".to_string(),
+            }),
+        ]),
+        Line::from(vec![
+            Span::styled("CPU:     ", label),
+            Span::raw(format!("{:.1}%", process.cpu_usage)),
+        ]),
+        Line::from(vec![
+            Span::styled("Memory:  ", label),
+            Span::raw(format_bytes(process.memory, app.config.binary_units)),
+        ]),
+        Line::from(vec![
+            Span::styled("Threads: ", label),
+            Span::raw(thread_label(process)),
+        ]),
+        Line::from(vec![
+            Span::styled("Open FDs:", label),
+            match &app.open_fds {
+                Some(Ok(count)) => Span::raw(format!(" {}", count)),
+                Some(Err(error)) => Span::styled(format!(" unknown ({})", error), Style::default().fg(Color::DarkGray)),
+                None => Span::styled(" unknown", Style::default().fg(Color::DarkGray)),
+            },
+        ]),
+    ]);
+    if let Some(enrichment) = app.config.enrichment.as_ref().filter(|_| app.enrichment_target.is_some()) {
+        let value = match &app.enrichment {
+            _ if app.enrichment_target != Some((process.pid, process.port)) => Span::raw("-"),
+            Some(Ok(output)) if output.is_empty() => Span::styled("(no output)", Style::default().fg(Color::DarkGray)),
+            Some(Ok(output)) => Span::raw(output.clone()),
+            Some(Err(error)) => Span::styled(format!("unavailable ({})", error), Style::default().fg(Color::DarkGray)),
+            None => Span::styled("running…", Style::default().fg(Color::DarkGray)),
+        };
+        details.push(Line::from(vec![Span::styled(format!("{}: ", enrichment.label), label), value]));
+    }
+    details.extend([
+        Line::from(""),
+        Line::from(Span::styled("Command:", label)),
+        Line::from(process.display_command(app.hide_commands).to_string()),
+    ]);
+    
+    // What discovery and sysinfo each reported, to explain odd names or filter matches
+    if app.show_detail_sources {
+        let sysinfo_command = if app.hide_commands { HIDDEN_COMMAND } else { process.sysinfo_command.as_str() };
+        details.extend([
+            Line::from(""),
+            Line::from(Span::styled("Sources:", label)),
+            Line::from(vec![
+                Span::styled(format!("{:<15}", format!("{} name:", app.backend.name())), label),
+                Span::raw(process.name.clone()),
+            ]),
+            Line::from(vec![
+                Span::styled("sysinfo name:  ", label),
+                Span::raw(process.sysinfo_name.clone()),
+            ]),
+            Line::from(vec![
+                Span::styled("sysinfo exe:   ", label),
+                Span::raw(process.sysinfo_exe.clone()),
+            ]),
+            Line::from(Span::styled("sysinfo command:", label)),
+            Line::from(sysinfo_command.to_string()),
+        ]);
+    }
+    
+    // Wrap the command within the popup width
+    let body = Paragraph::new(details)
+        .wrap(Wrap { trim: false })
+        .block(
+            bordered(app.config.border_style)
+                .title("Process Details")
+        );
+    
+    frame.render_widget(body, popup_layout[0]);
+    
+    // Connection count trend across refreshes
+    let history: Vec<u64> = app.connection_history
+        .get(&process.port)
+        .map(|counts| counts.iter().copied().collect())
+        .unwrap_or_default();
+    let current = history.last().copied().unwrap_or(0);
+    
+    let sparkline = Sparkline::default()
+        .data(&history)
+        .style(Style::default().fg(Color::Cyan))
+        .block(
+            bordered(app.config.border_style)
+                .title(format!("Connections ({} now)", current))
+        );
+    
+    frame.render_widget(sparkline, popup_layout[1]);
+    
+    // Help
+    let help = Paragraph::new(format!("r: Refresh | d: Sources | t: Timestamp/age | {}/Esc: Close", key_names(&app.config, KeyAction::Details)))
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[2]);
+}
+
+/// Render the profile switcher popup
+fn render_profile_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(40, 40, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Profile list
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    // Mark the active profile
+    let profiles = app.profile_names.iter()
+        .map(|name| {
+            if name == app.config.profile() {
+                ListItem::new(format!("{} (active)", name))
+            } else {
+                ListItem::new(name.clone())
+            }
+        })
+        .collect::<Vec<_>>();
+    
+    let profile_list = List::new(profiles)
+        .block(
+            bordered(app.config.border_style)
+                .title("Switch Profile")
+        )
+        .highlight_style(selection_style(app.config.highlight_style))
+        .highlight_symbol(">> ");
+    
+    let mut profile_state = ListState::default();
+    profile_state.select(Some(app.profile_selected_idx));
+    
+    frame.render_stateful_widget(profile_list, popup_layout[0], &mut profile_state);
+    
+    // Help
+    let help = Paragraph::new("↑/↓: Navigate | Enter: Switch | Esc: Cancel")
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[1]);
+}
+
+/// Render the actions menu for the selected process
+fn render_action_popup(frame: &mut Frame, app: &App) {
+    let Some(process) = app.selected_process() else {
+        return;
+    };
+    
+    let popup_area = centered_rect(60, 40, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Action list
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    // Show each command as it will run
+    let label = Style::default().fg(Color::Gray);
+    let actions = app.config.actions.iter()
+        .map(|action| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{} ", action.name), Style::default().bold()),
+                Span::styled(action.render(process.pid, process.port, &process.name), label),
+            ]))
+        })
+        .collect::<Vec<_>>();
+    
+    let action_list = List::new(actions)
+        .block(
+            bordered(app.config.border_style)
+                .title(format!("Run Action on {} (PID {})", process.name, process.pid))
+        )
+        .highlight_style(selection_style(app.config.highlight_style))
+        .highlight_symbol(">> ");
+    
+    let mut action_state = ListState::default();
+    action_state.select(Some(app.action_selected_idx));
+    
+    frame.render_stateful_widget(action_list, popup_layout[0], &mut action_state);
+    
+    // Help
+    let help = Paragraph::new("↑/↓: Navigate | Enter: Run | Esc: Cancel")
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[1]);
+}
+
+/// Render the raw config file, with any error from the last edit
+fn render_config_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(80, 80, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Config contents
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    let body = Paragraph::new(app.config_text.as_str())
+        .scroll((app.config_scroll, 0))
+        .block(
+            bordered(app.config.border_style)
+                .title(app.config.config_path().display().to_string())
+        );
+    
+    frame.render_widget(body, popup_layout[0]);
+    
+    // Help, replaced by the error when an edit could not be applied
+    let help = match &app.config_error {
+        Some(error) => Paragraph::new(error.as_str()).style(Style::default().fg(app.color(Color::Red))),
+        None => Paragraph::new("↑/↓: Scroll | e: Edit in $EDITOR | o: Open folder | c/Esc: Close").style(Style::default().fg(Color::Gray)),
+    };
+    let help = help
+        .wrap(Wrap { trim: true })
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[1]);
+}
+
+/// Number of names and ports listed in the kill stats
+const KILL_STATS_TOP: usize = 10;
+
+/// Render the most-killed processes and ports side by side
+fn render_setup_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(60, 60, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),     // Intro
+            Constraint::Length(3),  // Port range
+            Constraint::Length(3),  // System ports
+            Constraint::Length(3),  // Filters
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    let intro = Paragraph::new(format!(
+        "No config was found, so here are the main settings to start with. They are saved to {}.",
+        app.config.config_path().display()
+    ))
+        .wrap(Wrap { trim: true })
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+                .title("Welcome to Port Manager")
+        );
+    frame.render_widget(intro, popup_layout[0]);
+    
+    let form = &app.setup;
+    let field_block = |field: SetupField, title: &str| {
+        let block = bordered(app.config.border_style)
+            .title(title.to_string());
+        if form.field == field {
+            block.border_style(Style::default().fg(app.color(Color::Yellow)))
+        } else {
+            block
+        }
+    };
+    
+    let range = Paragraph::new(form.port_range.as_str())
+        .block(field_block(SetupField::PortRange, "Port range to show (start-end)"));
+    frame.render_widget(range, popup_layout[1]);
+    
+    let checkbox = if form.includes_system_ports() { "[x]" } else { "[ ]" };
+    let system = Paragraph::new(format!("{} Include system ports (below 1024)", checkbox))
+        .block(field_block(SetupField::SystemPorts, "System ports"));
+    frame.render_widget(system, popup_layout[2]);
+    
+    let filters = Paragraph::new(form.filters.as_str())
+        .block(field_block(SetupField::Filters, "Process names to hide (comma separated)"));
+    frame.render_widget(filters, popup_layout[3]);
+    
+    // Place the cursor at the end of the focused input
+    let input = match form.field {
+        SetupField::PortRange => Some((popup_layout[1], &form.port_range)),
+        SetupField::SystemPorts => None,
+        SetupField::Filters => Some((popup_layout[3], &form.filters)),
+    };
+    if let Some((area, text)) = input {
+        let (x, y) = input_cursor(&field_block(form.field, ""), area, text.len());
+        frame.set_cursor(x, y);
+    }
+    
+    // Help, replaced by the validation error when the answers were rejected
+    let help = match &form.error {
+        Some(error) => Paragraph::new(error.as_str()).style(Style::default().fg(app.color(Color::Red))),
+        None => Paragraph::new("Tab: Next | Space: Toggle | Enter: Save | Esc: Defaults")
+            .style(Style::default().fg(Color::Gray)),
+    };
+    let help = help
+        .wrap(Wrap { trim: true })
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    frame.render_widget(help, popup_layout[4]);
+}
+
+fn render_stats_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(60, 60, frame.size());
+    
+    // Clear the area
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Stats
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    let block = bordered(app.config.border_style)
+        .title("Kill Stats (local only, never sent anywhere)");
+    
+    if app.kill_stats.is_empty() {
+        let empty = Paragraph::new("No kills counted yet")
+            .alignment(Alignment::Center)
+            .block(block);
+        frame.render_widget(empty, popup_layout[0]);
+    } else {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(block.inner(popup_layout[0]));
+        frame.render_widget(block, popup_layout[0]);
+        
+        let names = app.kill_stats.top_names(KILL_STATS_TOP).into_iter()
+            .map(|(name, kills)| Row::new(vec![name.to_string(), kills.to_string()]));
+        let names = Table::new(names, [Constraint::Percentage(75), Constraint::Percentage(25)])
+            .header(Row::new(vec!["Most-killed processes", "Kills"]).style(Style::default().bold()));
+        frame.render_widget(names, columns[0]);
+        
+        let ports = app.kill_stats.top_ports(KILL_STATS_TOP).into_iter()
+            .map(|(port, kills)| Row::new(vec![port.to_string(), kills.to_string()]));
+        let ports = Table::new(ports, [Constraint::Percentage(75), Constraint::Percentage(25)])
+            .header(Row::new(vec!["Most-contested ports", "Kills"]).style(Style::default().bold()));
+        frame.render_widget(ports, columns[1]);
+    }
+    
+    // Help
+    let help = Paragraph::new("c: Clear stats | N/Esc: Close")
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[1]);
+}
+
+/// Render every process list key in as many columns as it takes to fit
+fn render_help_popup(frame: &mut Frame, app: &App) {
+    let popup_area = centered_rect(80, 80, frame.size());
+    frame.render_widget(Clear, popup_area);
+    
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),     // Keys
+            Constraint::Length(3),  // Help
+        ])
+        .split(popup_area);
+    
+    let block = bordered(app.config.border_style).title("Keys");
+    let inner = block.inner(popup_layout[0]);
+    frame.render_widget(block, popup_layout[0]);
+    
+    let keys = process_view_keys(app);
+    let rows = usize::from(inner.height.max(1));
+    let column_count = keys.len().div_ceil(rows);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, column_count as u32); column_count])
+        .split(inner);
+    for (keys, area) in keys.chunks(rows).zip(columns.iter()) {
+        let lines: Vec<Line> = keys.iter().map(|key| Line::from(key.as_str())).collect();
+        frame.render_widget(Paragraph::new(lines), *area);
+    }
+    
+    // Help
+    let help = Paragraph::new("?/Esc: Close")
+        .style(Style::default().fg(Color::Gray))
+        .alignment(Alignment::Center)
+        .block(
+            bordered(app.config.border_style)
+        );
+    
+    frame.render_widget(help, popup_layout[1]);
+}
+
+/// Helper function to create a centered rect using up certain percentage of the available rect
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    // Initial refresh
+    app.refresh_processes()?;
+    
+    if app.config.restore_session {
+        match Session::load(app.config.config_path()) {
+            Ok(Some(session)) => app.restore_session(session),
+            Ok(None) => {}
+            Err(e) => app.status_message = Some(format!("Ignoring the saved session: {}", e)),
+        }
+    }
+    
+    // A config that was just created means this is the first launch
+    if app.config.source() == ConfigSource::Defaulted && !app.backend.is_demo() {
+        app.open_setup_popup();
+    }
+    
+    let poll_timeout = Duration::from_millis(app.config.poll_timeout_ms);
+    let redraw_interval = Duration::from_millis(app.config.redraw_interval_ms);
+    let mut last_draw: Option<Instant> = None;
+    
+    loop {
+        // A termination signal exits through the normal teardown path
+        if shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+        
+        // Only redraw when something changed or the idle redraw interval has elapsed
+        let redraw_due = last_draw.is_none_or(|drawn| drawn.elapsed() >= redraw_interval);
+        if app.needs_redraw || redraw_due {
+            let mut links = Vec::new();
+            let frame = terminal.draw(|frame| links = ui(frame, app))?;
+            let link_cells = port_link_cells(frame.buffer, &links);
+            if !link_cells.is_empty() {
+                let backend = terminal.backend_mut();
+                ratatui::backend::Backend::draw(backend, link_cells.iter().map(|(x, y, cell)| (*x, *y, cell)))?;
+                ratatui::backend::Backend::flush(backend)?;
+            }
+            last_draw = Some(Instant::now());
+            app.needs_redraw = false;
+        }
+        
+        if app.collect_hostnames() {
+            app.needs_redraw = true;
+        }
+        if app.collect_enrichment() {
+            app.needs_redraw = true;
+        }
+        
+        // Auto-refresh waits while a popup is open and catches up once it's
+        // closed, so a popup never acts on a row that has just gone away
+        if app.auto_refresh_due() {
+            app.refresh_processes()?;
+            app.needs_redraw = true;
+        }
+        
+        if event::poll(poll_timeout)? {
+            let event = event::read()?;
+            if let Event::Resize(_, _) = event {
+                app.needs_redraw = true;
+            }
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    app.needs_redraw = true;
+                    app.status_message = None;
+                    app.reset_refresh_backoff();
+                    
+                    // Raw mode delivers Ctrl-C as a key press rather than SIGINT;
+                    // quit before a popup or binding can act on it too
+                    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                        break;
+                    }
+                    
+                    // Handle popups first if one is active
+                    if app.show_setup_popup {
+                        match key.code {
+                            KeyCode::Esc => {
+                                app.skip_setup();
+                            }
+                            KeyCode::Enter => {
+                                app.save_setup()?;
+                            }
+                            KeyCode::Tab | KeyCode::Down => {
+                                app.setup.field = app.setup.field.next();
+                            }
+                            KeyCode::BackTab | KeyCode::Up => {
+                                app.setup.field = app.setup.field.previous();
+                            }
+                            KeyCode::Char(' ') if app.setup.field == SetupField::SystemPorts => {
+                                app.setup.toggle_system_ports();
+                            }
+                            KeyCode::Char(c) => {
+                                if let Some(input) = app.setup.input_mut() {
+                                    input.push(c);
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                if let Some(input) = app.setup.input_mut() {
+                                    input.pop();
+                                }
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_kill_results {
+                        match key.code {
+                            KeyCode::Up => {
+                                app.scroll_kill_results(false);
+                            }
+                            KeyCode::Down => {
+                                app.scroll_kill_results(true);
+                            }
+                            _ => {
+                                app.close_kill_results();
+                            }
+                        }
+                    } else if app.show_details {
+                        match key.code {
+                            _ if app.config.key_action(key.code, key.modifiers) == Some(KeyAction::Details) => {
+                                app.toggle_details();
+                            }
+                            KeyCode::Esc | KeyCode::Enter => {
+                                app.toggle_details();
+                            }
+                            KeyCode::Char('d') => {
+                                app.toggle_detail_sources();
+                            }
+                            KeyCode::Char('t') => {
+                                app.toggle_absolute_times();
+                            }
+                            KeyCode::Char('r') => {
+                                app.refresh_processes()?;
+                                app.count_open_fds();
+                                app.read_accept_queue();
+                                app.run_enrichment();
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_profile_popup {
+                        match key.code {
+                            KeyCode::Up => {
+                                app.previous_profile();
+                            }
+                            KeyCode::Down => {
+                                app.next_profile();
+                            }
+                            KeyCode::Enter => {
+                                app.switch_to_selected_profile()?;
+                            }
+                            KeyCode::Esc => {
+                                app.close_profile_popup();
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_config_popup {
+                        match key.code {
+                            KeyCode::Up => {
+                                app.scroll_config(false);
+                            }
+                            KeyCode::Down => {
+                                app.scroll_config(true);
+                            }
+                            KeyCode::Char('e') => {
+                                edit_config(terminal, app)?;
+                            }
+                            KeyCode::Char('o') => {
+                                app.open_config_dir();
+                            }
+                            KeyCode::Char('c') | KeyCode::Esc => {
+                                app.close_config_popup();
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_stats_popup {
+                        match key.code {
+                            KeyCode::Char('c') => {
+                                app.clear_kill_stats();
+                            }
+                            KeyCode::Char('N') | KeyCode::Esc => {
+                                app.close_stats_popup();
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_help_popup {
+                        match key.code {
+                            KeyCode::Char('?') | KeyCode::Esc => {
+                                app.toggle_help_popup();
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_action_popup {
+                        match key.code {
+                            KeyCode::Up => {
+                                app.previous_action();
+                            }
+                            KeyCode::Down => {
+                                app.next_action();
+                            }
+                            KeyCode::Enter => {
+                                run_selected_action(terminal, app, shutdown)?;
+                            }
+                            KeyCode::Esc => {
+                                app.close_action_popup();
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_kill_confirm && app.needs_typed_confirmation() {
+                        match key.code {
+                            KeyCode::Enter => {
+                                app.confirm_kill()?;
+                            }
+                            KeyCode::Esc => {
+                                app.cancel_kill();
+                            }
+                            KeyCode::Char(c) => {
+                                app.kill_confirm_input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.kill_confirm_input.pop();
+                            }
+                            KeyCode::Up => {
+                                app.scroll_kill_confirm(false);
+                            }
+                            KeyCode::Down => {
+                                app.scroll_kill_confirm(true);
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_kill_confirm {
+                        match key.code {
+                            KeyCode::Char('y') | KeyCode::Enter => {
+                                app.confirm_kill()?;
+                            }
+                            KeyCode::Char('r') => {
+                                app.restart_service()?;
+                            }
+                            KeyCode::Char('n') | KeyCode::Esc => {
+                                app.cancel_kill();
+                            }
+                            KeyCode::Up => {
+                                app.scroll_kill_confirm(false);
+                            }
+                            KeyCode::Down => {
+                                app.scroll_kill_confirm(true);
+                            }
+                            _ => {}
+                        }
+                    } else if app.search_active {
+                        match key.code {
+                            KeyCode::Esc => {
+                                app.clear_search();
+                            }
+                            KeyCode::Enter => {
+                                app.close_search();
+                            }
+                            KeyCode::Tab => {
+                                app.toggle_search_scope();
+                            }
+                            KeyCode::Char(c) => {
+                                app.add_char_to_search(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.delete_char_from_search();
+                            }
+                            _ => {}
+                        }
+                    } else if app.highlight_active {
+                        match key.code {
+                            KeyCode::Esc => {
+                                app.clear_highlight();
+                            }
+                            KeyCode::Enter => {
+                                app.close_highlight();
+                            }
+                            KeyCode::Char(c) => {
+                                app.add_char_to_highlight(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.delete_char_from_highlight();
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_cleanup_name_popup {
+                        match key.code {
+                            KeyCode::Esc => {
+                                app.discard_cleanup_recording();
+                            }
+                            KeyCode::Char(c) => {
+                                app.cleanup_name_input.push(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.cleanup_name_input.pop();
+                            }
+                            KeyCode::Enter => {
+                                app.save_cleanup_script()?;
+                            }
+                            _ => {}
+                        }
+                    } else if app.show_add_filter_popup {
+                        match key.code {
+                            KeyCode::Esc => {
+                                app.toggle_add_filter_popup();
+                            }
+                            KeyCode::Char(c) => {
+                                app.add_char_to_filter(c);
+                            }
+                            KeyCode::Backspace => {
+                                app.delete_char_from_filter();
+                            }
+                            KeyCode::Enter => {
+                                app.save_filter()?;
+                            }
+                            _ => {}
+                        }
+                    } else {
+                        match app.current_view {
+                            View::ProcessList => match app.config.key_action(key.code, key.modifiers) {
+                                Some(action) => app.run_key_action(action)?,
+                                // Keep these in step with `RESERVED_KEYS`, which stops
+                                // keybindings from shadowing them
+                                None => match key.code {
+                                    KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                        app.undo_filter_change()?;
+                                    }
+                                    KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                        app.redo_filter_change()?;
+                                    }
+                                    KeyCode::Char('u') => {
+                                        app.undo_filter_change()?;
+                                    }
+                                    KeyCode::Char('U') => {
+                                        app.redo_filter_change()?;
+                                    }
+                                    KeyCode::Char('R') => {
+                                        app.rescan_selected()?;
+                                    }
+                                    KeyCode::Char('p') => {
+                                        app.toggle_refresh_paused();
+                                    }
+                                    KeyCode::Char('I') => {
+                                        app.ignore_selected_pid();
+                                    }
+                                    KeyCode::Char('t') => {
+                                        app.toggle_top()?;
+                                    }
+                                    KeyCode::Char('T') => {
+                                        app.cycle_top_by()?;
+                                    }
+                                    KeyCode::Char('h') => {
+                                        app.toggle_hide_commands();
+                                    }
+                                    KeyCode::Char('e') => {
+                                        app.toggle_short_commands();
+                                    }
+                                    KeyCode::Char('l') => {
+                                        app.toggle_hide_loopback()?;
+                                    }
+                                    KeyCode::Char('L') => {
+                                        app.toggle_port_links();
+                                    }
+                                    KeyCode::Char('o') => {
+                                        app.toggle_hide_recent();
+                                    }
+                                    KeyCode::Char('v') => {
+                                        app.cycle_preset();
+                                    }
+                                    KeyCode::Char('A') => {
+                                        app.toggle_age_coloring();
+                                    }
+                                    KeyCode::Char('g') => {
+                                        app.toggle_group_by_address();
+                                    }
+                                    KeyCode::Char('G') => {
+                                        app.toggle_group_by_unit();
+                                    }
+                                    KeyCode::Char('Y') => {
+                                        app.cycle_armed_kill();
+                                    }
+                                    KeyCode::Char('E') => {
+                                        app.set_baseline()?;
+                                    }
+                                    KeyCode::Char('Q') => {
+                                        app.toggle_unexpected_only();
+                                    }
+                                    KeyCode::Char('W') => {
+                                        app.cycle_placement_filter();
+                                    }
+                                    KeyCode::Char('C') => {
+                                        app.cycle_state_filter();
+                                    }
+                                    KeyCode::Char('N') => {
+                                        app.open_stats_popup();
+                                    }
+                                    KeyCode::Char('X') => {
+                                        app.request_clean_slate();
+                                    }
+                                    KeyCode::Char('O') => {
+                                        app.toggle_port_sort()?;
+                                    }
+                                    KeyCode::Char('V') => {
+                                        app.toggle_quick_filters()?;
+                                    }
+                                    KeyCode::Char('n') => {
+                                        app.select_next_same_name();
+                                    }
+                                    KeyCode::Char('H') => {
+                                        app.open_highlight();
+                                    }
+                                    KeyCode::Char('D') => {
+                                        app.toggle_focus_port();
+                                    }
+                                    KeyCode::Esc if app.focus_port.is_some() => {
+                                        app.focus_port = None;
+                                    }
+                                    KeyCode::Left => {
+                                        app.scroll_commands(false);
+                                    }
+                                    KeyCode::Right => {
+                                        app.scroll_commands(true);
+                                    }
+                                    KeyCode::Char('P') => {
+                                        app.open_profile_popup()?;
+                                    }
+                                    KeyCode::Char('M') => {
+                                        open_system_monitor(terminal, app, shutdown)?;
+                                    }
+                                    KeyCode::Char('x') => {
+                                        app.export_kill_script();
+                                    }
+                                    KeyCode::Char('c') => {
+                                        app.copy_selected_row();
+                                    }
+                                    KeyCode::Char('w') => {
+                                        app.share_view();
+                                    }
+                                    KeyCode::Char('b') => {
+                                        app.copy_diagnostics();
+                                    }
+                                    KeyCode::Char('z') => {
+                                        app.set_selected_suspended(true)?;
+                                    }
+                                    KeyCode::Char('Z') => {
+                                        app.set_selected_suspended(false)?;
+                                    }
+                                    KeyCode::Char('s') => {
+                                        app.cycle_sort_column()?;
+                                    }
+                                    KeyCode::Char('S') => {
+                                        app.toggle_sort_direction()?;
+                                    }
+                                    KeyCode::Char('a') => {
+                                        app.open_action_popup();
+                                    }
+                                    KeyCode::Char('B') => {
+                                        app.request_kill_and_filter();
+                                    }
+                                    KeyCode::Char('?') => {
+                                        app.toggle_help_popup();
+                                    }
+                                    _ => {}
+                                },
+                            },
+                            View::FilterManagement => match key.code {
+                                KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                    app.undo_filter_change()?;
+                                }
+                                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                    app.redo_filter_change()?;
+                                }
+                                KeyCode::Char('u') => {
+                                    app.undo_filter_change()?;
+                                }
+                                KeyCode::Char('U') => {
+                                    app.redo_filter_change()?;
+                                }
+                                KeyCode::Char('q') => {
+                                    app.should_quit = true;
+                                }
+                                KeyCode::Char('a') => {
+                                    app.toggle_add_filter_popup();
+                                }
+                                KeyCode::Char('m') => {
+                                    app.cycle_match_mode()?;
+                                }
+                                KeyCode::Char('I') => {
+                                    app.clear_ignored_pids();
+                                }
+                                KeyCode::Char('c') => {
+                                    app.open_config_popup();
+                                }
+                                KeyCode::Char('K') => {
+                                    app.move_selected_filter(true)?;
+                                }
+                                KeyCode::Char('s') => {
+                                    app.toggle_filters_sorted();
+                                }
+                                KeyCode::Char('z') => {
+                                    app.toggle_snooze_selected_filter();
+                                }
+                                KeyCode::Char('J') => {
+                                    app.move_selected_filter(false)?;
+                                }
+                                KeyCode::Char('F') => {
+                                    app.toggle_view();
+                                }
+                                KeyCode::Char(' ') => {
+                                    app.toggle_selected_filter_enabled()?;
+                                }
+                                KeyCode::Char('T') => {
+                                    app.toggle_selected_filter_quick()?;
+                                }
+                                KeyCode::Char('E') => {
+                                    app.export_filters();
+                                }
+                                KeyCode::Char('L') => {
+                                    app.import_filters()?;
+                                }
+                                KeyCode::Up => {
+                                    app.previous();
+                                }
+                                KeyCode::Down => {
+                                    app.next();
+                                }
+                                KeyCode::Enter | KeyCode::Delete => {
+                                    app.kill_selected()?;
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        
+        if app.should_quit {
+            break;
+        }
+    }
+    
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    // Setup logging
+    tracing_subscriber::fmt::init();
+    
+    // Parse command line arguments
+    let mut args = Args::parse();
+    if args.demo || std::env::var_os(DEMO_ENV_VAR).is_some_and(|value| value == "1") {
+        args.backend = BackendKind::Demo;
+    }
+    if args.lsof_input.is_none() {
+        args.lsof_input = std::env::var_os(LSOF_INPUT_ENV_VAR).map(PathBuf::from);
+    }
+    
+    // Assert that a port is free, e.g. before starting a server in CI
+    if let Some(port) = args.check_port {
+        let backend = discovery_backend(&args)?;
+        if !run_check_port(backend.as_ref(), port, args.quiet)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
+    // Compare the listeners with the baseline, e.g. from cron
+    if args.check_baseline {
+        let config = load_config(&args)?;
+        let backend = discovery_backend(&args)?;
+        if !run_check_baseline(backend.as_ref(), &config, args.quiet)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
+    // Print the listeners' PIDs for shell use, e.g. `kill $(port-manager --pid-on 8080)`
+    if let Some(port) = args.pid_on {
+        let backend = discovery_backend(&args)?;
+        if !run_pid_on(backend.as_ref(), port, args.quiet, &mut io::stdout().lock())? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
+    // Block until a port changes state, e.g. while a server starts up
+    if let Some((port, until_free)) = args.wait_for.map(|port| (port, false))
+        .or(args.wait_until_free.map(|port| (port, true)))
+    {
+        let config = load_config(&args)?;
+        let backend = discovery_backend(&args)?;
+        let on_change: Option<&dyn Fn(&str)> = config.notify_on_change.then_some(&notify);
+        if !run_wait(backend.as_ref(), port, until_free, args.timeout, args.quiet, on_change)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
+    // Replay a recorded cleanup script against whatever is listening now
+    if let Some(name) = &args.run_cleanup {
+        let config = load_config(&args)?;
+        let script = find_cleanup_script(&config, name)?;
+        let options = KillOptions {
+            dry_run: args.dry_run,
+            quiet: args.quiet,
+            // Recorded steps name both process and port; a step edited down
+            // to a name alone can match far more
+            confirm: script.steps.iter().any(|step| step.port.is_none()) && !args.yes,
+            kill_connections: args.kill_connections,
+            yes: args.yes,
+        };
+        let backend = discovery_backend(&args)?;
+        let rules = format!("cleanup script {}", script.name);
+        let matches = script.matcher(config.match_mode);
+        return run_auto_kill(backend.as_ref(), &config, &rules, |p| matches(p.port, &p.name), &options);
+    }
+    
+    // Free ports non-interactively without starting the TUI
+    if args.auto_kill || !args.kill_port.is_empty() || !args.kill_name.is_empty() {
+        let config = load_config(&args)?;
+        let (rules, options) = cli_kill_rules(&args, &config);
+        if rules.is_empty() {
+            if !args.quiet {
+                println!("No auto-kill rules configured");
+            }
+            return Ok(());
+        }
+        let backend = discovery_backend(&args)?;
+        let matches = rules.matcher(config.match_mode);
+        return run_auto_kill(backend.as_ref(), &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options);
+    }
+    
+    // Compare snapshots from before and after e.g. a deploy
+    if let Some(paths) = &args.diff {
+        return run_diff(&paths[0], &paths[1]);
+    }
+    
+    // Share filters between machines without starting the TUI
+    if let Some(path) = &args.export_filters {
+        let config = load_config(&args)?;
+        let count = config.export_filters(path)?;
+        println!("Exported {} filter(s) to {}", count, path.display());
+        return Ok(());
+    }
+    if let Some(path) = &args.import_filters {
+        let mut config = load_config(&args)?;
+        let import = config.import_filters(path)?;
+        println!("{}", import_summary(&import, path, config.match_mode));
+        return Ok(());
+    }
+    
+    // Show where refresh latency comes from
+    if args.time_refresh {
+        let backend = discovery_backend(&args)?;
+        let mode = if args.connections { DiscoveryMode::Connections } else { DiscoveryMode::Listeners };
+        return run_time_refresh(backend.as_ref(), mode);
+    }
+    
+    // Serve the process list to other tools without starting the TUI
+    if let Some(addr) = args.serve {
+        return run_server(&args, addr);
+    }
+    
+    // Keep printing the process list without starting the TUI
+    if args.watch {
+        return run_watch(&args, args.format.unwrap_or(OutputFormat::List));
+    }
+    
+    // Print the process list without starting the TUI
+    if args.format.is_some() || args.output.is_some() {
+        return run_snapshot(&args, args.format.unwrap_or(OutputFormat::List));
+    }
+    
+    // Create app state
+    let mut app = App::new(&args)?;
+    
+    // Request a clean shutdown on termination signals instead of dying with
+    // the terminal still in raw mode
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM, SIGHUP] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))
+            .context("Failed to register signal handler")?;
+    }
+    
+    // Restore the terminal before a panic message is printed
+    let default_panic_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_panic_hook(info);
+    }));
+    
+    // Dumb terminals, e.g. in some CI runners, can't enter raw mode, so print
+    // the list instead of failing
+    if !enter_raw_mode_or_list(&mut app, enable_raw_mode, &mut io::stdout().lock())? {
+        return Ok(());
+    }
+    
+    // Initialize terminal
+    let mut terminal = init_terminal()?;
+    
+    // Run the application
+    run_tui(&mut terminal, &mut app, &shutdown, restore_terminal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    
+    /// Files an app under test reads, removed along with it
+    struct TestDir(PathBuf);
+    
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+    
+    /// A captured lsof row for a TCP listener on all IPv4 interfaces
+    fn listener(name: &str, pid: u32, port: u16) -> String {
+        listener_at(name, pid, AddressFamily::Ipv4, &format!("*:{}", port))
+    }
+    
+    /// A captured lsof row for a TCP listener on `address`, e.g. `[::1]:9229`
+    fn listener_at(name: &str, pid: u32, family: AddressFamily, address: &str) -> String {
+        let family = match family {
+            AddressFamily::Ipv4 => "IPv4",
+            AddressFamily::Ipv6 => "IPv6",
+        };
+        format!("{} {} alice 20u {} 123 0t0 TCP {} (LISTEN)", name, pid, family, address)
+    }
+    
+    /// An empty directory unique to this test
+    fn test_dir() -> TestDir {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = TestDir(std::env::temp_dir().join(format!(
+            "port-manager-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        )));
+        fs::create_dir_all(&dir.0).unwrap();
+        dir
+    }
+    
+    /// Write captured lsof rows under a header, the way `--lsof-input` reads them
+    fn write_lsof(dir: &TestDir, rows: &[String]) -> PathBuf {
+        let path = dir.0.join("lsof.txt");
+        fs::write(&path, format!("COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME\n{}\n", rows.join("\n"))).unwrap();
+        path
+    }
+    
+    /// A backend serving captured lsof rows
+    fn captured(rows: &[String]) -> (CapturedLsofBackend, TestDir) {
+        let dir = test_dir();
+        let backend = CapturedLsofBackend::new(write_lsof(&dir, rows)).unwrap();
+        (backend, dir)
+    }
+    
+    /// An app showing captured lsof rows, with its config read from `config`
+    fn test_app(config: &str, rows: &[String]) -> (App, TestDir) {
+        test_app_with_args(config, rows, &[])
+    }
+    
+    /// An app like `test_app`, started with extra command line flags
+    fn test_app_with_args(config: &str, rows: &[String], flags: &[&str]) -> (App, TestDir) {
+        let dir = test_dir();
+        let config_path = dir.0.join("config.json");
+        let lsof_path = write_lsof(&dir, rows);
+        fs::write(&config_path, config).unwrap();
+        
+        let args = Args::parse_from([
+            "port-manager",
+            "--config", config_path.to_str().unwrap(),
+            "--lsof-input", lsof_path.to_str().unwrap(),
+        ].into_iter().chain(flags.iter().copied()));
+        let mut app = App::new(&args).unwrap();
+        app.refresh_processes().unwrap();
+        (app, dir)
+    }
+    
+    #[test]
+    fn port_ranges_parse() {
+        assert_eq!(parse_port_range("3000-3100"), Ok(3000..=3100));
+        assert_eq!(parse_port_range("8080-8080"), Ok(8080..=8080));
+        assert_eq!(parse_port_range("0-65535"), Ok(0..=65535));
+        assert_eq!(parse_port_range(" 3000 - 3100 "), Ok(3000..=3100));
+    }
+    
+    #[test]
+    fn port_ranges_reject_bad_input() {
+        assert_eq!(parse_port_range("3100-3000"), Err("range start 3100 is after its end 3000".to_string()));
+        assert_eq!(parse_port_range("3000-65536"), Err("\"65536\" is not a port between 0 and 65535".to_string()));
+        assert_eq!(parse_port_range("3000"), Err("expected START-END, got \"3000\"".to_string()));
+        assert!(parse_port_range("-3000").is_err());
+        assert!(parse_port_range("3000-").is_err());
+        assert!(parse_port_range("-1-3000").is_err());
+        assert!(parse_port_range("3000-3100,8000-8100").is_err());
+        assert!(parse_port_range("3000-3100 8000").is_err());
+        let error = Args::try_parse_from(["port-manager", "--format", "json", "--range", "3100-3000"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        assert!(Args::try_parse_from(["port-manager", "--format", "json", "--range", "3000-3100"]).is_ok());
+    }
+    
+    #[test]
+    fn refreshing_behind_the_filter_popup_leaves_its_input_alone() {
+        let (mut app, dir) = test_app("{}", &[listener("node", 1, 3000), listener("nodemon", 2, 3001), listener("nginx", 3, 8080)]);
+        let filters = app.config.filtered_process_names.len();
+        app.toggle_add_filter_popup();
+        for c in "node".chars() {
+            app.add_char_to_filter(c);
+        }
+        assert_eq!(app.filter_input_matches(), Some(2));
+        
+        write_lsof(&dir, &[listener("node", 1, 3000), listener("nginx", 3, 8080), listener("java", 4, 9000)]);
+        app.refresh_processes().unwrap();
+        assert!(app.show_add_filter_popup);
+        assert_eq!(app.add_filter_input, "node");
+        assert_eq!(app.filter_input_matches(), Some(1));
+        let screen = render(&app, 100, 30);
+        assert!(screen.iter().any(|row| row.contains("hides 1 of 3 shown")), "{:#?}", screen);
+        
+        // Typing after the refresh still only edits the input
+        app.add_char_to_filter('m');
+        assert_eq!(app.add_filter_input, "nodem");
+        assert_eq!(app.filter_input_matches(), Some(0));
+        assert_eq!(app.config.filtered_process_names.len(), filters);
+    }
+    
+    /// Draw the app the way the terminal would, one string per screen row
+    fn render(app: &App, width: u16, height: u16) -> Vec<String> {
+        let buffer = render_buffer(app, width, height);
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer.get(x, y).symbol()).collect())
+            .collect()
+    }
+    
+    /// Draw the app, keeping each cell's style
+    fn render_buffer(app: &App, width: u16, height: u16) -> ratatui::buffer::Buffer {
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| {
+            ui(frame, app);
+        }).unwrap();
+        terminal.backend().buffer().clone()
+    }
+    
+    /// The help line, the last row with text inside the bottom border
+    fn help_line(app: &App) -> String {
+        let screen = render(app, 240, 20);
+        screen[screen.len() - 2].trim_matches(|c: char| c.is_whitespace() || c == '│').to_string()
+    }
+    
+    #[test]
+    fn help_shows_ways_back_when_nothing_is_shown() {
+        let (app, _dir) = test_app("{}", &[]);
+        let help = help_line(&app);
+        assert!(help.starts_with("No processes shown"), "{}", help);
+        assert!(help.contains("?: All keys"), "{}", help);
+        assert!(!help.contains("Kill"), "{}", help);
+    }
+    
+    #[test]
+    fn help_shows_actions_on_the_selected_process() {
+        let (app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        assert!(app.selected_process().is_some());
+        let help = help_line(&app);
+        for key in ["k: Kill process", "i: Details", "c: Copy row", "Navigate", "?: All keys"] {
+            assert!(help.contains(key), "{:?} missing from {}", key, help);
+        }
+        // The rest waits behind `?`
+        assert!(!help.contains("Export kill script"), "{}", help);
+        assert!(!help.contains("Diagnostics"), "{}", help);
+    }
+    
+    #[test]
+    fn help_shows_the_confirmation_keys() {
+        let (mut app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        app.request_kill().unwrap();
+        assert!(app.show_kill_confirm);
+        assert_eq!(help_line(&app), "y/Enter: Kill | n/Esc: Cancel");
+    }
+    
+    #[test]
+    fn help_popup_lists_every_key() {
+        let (mut app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        app.toggle_help_popup();
+        let screen = render(&app, 240, 40).join("\n");
+        for key in ["k: Kill process", "x: Export kill script", "b: Diagnostics", "?/Esc: Close"] {
+            assert!(screen.contains(key), "{:?} missing from the help popup", key);
+        }
+    }
+    
+    /// Select the first row of a process
+    fn select(app: &mut App, pid: u32) {
+        app.selected_idx = app.port_processes.iter().position(|p| p.pid == pid);
+        assert!(app.selected_idx.is_some());
+    }
+    
+    const POLICIES: &str = r#"{
+        "min_port": 1,
+        "confirm_kill": true,
+        "port_policies": [
+            { "ports": [5432, 5432], "policy": "protected" },
+            { "ports": [3000, 3999], "policy": "never_confirm" },
+            { "ports": [1, 1024], "policy": "always_confirm" }
+        ]
+    }"#;
+    
+    #[test]
+    fn kill_follows_the_default_outside_policy_ranges() {
+        let (mut app, _dir) = test_app(POLICIES, &[listener("node", 4242, 8080)]);
+        select(&mut app, 4242);
+        app.request_kill().unwrap();
+        assert!(app.show_kill_confirm);
+        
+        let (mut app, _dir) = test_app(r#"{ "confirm_kill": false }"#, &[listener("node", 4242, 8080)]);
+        select(&mut app, 4242);
+        app.request_kill().unwrap();
+        assert!(!app.show_kill_confirm);
+    }
+    
+    #[test]
+    fn never_confirm_ports_skip_the_confirmation() {
+        let (mut app, _dir) = test_app(POLICIES, &[listener("node", 4242, 3000)]);
+        select(&mut app, 4242);
+        app.request_kill().unwrap();
+        assert!(!app.show_kill_confirm);
+        // Captured rows are never signalled, so the kill stops here
+        assert_eq!(app.status_message.as_deref(), Some("Demo mode: 1 process(es) left running"));
+    }
+    
+    #[test]
+    fn always_confirm_ports_ask_even_for_a_kill_without_confirmation() {
+        let (mut app, _dir) = test_app(r#"{
+            "min_port": 1,
+            "confirm_kill": false,
+            "port_policies": [{ "ports": [1, 1024], "policy": "always_confirm" }]
+        }"#, &[listener("nginx", 4242, 80)]);
+        select(&mut app, 4242);
+        app.kill_without_confirmation().unwrap();
+        assert!(app.show_kill_confirm);
+        assert_eq!(app.kill_targets, [4242]);
+    }
+    
+    #[test]
+    fn the_strictest_policy_of_a_process_wins() {
+        // One process on a free port and a confirmed one
+        let (mut app, _dir) = test_app(POLICIES, &[listener("node", 4242, 3000), listener("node", 4242, 443)]);
+        assert_eq!(app.kill_policy(4242), Some(KillPolicy::AlwaysConfirm));
+        select(&mut app, 4242);
+        app.request_kill().unwrap();
+        assert!(app.show_kill_confirm);
+    }
+    
+    #[test]
+    fn protected_ports_refuse_the_kill() {
+        let (mut app, _dir) = test_app(POLICIES, &[listener("postgres", 4242, 5432), listener("node", 4343, 3000)]);
+        select(&mut app, 4242);
+        app.request_kill().unwrap();
+        assert!(!app.show_kill_confirm);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Refusing to kill postgres (PID 4242): port 5432 is protected by port_policies")
+        );
+        
+        // Only the protected process is left out of a bulk kill
+        app.status_message = None;
+        app.marked_pids.extend([4242, 4343]);
+        assert_eq!(app.without_protected(app.kill_candidates()), [4343]);
+        assert!(app.status_message.is_some_and(|message| message.starts_with("Refusing to kill postgres")));
+    }
+    
+    fn step(name: &str, port: u16) -> CleanupStep {
+        CleanupStep { name: Some(name.to_string()), port: Some(port) }
+    }
+    
+    #[test]
+    fn recorded_cleanup_scripts_round_trip() {
+        let (mut app, _dir) = test_app("{}", &[]);
+        app.toggle_cleanup_recording();
+        app.record_cleanup_steps(&[
+            KillOutcome { pid: 10, name: "node".to_string(), ports: vec![3000, 3001], error: None },
+            KillOutcome { pid: 11, name: "ruby".to_string(), ports: vec![4000], error: Some("Operation not permitted".to_string()) },
+            KillOutcome { pid: 12, name: "node".to_string(), ports: vec![3000], error: None },
+        ]);
+        app.toggle_cleanup_recording();
+        assert!(app.show_cleanup_name_popup);
+        app.cleanup_name_input = "dev".to_string();
+        app.save_cleanup_script().unwrap();
+        
+        // Failed kills and repeats aren't recorded
+        let config = Config::load_override(app.config.config_path(), false).unwrap();
+        let script = find_cleanup_script(&config, "dev").unwrap();
+        assert_eq!(script.steps, [step("node", 3000), step("node", 3001)]);
+        assert_eq!(
+            serde_json::to_string(&config.cleanup_scripts).unwrap(),
+            r#"[{"name":"dev","steps":[{"name":"node","port":3000},{"name":"node","port":3001}]}]"#
+        );
+    }
+    
+    #[test]
+    fn j_moves_down_once_bound_and_j_records_cleanup() {
+        let (mut app, _dir) = test_app(r#"{ "keybindings": { "down": ["down", "j"] } }"#, &[listener("node", 1, 3000), listener("java", 2, 8080)]);
+        select(&mut app, 1);
+        let action = app.config.key_action(KeyCode::Char('j'), KeyModifiers::NONE).unwrap();
+        app.run_key_action(action).unwrap();
+        assert_eq!(app.selected_process().map(|p| p.pid), Some(2));
+        assert!(app.recorded_cleanup.is_none());
+        
+        let action = app.config.key_action(KeyCode::Char('J'), KeyModifiers::SHIFT).unwrap();
+        app.run_key_action(action).unwrap();
+        assert!(app.recorded_cleanup.is_some());
+        assert_eq!(app.status_message.as_deref(), Some("Recording kills into a cleanup script; J again to save it"));
+    }
+    
+    #[test]
+    fn cleanup_replay_matches_by_name_and_port() {
+        let config = Config::default();
+        let script = CleanupScript { name: "dev".to_string(), steps: vec![step("node", 3000), step("redis-server", 6379)] };
+        // node restarted under a new PID and redis is already gone
+        let processes = [
+            PortProcess::without_lookups(77, "node".to_string(), 3000, "*".to_string(), AddressFamily::Ipv4, None),
+            PortProcess::without_lookups(78, "node".to_string(), 3002, "*".to_string(), AddressFamily::Ipv4, None),
+            PortProcess::without_lookups(79, "vite".to_string(), 6379, "*".to_string(), AddressFamily::Ipv4, None),
+        ];
+        let matches = script.matcher(config.match_mode);
+        let (protected, targets) = auto_kill_targets(&processes, &config, |p| matches(p.port, &p.name));
+        assert!(protected.is_empty());
+        assert_eq!(targets.iter().map(|p| p.pid).collect::<Vec<_>>(), [77]);
+    }
+    
+    #[test]
+    fn cleanup_replay_leaves_protected_processes_alone() {
+        let mut config = Config::default();
+        config.port_policies = vec![config::PortPolicy { ports: [5432, 5432], policy: KillPolicy::Protected }];
+        let script = CleanupScript { name: "dev".to_string(), steps: vec![step("node", 3000), step("postgres", 5432), step("pgbouncer", 6432)] };
+        let processes = [
+            PortProcess::without_lookups(77, "node".to_string(), 3000, "*".to_string(), AddressFamily::Ipv4, None),
+            PortProcess::without_lookups(80, "postgres".to_string(), 5432, "*".to_string(), AddressFamily::Ipv4, None),
+            // Protected through its other port
+            PortProcess::without_lookups(81, "pgbouncer".to_string(), 6432, "*".to_string(), AddressFamily::Ipv4, None),
+            PortProcess::without_lookups(81, "pgbouncer".to_string(), 5432, "127.0.0.1".to_string(), AddressFamily::Ipv4, None),
+        ];
+        let matches = script.matcher(config.match_mode);
+        let (protected, targets) = auto_kill_targets(&processes, &config, |p| matches(p.port, &p.name));
+        assert_eq!(protected.iter().map(|p| (p.pid, p.port)).collect::<Vec<_>>(), [(80, 5432), (81, 6432)]);
+        assert_eq!(targets.iter().map(|p| p.pid).collect::<Vec<_>>(), [77]);
+    }
+    
+    #[test]
+    fn missing_cleanup_scripts_list_the_saved_ones() {
+        let mut config = Config::default();
+        let error = find_cleanup_script(&config, "dev").unwrap_err();
+        assert_eq!(error.to_string(), r#"No cleanup script named "dev" (saved: none, record one with j in the TUI)"#);
+        
+        config.cleanup_scripts = vec![
+            CleanupScript { name: "web".to_string(), steps: vec![step("node", 3000)] },
+            CleanupScript { name: "db".to_string(), steps: vec![step("postgres", 5432)] },
+        ];
+        let error = find_cleanup_script(&config, "dev").unwrap_err();
+        assert_eq!(error.to_string(), r#"No cleanup script named "dev" (saved: web, db)"#);
+        assert_eq!(find_cleanup_script(&config, "db").unwrap().steps, [step("postgres", 5432)]);
+    }
+    
+    #[test]
+    fn hidden_commands_never_reach_the_screen() {
+        let (mut app, _dir) = test_app(r#"{ "hide_commands": true }"#, &[listener("node", 4242, 3000)]);
+        for process in &mut app.port_processes {
+            process.command = "node server.js --token=s3cr3t".to_string();
+            process.sysinfo_command = process.command.clone();
+        }
+        let screen = render(&app, 200, 30).join("\n");
+        assert!(!screen.contains("s3cr3t"), "{}", screen);
+        assert!(screen.contains(HIDDEN_COMMAND));
+        
+        app.toggle_details();
+        assert!(app.show_details);
+        let screen = render(&app, 200, 50).join("\n");
+        assert!(!screen.contains("s3cr3t"), "{}", screen);
+        
+        assert!(!view_snapshot(&app, "now").contains("s3cr3t"));
+        // Searching can't reveal what's hidden either
+        assert!(!app.port_processes[0].matches_search("s3cr3t", &[SearchField::Command], app.hide_commands));
+    }
+    
+    #[test]
+    fn dual_stack_listeners_merge_by_default() {
+        let rows = [
+            listener("node", 4242, 3000),
+            listener_at("node", 4242, AddressFamily::Ipv6, "*:3000"),
+        ];
+        let (app, _dir) = test_app("{}", &rows);
+        assert_eq!(app.port_processes.len(), 1);
+        assert!(app.port_processes[0].dual_stack);
+        
+        let (app, _dir) = test_app(r#"{ "merge_dual_stack": false }"#, &rows);
+        assert_eq!(app.port_processes.len(), 2);
+        assert!(app.port_processes.iter().all(|p| !p.dual_stack));
+    }
+    
+    #[test]
+    fn loopback_only_listeners_hide_with_the_setting() {
+        let rows = [
+            listener_at("vite", 4242, AddressFamily::Ipv4, "127.0.0.1:5173"),
+            listener("node", 4343, 3000),
+        ];
+        let (app, _dir) = test_app(r#"{ "hide_loopback_only": true }"#, &rows);
+        assert_eq!(app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>(), [4343]);
+        
+        let (app, _dir) = test_app("{}", &rows);
+        assert_eq!(app.port_processes.len(), 2);
+    }
+    
+    /// Type a search query the way the `/` key does
+    fn search(app: &mut App, query: &str) -> Vec<u32> {
+        app.clear_search();
+        app.open_search();
+        for c in query.chars() {
+            app.add_char_to_search(c);
+        }
+        app.port_processes.iter().map(|p| p.pid).collect()
+    }
+    
+    #[test]
+    fn search_matches_the_configured_fields() {
+        let (mut app, _dir) = test_app(
+            r#"{ "search_fields": ["name"] }"#,
+            &[listener("node", 4242, 3000), listener("python3", 4343, 8000)],
+        );
+        for process in &mut app.discovered {
+            process.command = format!("{} --serve", process.name);
+        }
+        assert_eq!(search(&mut app, "PYTH"), [4343]);
+        assert!(search(&mut app, "serve").is_empty());
+        assert!(search(&mut app, "3000").is_empty());
+        assert!(search(&mut app, "alice").is_empty());
+        
+        // Tab switches to every field and back
+        app.toggle_search_scope();
+        assert_eq!(search(&mut app, "serve"), [4242, 4343]);
+        assert_eq!(search(&mut app, "3000"), [4242]);
+        assert_eq!(search(&mut app, "alice"), [4242, 4343]);
+        app.toggle_search_scope();
+        assert!(search(&mut app, "serve").is_empty());
+        
+        app.clear_search();
+        assert_eq!(app.port_processes.len(), 2);
+    }
+    
+    /// A local backend whose second listener exits as soon as it's been
+    /// discovered, before its PID is checked again
+    struct VanishingBackend;
+    
+    impl Backend for VanishingBackend {
+        fn name(&self) -> &'static str {
+            "vanishing"
+        }
+        
+        fn is_available(&self) -> bool {
+            true
+        }
+        
+        fn discover(&self, _mode: DiscoveryMode, _system: &System) -> error::Result<Vec<PortProcess>> {
+            let mut child = std::process::Command::new("true").spawn().unwrap();
+            let gone = child.id();
+            child.wait().unwrap();
+            Ok(vec![
+                // PID 1 outlives the test
+                PortProcess::without_lookups(1, "init".to_string(), 3000, "*".to_string(), AddressFamily::Ipv4, None),
+                PortProcess::without_lookups(gone, "node".to_string(), 3001, "*".to_string(), AddressFamily::Ipv4, None),
+            ])
+        }
+        
+        fn connection_counts(&self) -> error::Result<HashMap<u16, u64>> {
+            Ok(HashMap::new())
+        }
+        
+        fn raw_output(&self, _mode: DiscoveryMode) -> error::Result<String> {
+            Ok(String::new())
+        }
+    }
+    
+    #[test]
+    fn processes_exiting_mid_refresh_are_dropped_or_marked() {
+        let mut system = System::new();
+        let dropped = get_port_processes(&VanishingBackend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop).unwrap();
+        assert_eq!(dropped.iter().map(|p| (p.pid, p.exited)).collect::<Vec<_>>(), [(1, false)]);
+        
+        let marked = get_port_processes(&VanishingBackend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Mark).unwrap();
+        assert_eq!(marked.iter().map(|p| (p.port, p.exited)).collect::<Vec<_>>(), [(3000, false), (3001, true)]);
+    }
+    
+    #[test]
+    fn removing_a_filter_while_sorted_removes_the_one_shown() {
+        let (mut app, _dir) = test_app(r#"{ "filtered_process_names": ["zeta", "alpha", "mid"] }"#, &[]);
+        app.current_view = View::FilterManagement;
+        app.filter_selected_idx = Some(0);
+        app.toggle_filters_sorted();
+        assert_eq!(app.filter_display_order(), [1, 2, 0]);
+        
+        // The first row shown is "alpha", stored second
+        app.filter_selected_idx = Some(0);
+        app.kill_selected().unwrap();
+        let patterns: Vec<&str> = app.config.filtered_process_names.iter().map(|f| f.pattern.as_str()).collect();
+        assert_eq!(patterns, ["zeta", "mid"]);
+        
+        // Toggling keeps the same filter selected rather than the same row
+        app.filter_selected_idx = Some(1);
+        app.toggle_filters_sorted();
+        assert_eq!(app.selected_filter_index(), Some(0));
+        assert_eq!(app.filter_selected_idx, Some(0));
+    }
+    
+    #[test]
+    fn check_port_reports_whether_anything_listens() {
+        let (backend, _dir) = captured(&[listener("node", 4242, 3000), listener("nginx", 4343, 8080)]);
+        assert!(!run_check_port(&backend, 3000, true).unwrap());
+        assert!(!run_check_port(&backend, 8080, false).unwrap());
+        assert!(run_check_port(&backend, 3001, true).unwrap());
+        
+        let (empty, _dir) = captured(&[]);
+        assert!(run_check_port(&empty, 3000, false).unwrap());
+    }
+    
+    #[test]
+    fn older_than_takes_human_durations() {
+        let args = Args::parse_from(["port-manager", "--older-than", "5m"]);
+        assert_eq!(args.older_than, Some(Duration::from_secs(300)));
+        let args = Args::parse_from(["port-manager", "--older-than", "1h 30m"]);
+        assert_eq!(args.older_than, Some(Duration::from_secs(5400)));
+        assert!(Args::try_parse_from(["port-manager", "--older-than", "soon"]).is_err());
+        assert!(Args::try_parse_from(["port-manager", "--older-than", "5"]).is_err());
+    }
+    
+    #[test]
+    fn hiding_recent_processes_keeps_old_and_unknown_ones() {
+        let (mut app, _dir) = test_app("{}", &[listener("old", 4242, 3000), listener("new", 4343, 3001), listener("unknown", 4444, 3002)]);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        for process in &mut app.discovered {
+            process.start_time = match process.name.as_str() {
+                "old" => Some(now - 2 * 60 * 60),
+                "new" => Some(now - 60),
+                _ => None,
+            };
+        }
+        app.min_age = Duration::from_secs(5 * 60);
+        app.toggle_hide_recent();
+        assert_eq!(app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>(), [4242, 4444]);
+        
+        app.toggle_hide_recent();
+        assert_eq!(app.port_processes.len(), 3);
+    }
+    
+    #[test]
+    fn demo_mode_shows_fixtures_and_kills_nothing() {
+        let dir = test_dir();
+        let config_path = dir.0.join("config.json");
+        fs::write(&config_path, r#"{ "min_port": 1, "filtered_process_names": [] }"#).unwrap();
+        let args = Args::parse_from(["port-manager", "--config", config_path.to_str().unwrap(), "--backend", "demo"]);
+        let mut app = App::new(&args).unwrap();
+        app.refresh_processes().unwrap();
+        assert_eq!(app.backend.name(), "demo");
+        let mut ports: Vec<u16> = app.port_processes.iter().map(|p| p.port).collect();
+        ports.sort();
+        assert_eq!(ports, [80, 443, 3000, 5432, 6379, 8000, 8080]);
+        
+        app.kill_pids(&[5120, 5377]).unwrap();
+        assert_eq!(app.status_message.as_deref(), Some("Demo mode: 2 process(es) left running"));
+        app.refresh_processes().unwrap();
+        assert_eq!(app.port_processes.len(), 7);
+    }
+    
+    #[test]
+    fn system_processes_sink_below_the_primary_sort() {
+        let rows = [
+            listener("sshd", 700, 22).replace("alice", "root"),
+            listener("node", 4242, 3000),
+            listener("nginx", 701, 80).replace("alice", "root"),
+            listener("api", 4343, 8080),
+            listener("systemd", 1, 5000),
+        ];
+        let ports = |app: &App| app.port_processes.iter().map(|p| p.port).collect::<Vec<_>>();
+        let (mut app, _dir) = test_app(r#"{ "min_port": 1, "system_processes_last": true }"#, &rows);
+        assert_eq!(ports(&app), [3000, 8080, 22, 80, 5000]);
+        app.toggle_sort_direction().unwrap();
+        assert_eq!(ports(&app), [8080, 3000, 5000, 80, 22]);
+        
+        let (app, _dir) = test_app(r#"{ "min_port": 1 }"#, &rows);
+        assert_eq!(ports(&app), [22, 80, 3000, 5000, 8080]);
+    }
+    
+    #[test]
+    fn presets_replace_the_configured_filters() {
+        let (mut app, _dir) = test_app(
+            r#"{
+                "filtered_process_names": ["node"],
+                "presets": [
+                    { "name": "web", "ports": [80, 443, 3000, 8080] },
+                    { "name": "db", "names": ["postgres", "redis-server"] }
+                ]
+            }"#,
+            &[
+                listener("nginx", 4242, 80),
+                listener("node", 4343, 3000),
+                listener("postgres", 4444, 5432),
+                listener("redis-server", 4545, 6379),
+                listener("vite", 4646, 5173),
+            ],
+        );
+        let ports = |app: &App| app.port_processes.iter().map(|p| p.port).collect::<Vec<_>>();
+        assert_eq!(ports(&app), [5173, 5432, 6379]);
+        
+        // The preset shows its ports even below min_port, and names the filters hide
+        app.cycle_preset();
+        assert_eq!(ports(&app), [80, 3000]);
+        assert!(render(&app, 240, 20).iter().any(|row| row.contains("preset: web")));
+        
+        app.cycle_preset();
+        assert_eq!(ports(&app), [5432, 6379]);
+        
+        app.cycle_preset();
+        assert_eq!(app.active_preset, None);
+        assert_eq!(ports(&app), [5173, 5432, 6379]);
+    }
+    
+    #[test]
+    fn no_filters_shows_everything_the_backend_found() {
+        let config = r#"{ "filtered_process_names": ["node", "java"], "filtered_pids": [4545] }"#;
+        let rows = [
+            listener("node", 4242, 3000),
+            listener("java", 4343, 8080),
+            listener("sshd", 700, 22),
+            listener("vite", 4545, 5173),
+            listener("python3", 4646, 8000),
+        ];
+        let raw = |app: &App| app.backend.discover(DiscoveryMode::Listeners, &System::new()).unwrap().len();
+        
+        let (app, _dir) = test_app(config, &rows);
+        assert_eq!(app.port_processes.len(), 1);
+        
+        // The port range still hides sshd on 22 unless system ports are included
+        let (app, _dir) = test_app_with_args(config, &rows, &["--no-filters"]);
+        assert_eq!(app.port_processes.len(), raw(&app) - 1);
+        assert!(render(&app, 240, 20).iter().any(|row| row.contains("(filters off)")));
+        
+        let (app, _dir) = test_app_with_args(config, &rows, &["--no-filters", "--include-system"]);
+        assert_eq!(raw(&app), 5);
+        assert_eq!(app.port_processes.len(), raw(&app));
+        assert!(render(&app, 240, 20).iter().any(|row| row.contains("(filters and port range off)")));
+    }
+    
+    /// Foreground color of the first cell of `text` on screen
+    fn color_of(buffer: &ratatui::buffer::Buffer, text: &str) -> Option<Color> {
+        let area = buffer.area;
+        (0..area.height).find_map(|y| {
+            let row: String = (0..area.width).map(|x| buffer.get(x, y).symbol()).collect();
+            let x = row.find(text)?;
+            Some(buffer.get(x as u16, y).fg)
+        })
+    }
+    
+    #[test]
+    fn highlighted_rows_stand_out_without_hiding_others() {
+        let rows = [listener("node", 4242, 3000), listener("nginx", 4343, 8080), listener("python3", 4444, 8000)];
+        let (mut app, _dir) = test_app_with_args("{}", &rows, &["--highlight", "NGINX"]);
+        app.selected_idx = None;
+        assert_eq!(app.port_processes.len(), 3);
+        
+        let screen = render_buffer(&app, 200, 20);
+        assert_eq!(color_of(&screen, "nginx"), Some(Color::Cyan));
+        assert_eq!(color_of(&screen, "python3"), Some(Color::Reset));
+        assert!(render(&app, 200, 20).iter().any(|row| row.contains("highlighting \"NGINX\"")));
+        
+        app.clear_highlight();
+        assert_eq!(color_of(&render_buffer(&app, 200, 20), "nginx"), Some(Color::Reset));
+        assert_eq!(app.port_processes.len(), 3);
+    }
+    
+    /// Add a filter through the popup, as typing it and pressing Enter would
+    fn add_filter(app: &mut App, pattern: &str) {
+        app.toggle_add_filter_popup();
+        for c in pattern.chars() {
+            app.add_char_to_filter(c);
+        }
+        app.save_filter().unwrap();
+    }
+    
+    #[test]
+    fn filter_changes_undo_and_redo() {
+        let (mut app, _dir) = test_app(r#"{ "filtered_process_names": [] }"#, &[listener("node", 4242, 3000), listener("java", 4343, 8080), listener("vite", 4444, 5173)]);
+        let names = |app: &App| app.port_processes.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+        add_filter(&mut app, "node");
+        add_filter(&mut app, "java");
+        assert_eq!(names(&app), ["vite"]);
+        
+        app.undo_filter_change().unwrap();
+        assert_eq!(names(&app), ["vite", "java"]);
+        app.undo_filter_change().unwrap();
+        assert_eq!(names(&app), ["node", "vite", "java"]);
+        app.undo_filter_change().unwrap();
+        assert_eq!(app.status_message.as_deref(), Some("Nothing to undo"));
+        
+        app.redo_filter_change().unwrap();
+        assert_eq!(names(&app), ["vite", "java"]);
+        
+        // A new change drops what could have been redone
+        add_filter(&mut app, "vite");
+        assert_eq!(names(&app), ["java"]);
+        app.redo_filter_change().unwrap();
+        assert_eq!(app.status_message.as_deref(), Some("Nothing to redo"));
+        
+        // Removing a filter in the filter view is undone the same way
+        app.current_view = View::FilterManagement;
+        app.filter_selected_idx = Some(0);
+        app.kill_selected().unwrap();
+        assert_eq!(names(&app), ["node", "java"]);
+        app.undo_filter_change().unwrap();
+        assert_eq!(names(&app), ["java"]);
+    }
+    
+    #[test]
+    fn undo_history_is_bounded() {
+        let (mut app, _dir) = test_app(r#"{ "filtered_process_names": [] }"#, &[]);
+        for i in 0..UNDO_LIMIT + 5 {
+            add_filter(&mut app, &format!("server-{}", i));
+        }
+        for _ in 0..UNDO_LIMIT {
+            app.undo_filter_change().unwrap();
+        }
+        assert_eq!(app.config.filtered_process_names.len(), 5);
+        app.undo_filter_change().unwrap();
+        assert_eq!(app.status_message.as_deref(), Some("Nothing to undo"));
+    }
+    
+    /// A backend where port 3000 changes between listening and free once
+    /// it has been polled `flip_after` times
+    struct FlippingBackend {
+        listening_at_first: bool,
+        flip_after: usize,
+        polls: AtomicUsize,
+    }
+    
+    impl FlippingBackend {
+        fn new(listening_at_first: bool, flip_after: usize) -> Self {
+            Self { listening_at_first, flip_after, polls: AtomicUsize::new(0) }
+        }
+    }
+    
+    impl Backend for FlippingBackend {
+        fn name(&self) -> &'static str {
+            "flipping"
+        }
+        
+        fn is_available(&self) -> bool {
+            true
+        }
+        
+        fn discover(&self, _mode: DiscoveryMode, _system: &System) -> error::Result<Vec<PortProcess>> {
+            let flipped = self.polls.fetch_add(1, Ordering::Relaxed) >= self.flip_after;
+            Ok(if self.listening_at_first != flipped {
+                // PID 1 outlives the test
+                vec![PortProcess::without_lookups(1, "init".to_string(), 3000, "*".to_string(), AddressFamily::Ipv4, None)]
+            } else {
+                Vec::new()
+            })
+        }
+        
+        fn connection_counts(&self) -> error::Result<HashMap<u16, u64>> {
+            Ok(HashMap::new())
+        }
+        
+        fn raw_output(&self, _mode: DiscoveryMode) -> error::Result<String> {
+            Ok(String::new())
+        }
+    }
+    
+    #[test]
+    fn waiting_returns_once_the_port_flips() {
+        let timeout = Some(Duration::from_secs(10));
+        let backend = FlippingBackend::new(false, 2);
+        assert!(run_wait(&backend, 3000, false, timeout, true, None).unwrap());
+        assert_eq!(backend.polls.load(Ordering::Relaxed), 3);
+        
+        let backend = FlippingBackend::new(true, 2);
+        assert!(run_wait(&backend, 3000, true, timeout, true, None).unwrap());
+        assert_eq!(backend.polls.load(Ordering::Relaxed), 3);
+        
+        // Another port changing doesn't count
+        let backend = FlippingBackend::new(false, 0);
+        assert!(run_wait(&backend, 3001, true, timeout, true, None).unwrap());
+        assert_eq!(backend.polls.load(Ordering::Relaxed), 1);
+    }
+    
+    #[test]
+    fn waiting_times_out_when_the_port_never_flips() {
+        let backend = FlippingBackend::new(false, usize::MAX);
+        assert!(!run_wait(&backend, 3000, false, Some(Duration::ZERO), true, None).unwrap());
+        let backend = FlippingBackend::new(true, usize::MAX);
+        assert!(!run_wait(&backend, 3000, true, Some(WAIT_POLL_INTERVAL), true, None).unwrap());
+        assert_eq!(backend.polls.load(Ordering::Relaxed), 2);
+    }
+    
+    #[test]
+    fn short_commands_show_the_executable_in_the_table_only() {
+        let (mut app, _dir) = test_app("{}", &[listener("java", 4242, 8080)]);
+        app.port_processes[0].command = "/usr/bin/java -Xmx2g -jar api.jar --server.port=8080".to_string();
+        let process = app.port_processes[0].clone();
+        assert_eq!(app.table_command(&process), process.command);
+        app.toggle_short_commands();
+        assert_eq!(app.table_command(&process), "java");
+        
+        // Hidden commands stay hidden rather than showing the executable
+        app.hide_commands = true;
+        assert_eq!(app.table_command(&process), HIDDEN_COMMAND);
+    }
+    
+    #[test]
+    fn text_tables_align_columns() {
+        let rows = vec![
+            vec!["7".to_string(), "node".to_string(), "node server.js".to_string()],
+            vec!["123456".to_string(), "redis-server".to_string(), "".to_string()],
+        ];
+        assert_eq!(
+            text_table(&["PID", "Name", "Command"], &rows),
+            "PID     Name          Command\n\
+             ------  ------------  --------------\n\
+             7       node          node server.js\n\
+             123456  redis-server\n",
+        );
+    }
+    
+    #[test]
+    fn view_snapshot_lines_up_with_its_header() {
+        let (app, _dir) = test_app(
+            "{}",
+            &[listener("node", 7, 3000), listener("redis-server", 123456, 6379), listener("vite", 4242, 5173)],
+        );
+        let snapshot = view_snapshot(&app, "2024-06-01T14:32:05Z");
+        let mut lines = snapshot.lines();
+        assert_eq!(lines.next(), Some("port-manager listeners - 3 process(es) via lsof (captured) at 2024-06-01T14:32:05Z"));
+        assert_eq!(lines.next(), Some(""));
+        
+        let header = lines.next().unwrap();
+        let name_at = header.find("Process Name").unwrap();
+        let port_at = header.find("Port").unwrap();
+        let rows: Vec<&str> = lines.skip(1).collect();
+        assert_eq!(rows.len(), 3);
+        for (row, (name, port)) in rows.iter().zip([("node", "3000"), ("vite", "5173"), ("redis-server", "6379")]) {
+            assert!(row[name_at..].starts_with(name), "{:?}", row);
+            assert!(row[port_at..].starts_with(port), "{:?}", row);
+        }
+    }
+    
+    #[test]
+    fn ignored_pids_are_hidden_for_the_session() {
+        let (mut app, _dir) = test_app(
+            r#"{ "filtered_pids": [[5000, 5010]] }"#,
+            &[listener("node", 4242, 3000), listener("vite", 5005, 5173), listener("java", 4343, 8080)],
+        );
+        let pids = |app: &App| app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>();
+        assert_eq!(pids(&app), [4242, 4343]);
+        
+        select(&mut app, 4242);
+        app.ignore_selected_pid();
+        assert_eq!(pids(&app), [4343]);
+        // Still ignored after a refresh, but not saved
+        app.refresh_processes().unwrap();
+        assert_eq!(pids(&app), [4343]);
+        assert!(!app.config.reload().unwrap().is_pid_filtered(4242));
+        
+        app.clear_ignored_pids();
+        assert_eq!(pids(&app), [4242, 4343]);
+    }
+    
+    #[test]
+    fn ports_are_repeated_or_comma_separated() {
+        let ports = |flags: &[&str]| Args::try_parse_from(["port-manager"].iter().chain(flags)).map(|args| args.port);
+        assert_eq!(ports(&[]).unwrap(), Vec::<u16>::new());
+        assert_eq!(ports(&["--port", "3000"]).unwrap(), [3000]);
+        assert_eq!(ports(&["--port", "3000", "--port", "8080"]).unwrap(), [3000, 8080]);
+        assert_eq!(ports(&["--port", "3000,8080"]).unwrap(), [3000, 8080]);
+        assert_eq!(ports(&["-p", "22,3000", "-p", "8080"]).unwrap(), [22, 3000, 8080]);
+        assert!(ports(&["--port", "3000,http"]).is_err());
+        assert!(ports(&["--port", "70000"]).is_err());
+        assert!(ports(&["--port", "3000", "--range", "3000-3100"]).is_err());
+    }
+    
+    #[test]
+    fn any_listed_port_is_shown() {
+        let rows = [listener("sshd", 700, 22), listener("node", 4242, 3000), listener("java", 4343, 8080)];
+        let (app, _dir) = test_app_with_args("{}", &rows, &["--port", "22,8080"]);
+        assert_eq!(app.port_processes.iter().map(|p| p.port).collect::<Vec<_>>(), [22, 8080]);
+        assert!(render(&app, 240, 20).iter().any(|row| row.contains("on ports 22, 8080")));
+        
+        let (app, _dir) = test_app_with_args("{}", &rows, &["--port", "3000"]);
+        assert_eq!(app.port_processes.iter().map(|p| p.port).collect::<Vec<_>>(), [3000]);
+        assert!(render(&app, 240, 20).iter().any(|row| row.contains("on port 3000")));
+        
+        let (app, _dir) = test_app("{}", &rows);
+        assert_eq!(app.port_processes.iter().map(|p| p.port).collect::<Vec<_>>(), [3000, 8080]);
+    }
+    
+    #[test]
+    fn since_shows_only_recently_started_processes() {
+        let rows = [listener("old", 4242, 3000), listener("new", 4343, 3001), listener("unknown", 4444, 3002)];
+        let (mut app, _dir) = test_app_with_args("{}", &rows, &["--since", "5m"]);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        for process in &mut app.discovered {
+            process.start_time = match process.name.as_str() {
+                "old" => Some(now - 60 * 60),
+                "new" => Some(now - 60),
+                _ => None,
+            };
+        }
+        app.apply_view();
+        assert_eq!(app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>(), [4343]);
+        assert!(render(&app, 240, 20).iter().any(|row| row.contains("(started within 5m)")));
+    }
+    
+    /// A captured lsof row for a connection from `remote_port` on the same machine
+    fn connection(name: &str, pid: u32, port: u16, remote_port: u16, state: &str) -> String {
+        format!("{} {} alice 21u IPv4 124 0t0 TCP 127.0.0.1:{}->127.0.0.1:{} ({})", name, pid, port, remote_port, state)
+    }
+    
+    #[test]
+    fn sockets_filter_by_state() {
+        let rows = [
+            listener("node", 4242, 3000),
+            connection("node", 4242, 3000, 52114, "ESTABLISHED"),
+            connection("node", 4242, 3000, 52115, "TIME_WAIT"),
+            connection("java", 4343, 8080, 49822, "CLOSE_WAIT"),
+            connection("java", 4343, 8080, 49823, "TIME_WAIT"),
+        ];
+        let remotes = |app: &App| app.port_processes.iter().filter_map(|p| p.remote.clone()).collect::<Vec<_>>();
+        
+        // A state other than LISTEN switches to connections
+        let (mut app, _dir) = test_app_with_args("{}", &rows, &["--state", "time-wait"]);
+        assert!(app.discovery_mode == DiscoveryMode::Connections);
+        assert_eq!(remotes(&app), ["127.0.0.1:52115", "127.0.0.1:49823"]);
+        assert!(app.port_processes.iter().all(|p| p.state == TcpState::TimeWait));
+        
+        // The TUI steps through the states found, then shows them all
+        app.state_filter = None;
+        app.cycle_state_filter();
+        assert_eq!(app.state_filter, Some(TcpState::Established));
+        assert_eq!(remotes(&app), ["127.0.0.1:52114"]);
+        app.cycle_state_filter();
+        assert_eq!(app.state_filter, Some(TcpState::TimeWait));
+        app.cycle_state_filter();
+        assert_eq!(app.state_filter, Some(TcpState::CloseWait));
+        assert_eq!(remotes(&app), ["127.0.0.1:49822"]);
+        app.cycle_state_filter();
+        assert_eq!(app.state_filter, None);
+        assert_eq!(app.port_processes.len(), 4);
+        
+        let (app, _dir) = test_app_with_args("{}", &rows, &["--state", "listen"]);
+        assert!(app.discovery_mode == DiscoveryMode::Listeners);
+        assert_eq!(app.port_processes.len(), 1);
+    }
+    
+    #[test]
+    fn captured_lsof_output_is_read_from_a_file() {
+        let (backend, dir) = captured(&[
+            listener("node", 4242, 3000),
+            connection("node", 4242, 3000, 52114, "ESTABLISHED"),
+            "node 4242 alice 22u IPv4 125 0t0 UDP *:5353".to_string(),
+            // From another machine, so there is no such local PID
+            listener("postgres", 999_999, 5432),
+        ]);
+        let mut system = System::new();
+        let listeners = get_port_processes(&backend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop).unwrap();
+        assert_eq!(listeners.iter().map(|p| (p.pid, p.port)).collect::<Vec<_>>(), [(4242, 3000), (999_999, 5432)]);
+        // Nothing is looked up locally, so the capture's own user is kept
+        assert!(listeners.iter().all(|p| p.user == "alice" && !p.exited));
+        
+        let connections = get_port_processes(&backend, DiscoveryMode::Connections, &mut system, ExitedProcesses::Drop).unwrap();
+        assert_eq!(connections.iter().map(|p| p.remote.as_deref()).collect::<Vec<_>>(), [Some("127.0.0.1:52114")]);
+        assert_eq!(backend.connection_counts().unwrap(), HashMap::from([(3000, 1)]));
+        assert!(!backend.raw_output(DiscoveryMode::Listeners).unwrap().contains("UDP"));
+        
+        // The file is read again on every refresh
+        write_lsof(&dir, &[listener("vite", 4343, 5173)]);
+        let listeners = backend.discover(DiscoveryMode::Listeners, &system).unwrap();
+        assert_eq!(listeners.iter().map(|p| p.port).collect::<Vec<_>>(), [5173]);
+    }
+    
+    #[test]
+    fn missing_captured_output_names_the_file() {
+        let dir = test_dir();
+        let path = dir.0.join("missing.txt");
+        let backend = CapturedLsofBackend::new(path.clone()).unwrap();
+        let Err(error) = backend.discover(DiscoveryMode::Listeners, &System::new()) else {
+            panic!("read a missing file");
+        };
+        assert!(error.to_string().starts_with(&format!("Failed to read lsof output from {}: ", path.display())));
+    }
+    
+    #[test]
+    fn snoozed_filters_reactivate_after_expiry() {
+        let (mut app, _dir) = test_app(
+            r#"{ "filtered_process_names": ["node", "java"], "snooze_minutes": 5 }"#,
+            &[listener("node", 4242, 3000), listener("java", 4343, 8080), listener("vite", 4444, 5173)],
+        );
+        let names = |app: &App| app.port_processes.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+        app.current_view = View::FilterManagement;
+        app.filter_selected_idx = Some(0);
+        app.toggle_snooze_selected_filter();
+        assert_eq!(app.status_message.as_deref(), Some("Snoozed \"node\" for 5 minute(s)"));
+        assert_eq!(names(&app), ["node", "vite"]);
+        
+        // Still snoozed on the next refresh, until the time is up
+        app.refresh_processes().unwrap();
+        assert_eq!(names(&app), ["node", "vite"]);
+        app.snoozed_filters.insert("node".to_string(), Some(Instant::now()));
+        app.refresh_processes().unwrap();
+        assert_eq!(names(&app), ["vite"]);
+        assert_eq!(app.status_message.as_deref(), Some("Snooze over, filtering again: node"));
+        assert!(app.snoozed_filters.is_empty());
+        // The filter itself was never removed
+        assert_eq!(app.config.filtered_process_names.len(), 2);
+    }
+    
+    #[test]
+    fn snoozes_without_a_time_limit_last_until_woken() {
+        let (mut app, _dir) = test_app(
+            r#"{ "filtered_process_names": ["node"], "snooze_minutes": 0 }"#,
+            &[listener("node", 4242, 3000)],
+        );
+        app.current_view = View::FilterManagement;
+        app.filter_selected_idx = Some(0);
+        app.toggle_snooze_selected_filter();
+        assert_eq!(app.status_message.as_deref(), Some("Snoozed \"node\" until restart"));
+        app.refresh_processes().unwrap();
+        assert_eq!(app.port_processes.len(), 1);
+        
+        app.toggle_snooze_selected_filter();
+        assert_eq!(app.status_message.as_deref(), Some("Filter \"node\" is active again"));
+        assert!(app.port_processes.is_empty());
+    }
+    
+    #[test]
+    fn terminals_without_raw_mode_get_the_list() {
+        let (mut app, _dir) = test_app("{}", &[listener("node", 4242, 3000), listener("java", 4343, 8080)]);
+        let mut out = Vec::new();
+        let unsupported = || Err(io::Error::new(io::ErrorKind::Unsupported, "not a terminal"));
+        assert!(!enter_raw_mode_or_list(&mut app, unsupported, &mut out).unwrap());
+        
+        let mut expected = Vec::new();
+        let options = WriteOptions { binary_units: app.config.binary_units, hide_commands: false };
+        write_processes(&mut expected, OutputFormat::List, &app.port_processes, &options).unwrap();
+        let printed = String::from_utf8(out).unwrap();
+        assert_eq!(printed, String::from_utf8(expected).unwrap());
+        assert!(printed.contains("node") && printed.contains("java"));
+        
+        let mut out = Vec::new();
+        assert!(enter_raw_mode_or_list(&mut app, || Ok(()), &mut out).unwrap());
+        assert!(out.is_empty());
+    }
+    
+    #[test]
+    fn shutdown_requests_restore_the_terminal() {
+        let (mut app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(120, 30)).unwrap();
+        // Set as the signal handlers would on SIGINT, SIGTERM or SIGHUP
+        let shutdown = Arc::new(AtomicBool::new(true));
+        let mut restored = false;
+        run_tui(&mut terminal, &mut app, &shutdown, || {
+            restored = true;
+            Ok(())
+        }).unwrap();
+        assert!(restored);
+        
+        // Restoring still happens when the app fails, and its error wins
+        let (mut app, dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        fs::remove_file(dir.0.join("lsof.txt")).unwrap();
+        let mut restored = false;
+        let result = run_tui(&mut terminal, &mut app, &shutdown, || {
+            restored = true;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert!(restored);
+    }
+    
+    #[test]
+    fn the_title_says_only_tcp_is_shown() {
+        let (app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        assert!(render(&app, 120, 30).iter().any(|row| row.contains("listening on TCP ports (UDP isn't shown)")));
+    }
+    
+    #[test]
+    fn switching_profiles_changes_the_active_filters() {
+        let (mut app, dir) = test_app("{}", &[listener("node", 4242, 3000), listener("java", 4343, 8080)]);
+        let config_dir = dir.0.join("profiles-root");
+        fs::create_dir_all(config_dir.join("profiles")).unwrap();
+        fs::write(config_dir.join("config.json"), r#"{ "filtered_process_names": ["node"] }"#).unwrap();
+        fs::write(config_dir.join("profiles").join("backend.json"), r#"{ "filtered_process_names": ["java"] }"#).unwrap();
+        app.config_dir = Some(config_dir);
+        
+        app.open_profile_popup().unwrap();
+        assert_eq!(app.profile_names, ["default", "backend"]);
+        app.next_profile();
+        app.switch_to_selected_profile().unwrap();
+        assert!(!app.show_profile_popup);
+        assert_eq!(app.config.profile(), "backend");
+        assert_eq!(app.port_processes.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["node"]);
+        
+        app.open_profile_popup().unwrap();
+        assert_eq!(app.profile_selected_idx, 1);
+        app.previous_profile();
+        app.switch_to_selected_profile().unwrap();
+        assert_eq!(app.config.profile(), DEFAULT_PROFILE);
+        assert_eq!(app.port_processes.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["java"]);
+    }
+    
+    #[test]
+    fn details_show_socket_queues_when_known() {
+        let (mut app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        app.toggle_details();
+        assert!(render(&app, 120, 40).iter().any(|row| row.contains("Queues:  -")));
+        
+        app.port_processes[0].recv_queue = Some(3);
+        app.port_processes[0].send_queue = Some(0);
+        assert!(render(&app, 120, 40).iter().any(|row| row.contains("Queues:  Recv-Q 3, Send-Q 0")));
+    }
+    
+    #[test]
+    fn startup_warns_about_configs_from_other_machines() {
+        let (app, _dir) = test_app(r#"{ "hostname": "ci-runner-7", "warn_on_host_mismatch": true }"#, &[]);
+        let current = config::current_hostname().unwrap();
+        assert_eq!(
+            startup_message(&app.config),
+            Some(format!("Config was created on ci-runner-7, not this machine ({}); check its port range and filters", current)),
+        );
+        let (app, _dir) = test_app(r#"{ "hostname": "ci-runner-7" }"#, &[]);
+        assert_eq!(startup_message(&app.config), None);
+    }
+    
+    #[test]
+    fn unresolved_ports_are_shown_only_when_asked() {
+        let rows = [
+            listener_at("node", 4242, AddressFamily::Ipv4, "*:no-such-service"),
+            listener("java", 4343, 8080),
+        ];
+        let (app, _dir) = test_app("{}", &rows);
+        assert_eq!(app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>(), [4343]);
+        
+        let (app, _dir) = test_app(r#"{ "show_unresolved_ports": true }"#, &rows);
+        assert_eq!(app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>(), [4242, 4343]);
+        assert!(view_snapshot(&app, "now").lines().any(|row| row.contains("4242") && row.contains(" ? ")));
+    }
+    
+    #[test]
+    fn cpu_spikes_need_a_rise_of_the_threshold_since_the_last_refresh() {
+        let rows = [listener("node", 4242, 3000), listener("java", 4343, 8080)];
+        let (mut app, _dir) = test_app(r#"{ "cpu_spike_threshold": 30 }"#, &rows);
+        app.discovered[0].cpu_usage = 35.0;
+        app.discovered[1].cpu_usage = 25.0;
+        app.detect_cpu_spikes();
+        assert_eq!(app.cpu_spikes, HashSet::from([4242]));
+        select(&mut app, 4343);
+        assert_eq!(color_of(&render_buffer(&app, 120, 20), "node"), Some(Color::Red));
+        
+        app.discovered[0].cpu_usage = 60.0;
+        app.detect_cpu_spikes();
+        assert!(app.cpu_spikes.is_empty());
+        
+        app.discovered[1].pid = 4444;
+        app.discovered[1].cpu_usage = 99.0;
+        app.detect_cpu_spikes();
+        assert!(app.cpu_spikes.is_empty());
+        
+        let (mut app, _dir) = test_app(r#"{ "cpu_spike_threshold": 0 }"#, &rows);
+        app.discovered[0].cpu_usage = 100.0;
+        app.detect_cpu_spikes();
+        assert!(app.cpu_spikes.is_empty());
+    }
+    
+    #[test]
+    fn details_show_the_enrichment_for_the_selected_process() {
+        let config = r#"{ "enrichment": { "label": "Pod", "command": "cat", "timeout_ms": 1000 } }"#;
+        let (mut app, _dir) = test_app(config, &[listener("node", 4242, 3000), listener("java", 4343, 8080)]);
+        app.toggle_details();
+        // The captured backend isn't this machine, so nothing runs for it
+        assert_eq!(app.enrichment_target, None);
+        assert!(!render(&app, 120, 60).iter().any(|row| row.contains("Pod:")));
+        
+        app.enrichment_target = Some((4242, 3000));
+        assert!(render(&app, 120, 60).iter().any(|row| row.contains("Pod: running…")));
+        
+        app.enrichment_sender.send(((4343, 8080), Ok("stale".to_string()))).unwrap();
+        assert!(!app.collect_enrichment());
+        app.enrichment_sender.send(((4242, 3000), Ok("web-7d9f".to_string()))).unwrap();
+        assert!(app.collect_enrichment());
+        assert!(render(&app, 120, 60).iter().any(|row| row.contains("Pod: web-7d9f")));
+        
+        app.enrichment = Some(Err("sh failed: timed out after 1s".to_string()));
+        assert!(render(&app, 120, 60).iter().any(|row| row.contains("Pod: unavailable (sh failed: timed out after 1s)")));
+    }
+    
+    /// Serves captured rows like `CapturedLsofBackend`, recording signals
+    /// instead of sending them
+    struct RecordingBackend {
+        captured: CapturedLsofBackend,
+        signalled: Arc<Mutex<Vec<u32>>>,
+        /// Whether signalled processes stop showing up as listeners
+        exit_on_signal: bool,
+        /// PIDs that can't be signalled, as if owned by another user
+        refused: Vec<u32>,
+    }
+    
+    impl RecordingBackend {
+        /// Serve `rows`, with every process still listening after a signal
+        fn new(rows: &[String]) -> (Self, TestDir) {
+            let (captured, dir) = captured(rows);
+            (Self { captured, signalled: Arc::default(), exit_on_signal: false, refused: Vec::new() }, dir)
+        }
+        
+        /// Stop showing signalled processes, as if they exited
+        fn exiting(self) -> Self {
+            Self { exit_on_signal: true, ..self }
+        }
+        
+        /// PIDs signalled so far, in order
+        fn signalled(&self) -> Vec<u32> {
+            self.signalled.lock().unwrap().clone()
+        }
+    }
+    
+    impl Backend for RecordingBackend {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+        
+        fn is_available(&self) -> bool {
+            true
+        }
+        
+        fn discover(&self, mode: DiscoveryMode, system: &System) -> error::Result<Vec<PortProcess>> {
+            let signalled = self.signalled.lock().unwrap();
+            let mut processes = self.captured.discover(mode, system)?;
+            processes.retain(|p| !self.exit_on_signal || !signalled.contains(&p.pid));
+            Ok(processes)
+        }
+        
+        fn connection_counts(&self) -> error::Result<HashMap<u16, u64>> {
+            Ok(HashMap::new())
+        }
+        
+        fn raw_output(&self, mode: DiscoveryMode) -> error::Result<String> {
+            self.captured.raw_output(mode)
+        }
+        
+        fn is_local(&self) -> bool {
+            false
+        }
+        
+        fn send_signal(&self, pid: u32, signal: &str) -> error::Result<()> {
+            if self.refused.contains(&pid) {
+                return Err(error::PortManagerError::PermissionDenied { pid, signal: signal.to_string() });
+            }
+            self.signalled.lock().unwrap().push(pid);
+            Ok(())
+        }
+    }
+    
+    /// Replace an app's backend with one recording the PIDs it's asked to kill
+    fn record_kills(app: &mut App, rows: &[String]) -> (Arc<Mutex<Vec<u32>>>, TestDir) {
+        let (backend, dir) = RecordingBackend::new(rows);
+        let signalled = backend.signalled.clone();
+        app.backend = Box::new(backend);
+        (signalled, dir)
+    }
+    
+    const CLEAN_SLATE: &str = r#"{
+        "min_port": 1,
+        "keep_processes": ["postgres", "redis"],
+        "port_policies": [{ "ports": [9000, 9000], "policy": "protected" }]
+    }"#;
+    
+    fn clean_slate_rows() -> Vec<String> {
+        vec![
+            listener("node", 4242, 3000),
+            listener("postgres", 4343, 5432),
+            listener("redis", 4444, 6379),
+            listener("java", 4545, 8080),
+            listener("minio", 4646, 9000),
+            "sshd 4747 root 3u IPv4 123 0t0 TCP *:22 (LISTEN)".to_string(),
+        ]
+    }
+    
+    #[test]
+    fn clean_slate_kills_all_but_the_keep_list_once_typed() {
+        let rows = clean_slate_rows();
+        let (mut app, _dir) = test_app(CLEAN_SLATE, &rows);
+        let (signalled, _backend_dir) = record_kills(&mut app, &rows);
+        app.request_clean_slate();
+        assert!(app.show_kill_confirm);
+        assert_eq!(app.kill_targets, [4242, 4545]);
+        let mut kept = app.kill_kept.clone();
+        kept.sort();
+        assert_eq!(kept, [4343, 4444, 4646, 4747]);
+        
+        let screen = render(&app, 120, 60);
+        assert!(screen.iter().any(|row| row.contains("Clean Slate: Kill 2 Process(es)?")));
+        assert!(screen.iter().any(|row| row.contains("Kept (4):")));
+        assert!(screen.iter().any(|row| row.contains("redis :6379 (keep list)")));
+        assert!(screen.iter().any(|row| row.contains("sshd :22 (protected)")));
+        
+        // Neither "yes" nor the count will do
+        for input in ["yes", "2"] {
+            app.kill_confirm_input = input.to_string();
+            app.confirm_kill().unwrap();
+            assert!(app.show_kill_confirm);
+        }
+        assert!(signalled.lock().unwrap().is_empty());
+        
+        app.kill_confirm_input = CLEAN_SLATE_CONFIRMATION.to_string();
+        app.confirm_kill().unwrap();
+        assert!(!app.show_kill_confirm);
+        assert_eq!(*signalled.lock().unwrap(), [4242, 4545]);
+    }
+    
+    #[test]
+    fn clean_slate_respects_dry_run() {
+        let rows = clean_slate_rows();
+        let (mut app, _dir) = test_app_with_args(CLEAN_SLATE, &rows, &["--dry-run"]);
+        let (signalled, _backend_dir) = record_kills(&mut app, &rows);
+        app.request_clean_slate();
+        app.kill_confirm_input = CLEAN_SLATE_CONFIRMATION.to_string();
+        app.confirm_kill().unwrap();
+        assert!(signalled.lock().unwrap().is_empty());
+        assert_eq!(app.status_message.as_deref(), Some("Dry run: would send SIGKILL to node (4242), java (4545)"));
+    }
+    
+    #[test]
+    fn clean_slate_with_everything_kept_kills_nothing() {
+        let rows = [listener("postgres", 4343, 5432), listener("redis", 4444, 6379)];
+        let (mut app, _dir) = test_app(CLEAN_SLATE, &rows);
+        app.request_clean_slate();
+        assert!(!app.show_kill_confirm);
+        assert_eq!(app.status_message.as_deref(), Some("Nothing to kill: all 2 visible process(es) are protected or kept"));
+    }
+    
+    #[test]
+    fn thread_counts_come_from_the_backend_and_sort() {
+        let dir = test_dir();
+        let config_path = dir.0.join("config.json");
+        let config = r#"{ "min_port": 1, "filtered_process_names": [], "show_threads": true, "sort_column": "threads", "sort_descending": true }"#;
+        fs::write(&config_path, config).unwrap();
+        let args = Args::parse_from(["port-manager", "--config", config_path.to_str().unwrap(), "--backend", "demo"]);
+        let mut app = App::new(&args).unwrap();
+        app.refresh_processes().unwrap();
+        let threads: Vec<usize> = app.port_processes.iter().map(|p| p.threads.unwrap()).collect();
+        assert!(threads.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!(threads[0] > 1);
+        let screen = render(&app, 140, 30);
+        assert!(screen.iter().any(|row| row.contains("Threads")));
+        assert!(screen.iter().any(|row| row.contains(&format!(" {} ", threads[0]))));
+        
+        // Captured rows have no thread counts to read
+        let (app, _dir) = test_app(r#"{ "show_threads": true }"#, &[listener("node", 4242, 3000)]);
+        assert_eq!(app.port_processes[0].threads, None);
+        assert_eq!(thread_label(&app.port_processes[0]), "-");
+    }
+    
+    #[test]
+    fn row_window_pages_to_the_selection() {
+        assert_eq!(row_window(5, Some(3), None), 0..5);
+        assert_eq!(row_window(5, Some(3), Some(0)), 0..5);
+        assert_eq!(row_window(5, Some(3), Some(5)), 0..5);
+        assert_eq!(row_window(5, None, Some(2)), 0..2);
+        assert_eq!(row_window(5, Some(3), Some(2)), 2..4);
+        assert_eq!(row_window(5, Some(4), Some(2)), 4..5);
+    }
+    
+    #[test]
+    fn rows_over_the_cap_are_counted_below_the_list() {
+        let rows: Vec<String> = (0..5).map(|i| listener(&format!("svc{}", i), 4000 + i, 3000 + i as u16)).collect();
+        let (mut app, _dir) = test_app(r#"{ "max_rows": 2 }"#, &rows);
+        let screen = render(&app, 120, 30);
+        assert!(screen.iter().any(|row| row.contains("… 3 more")));
+        assert!(screen.iter().any(|row| row.contains("svc1")));
+        assert!(!screen.iter().any(|row| row.contains("svc2")));
+        
+        // Paging down shows the rest
+        select(&mut app, 4004);
+        let screen = render(&app, 120, 30);
+        assert!(screen.iter().any(|row| row.contains("svc4")));
+        assert!(screen.iter().any(|row| row.contains("… 4 more")));
+        
+        // Narrowing below the cap drops the indicator
+        assert_eq!(search(&mut app, "svc3"), [4003]);
+        assert!(!render(&app, 120, 30).iter().any(|row| row.contains("more")));
+    }
+    
+    #[test]
+    fn pid_on_prints_each_owner_once() {
+        let rows = [
+            listener("node", 4242, 3000),
+            listener_at("node", 4242, AddressFamily::Ipv6, "*:3000"),
+            listener("nginx", 4343, 8080),
+            listener("nginx", 4344, 8080),
+            connection("curl", 4545, 40000, 3000, "ESTABLISHED"),
+        ];
+        let (backend, _dir) = captured(&rows);
+        let mut out = Vec::new();
+        assert!(run_pid_on(&backend, 3000, false, &mut out).unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), "4242\n");
+        
+        let mut out = Vec::new();
+        assert!(run_pid_on(&backend, 8080, false, &mut out).unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), "4343\n4344\n");
+        
+        let mut out = Vec::new();
+        assert!(run_pid_on(&backend, 8080, true, &mut out).unwrap());
+        assert!(out.is_empty());
+        
+        let mut out = Vec::new();
+        assert!(!run_pid_on(&backend, 9999, false, &mut out).unwrap());
+        assert!(out.is_empty());
+    }
+    
+    #[test]
+    fn port_sort_toggles_in_place_keeping_the_selection() {
+        let rows = [listener("node", 4242, 3000), listener("java", 4343, 8080), listener("postgres", 4444, 5432)];
+        let (mut app, dir) = test_app(r#"{ "min_port": 1, "sort_column": "name" }"#, &rows);
+        select(&mut app, 4444);
+        // Rows appearing since the last refresh aren't discovered by the toggle
+        write_lsof(&dir, &[listener("redis", 4545, 6379)]);
+        let ports = |app: &App| app.port_processes.iter().map(|p| p.port).collect::<Vec<_>>();
+        
+        app.toggle_port_sort().unwrap();
+        assert_eq!(ports(&app), [8080, 5432, 3000]);
+        assert_eq!(app.selected_process().map(|p| p.pid), Some(4444));
+        assert!(render(&app, 120, 20).iter().any(|row| row.contains("Port ▼")));
+        assert_eq!(app.status_message.as_deref(), Some("Sorted by port, highest first"));
+        
+        app.toggle_port_sort().unwrap();
+        assert_eq!(ports(&app), [3000, 5432, 8080]);
+        assert_eq!(app.selected_process().map(|p| p.pid), Some(4444));
+        assert!(render(&app, 120, 20).iter().any(|row| row.contains("Port ▲")));
+    }
+    
+    #[test]
+    fn management_view_flags_invalid_command_regexes() {
+        let (mut app, _dir) = test_app(r#"{ "filter_rules": [{ "command": "--port (300" }, { "command": "^node" }] }"#, &[]);
+        app.current_view = View::FilterManagement;
+        let screen = render(&app, 120, 30);
+        assert!(screen.iter().any(|row| row.contains("rule: command --port (300 (invalid regex, never matches)")));
+        assert!(!screen.iter().any(|row| row.contains("^node")));
+    }
+    
+    /// A local backend that finds a listener on port 5000 no process could be
+    /// resolved for, as fuser does when the owner is gone
+    struct OrphanBackend;
+    
+    impl Backend for OrphanBackend {
+        fn name(&self) -> &'static str {
+            "orphan"
+        }
+        
+        fn is_available(&self) -> bool {
+            true
+        }
+        
+        fn discover(&self, _mode: DiscoveryMode, _system: &System) -> error::Result<Vec<PortProcess>> {
+            Ok(vec![
+                // PID 1 outlives the test
+                PortProcess::without_lookups(1, "init".to_string(), 3000, "*".to_string(), AddressFamily::Ipv4, None),
+                PortProcess::orphaned(5000, "*".to_string(), AddressFamily::Ipv4),
+            ])
+        }
+        
+        fn connection_counts(&self) -> error::Result<HashMap<u16, u64>> {
+            Ok(HashMap::new())
+        }
+        
+        fn raw_output(&self, _mode: DiscoveryMode) -> error::Result<String> {
+            Ok(String::new())
+        }
+    }
+    
+    #[test]
+    fn unresolvable_owners_are_shown_as_orphaned() {
+        let mut system = System::new();
+        let found = get_port_processes(&OrphanBackend, DiscoveryMode::Listeners, &mut system, ExitedProcesses::Drop).unwrap();
+        assert_eq!(found.iter().map(|p| (p.port, p.orphaned, p.exited)).collect::<Vec<_>>(), [(3000, false, false), (5000, true, false)]);
+        
+        let (mut app, _dir) = test_app(r#"{ "confirm_kill": false }"#, &[]);
+        app.backend = Box::new(OrphanBackend);
+        app.refresh_processes().unwrap();
+        let buffer = render_buffer(&app, 120, 20);
+        assert_eq!(color_of(&buffer, "(orphaned)"), Some(Color::Red));
+        
+        select(&mut app, 0);
+        app.toggle_details();
+        let screen = render(&app, 200, 60);
+        assert!(screen.iter().any(|row| row.contains("PID:     none")));
+        assert!(screen.iter().any(|row| row.contains("No process owns this socket, so a kill can't free it.")));
+        
+        app.toggle_details();
+        let Err(e) = app.request_kill() else {
+            panic!("killing an orphaned socket should fail");
+        };
+        assert!(e.to_string().starts_with("No process owns this socket"));
+        assert!(app.port_processes.iter().any(|p| p.orphaned));
+    }
+    
+    #[test]
+    fn reordered_columns_reorder_the_header() {
+        let (app, _dir) = test_app(r#"{ "columns": ["name", "port", "pid", "bogus", "command"] }"#, &[listener("node", 4242, 3000)]);
+        let screen = render(&app, 120, 20);
+        let header = screen.iter().find(|row| row.contains("Process Name")).unwrap();
+        let at = |label: &str| header.find(label).unwrap();
+        assert!(at("Process Name") < at("Port") && at("Port") < at("PID") && at("PID") < at("Command"));
+        assert!(!header.contains("CPU %") && !header.contains("Memory"));
+        let row = screen.iter().find(|row| row.contains("node")).unwrap();
+        assert!(row.find("node").unwrap() < row.find("3000").unwrap() && row.find("3000").unwrap() < row.find("4242").unwrap());
+        assert_eq!(startup_message(&app.config).as_deref(), Some("Skipped unknown column(s) in config: bogus"));
+    }
+    
+    #[test]
+    fn waiting_notifies_only_when_the_port_changed() {
+        let timeout = Some(Duration::from_secs(10));
+        let notified = std::cell::RefCell::new(Vec::new());
+        let record = |message: &str| notified.borrow_mut().push(message.to_string());
+        
+        let backend = FlippingBackend::new(false, 1);
+        assert!(run_wait(&backend, 3000, false, timeout, true, Some(&record)).unwrap());
+        let backend = FlippingBackend::new(true, 1);
+        assert!(run_wait(&backend, 3000, true, timeout, true, Some(&record)).unwrap());
+        assert_eq!(*notified.borrow(), ["Port 3000 is in use by init (PID 1)", "Port 3000 is free"]);
+        
+        // Already as wanted, so nothing changed
+        let backend = FlippingBackend::new(true, usize::MAX);
+        assert!(run_wait(&backend, 3000, false, timeout, true, Some(&record)).unwrap());
+        let backend = FlippingBackend::new(false, usize::MAX);
+        assert!(run_wait(&backend, 3000, true, timeout, true, Some(&record)).unwrap());
+        assert_eq!(notified.borrow().len(), 2);
+    }
+    
+    #[test]
+    fn mixed_sockets_are_summarized_in_groups_and_broken_down_in_details() {
+        let rows = [
+            listener("node", 4242, 3000),
+            listener_at("node", 4242, AddressFamily::Ipv6, "*:3000"),
+            listener_at("node", 4242, AddressFamily::Ipv6, "[::1]:9229"),
+            listener("java", 4343, 8080),
+        ];
+        let (mut app, _dir) = test_app(r#"{ "min_port": 1 }"#, &rows);
+        // Captures skip UDP sockets, so add one as a backend reporting it would
+        let mut mdns = PortProcess::without_lookups(4242, "node".to_string(), 5353, "*".to_string(), AddressFamily::Ipv4, None);
+        mdns.protocol = process::Protocol::Udp;
+        app.discovered.push(mdns.clone());
+        app.port_processes.insert(1, mdns);
+        select(&mut app, 4242);
+        assert_eq!(
+            socket_breakdown(&app, app.selected_process().unwrap()),
+            "3×TCP, 1×UDP, v4+v6: *:3000 TCP v4, *:3000 TCP v6, [::1]:9229 TCP v6, *:5353 UDP v4",
+        );
+        
+        app.toggle_group_by_address();
+        let screen = render(&app, 160, 30);
+        assert!(screen.iter().any(|row| row.contains("2 process(es), 3×TCP, 1×UDP, v4+v6")), "{:#?}", screen);
+        assert!(screen.iter().any(|row| row.contains("1 process(es), 1×TCP, v6")), "{:#?}", screen);
+    }
+    
+    #[test]
+    fn every_modal_holds_auto_refresh() {
+        let (mut app, _dir) = test_app(r#"{ "refresh_interval_ms": 1 }"#, &[listener("node", 1, 3000)]);
+        std::thread::sleep(Duration::from_millis(5));
+        let modals: [fn(&mut App) -> &mut bool; 13] = [
+            |app| &mut app.show_add_filter_popup,
+            |app| &mut app.show_kill_confirm,
+            |app| &mut app.show_details,
+            |app| &mut app.show_profile_popup,
+            |app| &mut app.show_action_popup,
+            |app| &mut app.show_config_popup,
+            |app| &mut app.show_stats_popup,
+            |app| &mut app.show_help_popup,
+            |app| &mut app.show_kill_results,
+            |app| &mut app.show_setup_popup,
+            |app| &mut app.search_active,
+            |app| &mut app.highlight_active,
+            |app| &mut app.show_cleanup_name_popup,
+        ];
+        for modal in modals {
+            assert!(app.auto_refresh_due());
+            *modal(&mut app) = true;
+            assert!(app.is_modal_open());
+            assert!(!app.auto_refresh_due());
+            *modal(&mut app) = false;
+        }
+        assert!(app.auto_refresh_due());
+        
+        app.toggle_refresh_paused();
+        assert!(!app.is_modal_open());
+        assert!(!app.auto_refresh_due());
+    }
+    
+    #[test]
+    fn paused_refresh_still_refreshes_on_demand() {
+        let (mut app, dir) = test_app(r#"{ "refresh_interval_ms": 1 }"#, &[listener("node", 4242, 3000)]);
+        app.toggle_refresh_paused();
+        assert!(app.refresh_paused);
+        assert!(status_footer(&app).contains("refresh PAUSED"));
+        
+        write_lsof(&dir, &[listener("node", 4242, 3000), listener("java", 4343, 8080)]);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!app.auto_refresh_due());
+        app.run_key_action(KeyAction::Refresh).unwrap();
+        assert_eq!(app.port_processes.len(), 2);
+        assert!(app.refresh_paused);
+        
+        app.toggle_refresh_paused();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(app.auto_refresh_due());
+    }
+    
+    #[test]
+    fn auto_refresh_is_off_by_default() {
+        let (mut app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!app.auto_refresh_due());
+        app.toggle_refresh_paused();
+        assert!(!app.refresh_paused);
+        assert_eq!(app.status_message.as_deref(), Some("Auto-refresh is off; set `refresh_interval_ms` in the config to turn it on"));
+    }
+    
+    #[test]
+    fn supervised_processes_can_be_restarted_instead() {
+        let (mut app, _dir) = test_app(r#"{ "confirm_kill": true }"#, &[listener("nginx", 4242, 8080)]);
+        select(&mut app, 4242);
+        app.request_kill().unwrap();
+        // Captured rows aren't on this machine, so no service is looked up
+        assert_eq!(app.kill_restart, None);
+        
+        app.kill_restart = Some(RestartCommand {
+            service: "nginx.service".to_string(),
+            program: "systemctl",
+            args: vec!["restart".to_string(), "nginx.service".to_string()],
+        });
+        let screen = render(&app, 140, 50);
+        assert!(screen.iter().any(|row| row.contains("r: Restart service")));
+        assert!(screen.iter().any(|row| row.contains("This runs under nginx.service")));
+        assert!(screen.iter().any(|row| row.contains("systemctl restart nginx.service")));
+        
+        app.restart_service().unwrap();
+        assert!(!app.show_kill_confirm);
+        assert_eq!(app.kill_restart, None);
+        assert_eq!(app.status_message.as_deref(), Some("Dry run: would run systemctl restart nginx.service"));
+    }
+    
+    #[test]
+    fn diagnostic_bundle_has_every_section() {
+        let config = r#"{ "enrichment": { "label": "Pod", "command": "kubectl --token s3cret get pod" } }"#;
+        let (mut app, _dir) = test_app(config, &[listener("node", 4242, 3000)]);
+        let bundle = diagnostic_bundle(&app, "2024-06-01T14:32:05Z");
+        let sections: Vec<&str> = bundle.lines().filter(|line| line.starts_with("== ")).collect();
+        assert_eq!(sections, ["== port-manager diagnostics ==", "== Config ==", "== View ==", "== Raw lsof (captured) output =="]);
+        assert!(bundle.contains(&format!("Version:   {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(bundle.contains("Generated: 2024-06-01T14:32:05Z\n"));
+        assert!(bundle.contains("Backend:   lsof (captured)\n"));
+        assert!(bundle.contains("port-manager listeners - 1 process(es) via lsof (captured) at 2024-06-01T14:32:05Z"));
+        assert!(bundle.contains(&listener("node", 4242, 3000)));
+        assert!(bundle.contains("s3cret"));
+        
+        // Hiding commands masks them in the config too
+        app.hide_commands = true;
+        let bundle = diagnostic_bundle(&app, "2024-06-01T14:32:05Z");
+        assert!(!bundle.contains("s3cret"));
+        assert!(bundle.contains(&format!("\"command\": \"{}\"", HIDDEN_COMMAND)));
+    }
+    
+    #[test]
+    fn ports_keep_when_they_were_first_seen_until_they_go_away() {
+        let (mut app, dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        let first = app.port_first_seen[&3000];
+        std::thread::sleep(Duration::from_millis(5));
+        
+        // A new owner doesn't make the port new
+        write_lsof(&dir, &[listener("node", 4343, 3000), listener("java", 4444, 8080)]);
+        app.refresh_processes().unwrap();
+        assert_eq!(app.port_first_seen[&3000], first);
+        assert!(app.port_first_seen[&8080] > first);
+        
+        write_lsof(&dir, &[listener("java", 4444, 8080)]);
+        app.refresh_processes().unwrap();
+        assert!(!app.port_first_seen.contains_key(&3000));
+        write_lsof(&dir, &[listener("node", 4242, 3000), listener("java", 4444, 8080)]);
+        app.refresh_processes().unwrap();
+        assert!(app.port_first_seen[&3000] > first);
+        
+        app.port_first_seen.insert(3000, Instant::now() - Duration::from_secs(125));
+        select(&mut app, 4242);
+        app.toggle_details();
+        assert!(render(&app, 120, 40).iter().any(|row| row.contains("Seen:    port first seen 2m 5s ago this session")));
+    }
+    
+    #[test]
+    fn inline_confirmation_asks_in_the_footer() {
+        let config = r#"{ "confirm_kill": true, "confirm_style": "inline", "bulk_kill_threshold": 2 }"#;
+        let rows = [listener("node", 4242, 3000), listener("java", 4343, 8080), listener("ruby", 4444, 4000)];
+        let (mut app, _dir) = test_app(config, &rows);
+        select(&mut app, 4242);
+        app.request_kill().unwrap();
+        assert!(app.inline_kill_confirm());
+        let buffer = render_buffer(&app, 120, 20);
+        assert_eq!(color_of(&buffer, "Kill node (PID 4242, :3000)? y/n"), Some(Color::Green));
+        let screen = render(&app, 120, 20);
+        // The list stays in view, with no popup over it
+        assert!(screen.iter().any(|row| row.contains("java")));
+        assert!(!screen.iter().any(|row| row.contains("Kill Process?") || row.contains("Command:")));
+        app.cancel_kill();
+        
+        app.marked_pids.extend([4242, 4343]);
+        app.request_kill().unwrap();
+        assert!(render(&app, 120, 20).iter().any(|row| row.contains("Kill 2 processes (node, java)? y/n")));
+        app.cancel_kill();
+        
+        // Typed confirmations still need the popup's input box
+        app.marked_pids.extend([4242, 4343, 4444]);
+        app.request_kill().unwrap();
+        assert!(app.show_kill_confirm && !app.inline_kill_confirm());
+        assert!(!render(&app, 120, 20).iter().any(|row| row.contains("y/n")));
+    }
+    
+    #[test]
+    fn container_and_host_processes_filter_apart() {
+        let rows = [listener("node", 4242, 3000), listener("postgres", 4343, 5432), listener("docker-proxy", 4444, 8080)];
+        let shown = |app: &mut App| {
+            app.discovered.iter_mut().filter(|p| p.pid == 4343).for_each(|p| p.container_id = Some("3f2a9c1d7b4e".to_string()));
+            app.apply_view();
+            app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>()
+        };
+        
+        let (mut app, _dir) = test_app_with_args("{}", &rows, &["--containers-only"]);
+        assert_eq!(shown(&mut app), [4343, 4444]);
+        assert!(render(&app, 120, 20).iter().any(|row| row.contains("(only containers)")));
+        let (mut app, _dir) = test_app_with_args("{}", &rows, &["--host-only"]);
+        assert_eq!(shown(&mut app), [4242]);
+        assert!(Args::try_parse_from(["port-manager", "--containers-only", "--host-only"]).is_err());
+        
+        let (mut app, _dir) = test_app("{}", &rows);
+        assert_eq!(shown(&mut app), [4242, 4343, 4444]);
+        app.cycle_placement_filter();
+        assert_eq!(shown(&mut app), [4343, 4444]);
+        app.cycle_placement_filter();
+        assert_eq!(shown(&mut app), [4242]);
+        assert_eq!(app.status_message.as_deref(), Some("Showing only host processes"));
+        app.cycle_placement_filter();
+        assert_eq!(shown(&mut app), [4242, 4343, 4444]);
+        let badges: Vec<String> = app.port_processes.iter().map(|p| row_indicators(p, false, false)).collect();
+        assert_eq!(badges, ["E", "EC", "EC"]);
+    }
+    
+    #[test]
+    fn new_listeners_stand_out_against_the_baseline() {
+        let (mut app, dir) = test_app("{}", &[listener("node", 4242, 3000), listener("java", 4343, 8080)]);
+        app.toggle_unexpected_only();
+        assert_eq!(app.status_message.as_deref(), Some("No baseline yet: press E to record the current listeners as expected"));
+        app.set_baseline().unwrap();
+        assert_eq!(app.config.baseline.len(), 2);
+        
+        write_lsof(&dir, &[listener("node", 4244, 3000), listener("java", 4343, 8080), listener("nc", 4545, 4444)]);
+        app.refresh_processes().unwrap();
+        let unexpected: Vec<u32> = app.port_processes.iter().filter(|p| app.is_unexpected(p)).map(|p| p.pid).collect();
+        assert_eq!(unexpected, [4545]);
+        assert!(render(&app, 120, 20).iter().any(|row| row.contains(" - 1 unexpected")));
+        
+        app.toggle_unexpected_only();
+        assert_eq!(app.port_processes.iter().map(|p| p.pid).collect::<Vec<_>>(), [4545]);
+        assert!(render(&app, 120, 20).iter().any(|row| row.contains("(only unexpected)")));
+    }
+    
+    #[test]
+    fn check_baseline_fails_on_unexpected_listeners() {
+        let (app, _dir) = test_app("{}", &[]);
+        assert!(run_check_baseline(&captured(&[]).0, &app.config, true).is_err());
+        
+        let (mut app, _dir) = test_app("{}", &[listener("node", 4242, 3000)]);
+        app.set_baseline().unwrap();
+        let (expected, _backend_dir) = captured(&[listener("node", 4243, 3000)]);
+        assert!(run_check_baseline(&expected, &app.config, true).unwrap());
+        let (unexpected, _backend_dir) = captured(&[listener("node", 4243, 3000), listener("nc", 4545, 4444)]);
+        assert!(!run_check_baseline(&unexpected, &app.config, true).unwrap());
+    }
+    
+    #[test]
+    fn auto_kill_frees_the_matching_ports() {
+        let (backend, _dir) = RecordingBackend::new(&[listener("node", 4242, 3000), listener("java", 4343, 8080), listener("postgres", 4444, 5432)]);
+        let backend = backend.exiting();
+        let mut config = Config::default();
+        config.port_policies = vec![config::PortPolicy { ports: [5432, 5432], policy: KillPolicy::Protected }];
+        let rules = AutoKillRules { ports: vec![3000], names: vec!["java".to_string()] };
+        let matches = rules.matcher(config.match_mode);
+        let mut options = KillOptions { dry_run: true, quiet: true, confirm: false, kill_connections: false, yes: false };
+        
+        run_auto_kill(&backend, &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options).unwrap();
+        assert!(backend.signalled().is_empty());
+        
+        options.dry_run = false;
+        run_auto_kill(&backend, &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options).unwrap();
+        assert_eq!(backend.signalled(), [4242, 4343]);
+        
+        // Nothing left to match once the ports are free
+        run_auto_kill(&backend, &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options).unwrap();
+        assert_eq!(backend.signalled().len(), 2);
+        
+        let rules = AutoKillRules { ports: vec![5432], names: Vec::new() };
+        let matches = rules.matcher(config.match_mode);
+        let error = run_auto_kill(&backend, &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options).unwrap_err();
+        assert_eq!(error.to_string(), "Refused to kill 1 listener(s) on protected ports");
+        assert_eq!(backend.signalled().len(), 2);
+    }
+    
+    #[test]
+    fn auto_kill_only_counts_ports_seen_free() {
+        let (backend, _dir) = RecordingBackend::new(&[listener("node", 4242, 3000), listener("java", 4343, 8080), listener("postgres", 4444, 5432)]);
+        let backend = RecordingBackend { refused: vec![4343], ..backend.exiting() };
+        let mut config = Config::default();
+        config.port_policies = vec![config::PortPolicy { ports: [5432, 5432], policy: KillPolicy::Protected }];
+        let rules = AutoKillRules { ports: vec![3000, 8080, 5432], names: Vec::new() };
+        let matches = rules.matcher(config.match_mode);
+        let options = KillOptions { dry_run: false, quiet: true, confirm: false, kill_connections: false, yes: false };
+        
+        let error = run_auto_kill(&backend, &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to free every port: 1 of 2 port(s) still in use, 1 listener(s) on protected ports skipped");
+        assert_eq!(backend.signalled(), [4242]);
+    }
+    
+    #[test]
+    fn stubborn_processes_get_sigkill_once() {
+        // node's ports aren't next to each other in the list
+        let (backend, _dir) = RecordingBackend::new(&[listener("node", 4242, 3000), listener("java", 4343, 8080), listener("node", 4242, 9000)]);
+        let mut config = Config::default();
+        config.kill_signal = "TERM".to_string();
+        config.kill_timeout_ms = 300;
+        let rules = AutoKillRules { ports: vec![3000, 8080, 9000], names: Vec::new() };
+        let matches = rules.matcher(config.match_mode);
+        let options = KillOptions { dry_run: false, quiet: true, confirm: false, kill_connections: false, yes: false };
+        
+        let error = run_auto_kill(&backend, &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to free every port: 3 of 3 port(s) still in use");
+        // SIGTERM to each, then SIGKILL to each
+        assert_eq!(backend.signalled(), [4242, 4343, 4242, 4343]);
+    }
+    
+    #[test]
+    fn kill_name_kills_only_matching_listeners() {
+        let (backend, _dir) = RecordingBackend::new(&[
+            listener("node", 4242, 3000),
+            listener("nodemon", 4343, 3001),
+            listener("java", 4444, 8080),
+            listener("python3", 4545, 8000),
+        ]);
+        let backend = backend.exiting();
+        let config = Config::default();
+        
+        // Broad by nature, so it asks first unless told not to
+        let args = Args::parse_from(["port-manager", "--kill-name", "node"]);
+        let (_, options) = cli_kill_rules(&args, &config);
+        assert!(options.confirm);
+        let args = Args::parse_from(["port-manager", "--kill-name", "node", "--kill-port", "8000", "--yes", "--quiet"]);
+        let (rules, options) = cli_kill_rules(&args, &config);
+        assert!(!options.confirm);
+        assert_eq!(rules.names, ["node"]);
+        assert_eq!(rules.ports, [8000]);
+        
+        let matches = rules.matcher(config.match_mode);
+        run_auto_kill(&backend, &config, "the auto-kill rules", |p| matches(p.port, &p.name), &options).unwrap();
+        assert_eq!(backend.signalled(), [4242, 4343, 4545]);
+    }
+    
+    #[test]
+    fn kill_stats_count_kills_locally_until_cleared() {
+        let rows = [listener("node", 4242, 3000), listener("node", 4242, 3001), listener("java", 4343, 8080)];
+        let (mut app, _dir) = test_app(r#"{ "confirm_kill": false }"#, &rows);
+        app.open_stats_popup();
+        assert!(!app.show_stats_popup);
+        assert_eq!(app.status_message.as_deref(), Some("Kill stats are off; set `kill_stats` in the config to count kills locally"));
+        
+        let (mut app, _dir) = test_app(r#"{ "confirm_kill": false, "kill_stats": true }"#, &rows);
+        let (_signalled, _backend_dir) = record_kills(&mut app, &rows);
+        for pid in [4242, 4343, 4242] {
+            select(&mut app, pid);
+            app.request_kill().unwrap();
+        }
+        let stats_path = KillStats::path(app.config.config_path());
+        assert!(stats_path.exists());
+        
+        app.open_stats_popup();
+        assert!(app.show_stats_popup);
+        assert_eq!(app.kill_stats.top_names(10), [("node", 2), ("java", 1)]);
+        assert_eq!(app.kill_stats.top_ports(10), [(3000, 2), (3001, 2), (8080, 1)]);
+        let screen = render(&app, 120, 40);
+        assert!(screen.iter().any(|row| row.contains("Kill Stats (local only, never sent anywhere)")));
+        assert!(screen.iter().any(|row| row.contains("Most-killed processes") && row.contains("Most-contested ports")));
+        
+        app.clear_kill_stats();
+        assert!(!stats_path.exists());
+        assert_eq!(app.status_message.as_deref(), Some("Cleared kill stats"));
+        assert!(render(&app, 120, 40).iter().any(|row| row.contains("No kills counted yet")));
+    }
+    
+    #[test]
+    fn armed_auto_kill_kills_matches_on_refresh_at_a_limited_rate() {
+        let rows = [listener("node", 4242, 3000), listener("node", 4343, 3001), listener("java", 4444, 8080)];
+        let (mut app, _dir) = test_app("{}", &rows);
+        let (signalled, _backend_dir) = record_kills(&mut app, &rows);
+        app.refresh_processes().unwrap();
+        assert!(signalled.lock().unwrap().is_empty());
+        
+        select(&mut app, 4242);
+        app.cycle_armed_kill();
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Auto-kill armed for port 3000: every refresh kills it on sight. Y again to change or disarm"),
+        );
+        app.refresh_processes().unwrap();
+        assert_eq!(*signalled.lock().unwrap(), [4242]);
+        assert_eq!(app.status_message.as_deref(), Some("Auto-killed node (PID 4242)"));
+        assert!(render(&app, 160, 20).iter().any(|row| row.contains("AUTO-KILL ARMED for port 3000 (1 killed)")));
+        
+        // Nothing more until the cooldown has passed
+        app.refresh_processes().unwrap();
+        assert_eq!(signalled.lock().unwrap().len(), 1);
+        
+        // Switching to the name kills every process with it
+        app.cycle_armed_kill();
+        app.armed_kill.as_mut().unwrap().recent_kills.push_back(Instant::now() - ARMED_KILL_COOLDOWN);
+        app.refresh_processes().unwrap();
+        assert_eq!(*signalled.lock().unwrap(), [4242, 4242, 4343]);
+        
+        // A process that keeps coming back disarms the rule
+        let armed = app.armed_kill.as_mut().unwrap();
+        armed.recent_kills = std::iter::repeat_n(Instant::now() - ARMED_KILL_COOLDOWN, ARMED_KILL_LIMIT).collect();
+        app.refresh_processes().unwrap();
+        assert_eq!(signalled.lock().unwrap().len(), 3);
+        assert!(app.armed_kill.is_none());
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Auto-kill for \"node\" disarmed after 10 kills in a minute; it keeps coming back"),
+        );
+    }
+    
+    #[test]
+    fn armed_auto_kill_spares_protected_ports_and_disarms() {
+        let rows = [listener("node", 4242, 3000)];
+        let (mut app, _dir) = test_app(r#"{ "port_policies": [{ "ports": [3000, 3000], "policy": "protected" }] }"#, &rows);
+        let (signalled, _backend_dir) = record_kills(&mut app, &rows);
+        select(&mut app, 4242);
+        app.cycle_armed_kill();
+        app.refresh_processes().unwrap();
+        assert!(signalled.lock().unwrap().is_empty());
+        
+        app.cycle_armed_kill();
+        app.cycle_armed_kill();
+        assert!(app.armed_kill.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("Auto-kill disarmed"));
+    }
+    
+    #[test]
+    fn border_styles_map_to_their_lines() {
+        let corners = [("rounded", "╭"), ("plain", "┌"), ("double", "╔")];
+        for (style, corner) in corners {
+            let (app, _dir) = test_app(&format!(r#"{{ "border_style": "{}" }}"#, style), &[listener("node", 4242, 3000)]);
+            let screen = render(&app, 120, 20);
+            assert!(screen[0].starts_with(corner), "{}: {}", style, screen[0]);
+        }
+        
+        let (app, _dir) = test_app(r#"{ "border_style": "none" }"#, &[listener("node", 4242, 3000)]);
+        let screen = render(&app, 120, 20);
+        let lines = ["╭", "┌", "╔", "│", "║"];
+        assert!(!screen.iter().any(|row| lines.iter().any(|line| row.contains(line))), "{:#?}", screen);
+        assert!(screen.iter().any(|row| row.contains("node")));
+    }
+    
+    #[test]
+    fn dense_mode_fits_more_rows() {
+        let rows: Vec<String> = (0..30).map(|i| listener(&format!("svc{}", i), 4000 + i, 3000 + i as u16)).collect();
+        let shown = |config: &str| {
+            let (app, _dir) = test_app(config, &rows);
+            render(&app, 120, 24).iter().filter(|row| row.contains("svc")).count()
+        };
+        assert!(shown(r#"{ "dense": true }"#) > shown("{}"));
+    }
+    
+    #[test]
+    fn top_keeps_the_heaviest_processes() {
+        let rows = [listener("small", 4242, 3000), listener("big", 4343, 3001), listener("busy", 4444, 3002)];
+        let shown = |flags: &[&str]| {
+            let (mut app, _dir) = test_app_with_args("{}", &rows, flags);
+            for process in &mut app.discovered {
+                (process.memory, process.cpu_usage) = match process.name.as_str() {
+                    "small" => (1024, 1.0),
+                    "big" => (4096, 2.0),
+                    _ => (2048, 90.0),
+                };
+            }
+            app.apply_view();
+            app.port_processes.iter().map(|p| p.name.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(shown(&["--top", "2"]), ["big", "busy"]);
+        assert_eq!(shown(&["--top", "1", "--top-by", "cpu"]), ["busy"]);
+        // More than there are shows them all
+        assert_eq!(shown(&["--top", "10"]), ["big", "busy", "small"]);
+        assert_eq!(shown(&[]), ["small", "big", "busy"]);
+    }
 }
@@ -0,0 +1,59 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Title of desktop notifications
+const NOTIFICATION_TITLE: &str = "port-manager";
+
+/// Whether there's a desktop to show a file manager or notification on.
+/// macOS always has one; elsewhere an X or Wayland display is needed.
+pub fn has_display() -> bool {
+    cfg!(target_os = "macos") || std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Let the user know something happened while they were looking elsewhere,
+/// with a desktop notification where one can be shown and the terminal bell
+/// otherwise
+pub fn notify(message: &str) {
+    if has_display() && desktop_notification(message) {
+        return;
+    }
+    // On stderr, so it still reaches the terminal when stdout is piped
+    let mut stderr = std::io::stderr();
+    let _ = stderr.write_all(b"\x07");
+    let _ = stderr.flush();
+}
+
+/// Show a desktop notification with `osascript`, returning false if it
+/// couldn't be shown
+#[cfg(target_os = "macos")]
+fn desktop_notification(message: &str) -> bool {
+    // Passed as arguments, so quotes in the message need no escaping
+    run_notifier("osascript", &[
+        "-e", "on run argv",
+        "-e", "display notification (item 2 of argv) with title (item 1 of argv)",
+        "-e", "end run",
+        NOTIFICATION_TITLE, message,
+    ])
+}
+
+/// Show a desktop notification with `notify-send`, returning false if it
+/// couldn't be shown, e.g. without a notification daemon
+#[cfg(not(target_os = "macos"))]
+fn desktop_notification(message: &str) -> bool {
+    run_notifier("notify-send", &[NOTIFICATION_TITLE, message])
+}
+
+/// Run a notification command, logging why it failed
+fn run_notifier(program: &str, args: &[&str]) -> bool {
+    match Command::new(program).args(args).stdin(Stdio::null()).output() {
+        Ok(output) if output.status.success() => true,
+        Ok(output) => {
+            tracing::debug!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+            false
+        }
+        Err(e) => {
+            tracing::debug!("Failed to run {}: {}", program, e);
+            false
+        }
+    }
+}
@@ -0,0 +1,1303 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{PortManagerError, Result};
+use sysinfo::{Pid, ProcessStatus, System};
+
+use crate::config::{SearchField, SortColumn};
+
+/// Shown in place of a command line when commands are hidden
+pub const HIDDEN_COMMAND: &str = "[hidden]";
+
+/// Process name shown for a socket without a process
+pub const ORPHANED_NAME: &str = "(orphaned)";
+
+/// Represents a process running on a port
+#[derive(Clone)]
+pub struct PortProcess {
+    pub pid: u32,
+    pub name: String,
+    pub port: u16,
+    /// Local address the socket is bound to, `*` for all interfaces
+    pub local_addr: String,
+    pub family: AddressFamily,
+    /// Transport protocol of the socket
+    pub protocol: Protocol,
+    /// Set when the process listens on this port over both IPv4 and IPv6
+    pub dual_stack: bool,
+    pub command: String,
+    /// Name of the user running the process
+    pub user: String,
+    /// Remote endpoint, only set for connections
+    pub remote: Option<String>,
+    /// TCP state of the socket
+    pub state: TcpState,
+    /// Resident memory in bytes
+    pub memory: u64,
+    /// CPU usage in percent since the previous refresh
+    pub cpu_usage: f32,
+    /// Number of threads, when it can be read (Linux only)
+    pub threads: Option<usize>,
+    /// Set when the process had exited by the time its details were looked up
+    pub exited: bool,
+    /// Set for a socket no process could be found for, e.g. one the kernel
+    /// still holds after its process died. `pid` is 0 for these rows.
+    pub orphaned: bool,
+    /// Set when the process is stopped, e.g. by SIGSTOP
+    pub stopped: bool,
+    /// When the process started, in seconds since the Unix epoch
+    pub start_time: Option<u64>,
+    /// Name as reported by sysinfo, which can differ from the discovered name
+    pub sysinfo_name: String,
+    /// Executable path as reported by sysinfo
+    pub sysinfo_exe: String,
+    /// Command line as reported by sysinfo, rather than `ps`
+    pub sysinfo_command: String,
+    /// Inode of the process's network namespace, when it can be read (Linux only)
+    pub net_namespace: Option<u64>,
+    /// systemd unit the process runs in, looked up when `systemd_units` is on
+    pub systemd_unit: Option<String>,
+    /// Short ID of the container the process runs in, read from its cgroup
+    /// (Linux only)
+    pub container_id: Option<String>,
+    /// File descriptor holding the socket, as lsof prints it (e.g. `12u`)
+    pub fd: Option<String>,
+    /// Inode of the socket, for matching against other tools (Linux only)
+    pub socket_inode: Option<u64>,
+    /// Bytes received but not yet read (Recv-Q). For a listener, connections
+    /// waiting to be accepted. Only the fuser backend reports queues.
+    pub recv_queue: Option<u64>,
+    /// Bytes sent but not yet acknowledged (Send-Q)
+    pub send_queue: Option<u64>,
+    /// Port as lsof printed it when it was neither a number nor a known
+    /// service name. `port` is 0 for these rows.
+    pub unresolved_port: Option<String>,
+}
+
+impl PortProcess {
+    /// Create a port process, looking up its command and resource usage
+    pub fn new(
+        pid: u32,
+        name: String,
+        port: u16,
+        local_addr: String,
+        family: AddressFamily,
+        remote: Option<String>,
+        system: &System,
+    ) -> Self {
+        let info = system.process(Pid::from_u32(pid));
+        let (memory, cpu_usage) = info
+            .map(|info| (info.memory(), info.cpu_usage()))
+            .unwrap_or_default();
+        let stopped = info.is_some_and(|info| info.status() == ProcessStatus::Stop);
+        let start_time = info.map(|info| info.start_time());
+        // Kept apart from the discovered values to explain mismatched names
+        let (sysinfo_name, sysinfo_exe, sysinfo_command) = info
+            .map(|info| (
+                info.name().to_string(),
+                info.exe().map(|exe| exe.display().to_string()).unwrap_or_default(),
+                info.cmd().join(" "),
+            ))
+            .unwrap_or_default();
+        let (user, command) = lookup_user_and_command(pid);
+        // Backends that read the state overwrite this guess
+        let state = if remote.is_some() { TcpState::Established } else { TcpState::Listen };
+        
+        Self {
+            pid,
+            name,
+            port,
+            local_addr,
+            family,
+            protocol: Protocol::Tcp,
+            dual_stack: false,
+            command,
+            user,
+            remote,
+            state,
+            memory,
+            cpu_usage,
+            threads: count_threads(pid),
+            exited: false,
+            orphaned: false,
+            stopped,
+            start_time,
+            sysinfo_name,
+            sysinfo_exe,
+            sysinfo_command,
+            net_namespace: lookup_net_namespace(pid),
+            systemd_unit: None,
+            container_id: lookup_container_id(pid),
+            fd: None,
+            socket_inode: None,
+            recv_queue: None,
+            send_queue: None,
+            unresolved_port: None,
+        }
+    }
+    
+    /// A process on another machine, with nothing looked up locally. The
+    /// backend fills in what it can find out remotely.
+    pub fn without_lookups(
+        pid: u32,
+        name: String,
+        port: u16,
+        local_addr: String,
+        family: AddressFamily,
+        remote: Option<String>,
+    ) -> Self {
+        let state = if remote.is_some() { TcpState::Established } else { TcpState::Listen };
+        Self {
+            pid,
+            name,
+            remote,
+            state,
+            orphaned: false,
+            ..Self::orphaned(port, local_addr, family)
+        }
+    }
+    
+    /// A listening socket no process could be found for. There's no PID, so
+    /// nothing is looked up.
+    pub fn orphaned(port: u16, local_addr: String, family: AddressFamily) -> Self {
+        Self {
+            pid: 0,
+            name: ORPHANED_NAME.to_string(),
+            port,
+            local_addr,
+            family,
+            protocol: Protocol::Tcp,
+            dual_stack: false,
+            command: String::new(),
+            user: String::new(),
+            remote: None,
+            state: TcpState::Listen,
+            memory: 0,
+            cpu_usage: 0.0,
+            threads: None,
+            exited: false,
+            orphaned: true,
+            stopped: false,
+            start_time: None,
+            sysinfo_name: String::new(),
+            sysinfo_exe: String::new(),
+            sysinfo_command: String::new(),
+            net_namespace: None,
+            systemd_unit: None,
+            container_id: None,
+            fd: None,
+            socket_inode: None,
+            recv_queue: None,
+            send_queue: None,
+            unresolved_port: None,
+        }
+    }
+    
+    /// Check whether the process belongs to the system rather than a user:
+    /// init, or anything run by root or a macOS `_` service account
+    pub fn is_system(&self) -> bool {
+        self.pid == 1 || self.user == "root" || self.user.starts_with('_')
+    }
+    
+    /// Check whether memory and CPU were read for the process. They are
+    /// zero, not measured, when it exited or sysinfo didn't list it.
+    pub fn has_resource_data(&self) -> bool {
+        !self.exited && self.start_time.is_some()
+    }
+    
+    /// Where the socket can be reached from, used to group the table by
+    /// address. Lower ranks are more exposed and listed first.
+    pub fn bind_scope(&self) -> (u8, &'static str) {
+        if self.local_addr == "*" {
+            (0, "all interfaces")
+        } else if self.is_loopback() {
+            (2, "loopback")
+        } else {
+            (1, "one address")
+        }
+    }
+    
+    /// The address the socket is bound to when it's a single address reachable
+    /// from other machines, rather than all interfaces or loopback
+    pub fn external_bind_ip(&self) -> Option<IpAddr> {
+        let host = self.local_addr.trim_start_matches('[').trim_end_matches(']');
+        host.parse::<IpAddr>().ok().filter(|ip| !ip.is_unspecified() && !self.is_loopback())
+    }
+    
+    /// Port as text, `?` when it couldn't be resolved
+    pub fn port_text(&self) -> String {
+        match self.unresolved_port {
+            Some(_) => "?".to_string(),
+            None => self.port.to_string(),
+        }
+    }
+    
+    /// Check whether the port is below 1024, which takes privileges to bind
+    pub fn is_privileged_port(&self) -> bool {
+        self.unresolved_port.is_none() && self.port < 1024
+    }
+    
+    /// Check whether the port is served from a container: the process runs
+    /// in one, or it's Docker's proxy publishing a container's port on the host
+    pub fn is_container(&self) -> bool {
+        self.container_id.is_some() || self.name == "docker-proxy"
+    }
+    
+    /// Number of sockets the row stands for, two for a dual-stack row
+    pub fn socket_count(&self) -> usize {
+        if self.dual_stack { 2 } else { 1 }
+    }
+    
+    /// Check whether the socket is bound to a loopback address only
+    pub fn is_loopback(&self) -> bool {
+        let host = self.local_addr.trim_start_matches('[').trim_end_matches(']');
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V6(ip)) => ip.is_loopback() || ip.to_ipv4_mapped().is_some_and(|v4| v4.is_loopback()),
+            Ok(ip) => ip.is_loopback(),
+            Err(_) => false,
+        }
+    }
+    
+    /// Check whether any of the given fields contains the query, ignoring case.
+    /// Hidden command lines are never searched, so a match can't leak them.
+    pub fn matches_search(&self, query: &str, fields: &[SearchField], hide_commands: bool) -> bool {
+        let query = query.to_lowercase();
+        fields.iter().any(|field| match field {
+            SearchField::Name => self.name.to_lowercase().contains(&query),
+            SearchField::Command => !hide_commands && self.command.to_lowercase().contains(&query),
+            SearchField::Port => self.port.to_string().contains(&query),
+            SearchField::User => self.user.to_lowercase().contains(&query),
+        })
+    }
+    
+    /// How long the process has been running, if its start time is known
+    pub fn age(&self, now: SystemTime) -> Option<Duration> {
+        let started = UNIX_EPOCH + Duration::from_secs(self.start_time?);
+        Some(now.duration_since(started).unwrap_or_default())
+    }
+    
+    /// Check whether the process started no longer than `window` ago. An
+    /// unknown start time can't be shown to be recent, so it doesn't count.
+    pub fn started_within(&self, now: SystemTime, window: Duration) -> bool {
+        self.age(now).is_some_and(|age| age <= window)
+    }
+    
+    /// When the process started, either as a UTC timestamp like
+    /// `2024-06-01 14:32:05 UTC` or relative like `3h 12m 5s ago`
+    pub fn started_label(&self, now: SystemTime, absolute: bool) -> Option<String> {
+        if absolute {
+            let started = UNIX_EPOCH + Duration::from_secs(self.start_time?);
+            let timestamp = humantime::format_rfc3339_seconds(started).to_string();
+            Some(format!("{} UTC", timestamp.trim_end_matches('Z').replace('T', " ")))
+        } else {
+            let age = Duration::from_secs(self.age(now)?.as_secs());
+            Some(format!("{} ago", humantime::format_duration(age)))
+        }
+    }
+    
+    /// Name of the program being run, without its path or arguments. Taken
+    /// from sysinfo's executable path when known, since some programs rewrite
+    /// their command line (e.g. `nginx: master process`).
+    pub fn executable(&self) -> &str {
+        let path = if self.sysinfo_exe.is_empty() {
+            self.command.split_whitespace().next().unwrap_or_default()
+        } else {
+            &self.sysinfo_exe
+        };
+        path.rsplit('/').next().unwrap_or(path)
+    }
+    
+    /// The command line to show, masked when commands are hidden
+    pub fn display_command(&self, hide: bool) -> &str {
+        if hide {
+            HIDDEN_COMMAND
+        } else {
+            &self.command
+        }
+    }
+}
+
+/// Which kind of sockets to discover
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiscoveryMode {
+    /// Processes listening on a port
+    Listeners,
+    /// Processes with connections in any state other than LISTEN
+    Connections,
+}
+
+/// TCP state of a socket. Sockets in TIME_WAIT usually belong to no process
+/// any more, so they are only seen while a process still holds one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TcpState {
+    Listen,
+    Established,
+    SynSent,
+    SynReceived,
+    FinWait1,
+    FinWait2,
+    TimeWait,
+    Closed,
+    CloseWait,
+    LastAck,
+    Closing,
+}
+
+impl TcpState {
+    /// Name shown in the table, as lsof and netstat print it
+    pub fn label(&self) -> &'static str {
+        match self {
+            TcpState::Listen => "LISTEN",
+            TcpState::Established => "ESTABLISHED",
+            TcpState::SynSent => "SYN_SENT",
+            TcpState::SynReceived => "SYN_RECV",
+            TcpState::FinWait1 => "FIN_WAIT1",
+            TcpState::FinWait2 => "FIN_WAIT2",
+            TcpState::TimeWait => "TIME_WAIT",
+            TcpState::Closed => "CLOSED",
+            TcpState::CloseWait => "CLOSE_WAIT",
+            TcpState::LastAck => "LAST_ACK",
+            TcpState::Closing => "CLOSING",
+        }
+    }
+    
+    /// State for a code in the `st` column of /proc/net/tcp
+    pub fn from_kernel_code(code: &str) -> Option<Self> {
+        Some(match code {
+            "01" => TcpState::Established,
+            "02" => TcpState::SynSent,
+            "03" => TcpState::SynReceived,
+            "04" => TcpState::FinWait1,
+            "05" => TcpState::FinWait2,
+            "06" => TcpState::TimeWait,
+            "07" => TcpState::Closed,
+            "08" => TcpState::CloseWait,
+            "09" => TcpState::LastAck,
+            "0A" => TcpState::Listen,
+            "0B" => TcpState::Closing,
+            _ => return None,
+        })
+    }
+}
+
+impl std::str::FromStr for TcpState {
+    type Err = String;
+    
+    /// Parse a state name in any case, with or without underscores, so both
+    /// lsof's `FIN_WAIT_1` and `time-wait` on the command line are understood
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let name: String = value.chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .collect::<String>()
+            .to_uppercase();
+        Ok(match name.as_str() {
+            "LISTEN" => TcpState::Listen,
+            "ESTABLISHED" => TcpState::Established,
+            "SYNSENT" => TcpState::SynSent,
+            "SYNRECV" | "SYNRCVD" | "SYNRECEIVED" => TcpState::SynReceived,
+            "FINWAIT1" => TcpState::FinWait1,
+            "FINWAIT2" => TcpState::FinWait2,
+            "TIMEWAIT" => TcpState::TimeWait,
+            "CLOSE" | "CLOSED" => TcpState::Closed,
+            "CLOSEWAIT" => TcpState::CloseWait,
+            "LASTACK" => TcpState::LastAck,
+            "CLOSING" => TcpState::Closing,
+            _ => return Err(format!("unknown TCP state {:?}, e.g. LISTEN, ESTABLISHED or TIME_WAIT", value)),
+        })
+    }
+}
+
+/// IP version of a socket
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+/// Transport protocol of a socket
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    /// Name as lsof prints it
+    pub fn label(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "TCP",
+            Protocol::Udp => "UDP",
+        }
+    }
+}
+
+/// Collapse each IPv4/IPv6 pair with the same PID, port and remote into a
+/// single dual-stack row, keeping whichever was found first. Only pairs bound
+/// to the same kind of address (all interfaces, or loopback) are merged, so a
+/// public bind is never hidden behind a loopback one.
+pub fn merge_dual_stack(processes: Vec<PortProcess>) -> Vec<PortProcess> {
+    let mut merged: Vec<PortProcess> = Vec::with_capacity(processes.len());
+    
+    for process in processes {
+        let pair = merged.iter_mut().find(|existing| {
+            !existing.dual_stack
+                && existing.pid == process.pid
+                && existing.port == process.port
+                && existing.remote == process.remote
+                && existing.family != process.family
+                && existing.protocol == process.protocol
+                && existing.is_loopback() == process.is_loopback()
+                && (existing.local_addr == "*") == (process.local_addr == "*")
+        });
+        
+        match pair {
+            Some(existing) => existing.dual_stack = true,
+            None => merged.push(process),
+        }
+    }
+    
+    merged
+}
+
+/// Summarize a set of sockets by protocol and IP version, e.g.
+/// "2×TCP, 1×UDP, v4+v6". A dual-stack row stands for one socket of each family.
+pub fn socket_summary<'a>(sockets: impl IntoIterator<Item = &'a PortProcess>) -> String {
+    let mut counts: BTreeMap<Protocol, usize> = BTreeMap::new();
+    let mut families = BTreeSet::new();
+    for socket in sockets {
+        *counts.entry(socket.protocol).or_default() += socket.socket_count();
+        if socket.dual_stack {
+            families.extend([AddressFamily::Ipv4, AddressFamily::Ipv6]);
+        } else {
+            families.insert(socket.family);
+        }
+    }
+    let counts = counts.iter().map(|(protocol, count)| format!("{}×{}", count, protocol.label()));
+    let families: Vec<&str> = families.iter()
+        .map(|family| match family {
+            AddressFamily::Ipv4 => "v4",
+            AddressFamily::Ipv6 => "v6",
+        })
+        .collect();
+    counts.chain([families.join("+")]).collect::<Vec<_>>().join(", ")
+}
+
+/// Drop listeners of processes that only listen on loopback addresses. A process
+/// that also listens on any other address keeps all of its rows.
+pub fn hide_loopback_only(processes: Vec<PortProcess>) -> Vec<PortProcess> {
+    let exposed: BTreeSet<u32> = processes.iter()
+        .filter(|p| !p.is_loopback())
+        .map(|p| p.pid)
+        .collect();
+    processes.into_iter().filter(|p| exposed.contains(&p.pid)).collect()
+}
+
+/// Tools port-manager runs while discovering processes and showing details
+const HELPER_COMMANDS: [&str; 5] = ["lsof", "ps", "fuser", "getent", "dscacheutil"];
+
+/// Most parents followed when looking for port-manager among a process's
+/// ancestors, in case the process table changes mid-walk
+const MAX_ANCESTORS: usize = 64;
+
+/// Drop port-manager itself and every process it started, like the helpers it
+/// runs or a server started from an action, so they can't be killed from the
+/// list. Helpers that had already exited by the time they were looked up can't
+/// be traced to port-manager, so they're recognised by name.
+pub fn exclude_own_processes(processes: Vec<PortProcess>, system: &System) -> Vec<PortProcess> {
+    let own_pid = Pid::from_u32(std::process::id());
+    let is_own = |pid: u32| {
+        let mut current = Some(Pid::from_u32(pid));
+        for _ in 0..MAX_ANCESTORS {
+            match current {
+                Some(pid) if pid == own_pid => return true,
+                Some(pid) => current = system.process(pid).and_then(|info| info.parent()),
+                None => return false,
+            }
+        }
+        false
+    };
+    
+    let is_exited_helper = |p: &PortProcess| p.exited && HELPER_COMMANDS.contains(&p.name.as_str());
+    
+    processes.into_iter()
+        .filter(|p| !is_own(p.pid) && !is_exited_helper(p))
+        .collect()
+}
+
+/// Sort processes by a column. The sort is stable, so ties keep their order.
+pub fn sort_processes(processes: &mut [PortProcess], column: SortColumn, descending: bool) {
+    let mut sockets: HashMap<u32, usize> = HashMap::new();
+    if column == SortColumn::Sockets {
+        for process in processes.iter() {
+            *sockets.entry(process.pid).or_default() += process.socket_count();
+        }
+    }
+    processes.sort_by(|a, b| {
+        let ordering = match column {
+            SortColumn::Pid => a.pid.cmp(&b.pid),
+            SortColumn::Port => a.port.cmp(&b.port),
+            SortColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortColumn::Cpu => a.cpu_usage.total_cmp(&b.cpu_usage),
+            SortColumn::Memory => a.memory.cmp(&b.memory),
+            SortColumn::Threads => a.threads.cmp(&b.threads),
+            SortColumn::Sockets => sockets[&a.pid].cmp(&sockets[&b.pid]),
+        };
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+/// Time spent in `ps` lookups since it was last taken, for `--time-refresh`
+static PS_LOOKUP_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of `ps` lookups since the time was last taken
+static PS_LOOKUP_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Total time and number of `ps` lookups since the last call, resetting both
+pub fn take_ps_lookup_time() -> (Duration, u64) {
+    let micros = PS_LOOKUP_MICROS.swap(0, Ordering::Relaxed);
+    let count = PS_LOOKUP_COUNT.swap(0, Ordering::Relaxed);
+    (Duration::from_micros(micros), count)
+}
+
+/// Get the user and full command line of a process via the ps command
+fn lookup_user_and_command(pid: u32) -> (String, String) {
+    let started = std::time::Instant::now();
+    let cmd_output = Command::new("ps")
+        .args(["-o", "user=", "-o", "command=", "-p", &pid.to_string()])
+        .output();
+    PS_LOOKUP_MICROS.fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+    PS_LOOKUP_COUNT.fetch_add(1, Ordering::Relaxed);
+    
+    let Ok(output) = cmd_output else {
+        return (String::new(), String::new());
+    };
+    
+    // User names can't contain whitespace, so the first word is the user
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match line.split_once(char::is_whitespace) {
+        Some((user, command)) => (user.to_string(), command.trim().to_string()),
+        None => (line, String::new()),
+    }
+}
+
+/// Read the inode of a process's network namespace from the `net:[4026531840]`
+/// link in /proc. Other users' processes usually can't be read without root.
+#[cfg(target_os = "linux")]
+fn lookup_net_namespace(pid: u32) -> Option<u64> {
+    let link = std::fs::read_link(format!("/proc/{}/ns/net", pid)).ok()?;
+    link.to_str()?
+        .strip_prefix("net:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// Read the container a process runs in from /proc/<pid>/cgroup
+#[cfg(target_os = "linux")]
+fn lookup_container_id(pid: u32) -> Option<String> {
+    parse_container_id(&std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?)
+}
+
+/// Short ID of the container in /proc/<pid>/cgroup contents. Docker, Podman
+/// and Kubernetes runtimes all name a container's cgroup after its 64-digit
+/// ID, e.g. `docker-<id>.scope`, `libpod-<id>.scope` or `/docker/<id>`.
+#[cfg(target_os = "linux")]
+fn parse_container_id(contents: &str) -> Option<String> {
+    contents.lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.rsplit('/'))
+        .find_map(|component| {
+            let id = component.strip_suffix(".scope").unwrap_or(component);
+            let id = ["docker-", "libpod-", "cri-containerd-", "crio-"].iter()
+                .find_map(|prefix| id.strip_prefix(prefix))
+                .unwrap_or(id);
+            (id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())).then(|| id[..12].to_string())
+        })
+}
+
+/// Count a process's threads from its entries in /proc/<pid>/task
+#[cfg(target_os = "linux")]
+pub fn count_threads(pid: u32) -> Option<usize> {
+    std::fs::read_dir(format!("/proc/{}/task", pid))
+        .ok()
+        .map(|entries| entries.count())
+}
+
+/// Count a process's open file descriptors, a rough sign of how busy or leaky
+/// it is. Other users' processes usually can't be read without root.
+#[cfg(target_os = "linux")]
+pub fn count_open_fds(pid: u32) -> Result<usize> {
+    let path = format!("/proc/{}/fd", pid);
+    let entries = std::fs::read_dir(&path)
+        .map_err(|error| PortManagerError::Io { context: format!("Failed to read {}", path), error })?;
+    Ok(entries.count())
+}
+
+/// Count a process's open file descriptors from `lsof -p`, skipping entries
+/// like the working directory and mapped files that aren't descriptors
+#[cfg(not(target_os = "linux"))]
+pub fn count_open_fds(pid: u32) -> Result<usize> {
+    let output = Command::new("lsof")
+        .args(["-p", &pid.to_string()])
+        .output()
+        .map_err(|e| PortManagerError::spawn("lsof", e))?;
+    if !output.status.success() {
+        return Err(PortManagerError::CommandFailed {
+            command: "lsof".to_string(),
+            message: format!("could not list the files of PID {}", pid),
+        });
+    }
+    
+    // The FD column holds a number and access mode for descriptors, e.g. `12u`
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter(|line| line.split_whitespace().nth(3).is_some_and(|fd| fd.starts_with(|c: char| c.is_ascii_digit())))
+        .count())
+}
+
+/// A listener's accept queue: connections the kernel has completed that the
+/// process hasn't accepted yet, out of the backlog it passed to `listen()`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AcceptQueue {
+    pub pending: u32,
+    pub backlog: u32,
+}
+
+impl AcceptQueue {
+    /// Check whether the queue is at least 80% full, when new connections
+    /// are about to be dropped or refused
+    pub fn nearly_full(&self) -> bool {
+        self.backlog > 0 && u64::from(self.pending) * 5 >= u64::from(self.backlog) * 4
+    }
+}
+
+/// Read a listener's accept queue from `ss`. On a LISTEN socket Recv-Q is
+/// the queue's current length and Send-Q its backlog.
+#[cfg(target_os = "linux")]
+pub fn accept_queue(port: u16, local_addr: &str) -> Result<AcceptQueue> {
+    let output = Command::new("ss")
+        .args(["-ltnH", &format!("sport = :{}", port)])
+        .output()
+        .map_err(|e| PortManagerError::spawn("ss", e))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(PortManagerError::CommandFailed {
+            command: "ss".to_string(),
+            message: error.lines().next().unwrap_or("no output").trim().to_string(),
+        });
+    }
+    
+    let queues = parse_ss_queues(&String::from_utf8_lossy(&output.stdout), port);
+    pick_accept_queue(queues, port, local_addr)
+}
+
+/// Addresses and accept queues of the listeners on `port` in `ss -ltnH`
+/// output, skipping lines that don't parse
+#[cfg(target_os = "linux")]
+fn parse_ss_queues(output: &str, port: u16) -> Vec<(String, AcceptQueue)> {
+    // LISTEN 0      511    127.0.0.1:8080 0.0.0.0:*
+    output.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (address, listen_port) = fields.get(3)?.rsplit_once(':')?;
+            let queue = AcceptQueue { pending: fields.get(1)?.parse().ok()?, backlog: fields.get(2)?.parse().ok()? };
+            (listen_port.parse() == Ok(port)).then(|| (address.to_string(), queue))
+        })
+        .collect()
+}
+
+/// Read a listener's accept queue from `netstat -L`, which shows it as
+/// `qlen/incqlen/maxqlen`: completed connections, ones still in the
+/// handshake, and the backlog
+#[cfg(not(target_os = "linux"))]
+pub fn accept_queue(port: u16, local_addr: &str) -> Result<AcceptQueue> {
+    let output = Command::new("netstat")
+        .args(["-Lan", "-p", "tcp"])
+        .output()
+        .map_err(|e| PortManagerError::spawn("netstat", e))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(PortManagerError::CommandFailed {
+            command: "netstat".to_string(),
+            message: error.lines().next().unwrap_or("no output").trim().to_string(),
+        });
+    }
+    
+    // 0/0/128        127.0.0.1.8080, some versions prefixed with the protocol
+    let queues = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip_while(|field| field.matches('/').count() != 2);
+            let mut lengths = fields.next()?.split('/');
+            let (address, listen_port) = fields.next()?.rsplit_once('.')?;
+            let pending = lengths.next()?.parse().ok()?;
+            let backlog = lengths.nth(1)?.parse().ok()?;
+            (listen_port.parse() == Ok(port)).then(|| (address.to_string(), AcceptQueue { pending, backlog }))
+        })
+        .collect::<Vec<_>>();
+    pick_accept_queue(queues, port, local_addr)
+}
+
+/// Pick the queue of the socket bound to `local_addr` among those on the
+/// port, or the first when the tools write the address differently
+fn pick_accept_queue(queues: Vec<(String, AcceptQueue)>, port: u16, local_addr: &str) -> Result<AcceptQueue> {
+    // All interfaces is `*`, `0.0.0.0` or `[::]`, and addresses may carry
+    // brackets or a `%interface` suffix
+    let normalize = |address: &str| {
+        let address = address.split('%').next().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+        match address.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => "*".to_string(),
+            _ => address.to_string(),
+        }
+    };
+    let wanted = normalize(local_addr);
+    queues.iter()
+        .find(|(address, _)| normalize(address) == wanted)
+        .or(queues.first())
+        .map(|(_, queue)| *queue)
+        .ok_or_else(|| PortManagerError::parse("accept queue", format!("no listener on port {}", port)))
+}
+
+/// Network namespaces are Linux only
+#[cfg(not(target_os = "linux"))]
+fn lookup_net_namespace(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Containers are only detected from Linux cgroups
+#[cfg(not(target_os = "linux"))]
+fn lookup_container_id(_pid: u32) -> Option<String> {
+    None
+}
+
+/// Thread counts are only read from /proc
+#[cfg(not(target_os = "linux"))]
+pub fn count_threads(_pid: u32) -> Option<usize> {
+    None
+}
+
+/// Build a shell script that sends `signal` to each process, one line per
+/// process, so a kill can be reviewed and run later
+pub fn kill_script(processes: &[PortProcess], signal: &str, generated_at: &str) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str(&format!("# Generated by port-manager at {}\n", generated_at));
+    script.push_str(&format!("# Sends SIG{} to {} process(es)\n", signal, processes.len()));
+    
+    for process in processes {
+        if process.orphaned {
+            script.push_str(&format!("# port {}: orphaned socket, no process to kill\n", process.port));
+            continue;
+        }
+        // Keep the whole line a comment even if the name contains a newline
+        let name = process.name.replace(['\n', '\r'], " ");
+        script.push_str(&format!("kill -{} {} # {}:{}\n", signal, process.pid, name, process.port));
+    }
+    
+    script
+}
+
+/// Why a PID must never be signalled: killing port-manager itself or the
+/// shell it was started from would take the user's session down with it
+pub fn protected_reason(pid: u32) -> Option<&'static str> {
+    if pid == 0 {
+        // `kill 0` would signal port-manager's whole process group
+        Some("an orphaned socket")
+    } else if pid == std::process::id() {
+        Some("port-manager itself")
+    } else if pid == std::os::unix::process::parent_id() {
+        Some("the shell that started port-manager")
+    } else {
+        None
+    }
+}
+
+/// Send a signal (e.g. `TERM` or `KILL`) to a process by PID, refusing
+/// protected processes
+pub fn send_signal(pid: u32, signal: &str) -> Result<()> {
+    if pid == 0 {
+        return Err(PortManagerError::OrphanedSocket);
+    }
+    if let Some(reason) = protected_reason(pid) {
+        return Err(PortManagerError::ProtectedProcess { pid, signal: signal.to_string(), reason });
+    }
+    
+    let output = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .output()
+        .map_err(|e| PortManagerError::spawn("kill", e))?;
+    kill_result(&output, pid, signal)
+}
+
+/// Turn the output of `kill`, here or on another machine, into the matching error
+pub fn kill_result(output: &Output, pid: u32, signal: &str) -> Result<()> {
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(if error.contains("Operation not permitted") {
+            PortManagerError::PermissionDenied { pid, signal: signal.to_string() }
+        } else if error.contains("No such process") {
+            PortManagerError::ProcessNotFound(pid)
+        } else {
+            PortManagerError::CommandFailed {
+                command: "kill".to_string(),
+                message: format!("Failed to send SIG{} to process: {}", signal, error.trim()),
+            }
+        });
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[cfg(target_os = "linux")]
+    const CONTAINER_ID: &str = "3f2a9c1d7b4e5f60718293a4b5c6d7e8f9012345678901234567890abcdef012";
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_id_from_cgroup_v1() {
+        let contents = format!(
+            "12:memory:/docker/{id}\n11:cpu,cpuacct:/docker/{id}\n1:name=systemd:/docker/{id}\n",
+            id = CONTAINER_ID
+        );
+        assert_eq!(parse_container_id(&contents).as_deref(), Some("3f2a9c1d7b4e"));
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_id_from_cgroup_v2_scopes() {
+        for path in [
+            format!("/system.slice/docker-{}.scope", CONTAINER_ID),
+            format!("/user.slice/user-1000.slice/user@1000.service/user.slice/libpod-{}.scope/container", CONTAINER_ID),
+        ] {
+            assert_eq!(parse_container_id(&format!("0::{}\n", path)).as_deref(), Some("3f2a9c1d7b4e"), "{}", path);
+        }
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_id_from_kubernetes() {
+        let containerd = format!(
+            "0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod1a2b3c4d_5e6f.slice/cri-containerd-{}.scope\n",
+            CONTAINER_ID
+        );
+        let crio = format!("0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod1a2b.slice/crio-{}.scope\n", CONTAINER_ID);
+        let cgroupfs = format!("11:pids:/kubepods/besteffort/pod1a2b3c4d-5e6f/{}\n", CONTAINER_ID);
+        for contents in [containerd, crio, cgroupfs] {
+            assert_eq!(parse_container_id(&contents).as_deref(), Some("3f2a9c1d7b4e"), "{}", contents);
+        }
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn host_processes_have_no_container_id() {
+        assert_eq!(parse_container_id("0::/user.slice/user-1000.slice/session-2.scope\n"), None);
+        assert_eq!(parse_container_id("0::/system.slice/nginx.service\n"), None);
+        assert_eq!(parse_container_id("0::/\n"), None);
+        assert_eq!(parse_container_id(""), None);
+        // 64 characters, but not an ID
+        assert_eq!(parse_container_id(&format!("0::/system.slice/docker-{}.scope\n", "z".repeat(64))), None);
+        assert_eq!(parse_container_id(&format!("0::/docker/{}\n", &CONTAINER_ID[..63])), None);
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ss_queues_of_the_port() {
+        let output = "\
+LISTEN 0      511        127.0.0.1:8080      0.0.0.0:*
+LISTEN 3      128          0.0.0.0:3000      0.0.0.0:*
+LISTEN 100    128             [::]:3000         [::]:*
+LISTEN 0      4096  [fe80::1%eth0]:3000         [::]:*
+LISTEN 0      128          0.0.0.0:30000     0.0.0.0:*
+";
+        assert_eq!(parse_ss_queues(output, 3000), [
+            ("0.0.0.0".to_string(), AcceptQueue { pending: 3, backlog: 128 }),
+            ("[::]".to_string(), AcceptQueue { pending: 100, backlog: 128 }),
+            ("[fe80::1%eth0]".to_string(), AcceptQueue { pending: 0, backlog: 4096 }),
+        ]);
+        assert_eq!(parse_ss_queues(output, 8080), [("127.0.0.1".to_string(), AcceptQueue { pending: 0, backlog: 511 })]);
+        assert!(parse_ss_queues(output, 9999).is_empty());
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ss_skips_malformed_lines() {
+        let output = "\
+LISTEN 0
+LISTEN x      128          0.0.0.0:3000      0.0.0.0:*
+LISTEN 0      -1           0.0.0.0:3000      0.0.0.0:*
+LISTEN 0      128          0.0.0.0           0.0.0.0:*
+
+LISTEN 1      128          0.0.0.0:3000      0.0.0.0:*
+";
+        assert_eq!(parse_ss_queues(output, 3000), [("0.0.0.0".to_string(), AcceptQueue { pending: 1, backlog: 128 })]);
+    }
+    
+    #[test]
+    fn accept_queue_of_the_listeners_address() {
+        let queues = vec![
+            ("127.0.0.1".to_string(), AcceptQueue { pending: 1, backlog: 128 }),
+            ("0.0.0.0".to_string(), AcceptQueue { pending: 2, backlog: 128 }),
+            ("[::1]".to_string(), AcceptQueue { pending: 3, backlog: 128 }),
+            ("[fe80::1%eth0]".to_string(), AcceptQueue { pending: 4, backlog: 128 }),
+        ];
+        let pick = |local_addr| pick_accept_queue(queues.clone(), 3000, local_addr).unwrap().pending;
+        assert_eq!(pick("127.0.0.1"), 1);
+        assert_eq!(pick("*"), 2);
+        assert_eq!(pick("[::1]"), 3);
+        assert_eq!(pick("fe80::1"), 4);
+        // Written differently by the two tools, so the first is taken
+        assert_eq!(pick("localhost"), 1);
+        assert!(pick_accept_queue(Vec::new(), 3000, "*").is_err());
+    }
+    
+    #[test]
+    fn accept_queue_nearly_full_at_80_percent() {
+        assert!(!AcceptQueue { pending: 0, backlog: 128 }.nearly_full());
+        assert!(!AcceptQueue { pending: 102, backlog: 128 }.nearly_full());
+        assert!(AcceptQueue { pending: 103, backlog: 128 }.nearly_full());
+        assert!(AcceptQueue { pending: 129, backlog: 128 }.nearly_full());
+        // A zero backlog can't fill up
+        assert!(!AcceptQueue { pending: 0, backlog: 0 }.nearly_full());
+        assert!(AcceptQueue { pending: u32::MAX, backlog: u32::MAX }.nearly_full());
+    }
+    
+    fn socket(pid: u32, port: u16, local_addr: &str, family: AddressFamily) -> PortProcess {
+        PortProcess::without_lookups(pid, "node".to_string(), port, local_addr.to_string(), family, None)
+    }
+    
+    #[test]
+    fn dual_stack_pairs_merge_into_one_row() {
+        let merged = merge_dual_stack(vec![
+            socket(10, 3000, "*", AddressFamily::Ipv4),
+            socket(10, 3000, "*", AddressFamily::Ipv6),
+            socket(11, 5173, "127.0.0.1", AddressFamily::Ipv4),
+            socket(11, 5173, "[::1]", AddressFamily::Ipv6),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert!(merged.iter().all(|p| p.dual_stack));
+        // The first one found is kept
+        assert_eq!((merged[0].family, merged[1].local_addr.as_str()), (AddressFamily::Ipv4, "127.0.0.1"));
+        assert_eq!(socket_summary(&merged), "4×TCP, v4+v6");
+    }
+    
+    #[test]
+    fn mixed_protocols_are_counted_apart() {
+        let mut mdns = socket(10, 5353, "*", AddressFamily::Ipv6);
+        mdns.protocol = Protocol::Udp;
+        let merged = merge_dual_stack(vec![
+            socket(10, 3000, "*", AddressFamily::Ipv4),
+            socket(10, 5353, "*", AddressFamily::Ipv4),
+            mdns,
+        ]);
+        // A TCP and a UDP socket on the same port aren't a dual-stack pair
+        assert_eq!(merged.len(), 3);
+        assert_eq!(socket_summary(&merged), "2×TCP, 1×UDP, v4+v6");
+    }
+    
+    #[test]
+    fn processes_sort_by_socket_count() {
+        let mut processes = vec![
+            socket(10, 3000, "*", AddressFamily::Ipv4),
+            socket(11, 4000, "*", AddressFamily::Ipv4),
+            socket(11, 4001, "*", AddressFamily::Ipv4),
+            socket(12, 5000, "*", AddressFamily::Ipv4),
+        ];
+        processes[3].dual_stack = true;
+        processes[3].port = 5000;
+        sort_processes(&mut processes, SortColumn::Sockets, true);
+        let order: Vec<(u32, u16)> = processes.iter().map(|p| (p.pid, p.port)).collect();
+        // Ties keep their order, and a dual-stack row counts twice
+        assert_eq!(order, [(11, 4000), (11, 4001), (12, 5000), (10, 3000)]);
+    }
+    
+    #[test]
+    fn only_matching_binds_merge() {
+        let sockets = vec![
+            // Public on IPv4 but loopback on IPv6
+            socket(10, 3000, "*", AddressFamily::Ipv4),
+            socket(10, 3000, "[::1]", AddressFamily::Ipv6),
+            // Another process on the same port
+            socket(11, 8080, "*", AddressFamily::Ipv4),
+            socket(12, 8080, "*", AddressFamily::Ipv6),
+            // Two IPv4 sockets of one process
+            socket(13, 9000, "*", AddressFamily::Ipv4),
+            socket(13, 9000, "*", AddressFamily::Ipv4),
+        ];
+        let merged = merge_dual_stack(sockets.clone());
+        assert_eq!(merged.len(), sockets.len());
+        assert!(merged.iter().all(|p| !p.dual_stack));
+    }
+    
+    #[test]
+    fn a_dual_stack_row_takes_one_partner() {
+        let merged = merge_dual_stack(vec![
+            socket(10, 3000, "*", AddressFamily::Ipv4),
+            socket(10, 3000, "*", AddressFamily::Ipv6),
+            socket(10, 3000, "*", AddressFamily::Ipv6),
+        ]);
+        assert_eq!(merged.iter().map(|p| p.dual_stack).collect::<Vec<_>>(), [true, false]);
+    }
+    
+    #[test]
+    fn loopback_addresses() {
+        for address in ["127.0.0.1", "127.0.0.53", "[::1]", "::1", "[::ffff:127.0.0.1]"] {
+            assert!(socket(10, 3000, address, AddressFamily::Ipv6).is_loopback(), "{}", address);
+        }
+        for address in ["*", "0.0.0.0", "[::]", "192.168.1.5", "[fe80::1%eth0]", "localhost"] {
+            assert!(!socket(10, 3000, address, AddressFamily::Ipv4).is_loopback(), "{}", address);
+        }
+    }
+    
+    #[test]
+    fn loopback_only_processes_are_hidden() {
+        let shown = hide_loopback_only(vec![
+            // Loopback only
+            socket(10, 5173, "127.0.0.1", AddressFamily::Ipv4),
+            socket(10, 5173, "[::1]", AddressFamily::Ipv6),
+            // Public only
+            socket(11, 8080, "*", AddressFamily::Ipv4),
+            // Mixed: the loopback row stays with the public one
+            socket(12, 9229, "127.0.0.1", AddressFamily::Ipv4),
+            socket(12, 3000, "192.168.1.5", AddressFamily::Ipv4),
+        ]);
+        assert_eq!(shown.iter().map(|p| (p.pid, p.port)).collect::<Vec<_>>(), [(11, 8080), (12, 9229), (12, 3000)]);
+    }
+    
+    #[test]
+    fn search_only_matches_the_given_fields() {
+        let process = PortProcess {
+            command: "/usr/bin/python3 -m http.server".to_string(),
+            user: "alice".to_string(),
+            ..socket(10, 8000, "*", AddressFamily::Ipv4)
+        };
+        use crate::config::SearchField::{Command, Name, Port, User};
+        assert!(process.matches_search("NODE", &[Name], false));
+        assert!(!process.matches_search("http", &[Name], false));
+        assert!(process.matches_search("http", &[Name, Command], false));
+        assert!(!process.matches_search("8000", &[Name, Command], false));
+        assert!(process.matches_search("800", &[Port], false));
+        assert!(process.matches_search("ALI", &[User], false));
+        assert!(!process.matches_search("alice", &[Name, Command, Port], false));
+        assert!(!process.matches_search("node", &[], false));
+    }
+    
+    #[test]
+    fn age_counts_from_the_start_time() {
+        let now = UNIX_EPOCH + Duration::from_secs(10_000);
+        let mut process = socket(10, 3000, "*", AddressFamily::Ipv4);
+        assert_eq!(process.age(now), None);
+        process.start_time = Some(9_700);
+        assert_eq!(process.age(now), Some(Duration::from_secs(300)));
+        assert!(process.age(now).unwrap() >= Duration::from_secs(5 * 60));
+        assert!(process.age(now).unwrap() < Duration::from_secs(60 * 60));
+        // A start time ahead of the clock counts as just started
+        process.start_time = Some(10_060);
+        assert_eq!(process.age(now), Some(Duration::ZERO));
+    }
+    
+    #[test]
+    fn start_time_shows_absolute_or_relative() {
+        // 2024-06-01 14:32:05 UTC, seen 3h 12m 5s later
+        let mut process = socket(10, 3000, "*", AddressFamily::Ipv4);
+        let now = UNIX_EPOCH + Duration::from_secs(1_717_252_325 + 3 * 3600 + 12 * 60 + 5);
+        assert_eq!(process.started_label(now, true), None);
+        assert_eq!(process.started_label(now, false), None);
+        
+        process.start_time = Some(1_717_252_325);
+        assert_eq!(process.started_label(now, true).as_deref(), Some("2024-06-01 14:32:05 UTC"));
+        assert_eq!(process.started_label(now, false).as_deref(), Some("3h 12m 5s ago"));
+        // Sub-second clock readings don't show up
+        let now = now + Duration::from_millis(750);
+        assert_eq!(process.started_label(now, false).as_deref(), Some("3h 12m 5s ago"));
+    }
+    
+    #[test]
+    fn own_process_and_shell_are_protected() {
+        assert_eq!(protected_reason(std::process::id()), Some("port-manager itself"));
+        assert_eq!(protected_reason(std::os::unix::process::parent_id()), Some("the shell that started port-manager"));
+        assert_eq!(protected_reason(0), Some("an orphaned socket"));
+        assert_eq!(protected_reason(u32::MAX), None);
+        
+        // Refused before `kill` runs; signal 0 would only probe anyway
+        let error = send_signal(std::process::id(), "0").unwrap_err();
+        assert!(matches!(error, PortManagerError::ProtectedProcess { reason: "port-manager itself", .. }));
+    }
+    
+    /// The executable shown for a command line, with sysinfo's path if known
+    fn executable_of(command: &str, exe: &str) -> String {
+        let mut process = socket(10, 3000, "*", AddressFamily::Ipv4);
+        process.command = command.to_string();
+        process.sysinfo_exe = exe.to_string();
+        process.executable().to_string()
+    }
+    
+    #[test]
+    fn executable_is_the_program_without_path_or_arguments() {
+        assert_eq!(executable_of("/usr/lib/jvm/java-17/bin/java -Xmx2g -jar build/libs/api.jar --server.port=8080", ""), "java");
+        assert_eq!(executable_of("node /home/dev/app/node_modules/.bin/next dev", ""), "node");
+        assert_eq!(executable_of("python3", ""), "python3");
+        assert_eq!(executable_of("", ""), "");
+        // Rewritten command lines fall back to sysinfo's executable path
+        assert_eq!(executable_of("nginx: master process /usr/sbin/nginx", "/usr/sbin/nginx"), "nginx");
+        assert_eq!(executable_of("postgres: checkpointer", "/usr/lib/postgresql/16/bin/postgres"), "postgres");
+    }
+    
+    /// What `kill` prints and exits with, as `Command::output` returns it
+    fn kill_output(code: i32, stderr: &str) -> Output {
+        use std::os::unix::process::ExitStatusExt;
+        Output {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+    
+    #[test]
+    fn kill_failures_map_to_error_kinds() {
+        assert!(kill_result(&kill_output(0, ""), 4242, "TERM").is_ok());
+        
+        let error = kill_result(&kill_output(1, "kill: (4242) - Operation not permitted\n"), 4242, "TERM").unwrap_err();
+        assert!(matches!(&error, PortManagerError::PermissionDenied { pid: 4242, signal } if signal == "TERM"));
+        
+        let error = kill_result(&kill_output(1, "kill: (4242) - No such process\n"), 4242, "KILL").unwrap_err();
+        assert!(matches!(error, PortManagerError::ProcessNotFound(4242)));
+        
+        let error = kill_result(&kill_output(1, "kill: unknown signal: SIGNOPE\n"), 4242, "NOPE").unwrap_err();
+        assert!(matches!(&error, PortManagerError::CommandFailed { command, .. } if command == "kill"));
+        assert_eq!(error.to_string(), "kill failed: Failed to send SIGNOPE to process: kill: unknown signal: SIGNOPE");
+    }
+    
+    #[test]
+    fn started_within_includes_the_window_boundary() {
+        let now = UNIX_EPOCH + Duration::from_secs(10_000);
+        let window = Duration::from_secs(5 * 60);
+        let mut process = socket(10, 3000, "*", AddressFamily::Ipv4);
+        assert!(!process.started_within(now, window));
+        
+        process.start_time = Some(10_000 - 300);
+        assert!(process.started_within(now, window));
+        process.start_time = Some(10_000 - 301);
+        assert!(!process.started_within(now, window));
+        process.start_time = Some(10_000);
+        assert!(process.started_within(now, window));
+        assert!(process.started_within(now, Duration::ZERO));
+    }
+    
+    const STATES: [TcpState; 11] = [
+        TcpState::Listen,
+        TcpState::Established,
+        TcpState::SynSent,
+        TcpState::SynReceived,
+        TcpState::FinWait1,
+        TcpState::FinWait2,
+        TcpState::TimeWait,
+        TcpState::Closed,
+        TcpState::CloseWait,
+        TcpState::LastAck,
+        TcpState::Closing,
+    ];
+    
+    #[test]
+    fn states_parse_from_their_labels() {
+        for state in STATES {
+            assert_eq!(state.label().parse(), Ok(state));
+        }
+    }
+    
+    #[test]
+    fn states_parse_in_any_spelling() {
+        assert_eq!("time_wait".parse(), Ok(TcpState::TimeWait));
+        assert_eq!("time-wait".parse(), Ok(TcpState::TimeWait));
+        assert_eq!("TIMEWAIT".parse(), Ok(TcpState::TimeWait));
+        assert_eq!("FIN_WAIT_1".parse(), Ok(TcpState::FinWait1));
+        assert_eq!("SYN_RCVD".parse(), Ok(TcpState::SynReceived));
+        assert_eq!("close".parse(), Ok(TcpState::Closed));
+        assert_eq!(
+            "WAITING".parse::<TcpState>(),
+            Err("unknown TCP state \"WAITING\", e.g. LISTEN, ESTABLISHED or TIME_WAIT".to_string()),
+        );
+    }
+    
+    #[test]
+    fn states_from_kernel_codes() {
+        let codes = ["0A", "01", "02", "03", "04", "05", "06", "07", "08", "09", "0B"];
+        for (code, state) in codes.into_iter().zip(STATES) {
+            assert_eq!(TcpState::from_kernel_code(code), Some(state));
+        }
+        assert_eq!(TcpState::from_kernel_code("0C"), None);
+        assert_eq!(TcpState::from_kernel_code("0a"), None);
+    }
+    
+    #[test]
+    fn own_processes_and_exited_helpers_are_excluded() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let mut system = System::new();
+        system.refresh_processes();
+        
+        let mut exited_lsof = socket(999_998, 3000, "*", AddressFamily::Ipv4);
+        exited_lsof.name = "lsof".to_string();
+        exited_lsof.exited = true;
+        let mut running_lsof = socket(999_999, 3001, "*", AddressFamily::Ipv4);
+        running_lsof.name = "lsof".to_string();
+        let mut exited_node = socket(999_997, 3002, "*", AddressFamily::Ipv4);
+        exited_node.exited = true;
+        
+        let kept = exclude_own_processes(vec![
+            socket(std::process::id(), 4000, "*", AddressFamily::Ipv4),
+            socket(child.id(), 4001, "*", AddressFamily::Ipv4),
+            exited_lsof,
+            running_lsof,
+            exited_node,
+            socket(1, 80, "*", AddressFamily::Ipv4),
+        ], &system);
+        child.kill().unwrap();
+        child.wait().unwrap();
+        
+        // Only a helper that exited is assumed to be ours; a live one isn't
+        assert_eq!(kept.iter().map(|p| p.port).collect::<Vec<_>>(), [3001, 3002, 80]);
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn threads_are_counted_for_a_multi_threaded_process() {
+        let (started, wait) = std::sync::mpsc::channel();
+        let (stop, stopped) = std::sync::mpsc::channel::<()>();
+        let worker = std::thread::spawn(move || {
+            started.send(()).unwrap();
+            let _ = stopped.recv();
+        });
+        wait.recv().unwrap();
+        assert!(count_threads(std::process::id()).is_some_and(|threads| threads >= 2));
+        drop(stop);
+        worker.join().unwrap();
+        
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let gone = child.id();
+        child.wait().unwrap();
+        assert_eq!(count_threads(gone), None);
+    }
+    
+    #[test]
+    fn mixed_sockets_summarize_by_family() {
+        assert_eq!(socket_summary(&[socket(10, 3000, "*", AddressFamily::Ipv4)]), "1×TCP, v4");
+        let mixed = [
+            socket(10, 3000, "*", AddressFamily::Ipv4),
+            socket(10, 9229, "[::1]", AddressFamily::Ipv6),
+            socket(10, 3001, "127.0.0.1", AddressFamily::Ipv4),
+        ];
+        assert_eq!(socket_summary(&mixed), "3×TCP, v4+v6");
+        let dual = PortProcess { dual_stack: true, ..socket(10, 3000, "*", AddressFamily::Ipv6) };
+        assert_eq!(socket_summary([&dual, &mixed[2]]), "3×TCP, v4+v6");
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::error::{IoContext, PortManagerError, Result};
+
+/// Path the process list is served on
+pub const PROCESSES_PATH: &str = "/processes";
+
+/// Address used when `--serve` is given without one. Only local clients can
+/// connect, since the list can reveal what is running on the machine.
+pub const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:7878";
+
+/// Longest request head read before giving up on a client
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// How long a client may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve the JSON produced by `render` on `/processes` until the process is
+/// killed. Each connection is handled on its own thread, so `render` must be
+/// safe to call concurrently. Its errors are sent to the client.
+pub fn serve<F, E>(addr: SocketAddr, render: F) -> Result<()>
+where
+    F: Fn() -> std::result::Result<Vec<u8>, E> + Send + Sync + 'static,
+    E: Display,
+{
+    let listener = TcpListener::bind(addr)
+        .map_err(|error| PortManagerError::Bind { address: addr, error })?;
+    let local_addr = listener.local_addr().io_context("Failed to get listening address")?;
+    println!("Serving the process list on http://{}{}", local_addr, PROCESSES_PATH);
+    
+    let render = Arc::new(render);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        
+        let render = Arc::clone(&render);
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, render.as_ref()) {
+                tracing::warn!("Failed to handle request: {}", e);
+            }
+        });
+    }
+    
+    Ok(())
+}
+
+/// Answer a single HTTP request, then close the connection
+fn handle_connection<E: Display>(stream: TcpStream, render: &dyn Fn() -> std::result::Result<Vec<u8>, E>) -> Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT)).io_context("Failed to set read timeout")?;
+    
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).io_context("Failed to read request")?;
+    
+    // Skip the headers; no request needs a body
+    loop {
+        let mut header = String::new();
+        let read = reader.read_line(&mut header).io_context("Failed to read request")?;
+        if read == 0 || header.trim_end().is_empty() {
+            break;
+        }
+    }
+    
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    // Ignore any query string
+    let path = parts.next().unwrap_or_default().split('?').next().unwrap_or_default();
+    
+    let (status, content_type, body) = match (method, path) {
+        ("GET", PROCESSES_PATH) => match render() {
+            Ok(body) => ("200 OK", "application/json", body),
+            Err(e) => ("500 Internal Server Error", "text/plain", format!("{:#}\n", e).into_bytes()),
+        },
+        ("GET", _) => ("404 Not Found", "text/plain", format!("Try {}\n", PROCESSES_PATH).into_bytes()),
+        _ => ("405 Method Not Allowed", "text/plain", b"Only GET is supported\n".to_vec()),
+    };
+    
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len(),
+    )
+    .io_context("Failed to write response")?;
+    stream.write_all(&body).io_context("Failed to write response")?;
+    stream.flush().io_context("Failed to write response")?;
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// Send a request to the server on `addr`, giving it a moment to start
+    /// listening, and return the whole response
+    fn request(addr: SocketAddr, request_line: &str) -> String {
+        let mut stream = (0..50)
+            .find_map(|_| TcpStream::connect(addr).inspect_err(|_| thread::sleep(Duration::from_millis(10))).ok())
+            .expect("server didn't start");
+        write!(stream, "{}\r\nHost: localhost\r\n\r\n", request_line).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+    
+    #[test]
+    fn taken_addresses_fail_to_bind() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap();
+        let error = serve(addr, || Ok::<_, String>(Vec::new())).unwrap_err();
+        assert!(matches!(&error, PortManagerError::Bind { address, .. } if *address == addr));
+        assert!(error.to_string().starts_with(&format!("Failed to listen on {}: ", addr)));
+    }
+    
+    #[test]
+    fn requests_get_the_rendered_list_or_its_error() {
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let calls = AtomicUsize::new(0);
+        thread::spawn(move || {
+            serve(addr, move || match calls.fetch_add(1, Ordering::Relaxed) {
+                0 => Ok(b"[]".to_vec()),
+                _ => Err("lsof failed: no output"),
+            })
+        });
+        
+        let response = request(addr, "GET /processes HTTP/1.1");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("\r\n\r\n[]"), "{}", response);
+        
+        let response = request(addr, "GET /processes?x=1 HTTP/1.1");
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"), "{}", response);
+        assert!(response.ends_with("lsof failed: no output\n"), "{}", response);
+        
+        assert!(request(addr, "GET / HTTP/1.1").starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(request(addr, "POST /processes HTTP/1.1").starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    }
+}
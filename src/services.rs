@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::{IoContext, PortManagerError, Result};
+
+/// Where the system's service names are read from
+const SYSTEM_SERVICES: &str = "/etc/services";
+
+/// Names for common development ports, for systems without /etc/services and
+/// for ports it doesn't name
+const BUILTIN_SERVICES: &[(u16, &str)] = &[
+    (22, "ssh"),
+    (80, "http"),
+    (443, "https"),
+    (1433, "mssql"),
+    (3000, "dev-server"),
+    (3306, "mysql"),
+    (5000, "flask"),
+    (5173, "vite"),
+    (5432, "postgres"),
+    (5672, "amqp"),
+    (6379, "redis"),
+    (8000, "http-dev"),
+    (8080, "http-alt"),
+    (8443, "https-alt"),
+    (9200, "elasticsearch"),
+    (11211, "memcached"),
+    (27017, "mongodb"),
+];
+
+/// Names of the services usually found on a port, used to annotate ports in
+/// the detail popup
+#[derive(Debug, Default)]
+pub struct ServiceNames {
+    names: HashMap<u16, String>,
+}
+
+impl ServiceNames {
+    /// Built-in names, overridden by /etc/services, overridden in turn by the
+    /// custom file, if one is configured. A missing /etc/services is skipped,
+    /// but a custom file must exist and parse.
+    pub fn load(custom: Option<&Path>) -> Result<Self> {
+        let system = fs::read_to_string(SYSTEM_SERVICES).map(|contents| parse_system_services(&contents)).unwrap_or_default();
+        let custom = match custom {
+            Some(path) => {
+                let contents = fs::read_to_string(path).io_context(format!("Failed to read {}", path.display()))?;
+                parse_custom_services(&contents)
+                    .map_err(|e| PortManagerError::parse("services file", format!("{}: {}", path.display(), e)))?
+            }
+            None => HashMap::new(),
+        };
+        Ok(Self::merge(system, custom))
+    }
+    
+    /// Layer the system and custom names over the built-in ones
+    fn merge(system: HashMap<u16, String>, custom: HashMap<u16, String>) -> Self {
+        let mut names: HashMap<u16, String> = BUILTIN_SERVICES.iter()
+            .map(|(port, name)| (*port, name.to_string()))
+            .collect();
+        names.extend(system);
+        names.extend(custom);
+        Self { names }
+    }
+    
+    /// Name of the service on a port, if any
+    pub fn name(&self, port: u16) -> Option<&str> {
+        self.names.get(&port).map(String::as_str)
+    }
+}
+
+/// Map TCP ports in /etc/services to their first name, e.g.
+/// `http  80/tcp  www  # WorldWideWeb HTTP`
+fn parse_system_services(contents: &str) -> HashMap<u16, String> {
+    let mut names = HashMap::new();
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_proto)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Some(Ok(port)) = port_proto.strip_suffix("/tcp").map(str::parse::<u16>) {
+            names.entry(port).or_insert_with(|| name.to_string());
+        }
+    }
+    names
+}
+
+/// Read a custom mapping of `port = name` lines, e.g. `7070 = auth-service`.
+/// Blank lines and `#` comments are skipped; a later line for the same port wins.
+fn parse_custom_services(contents: &str) -> std::result::Result<HashMap<u16, String>, String> {
+    let mut names = HashMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((port, name)) = line.split_once('=') else {
+            return Err(format!("line {}: expected `port = name`, got {:?}", number + 1, line));
+        };
+        let port = port.trim().parse::<u16>()
+            .map_err(|_| format!("line {}: {:?} is not a port", number + 1, port.trim()))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("line {}: no name for port {}", number + 1, port));
+        }
+        names.insert(port, name.to_string());
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn custom_files_map_ports_to_names() {
+        let names = parse_custom_services("# team ports\n7070 = auth-service\n\n 8081=billing # v2\n7070 = auth\n").unwrap();
+        assert_eq!(names.get(&7070).map(String::as_str), Some("auth"));
+        assert_eq!(names.get(&8081).map(String::as_str), Some("billing"));
+        assert_eq!(names.len(), 2);
+    }
+    
+    #[test]
+    fn custom_files_report_the_bad_line() {
+        assert_eq!(parse_custom_services("7070 = auth\n7071 auth").unwrap_err(), r#"line 2: expected `port = name`, got "7071 auth""#);
+        assert_eq!(parse_custom_services("70000 = big").unwrap_err(), r#"line 1: "70000" is not a port"#);
+        assert_eq!(parse_custom_services("7070 =  ").unwrap_err(), "line 1: no name for port 7070");
+    }
+    
+    #[test]
+    fn system_services_keep_the_first_tcp_name() {
+        let names = parse_system_services("http 80/tcp www # WorldWideWeb\nhttp 80/udp\nwww-alt 80/tcp\ndomain 53/udp\n");
+        assert_eq!(names.get(&80).map(String::as_str), Some("http"));
+        assert_eq!(names.get(&53), None);
+    }
+    
+    #[test]
+    fn custom_names_win_over_system_over_builtin() {
+        let system = HashMap::from([(5432, "postgresql".to_string()), (6379, "redis-db".to_string())]);
+        let custom = HashMap::from([(6379, "cache".to_string()), (7070, "auth-service".to_string())]);
+        let names = ServiceNames::merge(system, custom);
+        assert_eq!(names.name(3000), Some("dev-server"));
+        assert_eq!(names.name(5432), Some("postgresql"));
+        assert_eq!(names.name(6379), Some("cache"));
+        assert_eq!(names.name(7070), Some("auth-service"));
+        assert_eq!(names.name(7071), None);
+    }
+    
+    #[test]
+    fn configured_custom_files_must_exist_and_parse() {
+        let dir = std::env::temp_dir().join(format!("port-manager-services-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("services");
+        fs::write(&path, "7070 = auth-service\n3000 = web\n").unwrap();
+        let names = ServiceNames::load(Some(&path)).unwrap();
+        assert_eq!((names.name(7070), names.name(3000)), (Some("auth-service"), Some("web")));
+        
+        fs::write(&path, "auth-service").unwrap();
+        assert!(matches!(ServiceNames::load(Some(&path)), Err(PortManagerError::ParseError { what: "services file", .. })));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(ServiceNames::load(Some(&path)), Err(PortManagerError::Io { .. })));
+    }
+}
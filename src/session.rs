@@ -0,0 +1,56 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{IoContext, PortManagerError, Result};
+
+/// UI state saved on exit and restored on the next launch when
+/// `restore_session` is on. Kept apart from `Config`, which holds settings
+/// rather than where the user left off.
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    /// Whether the filter management view was open
+    pub filter_view: bool,
+    /// PID of the selected process. It may have exited by the next launch,
+    /// in which case the selection starts at the top.
+    pub selected_pid: Option<u32>,
+    pub search_query: String,
+    /// Name of the active preset, which may have been removed since
+    pub preset: Option<String>,
+    pub refresh_paused: bool,
+    pub top_enabled: bool,
+    pub hide_loopback: bool,
+    pub hide_recent: bool,
+    pub group_by_address: bool,
+}
+
+impl Session {
+    /// Session file kept next to a config file, e.g. `config.session.json`
+    pub fn path(config_path: &Path) -> PathBuf {
+        config_path.with_extension("session.json")
+    }
+    
+    /// Read the session saved for a config file, if there is one
+    pub fn load(config_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path(config_path);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(PortManagerError::Io { context: format!("Failed to read {}", path.display()), error }),
+        };
+        
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| PortManagerError::parse("session file", format!("{}: {}", path.display(), e)))
+    }
+    
+    /// Save the session next to a config file
+    pub fn save(&self, config_path: &Path) -> Result<()> {
+        let path = Self::path(config_path);
+        let contents = serde_json::to_string_pretty(self).expect("session serializes to JSON");
+        fs::write(&path, contents).io_context(format!("Failed to write {}", path.display()))
+    }
+}
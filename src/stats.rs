@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{IoContext, PortManagerError, Result};
+
+/// How often processes were killed from the TUI, counted when `kill_stats`
+/// is on. Kept in a local file next to the config and never sent anywhere.
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct KillStats {
+    /// Kills per process name
+    pub names: BTreeMap<String, u64>,
+    /// Kills per port held by the killed process
+    pub ports: BTreeMap<u16, u64>,
+}
+
+impl KillStats {
+    /// Stats file kept next to a config file, e.g. `config.stats.json`
+    pub fn path(config_path: &Path) -> PathBuf {
+        config_path.with_extension("stats.json")
+    }
+    
+    /// Read the stats for a config file, starting empty if there are none yet
+    pub fn load(config_path: &Path) -> Result<Self> {
+        let path = Self::path(config_path);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(PortManagerError::Io { context: format!("Failed to read {}", path.display()), error }),
+        };
+        
+        serde_json::from_str(&contents)
+            .map_err(|e| PortManagerError::parse("stats file", format!("{}: {}", path.display(), e)))
+    }
+    
+    /// Save the stats next to a config file
+    pub fn save(&self, config_path: &Path) -> Result<()> {
+        let path = Self::path(config_path);
+        let contents = serde_json::to_string_pretty(self).expect("stats serialize to JSON");
+        fs::write(&path, contents).io_context(format!("Failed to write {}", path.display()))
+    }
+    
+    /// Delete the stats file for a config file
+    pub fn clear(config_path: &Path) -> Result<()> {
+        let path = Self::path(config_path);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(PortManagerError::Io { context: format!("Failed to delete {}", path.display()), error: e })
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Count one kill of a process holding these ports
+    pub fn record(&mut self, name: &str, ports: &[u16]) {
+        *self.names.entry(name.to_string()).or_insert(0) += 1;
+        for &port in ports {
+            *self.ports.entry(port).or_insert(0) += 1;
+        }
+    }
+    
+    /// The most-killed process names, most kills first
+    pub fn top_names(&self, count: usize) -> Vec<(&str, u64)> {
+        let mut names: Vec<(&str, u64)> = self.names.iter().map(|(name, kills)| (name.as_str(), *kills)).collect();
+        names.sort_by_key(|(_, kills)| std::cmp::Reverse(*kills));
+        names.truncate(count);
+        names
+    }
+    
+    /// The ports whose holders were killed most, most kills first
+    pub fn top_ports(&self, count: usize) -> Vec<(u16, u64)> {
+        let mut ports: Vec<(u16, u64)> = self.ports.iter().map(|(port, kills)| (*port, *kills)).collect();
+        ports.sort_by_key(|(_, kills)| std::cmp::Reverse(*kills));
+        ports.truncate(count);
+        ports
+    }
+    
+    /// Check whether anything has been counted yet
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// A directory of test files, removed along with it
+    struct TestDir(PathBuf);
+    
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+    
+    fn test_dir(name: &str) -> TestDir {
+        let dir = TestDir(std::env::temp_dir().join(format!("port-manager-stats-test-{}-{}", std::process::id(), name)));
+        fs::create_dir_all(&dir.0).unwrap();
+        dir
+    }
+    
+    #[test]
+    fn stats_round_trip_and_rank_by_kills() {
+        let dir = test_dir("round-trip");
+        let config_path = dir.0.join("config.json");
+        assert_eq!(KillStats::path(&config_path), dir.0.join("config.stats.json"));
+        assert!(KillStats::load(&config_path).unwrap().is_empty());
+        
+        let mut stats = KillStats::default();
+        stats.record("node", &[3000, 3001]);
+        stats.record("java", &[8080]);
+        stats.record("node", &[3000]);
+        stats.save(&config_path).unwrap();
+        
+        let stats = KillStats::load(&config_path).unwrap();
+        assert_eq!(stats.top_names(1), [("node", 2)]);
+        assert_eq!(stats.top_ports(2), [(3000, 2), (3001, 1)]);
+        
+        KillStats::clear(&config_path).unwrap();
+        assert!(KillStats::load(&config_path).unwrap().is_empty());
+        // Clearing again is fine
+        KillStats::clear(&config_path).unwrap();
+    }
+    
+    #[test]
+    fn unreadable_stats_are_reported() {
+        let dir = test_dir("unreadable");
+        let config_path = dir.0.join("config.json");
+        fs::write(KillStats::path(&config_path), "not json").unwrap();
+        let error = KillStats::load(&config_path).unwrap_err();
+        assert!(error.to_string().contains("config.stats.json"), "{}", error);
+    }
+}
@@ -0,0 +1,203 @@
+use std::process::{Command, Stdio};
+
+use crate::error::{PortManagerError, Result};
+
+/// Name of the systemd unit a process runs in, read from its cgroup, e.g.
+/// `nginx.service`. `None` when the host doesn't run systemd or the process
+/// isn't in a service or scope.
+#[cfg(target_os = "linux")]
+pub fn systemd_unit(pid: u32) -> Option<String> {
+    // systemd creates this directory at boot, so it tells a systemd host
+    // from a container or a host with another init
+    if !std::path::Path::new("/run/systemd/system").is_dir() {
+        return None;
+    }
+    let contents = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    parse_cgroup_unit(&contents)
+}
+
+/// systemd units are Linux only
+#[cfg(not(target_os = "linux"))]
+pub fn systemd_unit(_pid: u32) -> Option<String> {
+    None
+}
+
+/// Find the innermost service or scope in /proc/<pid>/cgroup, e.g.
+/// `0::/user.slice/user-1000.slice/user@1000.service/app.slice/vite.service`
+/// is in `vite.service`
+#[cfg(target_os = "linux")]
+fn parse_cgroup_unit(contents: &str) -> Option<String> {
+    systemd_cgroup_path(contents)?
+        .rsplit('/')
+        .find(|unit| unit.ends_with(".service") || unit.ends_with(".scope"))
+        .map(str::to_string)
+}
+
+/// The path of the hierarchy systemd manages in /proc/<pid>/cgroup. The
+/// unified hierarchy has a single `0::` line; on hybrid and legacy hosts
+/// systemd keeps its own `name=systemd` hierarchy, which is preferred.
+#[cfg(target_os = "linux")]
+fn systemd_cgroup_path(contents: &str) -> Option<&str> {
+    contents.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ':');
+            let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+            matches!(controllers, "" | "name=systemd").then_some((controllers, path))
+        })
+        .max_by_key(|(controllers, _)| *controllers == "name=systemd")
+        .map(|(_, path)| path)
+}
+
+/// A command that restarts the service a process runs under, offered instead
+/// of a kill its supervisor would undo by starting it again
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartCommand {
+    /// What gets restarted, e.g. `nginx.service` or `container 3f2a9c1d7b4e`
+    pub service: String,
+    pub program: &'static str,
+    pub args: Vec<String>,
+}
+
+impl RestartCommand {
+    /// The command as it would be typed, shown before it's run
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.program)
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    
+    /// Run the command, failing with its error output, e.g. when restarting a
+    /// system unit needs root
+    pub fn run(&self) -> Result<()> {
+        let output = Command::new(self.program)
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| PortManagerError::spawn(self.program, e))?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(PortManagerError::CommandFailed {
+                command: self.command_line(),
+                message: error.lines().next().unwrap_or("no output").trim().to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// How to restart whatever supervises a process: its Docker container, or
+/// its systemd service. Scopes, e.g. login sessions, can't be restarted.
+#[cfg(target_os = "linux")]
+pub fn restart_command(pid: u32) -> Option<RestartCommand> {
+    let contents = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    let systemd = std::path::Path::new("/run/systemd/system").is_dir();
+    parse_restart_command(&contents, systemd)
+}
+
+/// launchd jobs and containers aren't detected on other systems
+#[cfg(not(target_os = "linux"))]
+pub fn restart_command(_pid: u32) -> Option<RestartCommand> {
+    None
+}
+
+/// Pick the restart command for a process's cgroups. Docker containers are
+/// in `docker-<id>.scope` under systemd, or `/docker/<id>` with the cgroupfs
+/// driver; user services sit under `user@<uid>.service`.
+#[cfg(target_os = "linux")]
+fn parse_restart_command(contents: &str, systemd: bool) -> Option<RestartCommand> {
+    let path = systemd_cgroup_path(contents)?;
+    let mut components = path.rsplit('/');
+    let last = components.next()?;
+    let container = last.strip_prefix("docker-").and_then(|scope| scope.strip_suffix(".scope"))
+        .or_else(|| (components.next() == Some("docker")).then_some(last));
+    if let Some(id) = container.filter(|id| id.len() >= 12 && id.is_ascii()) {
+        let short_id = &id[..12];
+        return Some(RestartCommand {
+            service: format!("container {}", short_id),
+            program: "docker",
+            args: vec!["restart".to_string(), short_id.to_string()],
+        });
+    }
+    
+    let unit = parse_cgroup_unit(contents).filter(|unit| systemd && unit.ends_with(".service"))?;
+    // The user's own manager runs services under user@<uid>.service
+    let mut args = Vec::new();
+    if path.split('/').any(|component| component.starts_with("user@") && component != unit) {
+        args.push("--user".to_string());
+    }
+    args.extend(["restart".to_string(), unit.clone()]);
+    Some(RestartCommand { service: unit, program: "systemctl", args })
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    
+    const CONTAINER_ID: &str = "3f2a9c1d7b4e5f60718293a4b5c6d7e8f9012345678901234567890abcdef012";
+    
+    #[test]
+    fn unit_from_cgroup_v2() {
+        assert_eq!(parse_cgroup_unit("0::/system.slice/nginx.service\n").as_deref(), Some("nginx.service"));
+        assert_eq!(
+            parse_cgroup_unit("0::/user.slice/user-1000.slice/user@1000.service/app.slice/vite.service\n").as_deref(),
+            Some("vite.service")
+        );
+        assert_eq!(parse_cgroup_unit("0::/user.slice/user-1000.slice/session-2.scope\n").as_deref(), Some("session-2.scope"));
+        assert_eq!(parse_cgroup_unit("0::/\n"), None);
+    }
+    
+    #[test]
+    fn unit_from_cgroup_v1_prefers_the_systemd_hierarchy() {
+        let contents = "12:memory:/system.slice/other.service\n1:name=systemd:/system.slice/sshd.service\n0::/\n";
+        assert_eq!(parse_cgroup_unit(contents).as_deref(), Some("sshd.service"));
+        // Controller hierarchies alone don't say which unit it is
+        assert_eq!(parse_cgroup_unit("12:memory:/system.slice/other.service\n"), None);
+    }
+    
+    #[test]
+    fn unit_of_a_container_is_its_scope() {
+        let contents = format!("0::/system.slice/docker-{}.scope\n", CONTAINER_ID);
+        assert_eq!(parse_cgroup_unit(&contents), Some(format!("docker-{}.scope", CONTAINER_ID)));
+        assert_eq!(parse_cgroup_unit(&format!("0::/kubepods/besteffort/pod1a2b/{}\n", CONTAINER_ID)), None);
+    }
+    
+    #[test]
+    fn restart_docker_containers() {
+        for contents in [
+            format!("0::/system.slice/docker-{}.scope\n", CONTAINER_ID),
+            format!("12:memory:/docker/{id}\n1:name=systemd:/docker/{id}\n", id = CONTAINER_ID),
+        ] {
+            let restart = parse_restart_command(&contents, true).unwrap();
+            assert_eq!(restart.service, "container 3f2a9c1d7b4e");
+            assert_eq!((restart.program, restart.args), ("docker", vec!["restart".to_string(), "3f2a9c1d7b4e".to_string()]));
+        }
+    }
+    
+    #[test]
+    fn restart_systemd_services() {
+        let system = parse_restart_command("0::/system.slice/nginx.service\n", true).unwrap();
+        assert_eq!(system.args, ["restart", "nginx.service"]);
+        let user = parse_restart_command("0::/user.slice/user-1000.slice/user@1000.service/app.slice/vite.service\n", true).unwrap();
+        assert_eq!(user.args, ["--user", "restart", "vite.service"]);
+        // Not without systemd running, and not for scopes
+        assert_eq!(parse_restart_command("0::/system.slice/nginx.service\n", false), None);
+        assert_eq!(parse_restart_command("0::/user.slice/user-1000.slice/session-2.scope\n", true), None);
+    }
+    
+    #[test]
+    fn restart_commands_show_as_typed() {
+        let user = parse_restart_command("0::/user.slice/user-1000.slice/user@1000.service/app.slice/vite.service\n", true).unwrap();
+        assert_eq!(user.command_line(), "systemctl --user restart vite.service");
+        let container = parse_restart_command(&format!("0::/system.slice/docker-{}.scope\n", CONTAINER_ID), true).unwrap();
+        assert_eq!(container.command_line(), "docker restart 3f2a9c1d7b4e");
+    }
+    
+    #[test]
+    fn failed_restarts_report_the_command() {
+        let restart = RestartCommand { service: "nginx.service".to_string(), program: "false", args: vec!["restart".to_string()] };
+        assert_eq!(restart.run().unwrap_err().to_string(), "false restart failed: no output");
+        let restart = RestartCommand { service: "nginx.service".to_string(), program: "true", args: Vec::new() };
+        assert!(restart.run().is_ok());
+    }
+}